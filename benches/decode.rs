@@ -0,0 +1,142 @@
+//! Decoding throughput over streams of several sizes and redundancies, and
+//! over the paths that only scan a stream.
+//!
+//! Streams are made with the test encoder. To measure real firmware as well,
+//! point `HPCMP_BENCH_CORPUS` at a directory of compressed streams:
+//!
+//!     HPCMP_BENCH_CORPUS=fw cargo bench --bench decode -- corpus
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hpcmp::Decoder;
+
+const SIZES: [(&str, usize); 3] = [
+    ("small", 4 << 10),
+    ("medium", 256 << 10),
+    ("large", 8 << 20),
+];
+
+/// Chunk size for the chunked benchmarks, about what a reader hands over.
+const CHUNK: usize = 8 << 10;
+
+/// `len` bytes of data that compresses well (`high`) or hardly at all.
+fn data(high: bool, len: usize) -> Vec<u8> {
+    // xorshift, so that every run measures the same data
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    match high {
+        // Runs of padding and a handful of repeated records, like firmware
+        true => {
+            let records: Vec<Vec<u8>> = (0..16).map(|_| (0..next() % 48 + 16).map(|_| next() as u8).collect()).collect();
+            let mut data = Vec::with_capacity(len + 0x1000);
+            while data.len() < len {
+                match next() % 4 {
+                    0 => data.extend(std::iter::repeat_n(0xff, (next() % 0x1000) as usize)),
+                    _ => data.extend_from_slice(&records[(next() % 16) as usize]),
+                }
+            }
+            data.truncate(len);
+            data
+        },
+        false => (0..len).map(|_| next() as u8).collect(),
+    }
+}
+
+/// Every stream in `dir` that decodes, by file name. Those that don't are
+/// skipped with a note, so one bad file doesn't spoil a run.
+fn load_corpus(dir: &Path) -> Vec<(String, Vec<u8>, usize)> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| entry.expect("reading the corpus").path())
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+    paths.into_iter().filter_map(|path| {
+        let stream = fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e));
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        match hpcmp::decompress(&stream) {
+            Ok(data) => Some((name, stream, data.len())),
+            Err(e)   => {
+                eprintln!("skipping {}: {}", name, e);
+                None
+            },
+        }
+    }).collect()
+}
+
+/// Streams of every size and redundancy, named like `high/medium`, with the
+/// length they decode to.
+fn streams() -> Vec<(String, Vec<u8>, usize)> {
+    let mut streams = vec![];
+    for high in [true, false] {
+        for (size, len) in SIZES {
+            let name = format!("{}/{}", if high { "high" } else { "low" }, size);
+            streams.push((name, common::compress(&data(high, len), None), len));
+        }
+    }
+    streams
+}
+
+fn decode(c: &mut Criterion, group: &str, streams: &[(String, Vec<u8>, usize)]) {
+    let mut group = c.benchmark_group(group);
+    for (name, stream, len) in streams {
+        group.throughput(Throughput::Bytes(*len as u64));
+        if *len >= 1 << 20 {
+            group.sample_size(10);
+        }
+        group.bench_with_input(BenchmarkId::new("decompress", name), stream, |b, stream| {
+            b.iter(|| hpcmp::decompress(stream).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("chunked", name), stream, |b, stream| {
+            b.iter(|| {
+                let mut decoder = Decoder::new();
+                let mut out = Vec::with_capacity(*len);
+                for chunk in stream.chunks(CHUNK) {
+                    decoder.decode_to_vec(chunk, &mut out).unwrap();
+                }
+                out
+            })
+        });
+    }
+    group.finish();
+}
+
+fn scan(c: &mut Criterion, streams: &[(String, Vec<u8>, usize)]) {
+    let mut group = c.benchmark_group("scan");
+    for (name, stream, len) in streams {
+        // Per input byte, since nothing is output
+        group.throughput(Throughput::Bytes(stream.len() as u64));
+        if *len >= 1 << 20 {
+            group.sample_size(10);
+        }
+        group.bench_with_input(BenchmarkId::new("validate", name), stream, |b, stream| {
+            b.iter(|| hpcmp::validate(&stream[..]).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("build_index", name), stream, |b, stream| {
+            b.iter(|| hpcmp::build_index(&stream[..]).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    let streams = streams();
+    decode(c, "decode", &streams);
+    scan(c, &streams);
+    if let Some(dir) = std::env::var_os("HPCMP_BENCH_CORPUS") {
+        decode(c, "corpus", &load_corpus(Path::new(&dir)));
+    }
+}
+
+criterion_group!(all, benches);
+criterion_main!(all);
@@ -0,0 +1,104 @@
+//! The io_uring backend against standard IO: reading an input, writing an
+//! output, and decoding an input as it is read rather than after.
+//!
+//!     cargo bench --features io-uring --bench io
+//!
+//! Files go in the temporary directory, or in `HPCMP_BENCH_DIR` to measure
+//! another disk. They're read back from the page cache unless it's dropped
+//! between runs, which hides most of what queueing reads ahead can save.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+// Not all of it is measured
+#[allow(dead_code)]
+#[path = "../src/bin/hpcmp/uring.rs"]
+mod uring;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use hpcmp::Decoder;
+
+/// Bytes handed to each write, as `--chunk-size` would.
+const CHUNK: usize = 1 << 20;
+
+fn dir() -> PathBuf {
+    let dir = std::env::var_os("HPCMP_BENCH_DIR").map_or_else(std::env::temp_dir, PathBuf::from);
+    dir.join(format!("hpcmp-bench-io-{}", std::process::id()))
+}
+
+/// `len` bytes that hardly compress, so the stream is as big as the output.
+fn data(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as u8
+    }).collect()
+}
+
+/// Reads the stream at `path`, then decodes it a chunk at a time, throwing
+/// the output away.
+fn decode_std(path: &Path) -> u64 {
+    let stream = fs::read(path).unwrap();
+    let mut decoder = Decoder::new();
+    let mut out = vec![0; CHUNK];
+    let (mut pos, mut total) = (0, 0);
+    while !decoder.is_done() {
+        let (consumed, written) = decoder.decode(&stream[pos..], &mut out).unwrap();
+        pos += consumed;
+        total += written as u64;
+    }
+    total
+}
+
+/// As `decode_std`, decoding what has been read while the rest is being.
+fn decode_uring(path: &Path) -> u64 {
+    let mut input = uring::Input::open(path).unwrap();
+    let mut decoder = Decoder::new();
+    let mut out = vec![0; CHUNK];
+    let (mut pos, mut total) = (0, 0);
+    while !decoder.is_done() {
+        let end = input.wait(pos).unwrap();
+        let (consumed, written) = decoder.decode(&input.data()[pos..end], &mut out).unwrap();
+        pos += consumed;
+        total += written as u64;
+    }
+    total
+}
+
+fn benches(c: &mut Criterion) {
+    let dir = dir();
+    fs::create_dir_all(&dir).unwrap();
+    let data = data(64 << 20);
+    let (plain, stream, written) = (dir.join("data"), dir.join("data.cmp"), dir.join("written"));
+    fs::write(&plain, &data).unwrap();
+    fs::write(&stream, common::compress(&data[..16 << 20], None)).unwrap();
+
+    let mut group = c.benchmark_group("io");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("read/std", |b| b.iter(|| fs::read(&plain).unwrap()));
+    group.bench_function("read/uring", |b| b.iter(|| uring::Input::open(&plain).unwrap().into_data().unwrap()));
+    group.bench_function("write/std", |b| b.iter(|| {
+        let mut file = File::create(&written).unwrap();
+        data.chunks(CHUNK).for_each(|chunk| file.write_all(chunk).unwrap());
+    }));
+    group.bench_function("write/uring", |b| b.iter(|| {
+        let mut output = uring::Output::new(File::create(&written).unwrap(), 0).unwrap();
+        data.chunks(CHUNK).for_each(|chunk| output.put(chunk).unwrap());
+        output.finish().unwrap();
+    }));
+
+    group.throughput(Throughput::Bytes(16 << 20));
+    group.bench_function("decode/std", |b| b.iter(|| decode_std(&stream)));
+    group.bench_function("decode/uring", |b| b.iter(|| decode_uring(&stream)));
+    group.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(all, benches);
+criterion_main!(all);
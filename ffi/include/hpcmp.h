@@ -0,0 +1,122 @@
+#ifndef HPCMP_H
+#define HPCMP_H
+
+/* Generated by cbindgen from ffi/src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Success.
+#define HPCMP_OK 0
+
+// The stream has been fully decoded and all output written.
+#define HPCMP_STREAM_END 1
+
+// A required pointer argument was null.
+#define HPCMP_ERR_NULL_POINTER -1
+
+// The stream did not begin with a reset command.
+#define HPCMP_ERR_MISSING_START_MARKER -2
+
+// A block began with something other than a literal byte.
+#define HPCMP_ERR_FIRST_CODE_NOT_VALUE -3
+
+// The end-of-file command was not followed by a literal byte.
+#define HPCMP_ERR_FINAL_CODE_NOT_VALUE -4
+
+// An index code referred past the end of the dictionary.
+#define HPCMP_ERR_INVALID_INDEX -5
+
+// The code width grew beyond what the decoder supports.
+#define HPCMP_ERR_WIDTH_OVERFLOW -6
+
+// The input ended before the end-of-file command.
+#define HPCMP_ERR_UNEXPECTED_EOF -7
+
+// The decompressed data did not fit in the buffer provided.
+#define HPCMP_ERR_OUTPUT_OVERFLOW -8
+
+// Streaming decoder state, created by `hpcmp_stream_new`.
+typedef struct HpcmpStream HpcmpStream;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Decompresses a complete stream.
+//
+// On success `*output` points to `*output_len` bytes which must be released
+// with `hpcmp_free`. On failure `*output` is set to null.
+//
+// # Safety
+//
+// `input` must point to `input_len` readable bytes, and `output` and
+// `output_len` must be valid for writes.
+int hpcmp_decompress(const uint8_t *input, size_t input_len, uint8_t **output, size_t *output_len);
+
+// Decompresses a complete stream into a caller-provided buffer, storing the
+// decompressed length in `*written`. Fails with `HPCMP_ERR_OUTPUT_OVERFLOW`
+// if the output does not fit.
+//
+// # Safety
+//
+// `input` must point to `input_len` readable bytes, `output` to
+// `output_len` writable bytes, and `written` must be valid for writes.
+int hpcmp_decompress_into(const uint8_t *input,
+                          size_t input_len,
+                          uint8_t *output,
+                          size_t output_len,
+                          size_t *written);
+
+// Releases a buffer returned by `hpcmp_decompress`.
+//
+// # Safety
+//
+// `data` and `len` must be exactly as returned by `hpcmp_decompress`, and
+// the buffer must not be freed twice. Null is ignored.
+void hpcmp_free(uint8_t *data, size_t len);
+
+// Creates a streaming decoder, to be released with `hpcmp_stream_free`.
+struct HpcmpStream *hpcmp_stream_new(void);
+
+// Releases a streaming decoder. Null is ignored.
+//
+// # Safety
+//
+// `stream` must have come from `hpcmp_stream_new` and not already been freed.
+void hpcmp_stream_free(struct HpcmpStream *stream);
+
+// Decodes as much as possible from `input` into `output`.
+//
+// The number of input bytes used and output bytes written are stored in
+// `*consumed` and `*produced`. Unconsumed input must be passed again on the
+// next call. Returns `HPCMP_OK` while more input or output space is needed,
+// `HPCMP_STREAM_END` once the whole stream has been written out, or an
+// error code. Running out of input early is not an error here; a caller
+// that has no more input and has not seen `HPCMP_STREAM_END` has a
+// truncated stream.
+//
+// # Safety
+//
+// `stream` must be a live decoder, `input` must point to `input_len`
+// readable bytes, `output` to `output_len` writable bytes, and `consumed`
+// and `produced` must be valid for writes.
+int hpcmp_stream_decode(struct HpcmpStream *stream,
+                        const uint8_t *input,
+                        size_t input_len,
+                        size_t *consumed,
+                        uint8_t *output,
+                        size_t output_len,
+                        size_t *produced);
+
+// Returns a static, human-readable description of a status code.
+const char *hpcmp_strerror(int code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HPCMP_H */
@@ -0,0 +1,237 @@
+//! C interface to the hpcmp decoder.
+//!
+//! Every function returns one of the `HPCMP_*` status codes below. Their
+//! values are part of the ABI and will not change.
+
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+
+use hpcmp::{Decoder, Error};
+
+/// Success.
+pub const HPCMP_OK: c_int = 0;
+/// The stream has been fully decoded and all output written.
+pub const HPCMP_STREAM_END: c_int = 1;
+/// A required pointer argument was null.
+pub const HPCMP_ERR_NULL_POINTER: c_int = -1;
+/// The stream did not begin with a reset command.
+pub const HPCMP_ERR_MISSING_START_MARKER: c_int = -2;
+/// A block began with something other than a literal byte.
+pub const HPCMP_ERR_FIRST_CODE_NOT_VALUE: c_int = -3;
+/// The end-of-file command was not followed by a literal byte.
+pub const HPCMP_ERR_FINAL_CODE_NOT_VALUE: c_int = -4;
+/// An index code referred past the end of the dictionary.
+pub const HPCMP_ERR_INVALID_INDEX: c_int = -5;
+/// The code width grew beyond what the decoder supports.
+pub const HPCMP_ERR_WIDTH_OVERFLOW: c_int = -6;
+/// The input ended before the end-of-file command.
+pub const HPCMP_ERR_UNEXPECTED_EOF: c_int = -7;
+/// The decompressed data did not fit in the buffer provided.
+pub const HPCMP_ERR_OUTPUT_OVERFLOW: c_int = -8;
+
+/// Streaming decoder state, created by `hpcmp_stream_new`.
+pub struct HpcmpStream {
+    decoder: Decoder,
+}
+
+fn status(e: &Error) -> c_int {
+    use Error::*;
+    match e {
+        MissingStartMarker    => HPCMP_ERR_MISSING_START_MARKER,
+        FirstCodeNotValue     => HPCMP_ERR_FIRST_CODE_NOT_VALUE,
+        FinalCodeNotValue     => HPCMP_ERR_FINAL_CODE_NOT_VALUE,
+        InvalidIndex { .. }   => HPCMP_ERR_INVALID_INDEX,
+        WidthOverflow(_)      => HPCMP_ERR_WIDTH_OVERFLOW,
+        UnexpectedEof         => HPCMP_ERR_UNEXPECTED_EOF,
+        OutputOverflow        => HPCMP_ERR_OUTPUT_OVERFLOW,
+    }
+}
+
+// A null pointer is accepted for an empty buffer.
+unsafe fn input_slice<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if len == 0 {
+        Some(&[])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+unsafe fn output_slice<'a>(data: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    if len == 0 {
+        Some(&mut [])
+    } else if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts_mut(data, len))
+    }
+}
+
+/// Decompresses a complete stream.
+///
+/// On success `*output` points to `*output_len` bytes which must be released
+/// with `hpcmp_free`. On failure `*output` is set to null.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, and `output` and
+/// `output_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hpcmp_decompress(
+    input: *const u8,
+    input_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> c_int {
+    if output.is_null() || output_len.is_null() {
+        return HPCMP_ERR_NULL_POINTER;
+    }
+    *output = ptr::null_mut();
+    *output_len = 0;
+    let input = match input_slice(input, input_len) {
+        Some(input) => input,
+        None        => return HPCMP_ERR_NULL_POINTER,
+    };
+
+    match hpcmp::decompress(input) {
+        Ok(data) => {
+            let data = data.into_boxed_slice();
+            *output_len = data.len();
+            *output = Box::into_raw(data) as *mut u8;
+            HPCMP_OK
+        },
+        Err(e) => status(&e),
+    }
+}
+
+/// Decompresses a complete stream into a caller-provided buffer, storing the
+/// decompressed length in `*written`. Fails with `HPCMP_ERR_OUTPUT_OVERFLOW`
+/// if the output does not fit.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `output` to
+/// `output_len` writable bytes, and `written` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hpcmp_decompress_into(
+    input: *const u8,
+    input_len: usize,
+    output: *mut u8,
+    output_len: usize,
+    written: *mut usize,
+) -> c_int {
+    if written.is_null() {
+        return HPCMP_ERR_NULL_POINTER;
+    }
+    *written = 0;
+    let (input, output) = match (input_slice(input, input_len), output_slice(output, output_len)) {
+        (Some(input), Some(output)) => (input, output),
+        _                           => return HPCMP_ERR_NULL_POINTER,
+    };
+
+    match hpcmp::decompress_into(input, output) {
+        Ok(n) => {
+            *written = n;
+            HPCMP_OK
+        },
+        Err(e) => status(&e),
+    }
+}
+
+/// Releases a buffer returned by `hpcmp_decompress`.
+///
+/// # Safety
+///
+/// `data` and `len` must be exactly as returned by `hpcmp_decompress`, and
+/// the buffer must not be freed twice. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn hpcmp_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Creates a streaming decoder, to be released with `hpcmp_stream_free`.
+#[no_mangle]
+pub extern "C" fn hpcmp_stream_new() -> *mut HpcmpStream {
+    Box::into_raw(Box::new(HpcmpStream{ decoder: Decoder::new() }))
+}
+
+/// Releases a streaming decoder. Null is ignored.
+///
+/// # Safety
+///
+/// `stream` must have come from `hpcmp_stream_new` and not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hpcmp_stream_free(stream: *mut HpcmpStream) {
+    if !stream.is_null() {
+        drop(Box::from_raw(stream));
+    }
+}
+
+/// Decodes as much as possible from `input` into `output`.
+///
+/// The number of input bytes used and output bytes written are stored in
+/// `*consumed` and `*produced`. Unconsumed input must be passed again on the
+/// next call. Returns `HPCMP_OK` while more input or output space is needed,
+/// `HPCMP_STREAM_END` once the whole stream has been written out, or an
+/// error code. Running out of input early is not an error here; a caller
+/// that has no more input and has not seen `HPCMP_STREAM_END` has a
+/// truncated stream.
+///
+/// # Safety
+///
+/// `stream` must be a live decoder, `input` must point to `input_len`
+/// readable bytes, `output` to `output_len` writable bytes, and `consumed`
+/// and `produced` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hpcmp_stream_decode(
+    stream: *mut HpcmpStream,
+    input: *const u8,
+    input_len: usize,
+    consumed: *mut usize,
+    output: *mut u8,
+    output_len: usize,
+    produced: *mut usize,
+) -> c_int {
+    if stream.is_null() || consumed.is_null() || produced.is_null() {
+        return HPCMP_ERR_NULL_POINTER;
+    }
+    *consumed = 0;
+    *produced = 0;
+    let (input, output) = match (input_slice(input, input_len), output_slice(output, output_len)) {
+        (Some(input), Some(output)) => (input, output),
+        _                           => return HPCMP_ERR_NULL_POINTER,
+    };
+
+    let decoder = &mut (*stream).decoder;
+    match decoder.decode(input, output) {
+        Ok((c, p)) => {
+            *consumed = c;
+            *produced = p;
+            if decoder.is_done() { HPCMP_STREAM_END } else { HPCMP_OK }
+        },
+        Err(e) => status(&e),
+    }
+}
+
+/// Returns a static, human-readable description of a status code.
+#[no_mangle]
+pub extern "C" fn hpcmp_strerror(code: c_int) -> *const c_char {
+    let message: &'static [u8] = match code {
+        HPCMP_OK                       => b"OK\0",
+        HPCMP_STREAM_END               => b"End of stream\0",
+        HPCMP_ERR_NULL_POINTER         => b"Null pointer argument\0",
+        HPCMP_ERR_MISSING_START_MARKER => b"Start marker not found\0",
+        HPCMP_ERR_FIRST_CODE_NOT_VALUE => b"First byte not Value\0",
+        HPCMP_ERR_FINAL_CODE_NOT_VALUE => b"Final byte not Value\0",
+        HPCMP_ERR_INVALID_INDEX        => b"Index beyond dictionary\0",
+        HPCMP_ERR_WIDTH_OVERFLOW       => b"Code width too large\0",
+        HPCMP_ERR_UNEXPECTED_EOF       => b"Unexpected end of input\0",
+        HPCMP_ERR_OUTPUT_OVERFLOW      => b"Output buffer too small\0",
+        _                              => b"Unknown status code\0",
+    };
+    message.as_ptr() as *const c_char
+}
@@ -0,0 +1,139 @@
+//! The C interface, called as C would, on the library's known vectors.
+
+use std::ffi::CStr;
+use std::ptr;
+
+use hpcmp::VECTORS;
+use hpcmp_ffi::*;
+
+#[test]
+fn decompress() {
+    for vector in VECTORS {
+        let expected = hpcmp::decompress(vector.stream).unwrap();
+        let (mut output, mut output_len) = (ptr::null_mut(), 0);
+        // SAFETY: every pointer is to a live value or buffer of the length given
+        let status = unsafe { hpcmp_decompress(vector.stream.as_ptr(), vector.stream.len(), &mut output, &mut output_len) };
+        assert_eq!(status, HPCMP_OK, "{}", vector.name);
+        // SAFETY: as returned by hpcmp_decompress, and freed only after
+        unsafe {
+            assert_eq!(std::slice::from_raw_parts(output, output_len), &expected[..], "{}", vector.name);
+            hpcmp_free(output, output_len);
+        }
+    }
+}
+
+#[test]
+fn decompress_truncated() {
+    let stream = VECTORS[0].stream;
+    let (mut output, mut output_len) = (ptr::dangling_mut(), 1);
+    // SAFETY: as above
+    let status = unsafe { hpcmp_decompress(stream.as_ptr(), stream.len() - 1, &mut output, &mut output_len) };
+    assert_eq!(status, HPCMP_ERR_UNEXPECTED_EOF);
+    assert!(output.is_null());
+    assert_eq!(output_len, 0);
+}
+
+#[test]
+fn null_pointers() {
+    let stream = VECTORS[0].stream;
+    let (mut output, mut output_len) = (ptr::null_mut(), 0);
+    let (mut consumed, mut produced) = (0, 0);
+    let mut buf = [0; 16];
+    // SAFETY: the functions check for null before using a pointer
+    unsafe {
+        assert_eq!(hpcmp_decompress(stream.as_ptr(), stream.len(), ptr::null_mut(), &mut output_len), HPCMP_ERR_NULL_POINTER);
+        assert_eq!(hpcmp_decompress(stream.as_ptr(), stream.len(), &mut output, ptr::null_mut()), HPCMP_ERR_NULL_POINTER);
+        assert_eq!(hpcmp_decompress(ptr::null(), stream.len(), &mut output, &mut output_len), HPCMP_ERR_NULL_POINTER);
+        assert!(output.is_null());
+        // An empty input may be null, and isn't a stream
+        assert_eq!(hpcmp_decompress(ptr::null(), 0, &mut output, &mut output_len), HPCMP_ERR_UNEXPECTED_EOF);
+
+        let mut written = 0;
+        assert_eq!(hpcmp_decompress_into(stream.as_ptr(), stream.len(), buf.as_mut_ptr(), buf.len(), ptr::null_mut()), HPCMP_ERR_NULL_POINTER);
+        assert_eq!(hpcmp_decompress_into(stream.as_ptr(), stream.len(), ptr::null_mut(), buf.len(), &mut written), HPCMP_ERR_NULL_POINTER);
+
+        let decoder = hpcmp_stream_new();
+        assert_eq!(hpcmp_stream_decode(ptr::null_mut(), stream.as_ptr(), stream.len(), &mut consumed, buf.as_mut_ptr(), buf.len(), &mut produced), HPCMP_ERR_NULL_POINTER);
+        assert_eq!(hpcmp_stream_decode(decoder, stream.as_ptr(), stream.len(), ptr::null_mut(), buf.as_mut_ptr(), buf.len(), &mut produced), HPCMP_ERR_NULL_POINTER);
+        assert_eq!(hpcmp_stream_decode(decoder, ptr::null(), stream.len(), &mut consumed, buf.as_mut_ptr(), buf.len(), &mut produced), HPCMP_ERR_NULL_POINTER);
+        hpcmp_stream_free(decoder);
+
+        hpcmp_free(ptr::null_mut(), 0);
+        hpcmp_stream_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn decompress_into() {
+    for vector in VECTORS {
+        let expected = hpcmp::decompress(vector.stream).unwrap();
+        let mut buf = vec![0; expected.len()];
+        let mut written = usize::MAX;
+        // SAFETY: as above
+        let status = unsafe { hpcmp_decompress_into(vector.stream.as_ptr(), vector.stream.len(), buf.as_mut_ptr(), buf.len(), &mut written) };
+        assert_eq!(status, HPCMP_OK, "{}", vector.name);
+        assert_eq!(&buf[..written], &expected[..], "{}", vector.name);
+
+        let mut short = vec![0; expected.len() - 1];
+        // SAFETY: as above
+        let status = unsafe { hpcmp_decompress_into(vector.stream.as_ptr(), vector.stream.len(), short.as_mut_ptr(), short.len(), &mut written) };
+        assert_eq!(status, HPCMP_ERR_OUTPUT_OVERFLOW, "{}", vector.name);
+    }
+}
+
+/// Feeds each vector a byte at a time into 3 bytes of output at a time.
+#[test]
+fn stream_decode() {
+    for vector in VECTORS {
+        let expected = hpcmp::decompress(vector.stream).unwrap();
+        let decoder = hpcmp_stream_new();
+        assert!(!decoder.is_null());
+        let mut output = vec![];
+        let mut buf = [0; 3];
+        let mut pos = 0;
+        let status = loop {
+            let (mut consumed, mut produced) = (0, 0);
+            let len = (vector.stream.len() - pos).min(1);
+            // SAFETY: `decoder` is live, and the rest are as above
+            let status = unsafe {
+                hpcmp_stream_decode(decoder, vector.stream[pos..].as_ptr(), len, &mut consumed, buf.as_mut_ptr(), buf.len(), &mut produced)
+            };
+            pos += consumed;
+            output.extend_from_slice(&buf[..produced]);
+            if status != HPCMP_OK || (consumed == 0 && produced == 0) {
+                break status;
+            }
+        };
+        // SAFETY: made above, and freed only here
+        unsafe { hpcmp_stream_free(decoder) };
+        assert_eq!(status, HPCMP_STREAM_END, "{}", vector.name);
+        assert_eq!(output, expected, "{}", vector.name);
+    }
+}
+
+/// A stream cut short runs out of input without ever ending.
+#[test]
+fn stream_decode_truncated() {
+    let stream = &VECTORS[0].stream[..VECTORS[0].stream.len() - 1];
+    let decoder = hpcmp_stream_new();
+    let (mut consumed, mut produced) = (0, 0);
+    let mut buf = [0; 64];
+    // SAFETY: as above
+    let status = unsafe { hpcmp_stream_decode(decoder, stream.as_ptr(), stream.len(), &mut consumed, buf.as_mut_ptr(), buf.len(), &mut produced) };
+    assert_eq!(status, HPCMP_OK);
+    assert_eq!(consumed, stream.len());
+    // SAFETY: as above
+    let status = unsafe { hpcmp_stream_decode(decoder, ptr::null(), 0, &mut consumed, buf.as_mut_ptr(), buf.len(), &mut produced) };
+    assert_eq!((status, consumed, produced), (HPCMP_OK, 0, 0));
+    // SAFETY: as above
+    unsafe { hpcmp_stream_free(decoder) };
+}
+
+#[test]
+fn strerror() {
+    for code in [HPCMP_OK, HPCMP_STREAM_END, HPCMP_ERR_NULL_POINTER, HPCMP_ERR_UNEXPECTED_EOF, HPCMP_ERR_OUTPUT_OVERFLOW, 99] {
+        // SAFETY: hpcmp_strerror returns a static, NUL-terminated string
+        let message = unsafe { CStr::from_ptr(hpcmp_strerror(code)) };
+        assert!(!message.to_bytes().is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+//! Decoding an input in chunks must give what decoding it in one go does.
+//! The first byte sets the chunk size.
+
+#![no_main]
+
+use hpcmp::Decoder;
+use libfuzzer_sys::fuzz_target;
+
+/// Output allowed per input, as for `hpcmp run-corpus`.
+const LIMIT: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let (size, input) = match data.split_first() {
+        Some((&size, input)) => (usize::from(size).max(1), input),
+        None                 => return,
+    };
+    let mut out = vec![0; LIMIT];
+    let whole = hpcmp::decompress_into(input, &mut out).map(|len| &out[..len]);
+
+    let mut decoder = Decoder::new();
+    let mut chunked = vec![];
+    for chunk in input.chunks(size) {
+        let result = decoder.decode_to_vec(chunk, &mut chunked);
+        // Past the limit within this chunk, the whole decode stopped first
+        if chunked.len() > LIMIT {
+            assert_eq!(whole, Err(hpcmp::Error::OutputOverflow));
+            return;
+        }
+        if let Err(e) = result {
+            assert_eq!(whole, Err(e));
+            return;
+        }
+    }
+    match decoder.is_done() {
+        true  => assert_eq!(whole, Ok(&chunked[..])),
+        false => assert_eq!(whole, Err(hpcmp::Error::UnexpectedEof)),
+    }
+});
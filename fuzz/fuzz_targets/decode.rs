@@ -0,0 +1,14 @@
+//! Any input must decode or fail cleanly, within a bounded output.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+/// Output allowed per input, as for `hpcmp run-corpus`, so that a stream
+/// that expands enormously fails rather than running out of memory.
+const LIMIT: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let mut out = vec![0; LIMIT];
+    let _ = hpcmp::decompress_into(data, &mut out);
+});
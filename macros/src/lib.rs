@@ -0,0 +1,58 @@
+//! Compile-time decompression of embedded resources.
+
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use proc_macro2::Literal;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// Decompresses a file at compile time, expanding to a `&'static [u8]` of
+/// its decompressed contents.
+///
+/// The path is relative to the root of the invoking crate, i.e. the
+/// directory holding its `Cargo.toml`.
+///
+/// ```ignore
+/// static LOGO: &[u8] = hpcmp_macros::hpcmp_include!("res/logo.cmp");
+/// ```
+///
+/// A file that can't be read or doesn't decompress is a compile error:
+///
+/// ```compile_fail
+/// static MISSING: &[u8] = hpcmp_macros::hpcmp_include!("res/missing.cmp");
+/// ```
+///
+/// ```compile_fail
+/// static NOT_A_STREAM: &[u8] = hpcmp_macros::hpcmp_include!("Cargo.toml");
+/// ```
+#[proc_macro]
+pub fn hpcmp_include(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    match include(&path) {
+        Ok(tokens) => tokens.into(),
+        Err(e)     => e.to_compile_error().into(),
+    }
+}
+
+fn include(path: &LitStr) -> syn::Result<proc_macro2::TokenStream> {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+    let full = root.join(path.value());
+    let compressed = std::fs::read(&full).map_err(|e| {
+        syn::Error::new(path.span(), format!("couldn't read {}: {}", full.display(), e))
+    })?;
+    let data = hpcmp::decompress(&compressed).map_err(|e| {
+        syn::Error::new(path.span(), format!("couldn't decompress {}: {}", full.display(), e))
+    })?;
+
+    // Including the compressed file makes rustc rebuild when it changes
+    let full = full.to_string_lossy();
+    let data = Literal::byte_string(&data);
+    Ok(quote! {
+        {
+            const _: &[u8] = include_bytes!(#full);
+            const DATA: &[u8] = #data;
+            DATA
+        }
+    })
+}
@@ -0,0 +1,16 @@
+//! `hpcmp_include!` must expand to what a stream decompresses to, usable
+//! anywhere a `&'static [u8]` constant is.
+
+use hpcmp_macros::hpcmp_include;
+
+static TEXT: &[u8] = hpcmp_include!("../tests/corpus/text.cmp");
+const TWO_BYTES: &[u8] = hpcmp_include!("../tests/corpus/two_bytes.cmp");
+
+#[test]
+fn includes() {
+    assert!(TEXT == hpcmp::decompress(include_bytes!("../../tests/corpus/text.cmp")).unwrap());
+    assert_eq!(TEXT.len(), 6000);
+    assert_eq!(TWO_BYTES, hpcmp::decompress(include_bytes!("../../tests/corpus/two_bytes.cmp")).unwrap());
+    let zeros = hpcmp_include!("../tests/corpus/zeros.cmp");
+    assert!(zeros.len() == 20000 && zeros.iter().all(|&b| b == 0));
+}
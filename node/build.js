@@ -0,0 +1,14 @@
+'use strict';
+
+// Copies the library cargo built to hpcmp.node, under the name it has on
+// this platform.
+const fs = require('fs');
+const path = require('path');
+
+const names = {
+  darwin: 'libhpcmp_node.dylib',
+  win32: 'hpcmp_node.dll',
+};
+const target = process.env.CARGO_TARGET_DIR || path.join(__dirname, '..', 'target');
+const built = path.join(target, 'release', names[process.platform] || 'libhpcmp_node.so');
+fs.copyFileSync(built, path.join(__dirname, 'hpcmp.node'));
@@ -0,0 +1,11 @@
+'use strict';
+
+const native = require('./hpcmp.node');
+const { createDecompressStream } = require('./stream');
+
+module.exports = {
+  decompress: native.decompress,
+  decompressAsync: native.decompressAsync,
+  StreamDecoder: native.StreamDecoder,
+  createDecompressStream,
+};
@@ -0,0 +1,190 @@
+//! Node.js bindings to the hpcmp decoder, built with napi-rs.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use hpcmp::Decoder;
+
+fn napi_error(e: hpcmp::Error) -> Error {
+    Error::new(Status::InvalidArg, e.to_string())
+}
+
+/// Decompresses a complete stream held in a `Buffer`.
+#[napi]
+pub fn decompress(input: Buffer) -> Result<Buffer> {
+    hpcmp::decompress(&input).map(Buffer::from).map_err(napi_error)
+}
+
+pub struct DecompressTask {
+    input: Buffer,
+}
+
+impl Task for DecompressTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Vec<u8>> {
+        hpcmp::decompress(&self.input).map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+/// Decompresses a complete stream on the libuv thread pool, resolving to
+/// a `Buffer` without blocking the event loop.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn decompress_async(input: Buffer) -> AsyncTask<DecompressTask> {
+    AsyncTask::new(DecompressTask{ input })
+}
+
+/// Locks `decoder`, whether or not a task panicked holding it.
+fn lock(decoder: &Mutex<Decoder>) -> MutexGuard<'_, Decoder> {
+    decoder.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Decodes `chunk`, returning the output it completes.
+fn push(decoder: &Mutex<Decoder>, chunk: &[u8]) -> std::result::Result<Vec<u8>, hpcmp::Error> {
+    let mut out = vec![];
+    lock(decoder).decode_to_vec(chunk, &mut out)?;
+    Ok(out)
+}
+
+/// Fails unless the stream has reached its end-of-file marker.
+fn finish(decoder: &Mutex<Decoder>) -> std::result::Result<(), hpcmp::Error> {
+    if lock(decoder).is_done() {
+        Ok(())
+    } else {
+        Err(hpcmp::Error::UnexpectedEof)
+    }
+}
+
+/// Incremental decoder for input that arrives in chunks; see `stream.js`
+/// for a `Transform` stream built on it.
+///
+/// The decoder is shared with the tasks `pushAsync` and `finishAsync` run
+/// on the libuv thread pool. Calls are expected one at a time, each waiting
+/// for the one before, as a `Transform` stream makes them.
+#[napi]
+pub struct StreamDecoder {
+    decoder: Arc<Mutex<Decoder>>,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> StreamDecoder {
+        StreamDecoder::new()
+    }
+}
+
+pub struct PushTask {
+    decoder: Arc<Mutex<Decoder>>,
+    chunk: Buffer,
+}
+
+impl Task for PushTask {
+    type Output = Vec<u8>;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Vec<u8>> {
+        push(&self.decoder, &self.chunk).map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Vec<u8>) -> Result<Buffer> {
+        Ok(output.into())
+    }
+}
+
+pub struct FinishTask {
+    decoder: Arc<Mutex<Decoder>>,
+}
+
+impl Task for FinishTask {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> Result<()> {
+        finish(&self.decoder).map_err(napi_error)
+    }
+
+    fn resolve(&mut self, _env: Env, _output: ()) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[napi]
+impl StreamDecoder {
+    #[napi(constructor)]
+    pub fn new() -> StreamDecoder {
+        StreamDecoder{ decoder: Arc::new(Mutex::new(Decoder::new())) }
+    }
+
+    /// Decodes `chunk`, returning the output it completes. Input after the
+    /// end of the stream is ignored.
+    #[napi]
+    pub fn push(&mut self, chunk: Buffer) -> Result<Buffer> {
+        push(&self.decoder, &chunk).map(Buffer::from).map_err(napi_error)
+    }
+
+    /// Like `push`, but decodes on the libuv thread pool, resolving to the
+    /// output without blocking the event loop.
+    #[napi(ts_return_type = "Promise<Buffer>")]
+    pub fn push_async(&self, chunk: Buffer) -> AsyncTask<PushTask> {
+        AsyncTask::new(PushTask{ decoder: self.decoder.clone(), chunk })
+    }
+
+    /// Throws if the stream has not reached its end-of-file marker.
+    #[napi]
+    pub fn finish(&self) -> Result<()> {
+        finish(&self.decoder).map_err(napi_error)
+    }
+
+    /// Like `finish`, but as a task on the libuv thread pool, rejecting
+    /// if the stream has not reached its end-of-file marker.
+    #[napi(ts_return_type = "Promise<void>")]
+    pub fn finish_async(&self) -> AsyncTask<FinishTask> {
+        AsyncTask::new(FinishTask{ decoder: self.decoder.clone() })
+    }
+
+    /// True once the end-of-file marker has been decoded.
+    #[napi(getter)]
+    pub fn done(&self) -> bool {
+        lock(&self.decoder).is_done()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hpcmp::VECTORS;
+
+    #[test]
+    fn push_and_finish() {
+        for vector in VECTORS {
+            let expected = hpcmp::decompress(vector.stream).unwrap();
+            for chunk_len in [1, 7, vector.stream.len()] {
+                let decoder = Mutex::new(Decoder::new());
+                let mut out = vec![];
+                for chunk in vector.stream.chunks(chunk_len) {
+                    assert!(finish(&decoder).is_err(), "{} finished early", vector.name);
+                    out.extend(push(&decoder, chunk).unwrap());
+                }
+                finish(&decoder).unwrap();
+                assert!(out == expected, "{} in chunks of {}", vector.name, chunk_len);
+                // Anything after the end is ignored
+                assert_eq!(push(&decoder, &[0xff; 4]).unwrap(), b"");
+            }
+        }
+    }
+
+    #[test]
+    fn push_truncated() {
+        let stream = VECTORS[0].stream;
+        let decoder = Mutex::new(Decoder::new());
+        push(&decoder, &stream[..stream.len() - 1]).unwrap();
+        assert_eq!(finish(&decoder), Err(hpcmp::Error::UnexpectedEof));
+    }
+}
@@ -0,0 +1,21 @@
+'use strict';
+
+const { Transform } = require('stream');
+const { StreamDecoder } = require('./hpcmp.node');
+
+// Returns a Transform stream that decompresses the bytes written to it,
+// decoding on the libuv thread pool rather than the event loop.
+function createDecompressStream(options) {
+  const decoder = new StreamDecoder();
+  return new Transform({
+    ...options,
+    transform(chunk, encoding, callback) {
+      decoder.pushAsync(chunk).then((output) => callback(null, output), callback);
+    },
+    flush(callback) {
+      decoder.finishAsync().then(() => callback(), callback);
+    },
+  });
+}
+
+module.exports = { createDecompressStream };
@@ -0,0 +1,64 @@
+'use strict';
+
+// Smoke tests of the built module on some of the crate's known vectors;
+// run `npm run build` first.
+const assert = require('assert');
+const crypto = require('crypto');
+const fs = require('fs');
+const path = require('path');
+const { Readable } = require('stream');
+const { pipeline } = require('stream/promises');
+const test = require('node:test');
+
+const hpcmp = require('.');
+
+// From src/vectors.rs
+const vectors = [
+  ['kwkwk', 'e33cdf9c7f7120b98e8c78408953e07f2ecd183006b5606df349b4c212acf43e'],
+  ['resets', '02ce94f0e6159ccf92553f10bdb10f43063c671012d081c837121c6fe491807e'],
+  ['widths', 'dbde82e4cf8354640eda937937e15db3dafd7bb4f28ff492c7f28cd620bafd74'],
+].map(([name, sha256]) => ({
+  name,
+  sha256,
+  stream: fs.readFileSync(path.join(__dirname, '..', 'src', 'vectors', `${name}.cmp`)),
+}));
+
+const sha256 = (data) => crypto.createHash('sha256').update(data).digest('hex');
+
+test('decompress', () => {
+  for (const { name, sha256: expected, stream } of vectors) {
+    assert.strictEqual(sha256(hpcmp.decompress(stream)), expected, name);
+  }
+  assert.throws(() => hpcmp.decompress(vectors[0].stream.subarray(0, -1)));
+});
+
+test('decompressAsync', async () => {
+  for (const { name, sha256: expected, stream } of vectors) {
+    assert.strictEqual(sha256(await hpcmp.decompressAsync(stream)), expected, name);
+  }
+  await assert.rejects(hpcmp.decompressAsync(vectors[0].stream.subarray(0, -1)));
+});
+
+// Decompresses `stream` fed to createDecompressStream `chunkLen` bytes at a time
+async function viaStream(stream, chunkLen) {
+  const chunks = [];
+  for (let i = 0; i < stream.length; i += chunkLen) {
+    chunks.push(stream.subarray(i, i + chunkLen));
+  }
+  const output = [];
+  await pipeline(Readable.from(chunks), hpcmp.createDecompressStream(), async function* (source) {
+    for await (const chunk of source) {
+      output.push(chunk);
+    }
+  });
+  return Buffer.concat(output);
+}
+
+test('createDecompressStream', async () => {
+  for (const { name, sha256: expected, stream } of vectors) {
+    for (const chunkLen of [1, 7, stream.length]) {
+      assert.strictEqual(sha256(await viaStream(stream, chunkLen)), expected, `${name} in chunks of ${chunkLen}`);
+    }
+  }
+  await assert.rejects(viaStream(vectors[0].stream.subarray(0, -1), 5));
+});
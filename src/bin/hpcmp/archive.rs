@@ -0,0 +1,103 @@
+//! Compression of outputs for archival, with `--post-compress`, and of
+//! inputs stored compressed, with `--pre-decompress`.
+
+use std::ffi::OsString;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Formats `--post-compress` can write.
+pub const NAMES: &[&str] = &["zstd", "xz"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Codec {
+    Zstd,
+    Xz,
+    Gzip,
+}
+
+impl Codec {
+    pub fn from_name(name: &str) -> Option<Codec> {
+        match name {
+            "zstd" => Some(Codec::Zstd),
+            "xz"   => Some(Codec::Xz),
+            _      => None,
+        }
+    }
+
+    /// `path` with this codec's extension appended.
+    pub fn path(self, path: &Path) -> PathBuf {
+        let mut name = OsString::from(path);
+        name.push(match self {
+            Codec::Zstd => ".zst",
+            Codec::Xz   => ".xz",
+            Codec::Gzip => ".gz",
+        });
+        name.into()
+    }
+
+    /// Compresses `data` at the codec's default level.
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Zstd => zstd::encode_all(data, 0),
+            Codec::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(vec![], 6);
+                encoder.write_all(data)?;
+                encoder.finish()
+            },
+            Codec::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            },
+        }
+    }
+
+    /// Recognises `data` by its magic number. A compressed stream always
+    /// starts with the byte 0x01, so it can't be mistaken for any of these.
+    pub fn detect(data: &[u8]) -> Option<Codec> {
+        if data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Codec::Zstd)
+        } else if data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Codec::Xz)
+        } else if data.starts_with(&[0x1f, 0x8b]) {
+            Some(Codec::Gzip)
+        } else {
+            None
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = vec![];
+        match self {
+            Codec::Zstd => return zstd::decode_all(data),
+            Codec::Xz   => xz2::read::XzDecoder::new_multi_decoder(data).read_to_end(&mut out)?,
+            Codec::Gzip => flate2::read::MultiGzDecoder::new(data).read_to_end(&mut out)?,
+        };
+        Ok(out)
+    }
+}
+
+/// Recognises a tar archive by the `ustar` magic in its first header.
+pub fn is_tar(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar")
+}
+
+/// Calls `f` with the path and contents of each regular file in the tar
+/// archive `data` whose path matches `pattern`.
+pub fn tar_members(data: &[u8], pattern: &glob::Pattern, mut f: impl FnMut(&Path, &[u8])) -> io::Result<()> {
+    let mut archive = tar::Archive::new(data);
+    let mut contents = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        if pattern.matches_path(&path) {
+            contents.clear();
+            entry.read_to_end(&mut contents)?;
+            f(&path, &contents);
+        }
+    }
+    Ok(())
+}
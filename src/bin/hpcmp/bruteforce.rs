@@ -0,0 +1,320 @@
+//! Guessing how an unfamiliar flavour of the format lays out its codes, for
+//! `hpcmp bruteforce`.
+//!
+//! Every combination of bit order, initial code width, command mapping and
+//! literal bias is tried on a prefix of the stream with a small decoder of
+//! its own, as the library's only varies what [`hpcmp::CodeMap`] covers.
+//! Whatever decodes furthest, most cleanly, to the most plausible output
+//! ranks first. The dictionary is taken to work as it does in the instrument
+//! firmware, resets to realign to a byte as there and codes to widen only
+//! when told to.
+
+use std::fmt::Write;
+
+use hpcmp::{DICT_LEN, MAX_PREV_LEN};
+
+use crate::scan::Output;
+
+/// Initial code widths tried, the firmware's first.
+const WIDTHS: &[u8] = &[9, 8, 10, 11, 12];
+
+/// Literal codes start at one of these. With 0 the commands follow the
+/// literals, otherwise they fill the codes below them.
+const BIASES: &[u32] = &[8, 0, 4, 16];
+
+/// Codes wider than this are taken as a wrong guess, well short of the
+/// widest the decoder reads.
+const MAX_GUESSED_WIDTH: u8 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitOrder {
+    /// Codes are packed from the least significant bit of each byte, as in
+    /// the firmware.
+    Lsb,
+    Msb,
+}
+
+impl BitOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            BitOrder::Lsb => "lsb",
+            BitOrder::Msb => "msb",
+        }
+    }
+}
+
+/// One guess at the layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hypothesis {
+    pub bit_order: BitOrder,
+    pub initial_width: u8,
+    /// Code of the literal byte 0.
+    pub value_bias: u32,
+    /// Codes from here up to `command_limit` are commands.
+    pub command_base: u32,
+    pub command_limit: u32,
+    /// Code of the first dictionary entry.
+    pub index_base: u32,
+    pub reset: u32,
+    pub widen: u32,
+    pub end: u32,
+}
+
+/// How far a hypothesis got.
+pub struct Attempt {
+    pub hypothesis: Hypothesis,
+    /// Bits of the prefix decoded before it failed or ended.
+    pub bits: u64,
+    pub codes: u64,
+    pub output: Vec<u8>,
+    /// Reached the end of the stream.
+    pub complete: bool,
+    /// Why it stopped, if it failed.
+    pub failure: Option<&'static str>,
+    /// Commands it didn't know.
+    pub anomalies: u64,
+    /// How plausible it is, from 0 to 1.
+    pub score: f64,
+}
+
+/// Every hypothesis, with the firmware's layout first, so that it wins a
+/// tie. Only codes within the first four of the commands are tried as
+/// reset, widen and end, and the reset must be what the prefix starts with.
+pub fn hypotheses(prefix: &[u8]) -> Vec<Hypothesis> {
+    let mut all = vec![];
+    for bit_order in [BitOrder::Lsb, BitOrder::Msb] {
+        for &initial_width in WIDTHS {
+            for &value_bias in BIASES {
+                let (command_base, command_limit, index_base) = match value_bias {
+                    0    => (0x100, 0x108, 0x108),
+                    bias => (0, bias, bias + 0x100),
+                };
+                let first = match Bits::new(bit_order).read(prefix, 0, initial_width) {
+                    Some(code) => code,
+                    None       => continue,
+                };
+                if !(command_base..(command_base + 4).min(command_limit)).contains(&first) {
+                    continue;
+                }
+                let others = (command_base..(command_base + 4).min(command_limit)).filter(|&code| code != first);
+                for widen in others.clone() {
+                    for end in others.clone().filter(|&code| code != widen) {
+                        all.push(Hypothesis{
+                            bit_order, initial_width, value_bias, command_base, command_limit, index_base,
+                            reset: first, widen, end,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    // The firmware's own layout ranks first among equals
+    all.sort_by_key(|h| (h.widen != h.reset + 1, h.end != h.reset + 2));
+    all
+}
+
+/// Tries every hypothesis on `prefix`, best first.
+pub fn rank(prefix: &[u8]) -> Vec<Attempt> {
+    let mut attempts: Vec<Attempt> = hypotheses(prefix).into_iter().map(|h| attempt(h, prefix)).collect();
+    // Stable, so ties stay in the order tried
+    attempts.sort_by(|a, b| b.score.total_cmp(&a.score));
+    attempts
+}
+
+/// Decodes `prefix` as `h` says, as far as it goes.
+pub fn attempt(h: Hypothesis, prefix: &[u8]) -> Attempt {
+    let mut attempt = Attempt{
+        hypothesis: h,
+        bits: 0,
+        codes: 0,
+        output: vec![],
+        complete: false,
+        failure: None,
+        anomalies: 0,
+        score: 0.0,
+    };
+    attempt.failure = decode(h, prefix, &mut attempt).err();
+    let mut output = Output::new();
+    output.add(&attempt.output);
+    let prefix_bits = prefix.len() as f64 * 8.0;
+    let decoded = (attempt.bits as f64 / prefix_bits.max(1.0)).min(1.0);
+    let clean = 1.0 - (attempt.anomalies as f64 * 100.0 / attempt.codes.max(1) as f64).min(1.0);
+    attempt.score = 0.6 * decoded + 0.25 * output.structure() + 0.15 * clean;
+    attempt
+}
+
+#[derive(Clone, Copy)]
+enum Code {
+    Command(u32),
+    Value(u8),
+    Index(usize),
+}
+
+/// Decodes into `attempt`, returning why it stopped if it failed. Running
+/// out of prefix isn't a failure.
+fn decode(h: Hypothesis, prefix: &[u8], attempt: &mut Attempt) -> Result<(), &'static str> {
+    let bits = Bits::new(h.bit_order);
+    let classify = |code: u32| match code {
+        c if (h.command_base..h.command_limit).contains(&c) => Code::Command(c),
+        c if c >= h.index_base                             => Code::Index((c - h.index_base) as usize),
+        c                                                  => Code::Value((c - h.value_bias) as u8),
+    };
+    // Entries as (previous code, last byte)
+    let mut dictionary: Vec<(Code, u8)> = Vec::with_capacity(DICT_LEN);
+    let mut width = h.initial_width;
+    let mut pos = 0;
+    let mut prev: Option<Code> = None;
+    // As in the firmware, the length of the last string a code other than a
+    // block's first decoded to, even in an earlier block
+    let mut prev_len = 0;
+    let mut started = false;
+    let mut last = false;
+    loop {
+        let code = match bits.read(prefix, pos, width) {
+            Some(code) => code,
+            None       => return Ok(()),
+        };
+        pos += u64::from(width);
+        attempt.codes += 1;
+        let code = classify(code);
+        if last {
+            return match code {
+                Code::Value(byte) => {
+                    attempt.output.push(byte);
+                    attempt.bits = pos;
+                    attempt.complete = true;
+                    Ok(())
+                },
+                _ => Err("no literal after the end"),
+            };
+        }
+        match code {
+            Code::Command(c) if c == h.reset => {
+                dictionary.clear();
+                width = h.initial_width;
+                pos = pos.div_ceil(8) * 8;
+                prev = None;
+                started = true;
+            },
+            _ if !started => return Err("no reset at the start"),
+            Code::Command(c) if c == h.widen => {
+                width += 1;
+                if width > MAX_GUESSED_WIDTH {
+                    return Err("codes too wide");
+                }
+            },
+            Code::Command(c) if c == h.end => {
+                pos = pos.div_ceil(8) * 8;
+                last = true;
+            },
+            Code::Command(_) => attempt.anomalies += 1,
+            Code::Value(byte) if prev.is_none() => {
+                attempt.output.push(byte);
+                prev = Some(code);
+            },
+            _ if prev.is_none() => return Err("a block starting with something other than a literal"),
+            code => {
+                let len = attempt.output.len();
+                let previous = prev.unwrap();
+                let first = match code {
+                    Code::Index(index) if index > dictionary.len() => return Err("an index past the end of the dictionary"),
+                    // Not added yet: the previous string and its own first
+                    // byte
+                    Code::Index(index) if index == dictionary.len() => {
+                        let first = expand(&dictionary, previous, &mut attempt.output);
+                        attempt.output.push(first);
+                        first
+                    },
+                    code => expand(&dictionary, code, &mut attempt.output),
+                };
+                if prev_len < MAX_PREV_LEN && dictionary.len() < DICT_LEN {
+                    dictionary.push((previous, first));
+                }
+                prev = Some(code);
+                prev_len = attempt.output.len() - len;
+            },
+        }
+        attempt.bits = pos;
+    }
+}
+
+/// Appends the string for `code`, a literal or an entry in `dictionary`,
+/// returning its first byte.
+fn expand(dictionary: &[(Code, u8)], mut code: Code, output: &mut Vec<u8>) -> u8 {
+    let start = output.len();
+    while let Code::Index(i) = code {
+        let (next, byte) = dictionary[i];
+        output.push(byte);
+        code = next;
+    }
+    if let Code::Value(byte) = code {
+        output.push(byte);
+    }
+    output[start..].reverse();
+    output[start]
+}
+
+/// Reads codes at any bit position, in either order.
+struct Bits(BitOrder);
+
+impl Bits {
+    fn new(order: BitOrder) -> Bits {
+        Bits(order)
+    }
+
+    /// The `width`-bit code at bit `pos` of `data`, unless `data` ends first.
+    fn read(&self, data: &[u8], pos: u64, width: u8) -> Option<u32> {
+        let end = pos + u64::from(width);
+        if end > data.len() as u64 * 8 {
+            return None;
+        }
+        let mut code = 0;
+        for (i, bit) in (pos..end).enumerate() {
+            let byte = data[(bit / 8) as usize];
+            match self.0 {
+                BitOrder::Lsb => code |= u32::from(byte >> (bit % 8) & 1) << i,
+                BitOrder::Msb => code = code << 1 | u32::from(byte >> (7 - bit % 8) & 1),
+            }
+        }
+        Some(code)
+    }
+}
+
+/// `attempt`'s layout as a variant spec, with how well the runners-up did
+/// as comments.
+pub fn spec(name: &str, prefix_len: usize, attempts: &[Attempt]) -> String {
+    let best = &attempts[0];
+    let h = &best.hypothesis;
+    let mut out = String::new();
+    let _ = writeln!(out, "# hpcmp bruteforce: the best of {} layouts tried on the first {} bytes of {}", attempts.len(), prefix_len, name);
+    let _ = writeln!(out, "# best: {}, {}", summary(h), describe(best));
+    for runner_up in attempts.iter().skip(1).take(3) {
+        let _ = writeln!(out, "# next: {}, {}", summary(&runner_up.hypothesis), describe(runner_up));
+    }
+    let _ = writeln!(out, "bit_order = \"{}\"", h.bit_order.name());
+    let _ = writeln!(out, "initial_width = {}", h.initial_width);
+    let _ = writeln!(out, "value_bias = {:#x}", h.value_bias);
+    let _ = writeln!(out, "index_base = {:#x}", h.index_base);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "[commands]");
+    let _ = writeln!(out, "first = {:#x}", h.command_base);
+    let _ = writeln!(out, "limit = {:#x}", h.command_limit);
+    let _ = writeln!(out, "reset = {:#x}", h.reset);
+    let _ = writeln!(out, "widen = {:#x}", h.widen);
+    let _ = writeln!(out, "end = {:#x}", h.end);
+    out
+}
+
+fn summary(h: &Hypothesis) -> String {
+    format!("{} first, {} bits, literals from {:#x}, reset {:#x}, widen {:#x}, end {:#x}",
+            h.bit_order.name(), h.initial_width, h.value_bias, h.reset, h.widen, h.end)
+}
+
+fn describe(attempt: &Attempt) -> String {
+    let how = match (attempt.complete, attempt.failure) {
+        (true, _)        => "reaching the end".to_string(),
+        (_, Some(why))   => format!("then failing on {}", why),
+        (_, None)        => "without failing".to_string(),
+    };
+    format!("score {:.2}: {} codes to {} bytes, {}", attempt.score, attempt.codes, attempt.output.len(), how)
+}
@@ -0,0 +1,165 @@
+//! The `<image>.hpcmp-index` file kept next to an image, holding what `scan`
+//! found in it, so that carving, comparing or patching it again doesn't
+//! mean scanning it again.
+//!
+//! It is plain text, like a stream index: a header line, the SHA-256 of the
+//! image and the options it was scanned with, then for each stream found a
+//! line giving its offset, how long it is, its score and how it ended,
+//! followed by a line for each of its reset points, and an `end` line. A
+//! cache of an image that has changed since, or of a scan with other
+//! options, is scanned again and replaced.
+
+use std::convert::TryFrom;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use hpcmp::ResetPoint;
+
+use crate::digest;
+use crate::scan::{self, Found};
+
+const MAGIC: &str = "hpcmp-scan 1";
+
+/// A scan of an image, as saved.
+pub struct Cached {
+    pub sha256: String,
+    pub options: scan::Options,
+    pub found: Vec<Found>,
+}
+
+impl Cached {
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "{}", MAGIC)?;
+        writeln!(w, "image {}", self.sha256)?;
+        writeln!(w, "options {} {} {} {}", self.options.align, self.options.min_output, self.options.partial as u8, self.options.min_score)?;
+        for found in &self.found {
+            writeln!(w, "stream {} {} {} {} {}", found.offset, found.input_len, found.output_len, found.score, error_name(found.error.as_ref()))?;
+            for point in &found.resets {
+                writeln!(w, "{} {} {} {}", point.bit_offset, point.input_offset, point.output_offset, point.prev_len)?;
+            }
+        }
+        writeln!(w, "end")
+    }
+
+    pub fn read_from(r: impl BufRead) -> io::Result<Cached> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad scan cache: {}", what));
+        let number = |field: Option<&str>| -> io::Result<u64> {
+            field.and_then(|f| f.parse().ok()).ok_or_else(|| invalid("malformed line"))
+        };
+
+        let mut lines = r.lines();
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(invalid("missing header"));
+        }
+        let sha256 = match lines.next().transpose()? {
+            Some(line) if line.starts_with("image ") => line["image ".len()..].to_string(),
+            _ => return Err(invalid("missing image")),
+        };
+        let options = match lines.next().transpose()? {
+            Some(line) if line.starts_with("options ") => {
+                let mut fields = line.split_whitespace().skip(1);
+                scan::Options{
+                    align:      number(fields.next())? as usize,
+                    min_output: number(fields.next())?,
+                    partial:    number(fields.next())? != 0,
+                    min_score:  number(fields.next())? as u32,
+                }
+            },
+            _ => return Err(invalid("missing options")),
+        };
+        let mut found: Vec<Found> = vec![];
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            if line == "end" {
+                return Ok(Cached{ sha256, options, found });
+            }
+            if line.starts_with("stream ") {
+                fields.next();
+                found.push(Found{
+                    offset:     number(fields.next())?,
+                    input_len:  number(fields.next())?,
+                    output_len: number(fields.next())?,
+                    score:      number(fields.next())? as u32,
+                    error:      parse_error(fields).ok_or_else(|| invalid("unknown error"))?,
+                    resets:     vec![],
+                });
+                continue;
+            }
+            let point = ResetPoint{
+                bit_offset:    number(fields.next())?,
+                input_offset:  number(fields.next())?,
+                output_offset: number(fields.next())?,
+                prev_len:      number(fields.next())? as usize,
+            };
+            found.last_mut().ok_or_else(|| invalid("reset point before any stream"))?.resets.push(point);
+        }
+        Err(invalid("truncated"))
+    }
+}
+
+/// Where the cache of the image at `path` is kept.
+pub fn path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(".hpcmp-index");
+    name.into()
+}
+
+/// The cache of `image`, read from `path`, if there is one and it is of
+/// this image as it is now.
+pub fn load(path: &Path, image: &[u8]) -> Option<Cached> {
+    let file = fs::File::open(self::path(path)).ok()?;
+    let cached = Cached::read_from(io::BufReader::new(file)).ok()?;
+    (cached.sha256 == digest::sha256(image)).then_some(cached)
+}
+
+/// What `scan` finds in `image`, read from `path`: the cached scan if it is
+/// of this image with these options, or else a new one, which is cached.
+pub fn scan(path: &Path, image: &[u8], options: scan::Options) -> Vec<Found> {
+    if let Some(cached) = load(path, image).filter(|cached| cached.options == options) {
+        return cached.found;
+    }
+    let cached = Cached{ sha256: digest::sha256(image), options, found: scan::scan(image, options) };
+    // Not being able to keep it, say next to an image on read-only media,
+    // only means scanning again next time
+    let _ = fs::File::create(self::path(path)).and_then(|file| {
+        let mut file = io::BufWriter::new(file);
+        cached.write_to(&mut file)?;
+        file.flush()
+    });
+    cached.found
+}
+
+fn error_name(error: Option<&hpcmp::Error>) -> String {
+    use hpcmp::Error::*;
+    match error {
+        None                                        => "ok".to_string(),
+        Some(MissingStartMarker)                    => "missing_start_marker".to_string(),
+        Some(FirstCodeNotValue)                     => "first_code_not_value".to_string(),
+        Some(FinalCodeNotValue)                     => "final_code_not_value".to_string(),
+        Some(InvalidIndex{ index, dictionary_len }) => format!("invalid_index {} {}", index, dictionary_len),
+        Some(WidthOverflow(width))                  => format!("width_overflow {}", width),
+        Some(UnexpectedEof)                         => "unexpected_eof".to_string(),
+        Some(OutputOverflow)                        => "output_overflow".to_string(),
+    }
+}
+
+/// The error [`error_name`] wrote in `fields`, or `None` if it isn't one.
+fn parse_error<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<Option<hpcmp::Error>> {
+    use hpcmp::Error::*;
+    let name = fields.next()?;
+    let mut number = || fields.next().and_then(|f| f.parse::<usize>().ok());
+    Some(Some(match name {
+        "ok"                   => return Some(None),
+        "missing_start_marker" => MissingStartMarker,
+        "first_code_not_value" => FirstCodeNotValue,
+        "final_code_not_value" => FinalCodeNotValue,
+        "invalid_index"        => InvalidIndex{ index: number()?, dictionary_len: number()? },
+        "width_overflow"       => WidthOverflow(u8::try_from(number()?).ok()?),
+        "unexpected_eof"       => UnexpectedEof,
+        "output_overflow"      => OutputOverflow,
+        _                      => return None,
+    }))
+}
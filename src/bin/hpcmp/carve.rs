@@ -0,0 +1,137 @@
+//! Decompressing every stream in an image into files of their own, for `hpcmp
+//! carve`, without writing the same output twice.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{digest, scan};
+
+pub const NAMES: &[&str] = &["link", "skip", "keep"];
+
+/// What to do with a stream that decodes the same as one carved already.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Duplicates {
+    /// Hard-links its file to the first one's, or writes a copy where links
+    /// can't be made.
+    Link,
+    /// Leaves it out, listing it in the manifest only.
+    Skip,
+    /// Writes a copy.
+    Keep,
+}
+
+impl Duplicates {
+    pub fn from_name(name: &str) -> Option<Duplicates> {
+        match name {
+            "link" => Some(Duplicates::Link),
+            "skip" => Some(Duplicates::Skip),
+            "keep" => Some(Duplicates::Keep),
+            _      => None,
+        }
+    }
+}
+
+/// A stream carved out of an image.
+pub struct Carved {
+    pub offset: u64,
+    pub compressed_len: u64,
+    pub decompressed_len: u64,
+    pub sha256: String,
+    /// Unless skipped as a duplicate.
+    pub file: Option<PathBuf>,
+    /// The offset of the first stream that decoded the same, if not this.
+    pub duplicate_of: Option<u64>,
+    /// Where a disassembler could load its output, making room for every
+    /// stream's output in turn.
+    pub load_address: u64,
+}
+
+/// What the outputs' load addresses are aligned to.
+const PAGE: u64 = 0x1000;
+
+/// Decompresses each stream `found` in `image` to the file `name` gives for
+/// its index and offset, suggesting they are loaded one after another from
+/// `load_base`.
+pub fn carve(image: &[u8], found: Vec<scan::Found>, duplicates: Duplicates, load_base: u64, name: impl Fn(usize, u64) -> PathBuf)
+             -> Result<Vec<Carved>, Box<dyn Error>> {
+    let mut seen: HashMap<String, (u64, PathBuf)> = HashMap::new();
+    let mut carved = vec![];
+    let mut load_address = load_base;
+    for (index, found) in found.into_iter().enumerate() {
+        let output = hpcmp::decompress(&image[found.offset as usize..])?;
+        let sha256 = digest::sha256(&output);
+        let path = name(index, found.offset);
+        let (file, duplicate_of) = match (seen.get(&sha256), duplicates) {
+            (None, _) => {
+                write(&path, &output)?;
+                (Some(path), None)
+            },
+            (Some((first, _)), Duplicates::Keep) => {
+                write(&path, &output)?;
+                (Some(path), Some(*first))
+            },
+            (Some((first, _)), Duplicates::Skip) => (None, Some(*first)),
+            (Some((first, first_path)), Duplicates::Link) => {
+                link(first_path, &path, &output)?;
+                (Some(path), Some(*first))
+            },
+        };
+        if let (Some(file), None) = (&file, duplicate_of) {
+            seen.insert(sha256.clone(), (found.offset, file.clone()));
+        }
+        carved.push(Carved{
+            offset: found.offset,
+            compressed_len: found.input_len,
+            decompressed_len: output.len() as u64,
+            sha256,
+            file,
+            duplicate_of,
+            load_address,
+        });
+        load_address = load_address.checked_add(output.len() as u64).and_then(|end| end.checked_next_multiple_of(PAGE))
+            .ok_or("the outputs don't fit in the address space after the load base")?;
+    }
+    Ok(carved)
+}
+
+/// Writes `output` to `path` as a file of its own. What's there is removed
+/// first, as it may be linked to another output by an earlier run.
+fn write(path: &Path, output: &[u8]) -> io::Result<()> {
+    remove(path)?;
+    fs::write(path, output)
+}
+
+/// Hard-links `path` to `first`, falling back to writing `output` to it.
+fn link(first: &Path, path: &Path, output: &[u8]) -> io::Result<()> {
+    remove(path)?;
+    if let Err(e) = fs::hard_link(first, path) {
+        eprintln!("hpcmp: can't link {} to {}, writing a copy: {}", path.display(), first.display(), e);
+        fs::write(path, output)?;
+    }
+    Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// The manifest of what was carved from `image`, giving each stream's file
+/// by its name in `dir`.
+pub fn manifest(image: &Path, dir: &Path, carved: &[Carved]) -> serde_json::Value {
+    let streams: Vec<_> = carved.iter().map(|carved| serde_json::json!({
+        "offset": carved.offset,
+        "compressed_len": carved.compressed_len,
+        "decompressed_len": carved.decompressed_len,
+        "sha256": carved.sha256,
+        "file": carved.file.as_ref().map(|file| file.strip_prefix(dir).unwrap_or(file).to_string_lossy()),
+        "duplicate_of": carved.duplicate_of,
+        "load_address": carved.load_address,
+    })).collect();
+    serde_json::json!({ "image": image.to_string_lossy(), "streams": streams })
+}
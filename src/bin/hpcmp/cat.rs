@@ -0,0 +1,64 @@
+//! Joining streams into one that decodes to all their outputs in turn, for
+//! `hpcmp cat`, without compressing anything again.
+//!
+//! Each stream but the last is copied up to its end-of-file command. Its
+//! final literal is written as an ordinary code of its last block instead,
+//! then a reset starts the next stream's first block, whose codes are copied
+//! from after its own start marker. Literals have the lowest codes, so the
+//! moved one fits whatever width the block had reached, and being a string
+//! of one byte, it leaves the next block adding entries as it did alone.
+
+use hpcmp::{Code, DecodeObserver, Decoder};
+
+use crate::writer::Writer;
+
+/// Where a stream's codes are: the bit its first block's first code starts
+/// at, its end-of-file command's, with the width of codes by then, and the
+/// bit after its final literal.
+#[derive(Default)]
+struct Bounds {
+    started: bool,
+    first: Option<u64>,
+    eof: Option<(u64, u8)>,
+    end: u64,
+}
+
+impl DecodeObserver for Bounds {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        match code {
+            Code::Command(1) if !self.started => self.started = true,
+            Code::Command(3)                  => self.eof = Some((bit_offset, width)),
+            _ if self.first.is_none()         => self.first = Some(bit_offset),
+            _                                 => (),
+        }
+        self.end = bit_offset + u64::from(width);
+    }
+}
+
+/// A stream that decodes to the outputs of `streams` one after the other,
+/// with that output. Anything after the end of a stream is left out. On
+/// failure, says which stream didn't decode.
+pub fn join(streams: &[&[u8]]) -> Result<(Vec<u8>, Vec<u8>), (usize, hpcmp::Error)> {
+    let mut w = Writer::new();
+    let mut output = vec![];
+    for (i, stream) in streams.iter().enumerate() {
+        let mut bounds = Bounds::default();
+        let mut decoder = Decoder::new();
+        decoder.decode_to_vec_with(stream, &mut output, &mut bounds).map_err(|e| (i, e))?;
+        let (first, (eof, width)) = match (decoder.is_done(), bounds.first, bounds.eof) {
+            (true, Some(first), Some(eof)) => (first, eof),
+            _                              => return Err((i, hpcmp::Error::UnexpectedEof)),
+        };
+        // The first stream's start marker starts the joined stream, and the
+        // others' blocks follow the reset written after the stream before
+        let from = if i == 0 { 0 } else { first };
+        if i == streams.len() - 1 {
+            w.copy(stream, from, bounds.end, u32::from(width));
+            break;
+        }
+        w.copy(stream, from, eof, u32::from(width));
+        w.put(8 + u32::from(output[output.len() - 1]));
+        w.reset();
+    }
+    Ok((w.finish(), output))
+}
@@ -0,0 +1,190 @@
+//! What an output goes through once decoded, whether held whole or written
+//! as it is decoded with `--chunk-size`: the checks of its size and digests
+//! against what it should be, and for each part of it written out, the
+//! filters, the digests printed and `--post-compress`.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+use crate::archive::Codec;
+use crate::checksums::{Checksums, Verifier};
+use crate::container::Stored;
+use crate::diagnose::CheckFailed;
+use crate::digest::{self, Crc32Stream, Sha256Stream};
+use crate::filter::Filters;
+use crate::progress;
+
+/// What the whole of an output must be, and which of its digests are
+/// wanted.
+pub struct Checks<'a> {
+    /// From the length header, `--expected-size` or `--index`.
+    pub expected_size: Option<u64>,
+    pub max_output: Option<u64>,
+    pub expect_sha256: Option<&'a str>,
+    /// From the container or `--block-crcs`.
+    pub crcs: Option<&'a Checksums>,
+    /// What the container says the output is.
+    pub stored: Option<Stored>,
+    pub sha256: bool,
+    pub crc32: bool,
+}
+
+/// The length and digests of an output, those that were wanted.
+#[derive(Clone, Debug, Default)]
+pub struct Digests {
+    pub len: u64,
+    pub sha256: Option<String>,
+    pub crc32: Option<String>,
+}
+
+/// An output checked as it is handed over a piece at a time.
+pub struct Tally<'a> {
+    checks: Checks<'a>,
+    len: u64,
+    sha256: Option<Sha256Stream>,
+    crc32: Option<Crc32Stream>,
+    blocks: Option<Verifier<'a>>,
+}
+
+impl<'a> Tally<'a> {
+    pub fn new(checks: Checks<'a>) -> Tally<'a> {
+        Tally{
+            len: 0,
+            sha256: (checks.sha256 || checks.expect_sha256.is_some()).then(Sha256Stream::default),
+            crc32: (checks.crc32 || checks.stored.is_some()).then(Crc32Stream::default),
+            blocks: checks.crcs.map(Verifier::new),
+            checks,
+        }
+    }
+
+    /// Takes in the next `chunk` of output, failing as soon as there is more
+    /// of it than there should be or a block of it is wrong.
+    pub fn update(&mut self, chunk: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.len += chunk.len() as u64;
+        if self.checks.expected_size.is_some_and(|size| self.len > size) {
+            return Err(size_mismatch(None, self.checks.expected_size));
+        }
+        if let Some(max) = self.checks.max_output.filter(|&max| self.len > max) {
+            return Err(CheckFailed{
+                code: "output_too_large",
+                message: format!("output is more than --max-output {} bytes", max),
+            }.into());
+        }
+        self.sha256.iter_mut().for_each(|digest| digest.update(chunk));
+        self.crc32.iter_mut().for_each(|digest| digest.update(chunk));
+        if let Some(blocks) = &mut self.blocks {
+            blocks.update(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Fails unless the output, now it has all been taken in, is what it
+    /// should be.
+    pub fn finish(self) -> Result<Digests, Box<dyn Error>> {
+        let Tally{ checks, len, sha256, crc32, blocks } = self;
+        if checks.expected_size.is_some_and(|size| size != len) {
+            return Err(size_mismatch(Some(len), checks.expected_size));
+        }
+        let sha256 = sha256.map(Sha256Stream::finish);
+        if let (Some(expected), Some(digest)) = (checks.expect_sha256, &sha256) {
+            if !digest.eq_ignore_ascii_case(expected.trim()) {
+                return Err(CheckFailed{
+                    code: "sha256_mismatch",
+                    message: format!("SHA-256 mismatch: expected {}, got {}", expected, digest),
+                }.into());
+            }
+        }
+        // After the whole output's digest, which a mismatch here may explain
+        if let Some(blocks) = blocks {
+            blocks.finish()?;
+        }
+        let crc32 = crc32.map(Crc32Stream::finish);
+        if let (Some(stored), Some(digest)) = (checks.stored, &crc32) {
+            stored.check(len, digest)?;
+        }
+        Ok(Digests{
+            len,
+            sha256: sha256.filter(|_| checks.sha256),
+            crc32: crc32.filter(|_| checks.crc32),
+        })
+    }
+}
+
+impl Checks<'_> {
+    /// Checks an output held whole.
+    pub fn check(self, data: &[u8]) -> Result<Digests, Box<dyn Error>> {
+        let mut tally = Tally::new(self);
+        tally.update(data)?;
+        tally.finish()
+    }
+}
+
+/// The failure for an output of `len` bytes, or of more than fit, when it
+/// was `expected` to be another size.
+pub fn size_mismatch(len: Option<u64>, expected: Option<u64>) -> Box<dyn Error> {
+    let expected = expected.unwrap_or_default();
+    CheckFailed{
+        code: "size_mismatch",
+        message: match len {
+            Some(len) => format!("output is {} bytes, expected {}", len, expected),
+            None      => format!("output is more than the {} bytes expected", expected),
+        },
+    }.into()
+}
+
+/// What is done to each part of an output on the way to the files it is
+/// written to.
+pub struct Pipeline<'a> {
+    pub filters: Option<&'a Filters>,
+    pub codec: Option<Codec>,
+    /// Whether the SHA-256 of each part, after the filters, is printed, and
+    /// its CRC-32.
+    pub sha256: bool,
+    pub crc32: bool,
+    /// Set when an output goes to stdout, where the digests would be mixed
+    /// into it, so they go to stderr instead.
+    pub to_stdout: bool,
+}
+
+/// A part of an output as it is written.
+pub struct Part<'d> {
+    /// Through the filters and compressed.
+    pub written: Cow<'d, [u8]>,
+    /// Of the part after the filters, to print.
+    pub digests: Digests,
+}
+
+impl Pipeline<'_> {
+    /// What `part` is written as.
+    pub fn run<'d>(&self, part: &'d [u8]) -> Result<Part<'d>, Box<dyn Error>> {
+        let part = match self.filters {
+            Some(filters) => Cow::Owned(filters.run(part)?),
+            None          => Cow::Borrowed(part),
+        };
+        let digests = Digests{
+            len: part.len() as u64,
+            sha256: self.sha256.then(|| digest::sha256(&part)),
+            crc32: self.crc32.then(|| digest::crc32(&part)),
+        };
+        let written = match self.codec {
+            Some(codec) => Cow::Owned(codec.compress(&part)?),
+            None        => part,
+        };
+        Ok(Part{ written, digests })
+    }
+
+    /// Prints the wanted `digests` of what is written to `path`.
+    pub fn print(&self, digests: &Digests, path: &Path) {
+        let wanted = [(self.sha256, &digests.sha256), (self.crc32, &digests.crc32)];
+        for digest in wanted.iter().filter(|(wanted, _)| *wanted).filter_map(|(_, digest)| digest.as_ref()) {
+            let line = format!("{}  {}", digest, path.display());
+            // Nothing is lost if stdout has been closed on these
+            progress::suspend(|| match self.to_stdout {
+                true  => eprintln!("{}", line),
+                false => { let _ = io::Write::write_fmt(&mut io::stdout(), format_args!("{}\n", line)); },
+            });
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! The CRC-32 of each block's output, recorded by `hpcmp compress
+//! --block-crcs` and checked when decompressing, so that a stream damaged
+//! in transfer is pinned to the block it was damaged in rather than only
+//! found to be wrong somewhere.
+//!
+//! They are kept in a container with the member they are of, or for a bare
+//! stream in a text file: a header line, a line for each block giving the
+//! offset in the stream of its first code, the offset and length of its
+//! output and the CRC-32 of that in hex, and an `end` line.
+
+use std::ffi::OsString;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::diagnose::CheckFailed;
+
+const MAGIC: &str = "hpcmp-crcs 1";
+
+/// Where `compress` writes the CRCs of the bare stream at `output`.
+pub fn path(output: &Path) -> PathBuf {
+    let mut name = OsString::from(output);
+    name.push(".crcs");
+    name.into()
+}
+
+/// One block's output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockCrc {
+    /// Byte offset in the stream of the block's first code.
+    pub input_offset: u64,
+    pub output_len: u64,
+    pub crc32: u32,
+}
+
+/// Every block of a stream, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checksums {
+    pub blocks: Vec<BlockCrc>,
+}
+
+impl Checksums {
+    /// The checksums of the blocks of all that `r` reads, which start at
+    /// each of `starts`, given as the offset of a block's first code in the
+    /// stream and of its output, as [`encode::compress_from`] returns them.
+    ///
+    /// [`encode::compress_from`]: crate::encode::compress_from
+    pub fn measure(mut r: impl Read, starts: &[(u64, u64)]) -> io::Result<Checksums> {
+        let mut blocks: Vec<BlockCrc> = starts.iter().map(|&(input_offset, _)| BlockCrc{ input_offset, output_len: 0, crc32: 0 }).collect();
+        let ends: Vec<u64> = starts.iter().skip(1).map(|&(_, output_offset)| output_offset).chain(Some(u64::MAX)).collect();
+        let mut hasher = crc32fast::Hasher::new();
+        let (mut block, mut at) = (0, starts.first().map_or(0, |&(_, output_offset)| output_offset));
+        let mut buf = vec![0; 1 << 16];
+        loop {
+            let mut chunk = match r.read(&mut buf) {
+                Ok(0)                                               => break,
+                Ok(read)                                            => &buf[..read],
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e)                                              => return Err(e),
+            };
+            while !chunk.is_empty() {
+                let len = chunk.len().min((ends[block] - at) as usize);
+                hasher.update(&chunk[..len]);
+                blocks[block].output_len += len as u64;
+                at += len as u64;
+                chunk = &chunk[len..];
+                if at == ends[block] {
+                    blocks[block].crc32 = std::mem::take(&mut hasher).finalize();
+                    block += 1;
+                }
+            }
+        }
+        if let Some(last) = blocks.last_mut() {
+            last.crc32 = hasher.finalize();
+        }
+        Ok(Checksums{ blocks })
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "{}", MAGIC)?;
+        let mut output_offset = 0;
+        for block in &self.blocks {
+            writeln!(w, "{} {} {} {:08x}", block.input_offset, output_offset, block.output_len, block.crc32)?;
+            output_offset += block.output_len;
+        }
+        writeln!(w, "end")
+    }
+
+    pub fn read_from(r: impl BufRead) -> io::Result<Checksums> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad block CRCs: {}", what));
+        let mut lines = r.lines();
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(invalid("missing header"));
+        }
+        let mut blocks = vec![];
+        let mut output_offset = 0;
+        for line in lines {
+            let line = line?;
+            if line == "end" {
+                return Ok(Checksums{ blocks });
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (input_offset, offset, output_len, crc32) = match fields.as_slice() {
+                [input_offset, offset, output_len, crc32] => (input_offset.parse(), offset.parse::<u64>(), output_len.parse(), u32::from_str_radix(crc32, 16)),
+                _                                         => return Err(invalid("malformed line")),
+            };
+            match (input_offset, offset, output_len, crc32) {
+                (Ok(input_offset), Ok(offset), Ok(output_len), Ok(crc32)) if offset == output_offset => {
+                    blocks.push(BlockCrc{ input_offset, output_len, crc32 });
+                    output_offset += output_len;
+                },
+                (_, Ok(_), _, _) => return Err(invalid("blocks not one after the other")),
+                _                => return Err(invalid("malformed line")),
+            }
+        }
+        Err(invalid("truncated"))
+    }
+
+    /// Fails unless `data` is the output these are of.
+    pub fn check_data(&self, data: &[u8]) -> Result<(), CheckFailed> {
+        let mut verifier = Verifier::new(self);
+        verifier.update(data)?;
+        verifier.finish()
+    }
+}
+
+/// Checks output against [`Checksums`] as it is decoded, failing as soon as
+/// a block is seen to be wrong.
+pub struct Verifier<'a> {
+    checksums: &'a Checksums,
+    block: usize,
+    /// Output of the block so far.
+    len: u64,
+    output_offset: u64,
+    hasher: crc32fast::Hasher,
+}
+
+impl Verifier<'_> {
+    pub fn new(checksums: &Checksums) -> Verifier<'_> {
+        Verifier{ checksums, block: 0, len: 0, output_offset: 0, hasher: crc32fast::Hasher::new() }
+    }
+
+    pub fn update(&mut self, mut chunk: &[u8]) -> Result<(), CheckFailed> {
+        while !chunk.is_empty() {
+            let expected = match self.checksums.blocks.get(self.block) {
+                Some(block) => block,
+                None        => return Err(CheckFailed{
+                    code: "size_mismatch",
+                    message: format!("output goes on past the {} bytes its block CRCs cover", self.output_offset),
+                }),
+            };
+            let len = chunk.len().min((expected.output_len - self.len) as usize);
+            self.hasher.update(&chunk[..len]);
+            self.len += len as u64;
+            chunk = &chunk[len..];
+            if self.len == expected.output_len {
+                let crc32 = std::mem::take(&mut self.hasher).finalize();
+                if crc32 != expected.crc32 {
+                    return Err(CheckFailed{
+                        code: "block_crc_mismatch",
+                        message: format!("block {} at stream offset {:#x}, output {:#x}..{:#x}: CRC-32 mismatch: stored {:08x}, got {:08x}",
+                                         self.block, expected.input_offset, self.output_offset, self.output_offset + expected.output_len,
+                                         expected.crc32, crc32),
+                    });
+                }
+                self.block += 1;
+                self.output_offset += self.len;
+                self.len = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails unless the output ended where the last block did.
+    pub fn finish(self) -> Result<(), CheckFailed> {
+        match self.checksums.blocks.get(self.block) {
+            None        => Ok(()),
+            Some(block) => Err(CheckFailed{
+                code: "size_mismatch",
+                message: format!("output ends {} bytes into block {} at stream offset {:#x}, which its CRCs say is {} bytes",
+                                 self.len, self.block, block.input_offset, block.output_len),
+            }),
+        }
+    }
+}
@@ -0,0 +1,819 @@
+use std::ffi::OsString;
+
+use clap::{App, AppSettings, Arg, ArgSettings, SubCommand};
+
+use crate::{archive, carve, encode, interrupt, logging, map, multistream, output, sample};
+
+/// The decompressing forms of the command line, whose inputs and outputs go
+/// in ways the arguments alone don't show.
+const FORMS: &str = "hpcmp [FLAGS] [OPTIONS] <input> <output>
+    hpcmp [FLAGS] [OPTIONS] <input> --output <output>...
+    hpcmp [FLAGS] [OPTIONS] --out-dir <dir> <input>...";
+
+/// The usage lines for `--help` and the man page: the decompressing forms,
+/// then one for each subcommand, made from the arguments it takes.
+pub fn usage() -> String {
+    let app = app(&[], "");
+    let mut usage = FORMS.to_string();
+    for command in &app.p.subcommands {
+        usage.push_str("\n    hpcmp ");
+        usage.push_str(&command.p.meta.name);
+        let options = command.p.flags.iter().map(|flag| (&flag.b, &flag.s, None))
+            .chain(command.p.opts.iter().map(|opt| (&opt.b, &opt.s, Some(&opt.v))));
+        for (base, switched, valued) in options {
+            let mut arg = match (switched.long, switched.short) {
+                (Some(long), _)     => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                (None, None)        => continue,
+            };
+            if let Some(valued) = valued {
+                let name = valued.val_names.as_ref()
+                    .and_then(|names| names.values().next().copied())
+                    .unwrap_or(base.name);
+                arg.push_str(&format!(" <{}>", name.to_lowercase()));
+            }
+            push_arg(&mut usage, &arg, base.is_set(ArgSettings::Required), base.is_set(ArgSettings::Multiple));
+        }
+        for positional in command.p.positionals.values() {
+            let base = &positional.b;
+            push_arg(&mut usage, &format!("<{}>", base.name), base.is_set(ArgSettings::Required), base.is_set(ArgSettings::Multiple));
+        }
+    }
+    usage
+}
+
+/// Adds `arg` to a usage line, in brackets unless it's `required`, and
+/// followed by `...` if it's `multiple`.
+fn push_arg(usage: &mut String, arg: &str, required: bool, multiple: bool) {
+    usage.push(' ');
+    match required {
+        true  => usage.push_str(arg),
+        false => usage.push_str(&format!("[{}]", arg)),
+    }
+    if multiple {
+        usage.push_str("...");
+    }
+}
+
+/// Turns the `module=level` pairs given to `-v`, as in `-v reader=trace` or
+/// `--verbose=reader=trace`, into a `--log-filter`. clap can't take them as
+/// the value of `-v` itself, which would swallow the input following it and
+/// read `-vv` as `-v` given the value `v`.
+pub fn verbose_filters(args: Vec<OsString>) -> Vec<OsString> {
+    let is_verbose = |arg: &str| arg == "--verbose" || arg.len() > 1 && arg.starts_with('-') && arg[1..].bytes().all(|b| b == b'v');
+    let mut rewritten = Vec::with_capacity(args.len());
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let attached = arg.to_str().and_then(|arg| arg.strip_prefix("--verbose=").or_else(|| arg.strip_prefix("-v=")));
+        if let Some(spec) = attached {
+            rewritten.extend(["-v".into(), "--log-filter".into(), spec.into()]);
+            continue;
+        }
+        if arg == "--" {
+            rewritten.push(arg);
+            rewritten.extend(args);
+            break;
+        }
+        let takes_spec = arg.to_str().is_some_and(is_verbose) && args.peek()
+            .and_then(|next| next.to_str())
+            .is_some_and(|next| next.contains('=') && logging::Filter::parse(next).is_ok());
+        rewritten.push(arg);
+        if takes_spec {
+            rewritten.push("--log-filter".into());
+            rewritten.extend(args.next());
+        }
+    }
+    rewritten
+}
+
+/// The command line, offering `presets` as the values of `--preset` if there
+/// are any, with `usage` as its usage lines.
+pub fn app<'b>(presets: &[&'b str], usage: &'b str) -> App<'static, 'b> {
+    let mut preset = Arg::with_name("preset")
+        .long("preset")
+        .value_name("NAME")
+        .takes_value(true)
+        .help("Uses the defaults in this [presets.<NAME>] table of the config file");
+    if !presets.is_empty() {
+        preset = preset.possible_values(presets);
+    }
+
+    App::new("hpcmp")
+        .usage(usage)
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .subcommand(SubCommand::with_name("completions")
+             .about("Prints a completion script for the shell")
+             .arg(Arg::with_name("shell")
+                  .required(true)
+                  .possible_values(&["bash", "zsh", "fish"])))
+        .subcommand(SubCommand::with_name("manpage")
+             .about("Prints this man page, in roff"))
+        .subcommand(SubCommand::with_name("self-test")
+             .about("Decodes the streams built into hpcmp and checks their outputs, to confirm a build works"))
+        .subcommand(SubCommand::with_name("run-corpus")
+             .about("Decodes every file in a directory as the fuzz targets do, failing if any panics")
+             .arg(Arg::with_name("dir")
+                  .required(true)))
+        .subcommand(SubCommand::with_name("gen-stream")
+             .about("Writes a pseudo-random stream that exercises the format's edge cases")
+             .arg(Arg::with_name("output")
+                  .help("Where to write the stream, or stdout if not given"))
+             .arg(Arg::with_name("seed")
+                  .long("seed")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("0")
+                  .help("Seeds the generator; the same seed always gives the same stream"))
+             .arg(Arg::with_name("size")
+                  .long("size")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("64K")
+                  .help("Bytes the stream decodes to, with an optional K, M or G suffix"))
+             .arg(Arg::with_name("expected")
+                  .long("expected")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Also writes what the stream should decode to here")))
+        .subcommand(SubCommand::with_name("gen-data")
+             .about("Writes pseudo-random uncompressed data like a firmware image's code, strings or bitmaps, for measuring the encoder")
+             .arg(Arg::with_name("output")
+                  .help("Where to write the data, or stdout if not given"))
+             .arg(Arg::with_name("profile")
+                  .long("profile")
+                  .value_name("PROFILE")
+                  .takes_value(true)
+                  .required(true)
+                  .possible_values(sample::PROFILE_NAMES)
+                  .help("What the data is like: machine code with literal pools, log and menu strings, or icons, gradients and glyphs"))
+             .arg(Arg::with_name("seed")
+                  .long("seed")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("0")
+                  .help("Seeds the generator; the same seed always gives the same data"))
+             .arg(Arg::with_name("size")
+                  .long("size")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("1M")
+                  .help("Bytes to write, with an optional K, M or G suffix")))
+        .subcommand(SubCommand::with_name("record")
+             .about("Writes a stream's codes as .hpcodes text, a line each, for editing and re-emitting")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("output")
+                  .help("Where to write the codes, or stdout if not given")))
+        .subcommand(SubCommand::with_name("emit")
+             .about("Writes the stream recorded in .hpcodes text")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("output")
+                  .help("Where to write the stream, or stdout if not given")))
+        .subcommand(SubCommand::with_name("compress")
+             .about("Compresses files into a .hpc container that records each one's name, source offset, size and CRC-32")
+             .arg(Arg::with_name("inputs")
+                  .value_name("input")
+                  .required(true)
+                  .multiple(true))
+             .arg(Arg::with_name("output")
+                  .short("o")
+                  .long("output")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Where to write the container, or stdout if not given"))
+             .arg(Arg::with_name("block-len")
+                  .long("block-len")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .help("Starts a new block each time one has decoded to this many bytes; takes a K, M or G suffix"))
+             .arg(Arg::with_name("when-full")
+                  .long("when-full")
+                  .value_name("HOW")
+                  .takes_value(true)
+                  .possible_values(encode::WHEN_FULL_NAMES)
+                  .default_value("freeze")
+                  .help("What to do once a block's dictionary is full: keep its entries until the block ends, start a new block, or start one once the entries stop compressing as well as they did"))
+             .arg(Arg::with_name("level")
+                  .long("level")
+                  .value_name("LEVEL")
+                  .takes_value(true)
+                  .possible_values(encode::LEVEL_NAMES)
+                  .default_value("greedy")
+                  .help("Takes the longest match at each point, or with max parses for the fewest codes, far more slowly"))
+             .arg(Arg::with_name("decisions")
+                  .long("decisions")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Writes a JSON line to FILE for each code, with the matches it was chosen from and why, and for each block ended, for comparing with another encoder's choices"))
+             .arg(Arg::with_name("max-compressed-size")
+                  .long("max-compressed-size")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .help("Fails, saying by how much, if a stream comes to more than this, as for a flash slot it must fit back into; takes a K, M or G suffix. Applies to each member of a container"))
+             .arg(Arg::with_name("block-crcs")
+                  .long("block-crcs")
+                  .help("Records the CRC-32 of each block's output, in the container or with --raw in <output>.crcs, \
+                         so that decompressing says which block is damaged"))
+             .arg(Arg::with_name("source-offset")
+                  .long("source-offset")
+                  .value_name("N")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1)
+                  .help("Where an input came from in an image, decimal or 0x-prefixed hex; given once for each input, in order"))
+             .arg(Arg::with_name("raw")
+                  .long("raw")
+                  .conflicts_with("source-offset")
+                  .help("Writes the bare stream of a single input, without a container")))
+        .subcommand(SubCommand::with_name("recompress")
+             .about("Decompresses a stream and compresses it again, checking the new stream decodes the same and printing how their sizes compare")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("output")
+                  .short("o")
+                  .long("output")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Where to write the new stream; without it, only says how large it would be"))
+             .arg(Arg::with_name("block-len")
+                  .long("block-len")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .help("Starts a new block each time one has decoded to this many bytes; takes a K, M or G suffix. By default, blocks are started as often as the original's were"))
+             .arg(Arg::with_name("when-full")
+                  .long("when-full")
+                  .value_name("HOW")
+                  .takes_value(true)
+                  .possible_values(encode::WHEN_FULL_NAMES)
+                  .default_value("freeze")
+                  .help("What the encoder does once a block's dictionary is full, as for compress"))
+             .arg(Arg::with_name("level")
+                  .long("level")
+                  .value_name("LEVEL")
+                  .takes_value(true)
+                  .possible_values(encode::LEVEL_NAMES)
+                  .default_value("greedy")
+                  .help("How hard the encoder looks for a short encoding, as for compress"))
+             .arg(Arg::with_name("max-compressed-size")
+                  .long("max-compressed-size")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .help("Fails, saying by how much, and writes nothing if the new stream comes to more than this; takes a K, M or G suffix")))
+        .subcommand(SubCommand::with_name("plan")
+             .about("Estimates, from trial compressions of windows of an input, which settings of compress bring it under a size; exits with 1 if none does")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("max-compressed-size")
+                  .long("max-compressed-size")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .required(true)
+                  .help("What the stream must come to no more than; takes a K, M or G suffix"))
+             .arg(Arg::with_name("windows")
+                  .long("windows")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("8")
+                  .help("How many windows to compress, spread evenly across the input"))
+             .arg(Arg::with_name("window")
+                  .long("window")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("1M")
+                  .help("How long each window is; longer ones judge long blocks better, more slowly. Takes a K, M or G suffix"))
+             .arg(Arg::with_name("confirm")
+                  .long("confirm")
+                  .help("Compresses the whole input with the settings recommended, to check they fit")))
+        .subcommand(SubCommand::with_name("cat")
+             .about("Joins streams into one that decodes to their outputs one after the other, without decompressing and compressing them again")
+             .arg(Arg::with_name("inputs")
+                  .required(true)
+                  .multiple(true))
+             .arg(Arg::with_name("output")
+                  .short("o")
+                  .long("output")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Where to write the joined stream, rather than to stdout")))
+        .subcommand(SubCommand::with_name("list")
+             .about("Lists the members of a .hpc container, with their sizes, source offsets and CRC-32s")
+             .arg(Arg::with_name("archive")
+                  .required(true)))
+        .subcommand(SubCommand::with_name("extract")
+             .about("Decompresses members of a .hpc container under their own names, checking each against its stored size and CRC-32")
+             .arg(Arg::with_name("archive")
+                  .required(true))
+             .arg(Arg::with_name("member")
+                  .long("member")
+                  .value_name("NAME")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1)
+                  .help("Extracts only this member, or those matching it as a glob; may be given more than once"))
+             .arg(Arg::with_name("out-dir")
+                  .short("d")
+                  .long("out-dir")
+                  .value_name("DIR")
+                  .takes_value(true)
+                  .default_value(".")
+                  .help("Where to write the members")))
+        .subcommand(SubCommand::with_name("grep")
+             .about("Prints where patterns occur in the decompressed output, without writing it anywhere")
+             .arg(Arg::with_name("input")
+                  .required(true)
+                  .multiple(true))
+             .arg(Arg::with_name("hex")
+                  .long("hex")
+                  .value_name("BYTES")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1)
+                  .required_unless("string")
+                  .help("Bytes to look for, in hex: \"DE AD BE EF\"; may be given more than once"))
+             .arg(Arg::with_name("string")
+                  .long("string")
+                  .value_name("TEXT")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1)
+                  .help("Text to look for; may be given more than once")))
+        .subcommand(SubCommand::with_name("scan")
+             .about("Prints where compressed streams start in images such as flash dumps, and how long they are")
+             .arg(Arg::with_name("input")
+                  .required(true)
+                  .multiple(true))
+             .arg(Arg::with_name("align")
+                  .long("align")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Only looks for streams at offsets that are a multiple of this, e.g. a flash sector"))
+             .arg(Arg::with_name("min-output")
+                  .long("min-output")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("1K")
+                  .help("Leaves out streams that decode to less than this, as noise can; takes a K, M or G suffix"))
+             .arg(Arg::with_name("min-score")
+                  .long("min-score")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("40")
+                  .help("Leaves out streams scoring less than this out of 100 for how likely they are to be streams rather than noise; lower finds more, higher fewer false ones"))
+             .arg(Arg::with_name("partial")
+                  .long("partial")
+                  .help("Also prints streams that fail before their end, with how far they got and why"))
+             .arg(Arg::with_name("no-cache")
+                  .long("no-cache")
+                  .help("Scans the image again rather than reading what was found before from <image>.hpcmp-index, nor keeping it there")))
+        .subcommand(SubCommand::with_name("carve")
+             .about("Decompresses every stream in an image such as a flash dump to a file of its own, listing them in carve.json")
+             .arg(Arg::with_name("image")
+                  .required(true))
+             .arg(Arg::with_name("out-dir")
+                  .short("d")
+                  .long("out-dir")
+                  .value_name("DIR")
+                  .takes_value(true)
+                  .required(true)
+                  .help("Where to write the streams' outputs and carve.json"))
+             .arg(Arg::with_name("template")
+                  .long("template")
+                  .value_name("TEMPLATE")
+                  .takes_value(true)
+                  .default_value("{stem}_{offset:#010x}.bin")
+                  .help("Names each output; {offset} is where its stream starts in the image and {index} numbers them"))
+             .arg(Arg::with_name("duplicates")
+                  .long("duplicates")
+                  .value_name("HOW")
+                  .takes_value(true)
+                  .possible_values(carve::NAMES)
+                  .default_value("link")
+                  .help("What to do with a stream that decodes the same as an earlier one: hard-link its output to the earlier one's, skip it or keep a copy"))
+             .arg(Arg::with_name("map")
+                  .long("map")
+                  .value_name("FORMAT")
+                  .takes_value(true)
+                  .multiple(true)
+                  .number_of_values(1)
+                  .possible_values(map::NAMES)
+                  .help("Also writes a script loading the outputs into Ghidra or IDA as segments, ghidra_import.py or ida_import.py; may be given more than once"))
+             .arg(Arg::with_name("load-base")
+                  .long("load-base")
+                  .value_name("ADDR")
+                  .takes_value(true)
+                  .default_value("0")
+                  .help("Where the first output is to be loaded, the rest following it a page apart; decimal or 0x-prefixed hex"))
+             .arg(Arg::with_name("align")
+                  .long("align")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Only looks for streams at offsets that are a multiple of this, as for scan"))
+             .arg(Arg::with_name("min-output")
+                  .long("min-output")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("1K")
+                  .help("Leaves out streams that decode to less than this, as for scan"))
+             .arg(Arg::with_name("min-score")
+                  .long("min-score")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("40")
+                  .help("Leaves out streams scoring less than this, as for scan"))
+             .arg(Arg::with_name("no-cache")
+                  .long("no-cache")
+                  .help("Scans the image again rather than reading <image>.hpcmp-index, as for scan")))
+        .subcommand(SubCommand::with_name("compare-images")
+             .about("Pairs up the streams in two images, such as two firmware releases, and prints which of their blocks changed")
+             .arg(Arg::with_name("old")
+                  .required(true))
+             .arg(Arg::with_name("new")
+                  .required(true))
+             .arg(Arg::with_name("align")
+                  .long("align")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Only looks for streams at offsets that are a multiple of this, as for scan"))
+             .arg(Arg::with_name("min-output")
+                  .long("min-output")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("1K")
+                  .help("Leaves out streams that decode to less than this, as for scan"))
+             .arg(Arg::with_name("min-score")
+                  .long("min-score")
+                  .value_name("N")
+                  .takes_value(true)
+                  .default_value("40")
+                  .help("Leaves out streams scoring less than this, as for scan"))
+             .arg(Arg::with_name("no-cache")
+                  .long("no-cache")
+                  .help("Scans the image again rather than reading <image>.hpcmp-index, as for scan")))
+        .subcommand(SubCommand::with_name("patch")
+             .about("Applies an xdelta3 or bsdiff patch to a stream's output in an image, and recompresses it in place if it still fits; exits with 1 if it doesn't")
+             .arg(Arg::with_name("image")
+                  .required(true))
+             .arg(Arg::with_name("output")
+                  .help("Where to write the patched image; without it, only says whether the patched stream fits"))
+             .arg(Arg::with_name("apply")
+                  .long("apply")
+                  .value_name("PATCH")
+                  .takes_value(true)
+                  .required(true)
+                  .help("The patch, made against the stream's decompressed output"))
+             .arg(Arg::with_name("offset")
+                  .long("offset")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Where the stream starts in the image, decimal or 0x-prefixed hex; by default, the only one `hpcmp scan` finds"))
+             .arg(Arg::with_name("fill")
+                  .long("fill")
+                  .value_name("BYTE")
+                  .takes_value(true)
+                  .default_value("0xff")
+                  .help("Fills what the patched stream leaves of the original's slot with this, as erased flash reads"))
+             .arg(Arg::with_name("when-full")
+                  .long("when-full")
+                  .value_name("HOW")
+                  .takes_value(true)
+                  .possible_values(encode::WHEN_FULL_NAMES)
+                  .default_value("freeze")
+                  .help("What the encoder does once a block's dictionary is full, as for compress"))
+             .arg(Arg::with_name("level")
+                  .long("level")
+                  .value_name("LEVEL")
+                  .takes_value(true)
+                  .possible_values(encode::LEVEL_NAMES)
+                  .default_value("greedy")
+                  .help("How hard the encoder looks for a short encoding, as for compress"))
+             .arg(Arg::with_name("no-cache")
+                  .long("no-cache")
+                  .help("Scans the image again rather than reading <image>.hpcmp-index, as for scan")))
+        .subcommand(SubCommand::with_name("bruteforce")
+             .about("Tries every bit order, initial code width, command mapping and literal bias on the start of a stream of an unfamiliar flavour, printing the most plausible as a variant spec in TOML")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("prefix")
+                  .long("prefix")
+                  .value_name("SIZE")
+                  .takes_value(true)
+                  .default_value("4K")
+                  .help("How much of the stream each layout decodes; takes a K, M or G suffix"))
+             .arg(Arg::with_name("offset")
+                  .long("offset")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Where the stream starts in the input, decimal or 0x-prefixed hex")))
+        .subcommand(SubCommand::with_name("extract-at")
+             .about("Writes a range of the decompressed output, decoding only the blocks it's in")
+             .arg(Arg::with_name("input")
+                  .required(true))
+             .arg(Arg::with_name("output")
+                  .help("Where to write the range, or stdout if not given"))
+             .arg(Arg::with_name("range")
+                  .long("range")
+                  .value_name("START..END")
+                  .takes_value(true)
+                  .required(true)
+                  .help("Offsets in the output, decimal or 0x-prefixed hex, e.g. 0x120000..0x121000"))
+             .arg(Arg::with_name("index")
+                  .long("index")
+                  .value_name("FILE")
+                  .takes_value(true)
+                  .help("Reads the stream's reset points from this file, or saves them to it if it doesn't exist"))
+             .arg(Arg::with_name("offset")
+                  .long("offset")
+                  .value_name("N")
+                  .takes_value(true)
+                  .help("Where the stream starts in the input, such as an image `hpcmp scan` found it in, decimal or 0x-prefixed hex; its reset points are read from <input>.hpcmp-index if that scan found them")))
+        .arg(Arg::with_name("files")
+             .value_name("input")
+             .required(true)
+             .multiple(true))
+        .arg(Arg::with_name("output")
+             .short("o")
+             .long("output")
+             .value_name("output")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1)
+             .conflicts_with("out-dir")
+             .help("Also writes the output here, or to stdout for -; may be given more than once"))
+        .arg(Arg::with_name("out-dir")
+             .short("d")
+             .long("out-dir")
+             .value_name("dir")
+             .takes_value(true)
+             .help("Decompresses every input into this directory, named after the input without its extension"))
+        .arg(Arg::with_name("jobs")
+             .short("j")
+             .long("jobs")
+             .value_name("N")
+             .takes_value(true)
+             .help("Decompresses up to this many inputs at once, or one per CPU for 0"))
+        .arg(Arg::with_name("no-progress")
+             .long("no-progress")
+             .help("Doesn't show progress through a batch decompressed with --jobs"))
+        .arg(Arg::with_name("v")
+             .short("v")
+             .long("verbose")
+             .multiple(true)
+             .help("Sets the level of verbosity, or given module=level pairs, e.g. -v reader=trace,dict=debug, those modules' levels"))
+        .arg(Arg::with_name("log-filter")
+             .long("log-filter")
+             .value_name("FILTER")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1)
+             .hidden(true)
+             .help("Same as the module=level pairs given to -v"))
+        .arg(Arg::with_name("log-file")
+             .long("log-file")
+             .value_name("FILE")
+             .takes_value(true)
+             .help("Also appends log messages to this file"))
+        .arg(Arg::with_name("log-format")
+             .long("log-format")
+             .value_name("FORMAT")
+             .takes_value(true)
+             .possible_values(logging::FORMATS)
+             .default_value("text")
+             .help("Writes the log file as text like stderr, or as a JSON object per line"))
+        .arg(Arg::with_name("config")
+             .long("config")
+             .value_name("FILE")
+             .takes_value(true)
+             .help("Reads default options from this file rather than ~/.config/hpcmp/config.toml"))
+        .arg(preset)
+        .arg(Arg::with_name("sha256")
+             .long("sha256")
+             .help("Prints the SHA-256 digest of the decompressed output"))
+        .arg(Arg::with_name("crc32")
+             .long("crc32")
+             .help("Prints the CRC-32 of the decompressed output"))
+        .arg(Arg::with_name("quiet")
+             .short("q")
+             .long("quiet")
+             .help("Doesn't print a summary of each input's sizes, blocks and time, or warnings"))
+        .arg(Arg::with_name("errors-json")
+             .long("errors-json")
+             .help("Prints each failure to stderr as a line of JSON, instead of a summary of each input"))
+        .arg(Arg::with_name("summary-csv")
+             .long("summary-csv")
+             .value_name("FILE")
+             .takes_value(true)
+             .help("Appends a row for each input to this CSV file: its status, sizes, ratio, time and SHA-256"))
+        .arg(Arg::with_name("invalid-index")
+             .long("invalid-index")
+             .value_name("POLICY")
+             .takes_value(true)
+             .possible_values(&["fail", "substitute"])
+             .default_value("fail")
+             .help("Fails on an index past the next dictionary entry, or with substitute decodes it as that entry \
+                    and warns, which salvages some damaged dumps"))
+        .arg(Arg::with_name("strict")
+             .long("strict")
+             .help("Fails inputs on the oddities otherwise only warned of, such as unknown commands, early widening, \
+                    redundant resets and data after the end, for checking an encoder's streams"))
+        .arg(Arg::with_name("strict-exit")
+             .long("strict-exit")
+             .help("Exits with status 1 if any input fails, rather than 2 when others succeeded"))
+        .arg(Arg::with_name("time")
+             .long("time")
+             .help("Prints how long each input took, and its input and output throughput, to stderr"))
+        .arg(Arg::with_name("timeout")
+             .long("timeout")
+             .value_name("DURATION")
+             .takes_value(true)
+             .help("Fails any input that takes longer than this to decompress, e.g. 30s, 500ms or 2m, \
+                    so that a pathological one can't hold up a batch"))
+        .arg(Arg::with_name("expect-sha256")
+             .long("expect-sha256")
+             .value_name("HEX")
+             .takes_value(true)
+             .help("Fails, without writing the output, unless it has this SHA-256 digest"))
+        .arg(Arg::with_name("reference-cmd")
+             .long("reference-cmd")
+             .value_name("COMMAND")
+             .takes_value(true)
+             .help("Fails, without writing the output, unless this shell command decompresses {in} to {out} the same"))
+        .arg(Arg::with_name("filter")
+             .long("filter")
+             .value_name("COMMAND")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1)
+             .help("Pipes the output through this shell command before writing it; may be given more than once"))
+        .arg(Arg::with_name("manifest")
+             .long("manifest")
+             .value_name("FILE")
+             .takes_value(true)
+             .help("Writes a SHA256SUMS-style manifest of every output"))
+        .arg(Arg::with_name("manifest-inputs")
+             .long("manifest-inputs")
+             .requires("manifest")
+             .help("Lists the inputs in the manifest as well"))
+        .arg(Arg::with_name("multistream")
+             .long("multistream")
+             .value_name("MODE")
+             .takes_value(true)
+             .possible_values(multistream::NAMES)
+             .conflicts_with_all(&["split", "sidecar", "reference-cmd"])
+             .requires_if("split", "out-dir")
+             .help("Decodes further streams stored after the first, concatenating their outputs, \
+                    or with split writing each to a file of its own in --out-dir"))
+        .arg(Arg::with_name("sidecar")
+             .long("sidecar")
+             .help("Writes <output>.trace and <output>.stats.json alongside each output"))
+        .arg(Arg::with_name("save-trailer")
+             .long("save-trailer")
+             .help("Writes whatever follows the end of the stream to <output>.trailer, or <input>.trailer when writing to stdout"))
+        .arg(Arg::with_name("offset")
+             .long("offset")
+             .value_name("N")
+             .takes_value(true)
+             .help("Where the stream starts in each input, decimal or 0x-prefixed hex"))
+        .arg(Arg::with_name("bit-offset")
+             .long("bit-offset")
+             .value_name("BITS")
+             .takes_value(true)
+             .possible_values(&["0", "1", "2", "3", "4", "5", "6", "7"])
+             .help("Starts the stream this many bits into the byte at --offset, counting from the least significant, \
+                    for streams packed into bitfields"))
+        .arg(Arg::with_name("reverse-input")
+             .long("reverse-input")
+             .help("Reads each input from its last byte to its first, for ROMs that store the stream reversed"))
+        .arg(Arg::with_name("swap")
+             .long("swap")
+             .value_name("BITS")
+             .takes_value(true)
+             .possible_values(&["16", "32"])
+             .help("Byte-swaps the output in 16- or 32-bit words before filtering and writing it, for ROMs stored word-swapped"))
+        .arg(Arg::with_name("post-compress")
+             .long("post-compress")
+             .value_name("FORMAT")
+             .takes_value(true)
+             .possible_values(archive::NAMES)
+             .help("Compresses outputs for archival, adding .zst or .xz to their names"))
+        .arg(Arg::with_name("max-output")
+             .long("max-output")
+             .value_name("SIZE")
+             .takes_value(true)
+             .help("Fails inputs that decompress to more than this many bytes; takes a K, M or G suffix"))
+        .arg(Arg::with_name("expected-size")
+             .long("expected-size")
+             .value_name("SIZE")
+             .takes_value(true)
+             .help("Fails inputs that don't decompress to exactly this many bytes; takes a K, M or G suffix"))
+        .arg(Arg::with_name("length-header")
+             .long("length-header")
+             .value_name("FORMAT")
+             .takes_value(true)
+             .possible_values(&["u32le", "u32be", "none"])
+             .conflicts_with("multistream")
+             .help("Reads the size of the output from a header before the stream, allocating it up front \
+                    and failing the input if it comes out another size"))
+        .arg(Arg::with_name("output-length")
+             .long("output-length")
+             .value_name("SIZE")
+             .takes_value(true)
+             .conflicts_with_all(&["mmap", "chunk-size", "sidecar", "multistream"])
+             .help("Stops decoding after exactly this many bytes, for streams whose length is stored outside them, \
+                    warning unless the end marker comes there; takes a K, M or G suffix"))
+        .arg(Arg::with_name("block-crcs")
+             .long("block-crcs")
+             .value_name("FILE")
+             .takes_value(true)
+             .help("Checks each block of a bare stream's output against the CRC-32s compress --block-crcs wrote to FILE, \
+                    failing at the first that doesn't match; a container's own are always checked"))
+        .arg(Arg::with_name("index")
+             .long("index")
+             .value_name("FILE")
+             .takes_value(true)
+             .requires("mmap")
+             .conflicts_with("out-dir")
+             .help("Takes the size of the output from an index saved by extract-at"))
+        .arg(Arg::with_name("mmap")
+             .long("mmap")
+             .conflicts_with_all(&["split", "filter", "swap", "post-compress", "chunk-size", "sparse", "sidecar", "multistream"])
+             .help("Allocates the output file up front and decodes straight into it through a memory map; \
+                    needs --expected-size, --index or --length-header"))
+        .arg(Arg::with_name("chunk-size")
+             .long("chunk-size")
+             .value_name("SIZE")
+             .takes_value(true)
+             .conflicts_with_all(&["split", "filter", "swap", "post-compress", "reference-cmd", "sidecar", "multistream"])
+             .help("Writes the output as it is decoded, this many bytes at a time, rather than holding it all; \
+                    takes a K, M or G suffix"))
+        .arg(Arg::with_name("io")
+             .long("io")
+             .value_name("BACKEND")
+             .takes_value(true)
+             .possible_values(output::IO_BACKENDS)
+             .default_value("std")
+             .help("Reads inputs and writes outputs with standard IO, or on Linux with io_uring, \
+                    queueing reads ahead of decoding and writes behind it"))
+        .arg(Arg::with_name("sparse")
+             .long("sparse")
+             .help("Leaves blocks of zeros in the output as holes rather than writing them"))
+        .arg(Arg::with_name("skip-existing")
+             .long("skip-existing")
+             .value_name("verify")
+             .takes_value(true)
+             .min_values(0)
+             .require_equals(true)
+             .possible_values(&["verify"])
+             .conflicts_with_all(&["append", "at", "split"])
+             .help("Skips inputs whose output already exists; with =verify, only if it matches its digest in --manifest"))
+        .arg(Arg::with_name("append")
+             .long("append")
+             .help("Adds to the end of existing output files rather than replacing them"))
+        .arg(Arg::with_name("at")
+             .long("at")
+             .value_name("OFFSET")
+             .takes_value(true)
+             .conflicts_with("append")
+             .help("Writes into existing output files at this offset, leaving the rest of them alone"))
+        .arg(Arg::with_name("preserve")
+             .long("preserve")
+             .help("Gives outputs the timestamps and permissions of their inputs"))
+        .arg(Arg::with_name("lock")
+             .long("lock")
+             .value_name("POLICY")
+             .takes_value(true)
+             .possible_values(&["wait", "skip"])
+             .help("Locks each output while writing it, and waits for or skips outputs another hpcmp is writing"))
+        .arg(Arg::with_name("on-interrupt")
+             .long("on-interrupt")
+             .value_name("ACTION")
+             .takes_value(true)
+             .possible_values(interrupt::NAMES)
+             .default_value("remove")
+             .help("On Ctrl-C, removes the output being written or renames it to <output>.partial"))
+        .arg(Arg::with_name("pre-decompress")
+             .long("pre-decompress")
+             .value_name("MODE")
+             .takes_value(true)
+             .possible_values(&["auto", "off"])
+             .default_value("auto")
+             .help("Unwraps gzip, xz or zstd compressed inputs before decoding them"))
+        .arg(Arg::with_name("member")
+             .long("member")
+             .value_name("GLOB")
+             .takes_value(true)
+             .requires("out-dir")
+             .help("Decompresses only the members of tar archive and .hpc container inputs matching this pattern"))
+        .arg(Arg::with_name("split")
+             .long("split")
+             .requires("out-dir")
+             .help("Writes each reset-delimited block of the output to a file of its own"))
+        .arg(Arg::with_name("template")
+             .long("template")
+             .value_name("TEMPLATE")
+             .takes_value(true)
+             .requires("out-dir")
+             .help("Names outputs in --out-dir from {stem}, {index}, {offset} and {out_offset}, \
+                    e.g. \"{stem}_{offset:#x}_{index}.bin\""))
+}
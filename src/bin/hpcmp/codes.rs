@@ -0,0 +1,133 @@
+//! The `.hpcodes` format, a stream's codes as text with a line each, for
+//! `hpcmp record` and `hpcmp emit`:
+//!
+//!     reset  # block 0 at byte 0x0
+//!     lit 0x48
+//!     lit 0x69
+//!     idx 0
+//!     widen
+//!     cmd 5
+//!     eof
+//!     lit 0x0a
+//!
+//! `reset`, `widen` and `eof` are commands 1, 2 and 3, and `cmd` any of the
+//! others. Codes are as wide as the `widen`s since the last `reset` make
+//! them, and the code after a `reset` or `eof` starts on a byte boundary, as
+//! the decoder reads them. Anything after a `#` is a comment.
+
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use hpcmp::{Code, CodeMap, DecodeObserver, Decoder, HpCodeMap, MAX_WIDTH};
+
+use crate::writer::Writer;
+
+/// The codes of `stream` as `.hpcodes` text, up to the end of the stream
+/// or the code decoding failed on, and whether it decoded. Anything after
+/// the end of the stream, and padding to a byte boundary, is left out.
+pub fn record(stream: &[u8]) -> (String, Result<(), hpcmp::Error>) {
+    let mut recorder = Recorder{ text: String::new(), blocks: 0 };
+    let mut decoder = Decoder::new();
+    let result = decoder.decode_to_vec_with(stream, &mut vec![], &mut recorder)
+        .and_then(|_| match decoder.is_done() {
+            true  => Ok(()),
+            false => Err(hpcmp::Error::UnexpectedEof),
+        });
+    (recorder.text, result)
+}
+
+struct Recorder {
+    text: String,
+    blocks: usize,
+}
+
+impl DecodeObserver for Recorder {
+    fn code(&mut self, bit_offset: u64, _width: u8, code: Code) {
+        let _ = match code {
+            // Noting where, to find blocks by when editing
+            Code::Command(1) => {
+                self.blocks += 1;
+                writeln!(self.text, "reset  # block {} at byte {:#x}", self.blocks - 1, bit_offset / 8)
+            },
+            Code::Command(2) => writeln!(self.text, "widen"),
+            Code::Command(3) => writeln!(self.text, "eof"),
+            Code::Command(c) => writeln!(self.text, "cmd {}", c),
+            Code::Value(v)   => writeln!(self.text, "lit {:#04x}", v),
+            Code::Index(i)   => writeln!(self.text, "idx {}", i),
+        };
+    }
+}
+
+/// A line of `.hpcodes` text that can't be emitted.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+/// The stream `text` records. Codes are written as they are, whether or not
+/// they make a stream that decodes, but each must fit its width.
+pub fn emit(text: &str) -> Result<Vec<u8>, ParseError> {
+    let map = HpCodeMap;
+    let mut w = Writer::new();
+    for (n, line) in text.lines().enumerate() {
+        let fail = |message: String| ParseError{ line: n + 1, message };
+        let line = line.split('#').next().unwrap();
+        let mut words = line.split_whitespace();
+        let token = match words.next() {
+            Some(token) => token,
+            None        => continue,
+        };
+        let arg = words.next();
+        if let Some(extra) = words.next() {
+            return Err(fail(format!("unexpected {:?}", extra)));
+        }
+        let number = |arg: Option<&str>| {
+            let arg = arg.ok_or_else(|| fail(format!("{} needs a number", token)))?;
+            let parsed = match arg.strip_prefix("0x") {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None      => arg.parse(),
+            };
+            parsed.map_err(|e| fail(format!("{:?}: {}", arg, e)))
+        };
+        let code = match token {
+            "reset" | "widen" | "eof" if arg.is_some() => return Err(fail(format!("{} takes no number", token))),
+            "reset" => 1,
+            "widen" => 2,
+            "eof"   => 3,
+            "cmd"   => match number(arg)? {
+                c if c < map.command_limit() => c,
+                c => return Err(fail(format!("command {} isn't below {}", c, map.command_limit()))),
+            },
+            "lit"   => match number(arg)? {
+                v if v < 0x100 => map.value_bias() + v,
+                v => return Err(fail(format!("literal {:#x} isn't a byte", v))),
+            },
+            "idx"   => map.index_base().checked_add(number(arg)?)
+                .ok_or_else(|| fail("index too large".to_string()))?,
+            _ => return Err(fail(format!("unknown code {:?}", token))),
+        };
+        if u64::from(code) >= 1 << w.width() {
+            return Err(fail(format!("code {:#x} doesn't fit in {} bits; widen first", code, w.width())));
+        }
+        match code {
+            1 => w.reset(),
+            2 if w.width() == u32::from(MAX_WIDTH) => return Err(fail(format!("codes can't be wider than {} bits", MAX_WIDTH))),
+            2 => w.widen(),
+            3 => {
+                w.put(3);
+                w.pad();
+            },
+            _ => w.put(code),
+        }
+    }
+    Ok(w.finish())
+}
@@ -0,0 +1,76 @@
+//! Pairing up the streams in two images, such as two releases of a
+//! firmware, and finding which of their blocks changed, for `hpcmp
+//! compare-images`.
+
+use std::ops::Range;
+
+use crate::scan;
+
+/// A stream found in an image, decoded.
+pub struct Stream {
+    pub offset: u64,
+    pub output: Vec<u8>,
+    /// Where each block's output is in `output`.
+    pub blocks: Vec<Range<usize>>,
+}
+
+impl Stream {
+    pub fn block(&self, index: usize) -> Option<&[u8]> {
+        self.blocks.get(index).map(|range| &self.output[range.clone()])
+    }
+}
+
+/// Decodes every stream `found` in `image`.
+pub fn streams(image: &[u8], found: Vec<scan::Found>) -> Result<Vec<Stream>, hpcmp::Error> {
+    found.into_iter().map(|found| {
+        let output = hpcmp::decompress(&image[found.offset as usize..])?;
+        // The last byte follows the end-of-file command, after the last
+        // block ends
+        let starts: Vec<usize> = found.resets.iter().map(|point| point.output_offset as usize).collect();
+        let ends = starts.iter().skip(1).copied().chain([output.len()]);
+        let blocks = starts.iter().copied().zip(ends).map(|(start, end)| start..end).collect();
+        Ok(Stream{ offset: found.offset, output, blocks })
+    }).collect()
+}
+
+/// Pairs each stream in `old` with the one in `new` it became, by index,
+/// in the order of `old` and then the streams only in `new`. Streams that
+/// decode the same pair up first, in order, even if others moved around
+/// them; the rest pair up in order too, leaving either out if it has no
+/// counterpart.
+pub fn pair(old: &[Stream], new: &[Stream]) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut pairs: Vec<Option<usize>> = vec![None; old.len()];
+    let mut next = 0;
+    for (i, stream) in old.iter().enumerate() {
+        if let Some(j) = (next..new.len()).find(|&j| new[j].output == stream.output) {
+            pairs[i] = Some(j);
+            next = j + 1;
+        }
+    }
+    let mut paired = vec![false; new.len()];
+    pairs.iter().flatten().for_each(|&j| paired[j] = true);
+    let mut rest = (0..new.len()).filter(|&j| !paired[j]);
+    for pair in pairs.iter_mut().filter(|pair| pair.is_none()) {
+        *pair = rest.next();
+    }
+    pairs.into_iter().enumerate().map(|(i, j)| (Some(i), j)).chain(rest.map(|j| (None, Some(j)))).collect()
+}
+
+/// How a block compares with the one at the same index in the other stream.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change {
+    Identical,
+    Changed,
+    OnlyInOld,
+    OnlyInNew,
+}
+
+/// How each block of `old` compares with the one at the same index in `new`.
+pub fn blocks(old: &Stream, new: &Stream) -> Vec<Change> {
+    (0..old.blocks.len().max(new.blocks.len())).map(|index| match (old.block(index), new.block(index)) {
+        (Some(a), Some(b)) if a == b => Change::Identical,
+        (Some(_), Some(_))           => Change::Changed,
+        (Some(_), None)              => Change::OnlyInOld,
+        _                            => Change::OnlyInNew,
+    }).collect()
+}
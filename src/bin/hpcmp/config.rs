@@ -0,0 +1,183 @@
+//! Defaults for command-line options, from a config file.
+//!
+//! The config file is TOML, with a key for each long option:
+//!
+//! ```toml
+//! verbose = 1
+//! out-dir = "extracted"
+//! post-compress = "zstd"
+//! sparse = true
+//! ```
+//!
+//! Strings and numbers give an option's value, booleans turn a flag on or
+//! off, arrays give an option more than once, and `verbose` is the number of
+//! `-v`s, or `module=level` pairs as given to `-v`.
+//!
+//! `[presets.<name>]` tables hold further sets of defaults, chosen with
+//! `--preset`, `HPCMP_PRESET` or a top-level `preset` key, which override the
+//! top-level ones. Environment variables override both, and options given on
+//! the command line override everything:
+//!
+//! - `HPCMP_LOG`: `error`, `info`, `debug` or `trace`, or a number of `-v`s;
+//!   or `module=level` pairs such as `reader=trace`, as given to `-v`
+//! - `HPCMP_PRESET`: a preset name
+//! - `HPCMP_MAX_OUTPUT`: `--max-output`
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+
+/// Where the config file is looked for when `--config` isn't given.
+fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("hpcmp").join("config.toml"))
+}
+
+type Args = BTreeMap<String, Vec<OsString>>;
+
+/// Default arguments, by option name.
+#[derive(Debug, Default)]
+pub struct Defaults {
+    args: Args,
+    presets: BTreeMap<String, Args>,
+    /// The preset used when none is named otherwise.
+    preset: Option<String>,
+}
+
+impl Defaults {
+    /// Reads the config file at `path`, or at the default location if none
+    /// is given, where it need not exist.
+    pub fn load(path: Option<&OsStr>) -> Result<Defaults, String> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(path), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None       => return Ok(Defaults::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) if !required => return Ok(Defaults::default()),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        let table: toml::Table = text.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let unsupported = |key: &str| format!("{}: unsupported value for '{}'", path.display(), key);
+        let options = |table: toml::Table| -> Result<Args, String> {
+            table.into_iter()
+                .map(|(key, value)| Ok((key.clone(), args(&key, &value).ok_or_else(|| unsupported(&key))?)))
+                .collect()
+        };
+
+        let mut defaults = Defaults::default();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("presets", toml::Value::Table(presets)) => for (name, preset) in presets {
+                    match preset {
+                        toml::Value::Table(preset) => { defaults.presets.insert(name, options(preset)?); },
+                        _ => return Err(unsupported(&format!("presets.{}", name))),
+                    }
+                },
+                ("preset", toml::Value::String(name)) => defaults.preset = Some(name),
+                (_, value) => {
+                    let args = args(&key, &value).ok_or_else(|| unsupported(&key))?;
+                    defaults.set(&key, args);
+                },
+            }
+        }
+        Ok(defaults)
+    }
+
+    /// Layers the chosen preset, then the environment variables, over the
+    /// config file's defaults.
+    pub fn layer(&mut self, matches: &ArgMatches) -> Result<(), String> {
+        let name = matches.value_of("preset").map(str::to_string)
+            .or_else(|| var("HPCMP_PRESET"))
+            .or_else(|| self.preset.clone());
+        if let Some(name) = name {
+            let preset = self.presets.get(&name).ok_or_else(|| format!("no preset '{}'", name))?;
+            self.args.extend(preset.clone());
+        }
+
+        if let Some(log) = var("HPCMP_LOG").filter(|log| log.contains('=')) {
+            self.set("log-filter", vec!["--log-filter".into(), log.into()]);
+        } else if let Some(log) = var("HPCMP_LOG") {
+            let count = match log.to_ascii_lowercase().as_str() {
+                "error" => 0,
+                "info"  => 1,
+                "debug" => 2,
+                "trace" => 3,
+                n => n.parse().map_err(|_| format!("HPCMP_LOG: unknown level '{}'", log))?,
+            };
+            self.set("verbose", vec![OsString::from("-v"); count]);
+        }
+        if let Some(size) = var("HPCMP_MAX_OUTPUT") {
+            self.set("max-output", vec!["--max-output".into(), size.into()]);
+        }
+        Ok(())
+    }
+
+    /// The names of the presets the config file defines.
+    pub fn preset_names(&self) -> Vec<&str> {
+        self.presets.keys().map(String::as_str).collect()
+    }
+
+    /// Sets the arguments for option `key`, replacing any already set.
+    pub fn set(&mut self, key: &str, args: Vec<OsString>) {
+        self.args.insert(key.to_string(), args);
+    }
+
+    /// Inserts the defaults for every option not given in `matches`, parsed
+    /// from `args`, just after the program name.
+    pub fn apply(&self, mut args: Vec<OsString>, matches: &ArgMatches) -> Vec<OsString> {
+        let defaults = self.args.iter()
+            .filter(|(key, _)| matches.occurrences_of(arg_name(key)) == 0)
+            .flat_map(|(_, args)| args.iter().cloned());
+        let at = args.len().min(1);
+        args.splice(at..at, defaults);
+        args
+    }
+}
+
+/// The value of environment variable `name`, if set and not empty.
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// The clap argument name for option `key`.
+fn arg_name(key: &str) -> &str {
+    match key {
+        "verbose" => "v",
+        key       => key,
+    }
+}
+
+/// The arguments giving option `key` the value `value`.
+fn args(key: &str, value: &toml::Value) -> Option<Vec<OsString>> {
+    use toml::Value::*;
+    let option = OsString::from(format!("--{}", key));
+    Some(match value {
+        Integer(n) if key == "verbose" => vec![OsString::from("-v"); (*n).max(0) as usize],
+        String(s) if key == "verbose"  => vec!["-v".into(), "--log-filter".into(), s.into()],
+        Boolean(true)  => vec![option],
+        Boolean(false) => vec![],
+        String(s)      => vec![option, s.into()],
+        Integer(n)     => vec![option, n.to_string().into()],
+        Float(n)       => vec![option, n.to_string().into()],
+        Array(values)  => {
+            let mut args = vec![];
+            for value in values {
+                args.extend(self::args(key, value)?);
+            }
+            args
+        },
+        _ => return None,
+    })
+}
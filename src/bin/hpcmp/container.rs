@@ -0,0 +1,175 @@
+//! The `.hpc` container `hpcmp compress` writes, holding one or more
+//! compressed streams, each with the name of the file it was made from,
+//! where that came from in an image, and the size and CRC-32 it decompresses
+//! to, so that re-packed resources carry their own provenance and are
+//! checked when decompressed again.
+//!
+//! After the magic number comes a count of members, then each member: the
+//! length of its name and the name in UTF-8, its source offset, decompressed
+//! size and CRC-32, and the length of its stream followed by the stream.
+//! Lengths of names are 16 bits, counts and CRCs 32 and offsets and sizes 64,
+//! all little-endian.
+//!
+//! A container made with `--block-crcs` has a magic number of its own, and
+//! each of its streams is followed by a count of blocks, none for a member
+//! without them, then for each block the offset of its first code in the
+//! stream, the length of its output and that output's CRC-32.
+
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Write};
+
+use crate::checksums::{BlockCrc, Checksums};
+use crate::diagnose::CheckFailed;
+use crate::digest;
+
+pub const MAGIC: &[u8] = b"HPC\x01";
+/// That of a container with block CRCs.
+pub const MAGIC_CRCS: &[u8] = b"HPC\x02";
+
+/// What a member's stream should decompress to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stored {
+    pub len: u64,
+    pub crc32: u32,
+}
+
+impl Stored {
+    /// The size and CRC-32 of all that `r` reads.
+    pub fn read(mut r: impl Read) -> io::Result<Stored> {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0; 1 << 16];
+        let mut len = 0;
+        loop {
+            match r.read(&mut buf) {
+                Ok(0)                                               => break,
+                Ok(read)                                            => {
+                    hasher.update(&buf[..read]);
+                    len += read as u64;
+                },
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e)                                              => return Err(e),
+            }
+        }
+        Ok(Stored{ len, crc32: hasher.finalize() })
+    }
+
+    /// Fails unless `len` bytes with the CRC-32 `crc32`, in hex as
+    /// [`digest::crc32`] gives it, is what was stored.
+    pub fn check(&self, len: u64, crc32: &str) -> Result<(), CheckFailed> {
+        if len != self.len {
+            return Err(CheckFailed{
+                code: "size_mismatch",
+                message: format!("output is {} bytes, the container says {}", len, self.len),
+            });
+        }
+        let stored = format!("{:08x}", self.crc32);
+        if crc32 != stored {
+            return Err(CheckFailed{
+                code: "crc32_mismatch",
+                message: format!("CRC-32 mismatch: the container says {}, got {}", stored, crc32),
+            });
+        }
+        Ok(())
+    }
+
+    /// Like [`check`](Stored::check), for the whole of the output.
+    pub fn check_data(&self, data: &[u8]) -> Result<(), CheckFailed> {
+        self.check(data.len() as u64, &digest::crc32(data))
+    }
+}
+
+/// One compressed stream in a container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member<'a> {
+    pub name: String,
+    /// Where what was compressed came from in an image, if anywhere.
+    pub source_offset: u64,
+    pub stored: Stored,
+    pub stream: &'a [u8],
+    pub crcs: Option<Checksums>,
+}
+
+pub fn is_container(data: &[u8]) -> bool {
+    data.starts_with(MAGIC) || data.starts_with(MAGIC_CRCS)
+}
+
+pub fn write(w: &mut impl Write, members: &[Member]) -> io::Result<()> {
+    let too_long = |what: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{} too long for a container", what));
+    let with_crcs = members.iter().any(|member| member.crcs.is_some());
+    w.write_all(if with_crcs { MAGIC_CRCS } else { MAGIC })?;
+    w.write_all(&u32::try_from(members.len()).map_err(|_| too_long("member list"))?.to_le_bytes())?;
+    for member in members {
+        w.write_all(&u16::try_from(member.name.len()).map_err(|_| too_long("name"))?.to_le_bytes())?;
+        w.write_all(member.name.as_bytes())?;
+        w.write_all(&member.source_offset.to_le_bytes())?;
+        w.write_all(&member.stored.len.to_le_bytes())?;
+        w.write_all(&member.stored.crc32.to_le_bytes())?;
+        w.write_all(&(member.stream.len() as u64).to_le_bytes())?;
+        w.write_all(member.stream)?;
+        if !with_crcs {
+            continue;
+        }
+        let blocks = member.crcs.as_ref().map_or(&[][..], |crcs| &crcs.blocks);
+        w.write_all(&u32::try_from(blocks.len()).map_err(|_| too_long("block list"))?.to_le_bytes())?;
+        for block in blocks {
+            w.write_all(&block.input_offset.to_le_bytes())?;
+            w.write_all(&block.output_len.to_le_bytes())?;
+            w.write_all(&block.crc32.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// The members of the container `data`, their streams borrowed from it.
+pub fn read(data: &[u8]) -> io::Result<Vec<Member<'_>>> {
+    if !is_container(data) {
+        return Err(invalid("missing header"));
+    }
+    let with_crcs = data.starts_with(MAGIC_CRCS);
+    let mut rest = &data[MAGIC.len()..];
+    let count = u32::from_le_bytes(take(&mut rest)?);
+    let mut members = vec![];
+    for _ in 0..count {
+        let name_len = u16::from_le_bytes(take(&mut rest)?);
+        let name = split(&mut rest, usize::from(name_len))?;
+        let name = std::str::from_utf8(name).map_err(|_| invalid("name not UTF-8"))?.to_string();
+        let source_offset = u64::from_le_bytes(take(&mut rest)?);
+        let len = u64::from_le_bytes(take(&mut rest)?);
+        let crc32 = u32::from_le_bytes(take(&mut rest)?);
+        let stream_len = usize::try_from(u64::from_le_bytes(take(&mut rest)?)).map_err(|_| invalid("truncated"))?;
+        let stream = split(&mut rest, stream_len)?;
+        let mut blocks = vec![];
+        for _ in 0..if with_crcs { u32::from_le_bytes(take(&mut rest)?) } else { 0 } {
+            blocks.push(BlockCrc{
+                input_offset: u64::from_le_bytes(take(&mut rest)?),
+                output_len:   u64::from_le_bytes(take(&mut rest)?),
+                crc32:        u32::from_le_bytes(take(&mut rest)?),
+            });
+        }
+        let crcs = (!blocks.is_empty()).then_some(Checksums{ blocks });
+        members.push(Member{ name, source_offset, stored: Stored{ len, crc32 }, stream, crcs });
+    }
+    if !rest.is_empty() {
+        return Err(invalid("data after the last member"));
+    }
+    Ok(members)
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("bad container: {}", what))
+}
+
+/// Takes the first `len` bytes off `rest`.
+fn split<'a>(rest: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if rest.len() < len {
+        return Err(invalid("truncated"));
+    }
+    let (taken, after) = rest.split_at(len);
+    *rest = after;
+    Ok(taken)
+}
+
+/// Takes a field `N` bytes long off `rest`.
+fn take<const N: usize>(rest: &mut &[u8]) -> io::Result<[u8; N]> {
+    Ok(split(rest, N)?.try_into().unwrap())
+}
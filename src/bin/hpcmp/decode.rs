@@ -0,0 +1,245 @@
+//! Decoding an input's stream, following `--invalid-index` and held to the
+//! `--timeout` deadline it was given when it started, with each worker's
+//! bar moved along as it goes.
+
+use std::error::Error;
+use std::io;
+use std::time::{Duration, Instant};
+
+use hpcmp::{Code, DecodeObserver, Decoder, IndexPolicy, ReportBuilder, ResetPoint, StreamReport};
+
+use crate::diagnose::CheckFailed;
+use crate::progress;
+
+/// Bytes of input decoded between checks of the deadline, which a single
+/// slice can take no more than a few milliseconds over.
+const SLICE_BYTES: usize = 1 << 12;
+
+/// How an input is to be decoded, set up as it starts.
+#[derive(Clone, Copy)]
+pub struct Decoding {
+    /// What decoding does with an index that refers to nothing.
+    index_policy: IndexPolicy,
+    /// When decoding gives up, and after how long, for `--timeout`.
+    deadline: Option<(Instant, Duration)>,
+}
+
+impl Decoding {
+    /// Decoding following `index_policy` that fails with `timeout` once
+    /// `timeout` has passed from now, or with `None` takes as long as it
+    /// takes.
+    pub fn new(index_policy: IndexPolicy, timeout: Option<Duration>) -> Decoding {
+        Decoding{ index_policy, deadline: timeout.map(|timeout| (Instant::now() + timeout, timeout)) }
+    }
+
+    /// A decoder for the input.
+    pub fn decoder(&self) -> Decoder {
+        let mut decoder = Decoder::new();
+        decoder.set_index_policy(self.index_policy);
+        decoder
+    }
+
+    /// How much input to decode before checking the deadline again.
+    fn slice_len(&self) -> usize {
+        match self.deadline {
+            Some(_) => SLICE_BYTES,
+            None    => usize::MAX,
+        }
+    }
+
+    /// Fails once the deadline has passed, saying how far `decoder` got.
+    fn check_deadline(&self, decoder: &Decoder) -> Result<(), Box<dyn Error>> {
+        match self.deadline {
+            Some((deadline, timeout)) if Instant::now() >= deadline => Err(CheckFailed{
+                code: "timeout",
+                message: format!("gave up after --timeout {:?}, {} bytes into the stream with {} bytes decoded",
+                                 timeout, decoder.total_in(), decoder.total_out()),
+            }.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// As `Decoder::decode_with`, a slice of `input` at a time while there's
+    /// a deadline to check.
+    fn decode_with(&self, decoder: &mut Decoder, input: &[u8], out: &mut [u8], mut observer: impl DecodeObserver) -> Result<(usize, usize), Box<dyn Error>> {
+        let (mut consumed, mut written) = (0usize, 0);
+        loop {
+            self.check_deadline(decoder)?;
+            let end = input.len().min(consumed.saturating_add(self.slice_len()));
+            let (more_consumed, more_written) = decoder.decode_with(&input[consumed..end], &mut out[written..], &mut observer)?;
+            consumed += more_consumed;
+            written += more_written;
+            if decoder.is_done() || written == out.len() || consumed == input.len() {
+                return Ok((consumed, written));
+            }
+        }
+    }
+
+    /// As `Decoder::decode_to_vec_with`, a slice of `input` at a time while
+    /// there's a deadline to check.
+    pub fn decode_to_vec_with(&self, decoder: &mut Decoder, input: &[u8], out: &mut Vec<u8>, mut observer: impl DecodeObserver) -> Result<usize, Box<dyn Error>> {
+        let mut consumed = 0usize;
+        loop {
+            self.check_deadline(decoder)?;
+            let end = input.len().min(consumed.saturating_add(self.slice_len()));
+            consumed += decoder.decode_to_vec_with(&input[consumed..end], out, &mut observer)?;
+            if decoder.is_done() || consumed == input.len() {
+                return Ok(consumed);
+            }
+        }
+    }
+
+    /// As `hpcmp::decompress_with_report`, moving this thread's worker's bar
+    /// along as `stream` is read. Room is made for `capacity` bytes of output
+    /// up front. Fails with a boxed `hpcmp::Error` if decoding does, or with
+    /// `timeout`.
+    pub fn decompress_with_report(&self, stream: &[u8], capacity: usize) -> Result<(Vec<u8>, StreamReport), Box<dyn Error>> {
+        let mut decoder = self.decoder();
+        let mut observer = (ReportBuilder::new(), progress::observer(stream.len()));
+        let mut out = Vec::with_capacity(capacity);
+        self.decode_to_vec_with(&mut decoder, stream, &mut out, &mut observer)?;
+        if !decoder.is_done() {
+            return Err(hpcmp::Error::UnexpectedEof.into());
+        }
+        Ok((out, observer.0.finish_input(&decoder, stream.len() as u64)))
+    }
+
+    /// As `hpcmp::decompress_into`, returning the report along with the
+    /// length and moving this thread's worker's bar along as `stream` is
+    /// read. Fails as `decompress_with_report` does.
+    pub fn decompress_into_with_report(&self, stream: &[u8], out: &mut [u8]) -> Result<(usize, StreamReport), Box<dyn Error>> {
+        let mut decoder = self.decoder();
+        let mut observer = (ReportBuilder::new(), progress::observer(stream.len()));
+        let (consumed, written) = self.decode_with(&mut decoder, stream, out, &mut observer)?;
+        if !decoder.is_done() {
+            // The output is full, unless the input ran out first
+            let (_, extra) = self.decode_with(&mut decoder, &stream[consumed..], &mut [0], &mut observer)?;
+            if extra > 0 {
+                return Err(hpcmp::Error::OutputOverflow.into());
+            }
+            if !decoder.is_done() {
+                return Err(hpcmp::Error::UnexpectedEof.into());
+            }
+        }
+        Ok((written, observer.0.finish_input(&decoder, stream.len() as u64)))
+    }
+
+    /// Decodes exactly `len` bytes of `stream`, for `--output-length`,
+    /// whether or not it ends there, returning the output, the report, and
+    /// whether the stream's end marker came just as the output reached
+    /// `len`. A stream that ends sooner is returned short; one cut short
+    /// fails with [`hpcmp::Error::UnexpectedEof`], boxed as
+    /// `decompress_with_report` fails.
+    pub fn decompress_exact_with_report(&self, stream: &[u8], len: usize) -> Result<(Vec<u8>, StreamReport, bool), Box<dyn Error>> {
+        let mut decoder = self.decoder();
+        let mut observer = (ReportBuilder::new(), progress::observer(stream.len()));
+        let mut dictionary = Dictionary(0);
+        let mut out = vec![0; len];
+        let (_, written) = self.decode_with(&mut decoder, stream, &mut out, (&mut observer, &mut dictionary))?;
+        out.truncate(written);
+        if decoder.is_done() {
+            return Ok((out, observer.0.finish_input(&decoder, stream.len() as u64), written == len));
+        }
+        if written < len {
+            return Err(hpcmp::Error::UnexpectedEof.into());
+        }
+        // Stopped in the middle of a block, which ends here
+        observer.0.block_end(len as u64, dictionary.0);
+        let mut report = observer.0.finish(&decoder);
+        report.decompressed_len = len as u64;
+        Ok((out, report, false))
+    }
+
+    /// As `decompress_with_report`, but handing the output to `each` as it
+    /// is decoded, `size` bytes at a time and the rest at the end, rather
+    /// than keeping it, and decoding the input as it arrives. Fails with a
+    /// boxed `hpcmp::Error` if decoding does, with `timeout`, or with
+    /// whatever reading the input or `each` fails with.
+    pub fn decompress_chunks(&self, stream: &mut impl Source, size: usize, mut each: impl FnMut(&[u8]) -> Result<(), Box<dyn Error>>) -> Result<StreamReport, Box<dyn Error>> {
+        let mut decoder = self.decoder();
+        let mut observer = (ReportBuilder::new(), progress::observer(stream.total_len()));
+        let mut chunk = vec![0; size];
+        let mut filled = 0;
+        let mut pos = 0;
+        while !decoder.is_done() {
+            let end = stream.wait(pos)?;
+            let (consumed, written) = self.decode_with(&mut decoder, &stream.data()[pos..end], &mut chunk[filled..], &mut observer)?;
+            if consumed == 0 && written == 0 {
+                return Err(hpcmp::Error::UnexpectedEof.into());
+            }
+            pos += consumed;
+            filled += written;
+            if filled == size || (decoder.is_done() && filled > 0) {
+                each(&chunk[..filled])?;
+                filled = 0;
+            }
+        }
+        Ok(observer.0.finish_input(&decoder, stream.total_len() as u64))
+    }
+}
+
+/// Counts the entries in the current block's dictionary.
+struct Dictionary(usize);
+
+impl DecodeObserver for Dictionary {
+    fn insert(&mut self, index: usize, _value: u8, _next: Code) {
+        self.0 = index + 1;
+    }
+
+    fn reset(&mut self, _point: &ResetPoint) {
+        self.0 = 0;
+    }
+}
+
+/// Input to `decompress_chunks`, which may still be arriving.
+pub trait Source {
+    /// As much of the input as `wait` has said has arrived.
+    fn data(&self) -> &[u8];
+
+    /// The length of the whole input.
+    fn total_len(&self) -> usize;
+
+    /// Waits until more than `pos` bytes have arrived, and returns how many
+    /// have, or `pos` if that's all of the input.
+    fn wait(&mut self, pos: usize) -> io::Result<usize>;
+
+    /// The whole input, once it has all arrived.
+    fn all(&mut self) -> io::Result<&[u8]> {
+        let mut pos = 0;
+        loop {
+            match self.wait(pos)? {
+                end if end == pos => return Ok(self.data()),
+                end => pos = end,
+            }
+        }
+    }
+}
+
+impl Source for &[u8] {
+    fn data(&self) -> &[u8] {
+        self
+    }
+
+    fn total_len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn wait(&mut self, _pos: usize) -> io::Result<usize> {
+        Ok(self.len())
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+impl Source for crate::uring::Input {
+    fn data(&self) -> &[u8] {
+        self.data()
+    }
+
+    fn total_len(&self) -> usize {
+        self.total_len()
+    }
+
+    fn wait(&mut self, pos: usize) -> io::Result<usize> {
+        self.wait(pos)
+    }
+}
@@ -0,0 +1,213 @@
+//! Showing where in the input a decode went wrong, and what kind of
+//! failure it was.
+
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::io::{self, IsTerminal};
+
+use hpcmp::{Anomaly, AnomalyKind, Code, DecodeObserver, Decoder};
+
+
+/// Bytes per hexdump line.
+const LINE: usize = 16;
+/// Lines shown either side of the failure.
+const CONTEXT: usize = 2;
+
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/// Remembers the last code read, which is where a failing decode failed.
+#[derive(Default)]
+struct LastCode(Option<(u64, u8, Code)>);
+
+impl DecodeObserver for LastCode {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        self.0 = Some((bit_offset, width, code));
+    }
+}
+
+/// A decode error, explained.
+#[derive(Debug)]
+pub struct Diagnosis {
+    pub error: hpcmp::Error,
+    /// Where in the input the failing code starts.
+    pub bit_offset: u64,
+    text: String,
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Error for Diagnosis {}
+
+/// Something decodable but suspicious in a stream, which `--strict` fails
+/// rather than warning of, explained.
+#[derive(Debug)]
+pub struct Nonconforming {
+    pub anomaly: Anomaly,
+    text: String,
+}
+
+impl fmt::Display for Nonconforming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Error for Nonconforming {}
+
+/// A check on an output that failed.
+#[derive(Debug)]
+pub struct CheckFailed {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for CheckFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for CheckFailed {}
+
+/// A short name for what kind of failure `e` is, for `--errors-json`.
+pub fn code(e: &(dyn Error + 'static)) -> &'static str {
+    use hpcmp::Error::*;
+    if let Some(diagnosis) = e.downcast_ref::<Diagnosis>() {
+        return match diagnosis.error {
+            MissingStartMarker => "missing_start_marker",
+            FirstCodeNotValue  => "first_code_not_value",
+            FinalCodeNotValue  => "final_code_not_value",
+            InvalidIndex{ .. } => "invalid_index",
+            WidthOverflow(_)   => "width_overflow",
+            UnexpectedEof      => "unexpected_eof",
+            OutputOverflow     => "output_overflow",
+        };
+    }
+    if let Some(nonconforming) = e.downcast_ref::<Nonconforming>() {
+        return warning(&nonconforming.anomaly.kind);
+    }
+    if let Some(failed) = e.downcast_ref::<CheckFailed>() {
+        return failed.code;
+    }
+    match e.is::<io::Error>() {
+        true  => "io",
+        false => "error",
+    }
+}
+
+/// Where in the input `e` went wrong, and what went wrong there without the
+/// hexdump, if it's a decode error or an anomaly `--strict` failed on.
+pub fn located(e: &(dyn Error + 'static)) -> Option<(u64, String)> {
+    if let Some(diagnosis) = e.downcast_ref::<Diagnosis>() {
+        return Some((diagnosis.bit_offset, diagnosis.error.to_string()));
+    }
+    e.downcast_ref::<Nonconforming>()
+        .map(|nonconforming| (nonconforming.anomaly.bit_offset, nonconforming.anomaly.kind.to_string()))
+}
+
+/// A short name for an anomaly, for `--errors-json`.
+pub fn warning(kind: &AnomalyKind) -> &'static str {
+    match kind {
+        AnomalyKind::UnknownCommand(_)     => "unknown_command",
+        AnomalyKind::EarlyWiden{ .. }      => "early_widen",
+        AnomalyKind::RedundantReset        => "redundant_reset",
+        AnomalyKind::TrailingData(_)       => "trailing_data",
+        AnomalyKind::EarlySaturation{ .. } => "early_saturation",
+        AnomalyKind::IndexOutOfRange{ .. } => "index_out_of_range",
+    }
+}
+
+/// Explains `error`, from decoding `stream`, with a hexdump of the input
+/// around the code it failed on. `decoder` is a new one set up as the one
+/// that failed was, to find the code again.
+pub fn explain(error: hpcmp::Error, decoder: Decoder, stream: &[u8]) -> Diagnosis {
+    explain_in(error, decoder, stream, 0)
+}
+
+/// As [`explain`], for the stream starting `start` bytes into `input`, as
+/// with `--multistream`, giving where it failed in `input`.
+pub fn explain_in(error: hpcmp::Error, mut decoder: Decoder, input: &[u8], start: usize) -> Diagnosis {
+    let mut last = LastCode::default();
+    let _ = decoder.decode_to_vec_with(&input[start..], &mut vec![], &mut last);
+
+    // Running out of input fails after the last complete code
+    let (bit_offset, width, code) = match (&error, last.0) {
+        (hpcmp::Error::UnexpectedEof, _) | (_, None) => (decoder.bit_position(), 0, None),
+        (_, Some((bit_offset, width, code))) => (bit_offset, width, Some(code)),
+    };
+    let bit_offset = bit_offset + start as u64 * 8;
+    let mut text = match code {
+        Some(code) => format!(
+            "{}\n  at {} code of {} bits at bit {} (byte 0x{:x}, bit {})",
+            error, describe(code), width, bit_offset, bit_offset / 8, bit_offset % 8,
+        ),
+        None => format!("{}\n  at bit {} (byte 0x{:x})", error, bit_offset, bit_offset / 8),
+    };
+    hexdump(&mut text, input, bit_offset, width, std::io::stderr().is_terminal())
+        .expect("writing to a String");
+    Diagnosis{ error, bit_offset, text }
+}
+
+/// Explains the first of `anomalies`, found decoding `stream`, with a
+/// hexdump around where it is, for `--strict`.
+pub fn reject(anomalies: &[Anomaly], stream: &[u8]) -> Nonconforming {
+    let anomaly = anomalies[0].clone();
+    let bit_offset = anomaly.bit_offset;
+    let mut text = format!(
+        "{}, which --strict rejects\n  at bit {} (byte 0x{:x}, bit {})",
+        anomaly.kind, bit_offset, bit_offset / 8, bit_offset % 8,
+    );
+    if anomalies.len() > 1 {
+        write!(text, ", the first of {} anomalies", anomalies.len()).expect("writing to a String");
+    }
+    hexdump(&mut text, stream, bit_offset, 0, std::io::stderr().is_terminal())
+        .expect("writing to a String");
+    Nonconforming{ anomaly, text }
+}
+
+fn describe(code: Code) -> String {
+    match code {
+        Code::Command(n) => format!("command {}", n),
+        Code::Value(b)   => format!("value 0x{:02x}", b),
+        Code::Index(i)   => format!("index 0x{:x}", i),
+    }
+}
+
+/// Dumps to `out` the lines of `stream` around the `width` bits at
+/// `bit_offset`, with carets under the bytes they span.
+fn hexdump(out: &mut String, stream: &[u8], bit_offset: u64, width: u8, color: bool) -> fmt::Result {
+    let first = (bit_offset / 8) as usize;
+    let last = ((bit_offset + u64::from(width.max(1)) - 1) / 8) as usize;
+    let start = (first / LINE).saturating_sub(CONTEXT) * LINE;
+    // Taking in the failing code even if it lies past the end of the input,
+    // as when the input was cut short
+    let end = ((last / LINE + CONTEXT + 1) * LINE).min(stream.len()).max(last + 1);
+    let marked = |i: usize| (first..=last).contains(&i);
+
+    for line in (start..end).step_by(LINE) {
+        let bytes = &stream[line.min(stream.len())..(line + LINE).min(stream.len())];
+        write!(out, "\n  {:08x} ", line)?;
+        for (i, byte) in bytes.iter().enumerate() {
+            match color && marked(line + i) {
+                true  => write!(out, " {}{:02x}{}", RED, byte, RESET)?,
+                false => write!(out, " {:02x}", byte)?,
+            }
+        }
+        let carets: String = (0..LINE)
+            .map(|i| if marked(line + i) { " ^^" } else { "   " })
+            .collect();
+        if carets.contains('^') {
+            let carets = carets.trim_end();
+            match color {
+                true  => write!(out, "\n           {}{}{}", RED, carets, RESET)?,
+                false => write!(out, "\n           {}", carets)?,
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,464 @@
+//! An encoder for putting modified data back into an image, greedy by
+//! default, taking the longest dictionary match at each point.
+//!
+//! [`Level::Max`] parses for fewer codes instead. Once a block's dictionary
+//! is full it no longer changes, and each window of what the block has left
+//! is parsed into the fewest codes it can be by a shortest path over its
+//! positions. While it is still filling, the entries a parse leaves decide
+//! how well the rest compresses, so there is no such shortcut: a shorter
+//! match can let the next one cover more, but every code adds an entry, and
+//! one after a shorter match than there was repeats an entry already there.
+//! The stream is encoded both greedily and looking one match ahead, taking a
+//! shorter match only when it gains by more than each of a few margins, and
+//! the smallest kept.
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, Write};
+
+use hpcmp::{StreamReport, DICT_LEN, MAX_PREV_LEN};
+use serde_json::json;
+
+use crate::writer::Writer;
+
+/// Input bytes over which [`WhenFull::Adaptive`] measures how well a full
+/// dictionary still compresses.
+const WINDOW: usize = 0x1000;
+
+/// How much of the input is read at a time, and how much of the stream is
+/// written.
+const READ_BYTES: usize = 1 << 16;
+const FLUSH_BYTES: usize = 1 << 16;
+
+pub const LEVEL_NAMES: &[&str] = &["greedy", "max"];
+
+/// How hard to look for a short encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Level {
+    #[default]
+    Greedy,
+    /// Far slower, for the smallest streams.
+    Max,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "greedy" => Some(Level::Greedy),
+            "max"    => Some(Level::Max),
+            _        => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Greedy => "greedy",
+            Level::Max    => "max",
+        }
+    }
+}
+
+pub const WHEN_FULL_NAMES: &[&str] = &["freeze", "reset", "adaptive"];
+
+/// What to do once a block's dictionary is full. Pruning entries to make
+/// room, as some LZW variants do, isn't open to an encoder here: the decoder
+/// only ever appends to the dictionary, so the choice is between keeping all
+/// of it and starting over.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WhenFull {
+    /// Keeps the entries it has until the block ends.
+    #[default]
+    Freeze,
+    /// Starts a new block straight away.
+    Reset,
+    /// Keeps the entries while they compress each window of the input as
+    /// well as the block had up to when they filled, and starts a new block
+    /// once they don't, as when the data has changed character.
+    Adaptive,
+}
+
+impl WhenFull {
+    pub fn from_name(name: &str) -> Option<WhenFull> {
+        match name {
+            "freeze"   => Some(WhenFull::Freeze),
+            "reset"    => Some(WhenFull::Reset),
+            "adaptive" => Some(WhenFull::Adaptive),
+            _          => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WhenFull::Freeze   => "freeze",
+            WhenFull::Reset    => "reset",
+            WhenFull::Adaptive => "adaptive",
+        }
+    }
+}
+
+/// How to compress.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Options {
+    /// Starts a new block once one has decoded to this many bytes.
+    pub block_len: Option<u64>,
+    pub when_full: WhenFull,
+    pub level: Level,
+}
+
+/// Compresses `data`, which must be at least two bytes long.
+pub fn compress(data: &[u8], options: Options) -> Vec<u8> {
+    assert!(data.len() >= 2, "the format can't hold fewer than two bytes");
+    let mut out = vec![];
+    // Reading and writing memory can't fail
+    compress_from(io::Cursor::new(data), &mut out, options, None).unwrap();
+    out
+}
+
+/// Compresses all of `r`, which must hold at least two bytes, to `w`, with
+/// no more than the dictionary and a window of the input around where it is
+/// in memory. `r` is read again for each parse [`Level::Max`] tries. Given
+/// `decisions`, writes a JSON line there for each code, with the matches it
+/// was chosen from and why, and for each block ended. Returns where each
+/// block starts, as the offset in the stream of its first code and the
+/// offset of its output.
+pub fn compress_from<R: Read + Seek>(mut r: R, mut w: impl Write, options: Options, decisions: Option<&mut dyn Write>) -> io::Result<Vec<(u64, u64)>> {
+    let margin = match options.level {
+        Level::Greedy => None,
+        // Which does best depends on the data
+        Level::Max    => {
+            let mut best = (u64::MAX, None);
+            for &margin in &[None, Some(0), Some(1), Some(2)] {
+                r.rewind()?;
+                best = best.min((encode(&mut r, io::sink(), options, margin, &mut Log(None), &mut vec![])?, margin));
+            }
+            r.rewind()?;
+            best.1
+        },
+    };
+    let mut log = Log(decisions);
+    let mut starts = vec![];
+    encode(r, &mut w, options, margin, &mut log, &mut starts)?;
+    log.flush()?;
+    w.flush()?;
+    Ok(starts)
+}
+
+/// Compresses `r` to `w`, looking one match ahead while the dictionary
+/// fills if given a `margin`: a shorter match is taken if, with the longest
+/// match after it, it covers more than `margin` bytes more than the longest
+/// would. Returns the length of the stream, adding where each block starts
+/// to `starts`.
+fn encode(r: impl Read, mut out: impl Write, options: Options, margin: Option<usize>, log: &mut Log, starts: &mut Vec<(u64, u64)>) -> io::Result<u64> {
+    let mut input = Window::new(r);
+    let mut w = Writer::new();
+    let mut written = 0;
+    let mut i = 0;
+    // Like the decoder's, only reset by an expanded code, not a block's first
+    let mut prev_len = 0;
+    let mut block = 0;
+    // Why the last block ended, if it did before the input
+    let mut ended = None;
+    while let Some(&first) = input.ahead(i, 1)?.first() {
+        if let Some(why) = ended.take() {
+            log.line(json!({ "event": "reset", "offset": i, "block": block, "why": why }))?;
+            block += 1;
+        }
+        w.reset();
+        starts.push((w.bits() / 8, i as u64));
+        let mut dictionary: HashMap<(u32, u8), u32> = HashMap::new();
+        // Not the map's length: a parse other than the greedy one can add an
+        // entry for a string there is one for already, which the decoder adds
+        // all the same
+        let mut entries = 0;
+        let mut prev = 8 + u32::from(first);
+        w.code(prev);
+        let start = (i, w.bits());
+        let block_end = options.block_len.map_or(usize::MAX, |len| start.0.saturating_add(len as usize));
+        if log.on() {
+            log.line(json!({ "event": "code", "offset": i, "block": block, "code": prev, "len": 1, "bits": w.bits(), "why": "block_start" }))?;
+        }
+        i += 1;
+        // Bits per byte of the block up to when the dictionary filled, and
+        // where the window being measured against it started
+        let mut full: Option<(f64, (usize, u64))> = None;
+        while i < block_end {
+            if w.pending() >= FLUSH_BYTES {
+                let bytes = w.take();
+                out.write_all(&bytes)?;
+                written += bytes.len() as u64;
+            }
+            let ahead = input.ahead(i, WINDOW)?;
+            if ahead.is_empty() {
+                break;
+            }
+            // As much of what's ahead as is in the block
+            let end = ahead.len().min(block_end - i);
+            let block_left = options.block_len.map(|_| block_end - i);
+            if entries == DICT_LEN {
+                match options.when_full {
+                    WhenFull::Freeze   => (),
+                    WhenFull::Reset    => {
+                        ended = Some("dictionary_full");
+                        break;
+                    },
+                    WhenFull::Adaptive => {
+                        let here = (i, w.bits());
+                        let rate = |from: (usize, u64)| (here.1 - from.1) as f64 / (here.0 - from.0) as f64;
+                        match full {
+                            None => full = Some((rate(start), here)),
+                            Some((filled, window)) if here.0 - window.0 >= WINDOW => {
+                                if rate(window) > filled {
+                                    ended = Some("compressing_worse");
+                                    break;
+                                }
+                                full = Some((filled, here));
+                            },
+                            Some(_) => (),
+                        }
+                    },
+                }
+                if options.level == Level::Max {
+                    let (parse, codes_to_end) = shortest(&dictionary, &ahead[..end]);
+                    let mut at = 0;
+                    for (code, len) in parse {
+                        w.code(code);
+                        if log.on() {
+                            let candidates: Vec<_> = matches(&dictionary, &ahead[at..end]).into_iter().map(|(code, len)| {
+                                json!({ "code": code, "len": len, "codes_to_end": codes_to_end[at + len] + 1 })
+                            }).collect();
+                            log.line(json!({
+                                "event": "code", "offset": i + at, "block": block, "code": code, "len": len, "bits": w.bits(),
+                                "why": "fewest_codes", "candidates": candidates, "not_added": "dictionary_full",
+                                "block_left": block_left.map(|left| left - at),
+                            }))?;
+                        }
+                        prev_len = len;
+                        at += len;
+                    }
+                    i += end;
+                    continue;
+                }
+            }
+            let added = match (prev_len < MAX_PREV_LEN, entries < DICT_LEN) {
+                (true, true) => {
+                    let duplicate = dictionary.contains_key(&(prev, ahead[0]));
+                    dictionary.entry((prev, ahead[0])).or_insert(0x108 + entries as u32);
+                    entries += 1;
+                    Ok((entries - 1, duplicate))
+                },
+                (_, false)   => Err("dictionary_full"),
+                (false, _)   => Err("long_string"),
+            };
+            // What the next code could cover after each match, only within
+            // the block
+            let covers = |len: usize| len + ahead.get(len..end).filter(|rest| !rest.is_empty()).map_or(0, |rest| longest(&dictionary, rest).1);
+            let (code, len) = match margin {
+                None         => longest(&dictionary, ahead),
+                Some(margin) => {
+                    let matches = matches(&dictionary, ahead);
+                    let longest_len = matches.len();
+                    matches.into_iter().max_by_key(|&(_, len)| {
+                        (covers(len) + if len == longest_len { margin } else { 0 }, len)
+                    }).unwrap()
+                },
+            };
+            w.code(code);
+            if log.on() {
+                let matches = matches(&dictionary, ahead);
+                let why = match len == matches.len() {
+                    true  => "longest",
+                    false => "lookahead",
+                };
+                let candidates: Vec<_> = matches.into_iter().map(|(code, len)| match margin {
+                    None    => json!({ "code": code, "len": len }),
+                    Some(_) => json!({ "code": code, "len": len, "covers": covers(len) }),
+                }).collect();
+                log.line(json!({
+                    "event": "code", "offset": i, "block": block, "code": code, "len": len, "bits": w.bits(),
+                    "why": why, "candidates": candidates,
+                    "entry": added.ok().map(|(index, duplicate)| json!({ "index": index, "duplicate": duplicate })),
+                    "not_added": added.err(), "block_left": block_left,
+                }))?;
+            }
+            prev_len = len;
+            i += len;
+            prev = code;
+        }
+        ended = ended.or(Some("block_len"));
+    }
+    let last = input.last().filter(|_| i > 0).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "the format can't hold fewer than two bytes")
+    })?;
+    log.line(json!({ "event": "end", "offset": i, "block": block, "last": last }))?;
+    w.put(3);
+    w.pad();
+    w.put(8 + u32::from(last));
+    let bytes = w.finish();
+    out.write_all(&bytes)?;
+    Ok(written + bytes.len() as u64)
+}
+
+/// Holds a stream as it is written until it is known to fit in `limit`
+/// bytes, only counting what it comes to once it doesn't.
+pub struct Budget {
+    limit: u64,
+    held: Vec<u8>,
+    len: u64,
+}
+
+impl Budget {
+    pub fn new(limit: u64) -> Budget {
+        Budget{ limit, held: vec![], len: 0 }
+    }
+
+    /// The stream, or how long it came to if that's over the limit.
+    pub fn finish(self) -> Result<Vec<u8>, u64> {
+        match self.len <= self.limit {
+            true  => Ok(self.held),
+            false => Err(self.len),
+        }
+    }
+}
+
+impl Write for Budget {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.len += buf.len() as u64;
+        match self.len <= self.limit {
+            true  => self.held.extend_from_slice(buf),
+            false => self.held = vec![],
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Says how far a stream of `len` bytes is over a `limit`.
+pub fn over_budget(len: u64, limit: u64) -> String {
+    format!("the stream is {} bytes, {} over the --max-compressed-size of {} ({:.1}% over)",
+            len, len - limit, limit, (len - limit) as f64 * 100.0 / limit.max(1) as f64)
+}
+
+/// Where [`compress_from`] explains its choices, if anywhere.
+struct Log<'a>(Option<&'a mut dyn Write>);
+
+impl Log<'_> {
+    fn on(&self) -> bool {
+        self.0.is_some()
+    }
+
+    fn line(&mut self, line: serde_json::Value) -> io::Result<()> {
+        match &mut self.0 {
+            Some(w) => writeln!(w, "{}", line),
+            None    => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.0 {
+            Some(w) => w.flush(),
+            None    => Ok(()),
+        }
+    }
+}
+
+/// The input around where the encoder is, read as it goes. All of it but
+/// the last byte is the body of the stream, that byte following its end.
+struct Window<R> {
+    r: R,
+    buf: Vec<u8>,
+    /// Where `buf` starts in the input.
+    base: usize,
+    eof: bool,
+}
+
+impl<R: Read> Window<R> {
+    fn new(r: R) -> Window<R> {
+        Window{ r, buf: Vec::with_capacity(2 * READ_BYTES), base: 0, eof: false }
+    }
+
+    /// Up to `len` bytes of the body from `i`, fewer only at its end. What
+    /// comes before `i` may be dropped.
+    fn ahead(&mut self, i: usize, len: usize) -> io::Result<&[u8]> {
+        if i - self.base >= READ_BYTES {
+            self.buf.drain(..i - self.base);
+            self.base = i;
+        }
+        let from = i - self.base;
+        // A byte more, to know whether the last is the input's
+        while !self.eof && self.buf.len() <= from + len {
+            let filled = self.buf.len();
+            self.buf.resize(filled + READ_BYTES, 0);
+            let read = self.r.read(&mut self.buf[filled..]);
+            self.buf.truncate(filled + read.as_ref().map_or(0, |&read| read));
+            match read {
+                Ok(0)                                               => self.eof = true,
+                Ok(_)                                               => (),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e)                                              => return Err(e),
+            }
+        }
+        let body = self.buf.len().saturating_sub(1);
+        Ok(&self.buf[from.min(body)..body.min(from + len)])
+    }
+
+    /// The input's last byte, once [`ahead`](Window::ahead) has found the
+    /// end of the body.
+    fn last(&self) -> Option<u8> {
+        self.buf.last().copied()
+    }
+}
+/// The code for the longest string in `dictionary` that `data` starts with,
+/// and its length.
+fn longest(dictionary: &HashMap<(u32, u8), u32>, data: &[u8]) -> (u32, usize) {
+    let mut code = 8 + u32::from(data[0]);
+    let mut len = 1;
+    while let Some(&longer) = data.get(len).and_then(|&byte| dictionary.get(&(code, byte))) {
+        code = longer;
+        len += 1;
+    }
+    (code, len)
+}
+
+/// Like [`longest`], for every string in `dictionary` that `data` starts
+/// with, shortest first. Each entry extends a shorter one, so these are the
+/// prefixes of the longest.
+fn matches(dictionary: &HashMap<(u32, u8), u32>, data: &[u8]) -> Vec<(u32, usize)> {
+    let mut all = vec![(8 + u32::from(data[0]), 1)];
+    while let Some(&longer) = data.get(all.len()).and_then(|&byte| dictionary.get(&(all[all.len() - 1].0, byte))) {
+        all.push((longer, all.len() + 1));
+    }
+    all
+}
+
+/// The fewest codes, with their lengths, that `data` can be written in from
+/// `dictionary`, none running past its end, and how many it takes from each
+/// position to the end.
+fn shortest(dictionary: &HashMap<(u32, u8), u32>, data: &[u8]) -> (Vec<(u32, usize)>, Vec<usize>) {
+    // For each position, the codes it takes to the end and the one to take
+    let mut best = vec![(0, (0, 0)); data.len() + 1];
+    for i in (0..data.len()).rev() {
+        best[i] = matches(dictionary, &data[i..]).into_iter()
+            .map(|(code, len)| (best[i + len].0 + 1, (code, len)))
+            // The longest of the fewest
+            .min_by_key(|&(codes, (_, len))| (codes, usize::MAX - len))
+            .unwrap();
+    }
+    let mut parse = vec![];
+    let mut i = 0;
+    while i < data.len() {
+        let (code, len) = best[i].1;
+        parse.push((code, len));
+        i += len;
+    }
+    (parse, best.into_iter().map(|(codes, _)| codes).collect())
+}
+
+/// The `block_len` that would compress the data as the stream `report`
+/// describes was: the output of its longest block but the last, which the
+/// end of the data may have cut short, or none if it has only one.
+pub fn block_len(report: &StreamReport) -> Option<u64> {
+    let (_, blocks) = report.blocks.split_last()?;
+    blocks.iter().map(|block| block.output_len).max()
+}
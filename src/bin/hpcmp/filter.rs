@@ -0,0 +1,88 @@
+//! Piping outputs through external commands, for `--filter`.
+
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::process::{Child, ExitStatus, Stdio};
+use std::thread;
+
+use crate::diagnose::CheckFailed;
+use crate::reference::shell;
+
+/// Shell commands every output goes through in turn before it is written,
+/// as in `cmd1 | cmd2`.
+pub struct Filters {
+    commands: Vec<String>,
+}
+
+impl Filters {
+    pub fn new(commands: Vec<String>) -> Filters {
+        Filters{ commands }
+    }
+
+    /// What `data` comes out of the pipeline as. A filter may stop reading
+    /// before the end, and one that fails for having nothing left to write
+    /// to, because a later one stopped, isn't held against it; otherwise any
+    /// filter failing fails the output, giving its exit status.
+    pub fn run(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut children: Vec<Child> = vec![];
+        for (i, command) in self.commands.iter().enumerate() {
+            let stdin = match children.last_mut() {
+                Some(prev) => Stdio::from(prev.stdout.take().unwrap()),
+                None       => Stdio::piped(),
+            };
+            let child = shell(command).stdin(stdin).stdout(Stdio::piped()).spawn();
+            match child {
+                Ok(child) => children.push(child),
+                Err(e) => {
+                    for mut child in children {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    return Err(format!("running filter {}, {:?}: {}", i + 1, command, e).into());
+                },
+            }
+        }
+
+        let mut stdin = children[0].stdin.take().unwrap();
+        let mut stdout = children.last_mut().unwrap().stdout.take().unwrap();
+        let (written, output) = thread::scope(|scope| {
+            // Fed from another thread, so that a filter writing as it reads
+            // can't fill its stdout while this waits on its stdin
+            let writer = scope.spawn(move || match stdin.write_all(data) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result,
+            });
+            let mut output = vec![];
+            let read = stdout.read_to_end(&mut output).map(|_| output);
+            (writer.join().unwrap(), read)
+        });
+        let statuses = children.iter_mut().map(Child::wait).collect::<io::Result<Vec<_>>>()?;
+
+        let last = statuses.len() - 1;
+        for (i, status) in statuses.iter().enumerate() {
+            let stopped_early = i < last && broken_pipe(status);
+            if !status.success() && !stopped_early {
+                return Err(CheckFailed{
+                    code: "filter_failed",
+                    message: format!("filter {:?} failed: {}", self.commands[i], status),
+                }.into());
+            }
+        }
+        written?;
+        Ok(output?)
+    }
+}
+
+/// Whether `status` is of a process killed for writing to a closed pipe,
+/// or of a shell saying that its command was.
+#[cfg(unix)]
+fn broken_pipe(status: &ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    const SIGPIPE: i32 = 13;
+    status.signal() == Some(SIGPIPE) || status.code() == Some(128 + SIGPIPE)
+}
+
+#[cfg(not(unix))]
+fn broken_pipe(_status: &ExitStatus) -> bool {
+    false
+}
@@ -0,0 +1,133 @@
+//! Pseudo-random streams that follow the format's rules while going out of
+//! their way to hit its edge cases, for `hpcmp gen-stream`.
+
+use hpcmp::{DICT_LEN, MAX_PREV_LEN, MAX_WIDTH};
+
+use crate::writer::Writer;
+
+/// SplitMix64, so that a seed gives the same stream on every platform.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// True one time in `n`.
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// What a block sets out to do.
+#[derive(Clone, Copy, PartialEq)]
+enum Block {
+    /// Literals and dictionary references, with the odd ignored command.
+    Mixed,
+    /// Runs until the dictionary is full, then a while longer.
+    Fill,
+    /// A single literal, so that resets come as close together as they can.
+    Tiny,
+    /// Widens codes early, all the way to the widest.
+    Wide,
+}
+
+/// A stream, and the output it decodes to.
+pub struct Generated {
+    pub stream: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+/// Generates a stream from `seed` decoding to `size` bytes, which must be at
+/// least 2.
+pub fn generate(seed: u64, size: usize) -> Generated {
+    assert!(size >= 2, "a stream decodes to at least two bytes");
+    let mut rng = Rng(seed);
+    let mut w = Writer::new();
+    let mut output = Vec::with_capacity(size);
+    // Like the decoder's, not updated by the literal that opens a block
+    let mut prev_scratch_len = 0;
+
+    // The last byte comes after the end-of-file command
+    while output.len() < size - 1 {
+        w.reset();
+        let block = match rng.below(8) {
+            0 => Block::Fill,
+            1 => Block::Tiny,
+            2 => Block::Wide,
+            _ => Block::Mixed,
+        };
+        let mut dictionary: Vec<Vec<u8>> = vec![];
+        let mut prev = vec![rng.below(256) as u8];
+        w.code(8 + u32::from(prev[0]));
+        output.extend_from_slice(&prev);
+        let mut codes = 1;
+
+        loop {
+            let done = match block {
+                Block::Tiny  => true,
+                Block::Fill  => dictionary.len() == DICT_LEN && rng.one_in(64),
+                Block::Wide  => w.width() == u32::from(MAX_WIDTH) && rng.one_in(64),
+                Block::Mixed => rng.one_in(512),
+            };
+            if done || output.len() >= size - 1 {
+                break;
+            }
+            if block == Block::Wide && w.width() < u32::from(MAX_WIDTH) && rng.one_in(8) {
+                w.widen();
+            }
+            if block == Block::Mixed && rng.one_in(256) {
+                w.put([0, 4, 5, 6, 7][rng.below(5)]);
+            }
+
+            // Favouring the newest entries fills the dictionary with ever
+            // longer strings; filling it needs some short ones too
+            let index = match rng.one_in(4) {
+                true                          => None,
+                false if block == Block::Fill => Some(rng.below(dictionary.len() + 1)),
+                false                         => Some(dictionary.len() - rng.below(dictionary.len().min(16) + 1)),
+            };
+            let inserts = prev_scratch_len < MAX_PREV_LEN && dictionary.len() < DICT_LEN;
+            // The entry about to be added can only be referred to if one is
+            let index = index.filter(|&index| index < dictionary.len() || inserts);
+            let string = match index {
+                None => vec![rng.below(256) as u8],
+                Some(index) if index == dictionary.len() => [&prev[..], &prev[..1]].concat(),
+                Some(index) => dictionary[index].clone(),
+            };
+            // Nothing may take the output past its size
+            let (index, string) = match string.len() > size - 1 - output.len() {
+                true  => (None, vec![rng.below(256) as u8]),
+                false => (index, string),
+            };
+            match index {
+                None        => w.code(8 + u32::from(string[0])),
+                Some(index) => w.code(0x108 + index as u32),
+            }
+            if inserts {
+                dictionary.push([&prev[..], &string[..1]].concat());
+            }
+            output.extend_from_slice(&string);
+            prev_scratch_len = string.len();
+            prev = string;
+            codes += 1;
+        }
+        log::debug!("generated a block of {} codes, {} entries, up to width {}", codes, dictionary.len(), w.width());
+    }
+
+    let last = rng.below(256) as u8;
+    w.put(3);
+    w.pad();
+    w.put(8 + u32::from(last));
+    output.push(last);
+    Generated{ stream: w.finish(), output }
+}
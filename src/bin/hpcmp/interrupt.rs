@@ -0,0 +1,124 @@
+//! Cleaning up after Ctrl-C, so an interrupted run never leaves a partial
+//! output that looks complete.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use log::{error, warn};
+
+/// Exit status after an interrupt, as a shell reports for SIGINT.
+pub const EXIT_STATUS: i32 = 130;
+
+pub const NAMES: &[&str] = &["remove", "partial"];
+
+/// What to do with an output interrupted while being written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cleanup {
+    Remove,
+    /// Renames it with a `.partial` suffix.
+    Partial,
+}
+
+impl Cleanup {
+    pub fn from_name(name: &str) -> Option<Cleanup> {
+        match name {
+            "remove"  => Some(Cleanup::Remove),
+            "partial" => Some(Cleanup::Partial),
+            _         => None,
+        }
+    }
+}
+
+/// An output being written.
+struct Writing {
+    id: u64,
+    path: PathBuf,
+    /// Length to truncate an appended-to file back to.
+    restore_len: Option<u64>,
+    /// Set when writing into the middle of an existing file, which can't be
+    /// undone.
+    in_place: bool,
+}
+
+/// The outputs being written, one for each job running.
+static WRITING: Mutex<Vec<Writing>> = Mutex::new(vec![]);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+/// Held across each write to an output, then by the handler for good, so
+/// an output isn't written to again once it's been cleaned up.
+static WRITES: RwLock<()> = RwLock::new(());
+
+pub fn install(cleanup: Cleanup) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(move || {
+        // Holding the locks keeps any job from starting another write
+        let _writes = WRITES.write().unwrap_or_else(|e| e.into_inner());
+        let writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+        for writing in writing.iter() {
+            clean_up(writing, cleanup);
+        }
+        error!("interrupted");
+        log::logger().flush();
+        std::process::exit(EXIT_STATUS);
+    })
+}
+
+fn clean_up(writing: &Writing, cleanup: Cleanup) {
+    let path = &writing.path;
+    let result = if writing.in_place {
+        warn!("{}: stopped while writing in place; it may be partly overwritten", path.display());
+        Ok(())
+    } else if let Some(len) = writing.restore_len {
+        OpenOptions::new().write(true).open(path).and_then(|file| file.set_len(len))
+    } else {
+        match cleanup {
+            Cleanup::Remove  => fs::remove_file(path),
+            Cleanup::Partial => {
+                let mut partial = OsString::from(path);
+                partial.push(".partial");
+                fs::rename(path, partial)
+            },
+        }
+    };
+    if let Err(e) = result {
+        error!("{}: cleaning up interrupted output: {}", path.display(), e);
+    }
+}
+
+/// Marks `path` as being written until the guard is dropped. An appended-to
+/// file is cut back to `restore_len` if interrupted, and one written in place
+/// is left alone.
+pub fn writing(path: &Path, restore_len: Option<u64>, in_place: bool) -> Guard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    writing.push(Writing{ id, path: path.to_path_buf(), restore_len, in_place });
+    Guard(id)
+}
+
+pub struct Guard(u64);
+
+impl Guard {
+    /// Runs `write`, which writes to the output, before or after any
+    /// interrupt cleans it up but never during.
+    pub fn write<T>(&self, write: impl FnOnce() -> T) -> T {
+        let _writes = WRITES.read().unwrap_or_else(|e| e.into_inner());
+        write()
+    }
+
+    /// Cleans up the output as for an interrupt with `Cleanup::Remove`, for
+    /// one that won't be finished.
+    pub fn undo(self) {
+        let mut writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = writing.iter().position(|writing| writing.id == self.0) {
+            clean_up(&writing.remove(i), Cleanup::Remove);
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+        writing.retain(|writing| writing.id != self.0);
+    }
+}
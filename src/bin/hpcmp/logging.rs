@@ -0,0 +1,216 @@
+//! Logging to stderr and, with `--log-file`, to a file as well.
+
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{Local, SecondsFormat, Utc};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+pub const FORMATS: &[&str] = &["text", "json"];
+
+/// How lines are written to the log file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// As on stderr.
+    Text,
+    /// An object per line, with the time, level, target, the input being
+    /// decoded and the bit offset of a decode error where there is one.
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            _      => None,
+        }
+    }
+}
+
+/// What the current thread is working on, for the log file.
+#[derive(Default)]
+struct Context {
+    file: Option<PathBuf>,
+    bit_offset: Option<u64>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::default();
+}
+
+/// Notes the input this thread is decoding, or that it's decoding none.
+pub fn set_file(path: Option<&Path>) {
+    CONTEXT.with(|context| context.borrow_mut().file = path.map(Path::to_path_buf));
+}
+
+/// Logs the bit offset of a decode error with anything logged by `f`.
+pub fn with_bit_offset(bit_offset: Option<u64>, f: impl FnOnce()) {
+    CONTEXT.with(|context| context.borrow_mut().bit_offset = bit_offset);
+    f();
+    CONTEXT.with(|context| context.borrow_mut().bit_offset = None);
+}
+
+/// Log levels by target, as given to `-v` or `--log-filter`.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Overrides the level set by `-v`.
+    level: Option<LevelFilter>,
+    /// Most specific first.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Parses a comma-separated list of `module=level`s, where `module` is
+    /// `reader`, `dict` or another module of hpcmp, or a full log target
+    /// with `::`. A bare level applies to everything else.
+    pub fn parse(spec: &str) -> Result<Filter, String> {
+        let level = |name: &str| name.parse::<LevelFilter>().map_err(|_| format!("unknown level '{}'", name));
+        let mut filter = Filter::default();
+        for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('=') {
+                Some((module, name)) => {
+                    let target = match module.contains("::") {
+                        true  => module.to_string(),
+                        false => format!("hpcmp::{}", module),
+                    };
+                    filter.targets.push((target, level(name)?));
+                },
+                None => filter.level = Some(level(part)?),
+            }
+        }
+        filter.targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(filter)
+    }
+
+    fn level(&self, target: &str, default: LevelFilter) -> LevelFilter {
+        self.targets.iter()
+            .find(|(prefix, _)| target == prefix || target.starts_with(&format!("{}::", prefix)))
+            .map(|&(_, level)| level)
+            .unwrap_or(self.level.unwrap_or(default))
+    }
+
+    fn max_level(&self, default: LevelFilter) -> LevelFilter {
+        self.targets.iter().map(|&(_, level)| level).fold(self.level.unwrap_or(default), Ord::max)
+    }
+}
+
+struct Logger {
+    level: LevelFilter,
+    filter: Filter,
+    /// Starts each line with the input it's about.
+    prefix: bool,
+    /// Whether stderr is a terminal, to color levels on.
+    color: bool,
+    file: Option<(Format, Mutex<LineWriter<File>>)>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level(metadata.target(), self.level)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let file = CONTEXT.with(|context| context.borrow().file.clone()).filter(|_| self.prefix);
+        match file {
+            Some(file) => self.write(&Record::builder()
+                .args(format_args!("[{}] {}", file.display(), record.args()))
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build()),
+            None => self.write(record),
+        }
+    }
+
+    fn flush(&self) {
+        if let Some((_, file)) = &self.file {
+            let _ = file.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        }
+    }
+}
+
+impl Logger {
+    // Each line is written whole, so lines from different threads never mix
+    fn write(&self, record: &Record) {
+        let time = Local::now().format("%Y-%m-%d %H:%M:%S,%3f");
+        let level = format!("{:<5}", record.level());
+        let level = match self.color {
+            true  => colored(record.level(), &level),
+            false => level,
+        };
+        let line = format!("{} {} [{}] {}\n", time, level, record.target(), record.args());
+        // Not stdout, where it would be mixed into output written there
+        crate::progress::suspend(|| { let _ = io::stderr().lock().write_all(line.as_bytes()); });
+        if let Some((format, file)) = &self.file {
+            let message = strip_colors(&record.args().to_string());
+            let line = match format {
+                Format::Text => format!("{} {:<5} [{}] {}", time, record.level(), record.target(), message),
+                Format::Json => CONTEXT.with(|context| {
+                    let context = context.borrow();
+                    serde_json::json!({
+                        "time": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                        "level": record.level().to_string(),
+                        "target": record.target(),
+                        "file": context.file.as_ref().map(|path| path.to_string_lossy()),
+                        "bit_offset": context.bit_offset,
+                        "message": message,
+                    }).to_string()
+                }),
+            };
+            let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+            // Nowhere left to report a failure to log
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+/// `text`, naming `level`, in the level's color.
+fn colored(level: Level, text: &str) -> String {
+    let color = match level {
+        Level::Error => 31,
+        Level::Warn  => 33,
+        Level::Info  => 36,
+        Level::Debug => 35,
+        Level::Trace => return text.to_string(),
+    };
+    format!("\x1b[{}m{}\x1b[0m", color, text)
+}
+
+/// Sets up logging at `level`, or as `filter` says for the targets it names,
+/// appending to the log file at `path` if one is given. With `prefix`, each
+/// line starts with the input being decoded when it was logged.
+pub fn init(level: LevelFilter, filter: Filter, file: Option<(&Path, Format)>, prefix: bool) -> io::Result<()> {
+    let file = match file {
+        Some((path, format)) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some((format, Mutex::new(LineWriter::new(file))))
+        },
+        None => None,
+    };
+    log::set_max_level(filter.max_level(level));
+    let logger = Logger{ level, filter, prefix, color: io::stderr().is_terminal(), file };
+    log::set_boxed_logger(Box::new(logger)).map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// `text` without the ANSI color sequences in diagnostics meant for a
+/// terminal.
+fn strip_colors(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
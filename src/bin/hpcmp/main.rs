@@ -0,0 +1,1989 @@
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use hpcmp::{AnomalyKind, IndexPolicy, StreamReport};
+use log::{LevelFilter, error, info, warn};
+
+mod archive;
+mod bruteforce;
+mod cache;
+mod carve;
+mod cat;
+mod checks;
+mod checksums;
+mod cli;
+mod codes;
+mod compare;
+mod config;
+mod container;
+mod decode;
+mod diagnose;
+mod digest;
+mod encode;
+mod filter;
+mod generate;
+mod interrupt;
+mod logging;
+mod manifest;
+mod manpage;
+mod map;
+mod multistream;
+mod output;
+mod patch;
+mod plan;
+mod progress;
+mod reference;
+mod replay;
+mod sample;
+mod scan;
+mod search;
+mod sidecar;
+mod stats;
+mod template;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+mod writer;
+
+use archive::Codec;
+use checks::{size_mismatch, Checks, Pipeline, Tally};
+use decode::Decoding;
+use diagnose::CheckFailed;
+use filter::Filters;
+use manifest::Manifest;
+use output::{is_stdout, is_stream, Lock, Position};
+use progress::Progress;
+use reference::Reference;
+use stats::{Csv, Stats, Status};
+use template::{Template, Vars};
+
+/// One input to decompress, and where its output goes.
+#[derive(Clone)]
+struct Job {
+    input: PathBuf,
+    output: PathBuf,
+    /// Further copies of the output.
+    tee: Vec<PathBuf>,
+    /// What the container the input came from says it decompresses to.
+    stored: Option<container::Stored>,
+    /// The CRC-32s of its blocks, from the container or `--block-crcs`.
+    crcs: Option<checksums::Checksums>,
+}
+
+fn main() {
+    // Parsed once to find the config file, then again with its defaults
+    let args = cli::verbose_filters(std::env::args_os().collect());
+    let usage = cli::usage();
+    let matches = cli::app(&[], &usage).get_matches_from_safe(&args).unwrap_or_else(|e| e.exit());
+    let mut defaults = config::Defaults::load(matches.value_of_os("config"))
+        .unwrap_or_else(|e| invalid("config", e));
+    defaults.layer(&matches).unwrap_or_else(|e| invalid("config", e));
+    let presets = defaults.preset_names();
+    if let Some(completions) = matches.subcommand_matches("completions") {
+        let shell = completions.value_of("shell").unwrap().parse().unwrap();
+        cli::app(&presets, &usage).gen_completions_to("hpcmp", shell, &mut io::stdout());
+        return;
+    }
+    if matches.subcommand_matches("manpage").is_some() {
+        if let Err(e) = manpage::write(&cli::app(&presets, &usage), &mut io::stdout()) {
+            failed("writing man page", e);
+        }
+        return;
+    }
+    if matches.subcommand_matches("self-test").is_some() {
+        if !self_test() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(corpus) = matches.subcommand_matches("run-corpus") {
+        match replay::run(Path::new(corpus.value_of_os("dir").unwrap())) {
+            Ok(0) => (),
+            Ok(_) => std::process::exit(1),
+            Err(e) => failed("reading corpus", e),
+        }
+        return;
+    }
+    if let Some(gen) = matches.subcommand_matches("gen-stream") {
+        if let Err(e) = gen_stream(gen) {
+            failed("generating stream", e);
+        }
+        return;
+    }
+    if let Some(gen) = matches.subcommand_matches("gen-data") {
+        if let Err(e) = gen_data(gen) {
+            failed("generating data", e);
+        }
+        return;
+    }
+    if let Some(record) = matches.subcommand_matches("record") {
+        if let Err(e) = record_codes(record) {
+            failed("recording codes", e);
+        }
+        return;
+    }
+    if let Some(emit) = matches.subcommand_matches("emit") {
+        if let Err(e) = emit_codes(emit) {
+            failed("emitting codes", e);
+        }
+        return;
+    }
+    if let Some(patch) = matches.subcommand_matches("patch") {
+        match patch_image(patch) {
+            Ok(true)  => return,
+            Ok(false) => std::process::exit(1),
+            Err(e)    => failed("patching image", e),
+        }
+    }
+    if let Some(bruteforce) = matches.subcommand_matches("bruteforce") {
+        match bruteforce_layout(bruteforce) {
+            Ok(true)  => return,
+            Ok(false) => std::process::exit(1),
+            Err(e)    => failed("bruteforce", e),
+        }
+    }
+    if let Some(extract) = matches.subcommand_matches("extract-at") {
+        if let Err(e) = extract_at(extract) {
+            failed("extracting range", e);
+        }
+        return;
+    }
+    if let Some(compress) = matches.subcommand_matches("compress") {
+        if let Err(e) = compress_files(compress) {
+            failed("compressing", e);
+        }
+        return;
+    }
+    if let Some(recompress) = matches.subcommand_matches("recompress") {
+        if let Err(e) = recompress_stream(recompress) {
+            failed("recompressing", e);
+        }
+        return;
+    }
+    if let Some(plan) = matches.subcommand_matches("plan") {
+        match plan_settings(plan) {
+            Ok(true)  => return,
+            Ok(false) => std::process::exit(1),
+            Err(e)    => failed("planning", e),
+        }
+    }
+    if let Some(cat) = matches.subcommand_matches("cat") {
+        if let Err(e) = cat_streams(cat) {
+            failed("joining streams", e);
+        }
+        return;
+    }
+    if let Some(list) = matches.subcommand_matches("list") {
+        if let Err(e) = list_members(list) {
+            failed("listing container", e);
+        }
+        return;
+    }
+    if let Some(extract) = matches.subcommand_matches("extract") {
+        match extract_members(extract) {
+            Ok(true)  => return,
+            Ok(false) => std::process::exit(1),
+            Err(e)    => failed("extracting", e),
+        }
+    }
+    if let Some(grep) = matches.subcommand_matches("grep") {
+        std::process::exit(grep_inputs(grep));
+    }
+    if let Some(scan) = matches.subcommand_matches("scan") {
+        std::process::exit(scan_images(scan));
+    }
+    if let Some(carve) = matches.subcommand_matches("carve") {
+        match carve_image(carve) {
+            Ok(true)  => return,
+            Ok(false) => std::process::exit(1),
+            Err(e)    => failed("carving image", e),
+        }
+    }
+    if let Some(compare) = matches.subcommand_matches("compare-images") {
+        std::process::exit(compare_images(compare));
+    }
+    let matches = cli::app(&presets, &usage).get_matches_from(defaults.apply(args, &matches));
+
+    // Warnings are left out with --quiet, and with --errors-json, which
+    // prints them as JSON instead
+    let quiet = matches.is_present("quiet") || matches.is_present("errors-json");
+    let log_level = match matches.occurrences_of("v") {
+        0 if quiet => LevelFilter::Error,
+        0          => LevelFilter::Warn,
+        1          => LevelFilter::Info,
+        2          => LevelFilter::Debug,
+        _          => LevelFilter::Trace,
+    };
+
+    let log_file = matches.value_of_os("log-file").map(|path| {
+        let format = matches.value_of("log-format").and_then(logging::Format::from_name).unwrap();
+        (Path::new(path), format)
+    });
+    let log_filter = matches.values_of("log-filter").map(|specs| logging::Filter::parse(&specs.collect::<Vec<_>>().join(",")))
+        .transpose()
+        .unwrap_or_else(|e| invalid("-v", e))
+        .unwrap_or_default();
+    let workers = match matches.value_of("jobs").map(str::parse::<usize>) {
+        Some(Ok(0))  => thread::available_parallelism().map_or(1, |n| n.get()),
+        Some(Ok(n))  => n,
+        Some(Err(e)) => invalid("--jobs", e),
+        None         => 1,
+    };
+    // With several files logging at once, every line says which it's about
+    let prefix = workers > 1;
+    logging::init(log_level, log_filter, log_file, prefix).unwrap_or_else(|e| invalid("--log-file", e));
+
+    let cleanup = matches.value_of("on-interrupt").and_then(interrupt::Cleanup::from_name).unwrap();
+    if let Err(e) = interrupt::install(cleanup) {
+        warn!("can't handle interrupts: {}", e);
+    }
+
+    let runner = Runner::new(&matches);
+    let jobs = runner.jobs();
+    let workers = workers.min(jobs.len());
+    let progress = (workers > 1 && !runner.quiet && !matches.is_present("no-progress"))
+        .then(|| Progress::start(jobs.len(), workers));
+    let (succeeded, failed) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    // Each worker takes the next job until there are none left
+    let next = AtomicUsize::new(0);
+    let work = |worker| {
+        if let Some(progress) = &progress {
+            progress.attach(worker);
+        }
+        while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+            progress::set_file(Some(&job.input));
+            match runner.run(job) {
+                Ok(())  => succeeded.fetch_add(1, Ordering::Relaxed),
+                Err(e) => {
+                    log_failure(&job.input, &*e, runner.errors_json);
+                    failed.fetch_add(1, Ordering::Relaxed)
+                },
+            };
+            logging::set_file(None);
+            progress::set_file(None);
+            if let Some(progress) = &progress {
+                progress.file_done();
+            }
+        }
+    };
+    thread::scope(|scope| {
+        let work = &work;
+        for worker in 1..workers {
+            scope.spawn(move || work(worker));
+        }
+        work(0);
+    });
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    let strict = matches.is_present("strict-exit");
+    let status = match (succeeded.into_inner(), failed.into_inner()) {
+        (_, 0)      => 0,
+        (0, _)      => 1,
+        _ if strict => 1,
+        _           => PARTIAL_EXIT_STATUS,
+    };
+    if let Err(e) = runner.finish() {
+        error!("writing manifest: {}", e);
+        std::process::exit(1);
+    }
+    if status != 0 {
+        std::process::exit(status);
+    }
+}
+
+/// Exit status when some inputs of a batch failed and others didn't.
+const PARTIAL_EXIT_STATUS: i32 = 2;
+
+/// Logs that `input` failed, noting where for a decode error or a
+/// `--strict` one, and with `errors_json` prints it to stderr as JSON too.
+fn log_failure(input: &Path, e: &(dyn Error + 'static), errors_json: bool) {
+    let located = diagnose::located(e);
+    let bit_offset = located.as_ref().map(|&(bit_offset, _)| bit_offset);
+    if errors_json {
+        let error = serde_json::json!({
+            "file": input.to_string_lossy(),
+            "code": diagnose::code(e),
+            "message": located.map_or_else(|| e.to_string(), |(_, message)| message),
+            "byte_offset": bit_offset.map(|bit_offset| bit_offset / 8),
+            "bit_offset": bit_offset,
+        });
+        progress::suspend(|| eprintln!("{}", error));
+    }
+    logging::with_bit_offset(bit_offset, || error!("{}: {}", input.display(), e));
+}
+
+/// Runs `hpcmp self-test`, returning whether every vector decoded right.
+fn self_test() -> bool {
+    let mut failures = 0;
+    for vector in hpcmp::VECTORS {
+        // A byte at a time as well, for ports that feed the decoder in pieces
+        let mut decoder = hpcmp::Decoder::new();
+        let mut chunked = vec![];
+        let chunked = vector.stream.chunks(1)
+            .try_for_each(|byte| decoder.decode_to_vec(byte, &mut chunked).map(drop))
+            .map(|()| chunked);
+        let problem = match (hpcmp::decompress(vector.stream), chunked) {
+            (Err(e), _) | (_, Err(e)) => Some(e.to_string()),
+            (Ok(whole), Ok(chunked)) if whole != chunked => Some("differs when decoded a byte at a time".to_string()),
+            (Ok(whole), _) if whole.len() != vector.output_len =>
+                Some(format!("{} bytes, expected {}", whole.len(), vector.output_len)),
+            (Ok(whole), _) if digest::sha256(&whole) != vector.sha256 =>
+                Some(format!("SHA-256 {}, expected {}", digest::sha256(&whole), vector.sha256)),
+            _ => None,
+        };
+        match problem {
+            Some(problem) => {
+                println!("{} ... FAILED, {}", vector.name, problem);
+                failures += 1;
+            },
+            None => println!("{} ... ok", vector.name),
+        }
+    }
+    println!("{} vectors, {} failed", hpcmp::VECTORS.len(), failures);
+    failures == 0
+}
+
+/// Runs `hpcmp gen-stream`.
+fn gen_stream(matches: &ArgMatches) -> io::Result<()> {
+    let seed = matches.value_of("seed").unwrap().parse().unwrap_or_else(|e| invalid("--seed", e));
+    let size = parse_size(matches.value_of("size").unwrap()).unwrap_or_else(|e| invalid("--size", e));
+    if size < 2 {
+        invalid("--size", "a stream decodes to at least 2 bytes");
+    }
+    let generated = generate::generate(seed, size as usize);
+    if let Some(path) = matches.value_of_os("expected") {
+        fs::write(path, &generated.output)?;
+    }
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &generated.stream),
+        None       => io::Write::write_all(&mut io::stdout(), &generated.stream),
+    }
+}
+
+/// Runs `hpcmp gen-data`.
+fn gen_data(matches: &ArgMatches) -> io::Result<()> {
+    use std::io::Write;
+    let profile = matches.value_of("profile").and_then(sample::Profile::from_name).unwrap();
+    let seed = matches.value_of("seed").unwrap().parse().unwrap_or_else(|e| invalid("--seed", e));
+    let size = parse_size(matches.value_of("size").unwrap()).unwrap_or_else(|e| invalid("--size", e));
+    match matches.value_of_os("output") {
+        Some(path) => {
+            let mut file = io::BufWriter::new(fs::File::create(path)?);
+            sample::write(profile, seed, size, &mut file)?;
+            file.flush()
+        },
+        None => {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            sample::write(profile, seed, size, &mut stdout)?;
+            stdout.flush()
+        },
+    }
+}
+
+/// Runs `hpcmp record`. The codes up to where a stream fails to decode are
+/// still written, ending with the one it failed on.
+fn record_codes(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let stream = fs::read(matches.value_of_os("input").unwrap())?;
+    let (text, result) = codes::record(&stream);
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &text)?,
+        None       => io::Write::write_all(&mut io::stdout(), text.as_bytes())?,
+    }
+    Ok(result?)
+}
+
+/// Runs `hpcmp emit`.
+fn emit_codes(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(matches.value_of_os("input").unwrap())?;
+    let stream = codes::emit(&text)?;
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &stream)?,
+        None       => io::Write::write_all(&mut io::stdout(), &stream)?,
+    }
+    Ok(())
+}
+
+/// Runs `hpcmp compress`.
+fn compress_files(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    use std::io::Write;
+
+    let inputs: Vec<&Path> = matches.values_of_os("inputs").unwrap().map(Path::new).collect();
+    let options = encode::Options{
+        block_len: matches.value_of("block-len").map(parse_size).transpose().unwrap_or_else(|e| invalid("--block-len", e)),
+        when_full: matches.value_of("when-full").and_then(encode::WhenFull::from_name).unwrap(),
+        level: matches.value_of("level").and_then(encode::Level::from_name).unwrap(),
+    };
+    let offsets = match matches.values_of("source-offset") {
+        Some(offsets) => offsets.map(parse_offset).collect::<Result<Vec<_>, _>>().unwrap_or_else(|e| invalid("--source-offset", e)),
+        None          => vec![0; inputs.len()],
+    };
+    if offsets.len() != inputs.len() {
+        invalid("--source-offset", format!("given {} times for {} inputs", offsets.len(), inputs.len()));
+    }
+    if matches.is_present("raw") && inputs.len() > 1 {
+        invalid("--raw", "only a single input can be written as a bare stream");
+    }
+    for path in &inputs {
+        let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if metadata.is_file() && metadata.len() < 2 {
+            return Err(format!("{}: the format can't hold fewer than two bytes", path.display()).into());
+        }
+    }
+    // Raw streams are written as they are made, and read from their files
+    // as they go, however large
+    let budget = matches.value_of("max-compressed-size").map(parse_size).transpose().unwrap_or_else(|e| invalid("--max-compressed-size", e));
+    let mut decisions = match matches.value_of_os("decisions") {
+        Some(path) => Some(io::BufWriter::new(fs::File::create(path)?)),
+        None       => None,
+    };
+    let block_crcs = matches.is_present("block-crcs");
+    if block_crcs && matches.is_present("raw") && !matches.is_present("output") {
+        invalid("--block-crcs", "needs --output to write a bare stream's CRCs next to");
+    }
+    let mut compress = |path: &Path, out: &mut dyn io::Write| -> Result<Vec<(u64, u64)>, Box<dyn Error>> {
+        let file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(decisions) = &mut decisions {
+            writeln!(decisions, "{}", serde_json::json!({ "event": "input", "path": path.to_string_lossy() }))?;
+        }
+        let decisions = decisions.as_mut().map(|w| w as &mut dyn io::Write);
+        let failed = |e: io::Error| format!("{}: {}", path.display(), e);
+        match budget {
+            Some(limit) => {
+                // Held back until it's known to fit
+                let mut held = encode::Budget::new(limit);
+                let starts = encode::compress_from(io::BufReader::new(file), &mut held, options, decisions).map_err(failed)?;
+                let stream = held.finish().map_err(|len| format!("{}: {}", path.display(), encode::over_budget(len, limit)))?;
+                out.write_all(&stream)?;
+                Ok(starts)
+            },
+            None        => Ok(encode::compress_from(io::BufReader::new(file), out, options, decisions).map_err(failed)?),
+        }
+    };
+    // Read again for them, rather than held while the stream is made
+    let measure = |path: &Path, starts: &[(u64, u64)]| checksums::Checksums::measure(fs::File::open(path)?, starts);
+    if matches.is_present("raw") {
+        return match matches.value_of_os("output") {
+            Some(path) => {
+                let mut out = io::BufWriter::new(fs::File::create(path)?);
+                let result = compress(inputs[0], &mut out).and_then(|starts| {
+                    // Or a write that failed goes unnoticed when it's dropped
+                    out.flush()?;
+                    if block_crcs {
+                        let mut file = io::BufWriter::new(fs::File::create(checksums::path(Path::new(path)))?);
+                        measure(inputs[0], &starts)?.write_to(&mut file)?;
+                        file.flush()?;
+                    }
+                    Ok(())
+                });
+                if result.is_err() {
+                    let _ = fs::remove_file(path);
+                }
+                result
+            },
+            None       => compress(inputs[0], &mut io::stdout().lock()).map(drop),
+        };
+    }
+    let mut compressed = vec![];
+    for path in &inputs {
+        let mut stream = vec![];
+        let starts = compress(path, &mut stream)?;
+        let stored = container::Stored::read(fs::File::open(path)?)?;
+        let crcs = if block_crcs { Some(measure(path, &starts)?) } else { None };
+        compressed.push((stored, stream, crcs));
+    }
+    let members: Vec<_> = inputs.iter().zip(&offsets).zip(compressed.iter()).map(|((path, &source_offset), (stored, stream, crcs))| container::Member{
+        name: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        source_offset,
+        stored: *stored,
+        stream,
+        crcs: crcs.clone(),
+    }).collect();
+    let mut out = vec![];
+    container::write(&mut out, &members)?;
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &out)?,
+        None       => io::stdout().write_all(&out)?,
+    }
+    Ok(())
+}
+
+/// Runs `hpcmp recompress`.
+fn recompress_stream(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let stream = fs::read(matches.value_of_os("input").unwrap())?;
+    let (data, report) = hpcmp::decompress_with_report(&stream)?;
+    let options = encode::Options{
+        block_len: match matches.value_of("block-len") {
+            Some(len) => Some(parse_size(len).unwrap_or_else(|e| invalid("--block-len", e))),
+            None      => encode::block_len(&report),
+        },
+        when_full: matches.value_of("when-full").and_then(encode::WhenFull::from_name).unwrap(),
+        level: matches.value_of("level").and_then(encode::Level::from_name).unwrap(),
+    };
+    let original = &stream[..report.compressed_len as usize];
+    println!("original: {} bytes, {} decompressed", original.len(), data.len());
+    let new = encode::compress(&data, options);
+    if hpcmp::decompress(&new).ok().as_ref() != Some(&data) {
+        return Err("the new stream doesn't decode to the original's output".into());
+    }
+    let change = new.len() as i64 - original.len() as i64;
+    let how = match change {
+        _ if new == original => "identical to the original".to_string(),
+        0                    => "the same size".to_string(),
+        _                    => format!("{} bytes {} ({:+.1}%)", change.abs(), if change < 0 { "smaller" } else { "larger" },
+                                        change as f64 * 100.0 / original.len() as f64),
+    };
+    println!("recompressed: {} bytes, {}", new.len(), how);
+    let budget = matches.value_of("max-compressed-size").map(parse_size).transpose().unwrap_or_else(|e| invalid("--max-compressed-size", e));
+    if let Some(limit) = budget.filter(|&limit| new.len() as u64 > limit) {
+        return Err(encode::over_budget(new.len() as u64, limit).into());
+    }
+    if let Some(path) = matches.value_of_os("output") {
+        fs::write(path, &new)?;
+    }
+    Ok(())
+}
+
+/// Runs `hpcmp plan`, returning whether any settings are estimated to fit.
+fn plan_settings(matches: &ArgMatches) -> Result<bool, Box<dyn Error>> {
+    use std::io::Seek;
+    let path = Path::new(matches.value_of_os("input").unwrap());
+    let limit = parse_size(matches.value_of("max-compressed-size").unwrap()).unwrap_or_else(|e| invalid("--max-compressed-size", e));
+    let count = matches.value_of("windows").unwrap().parse::<u64>().unwrap_or_else(|e| invalid("--windows", e));
+    let window = parse_size(matches.value_of("window").unwrap()).unwrap_or_else(|e| invalid("--window", e));
+    if count == 0 {
+        invalid("--windows", "must be more than 0");
+    }
+    if window < 2 {
+        invalid("--window", "the format can't hold fewer than two bytes");
+    }
+    let mut file = fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let len = file.metadata()?.len();
+    if len < 2 {
+        return Err(format!("{}: the format can't hold fewer than two bytes", path.display()).into());
+    }
+    let windows = plan::windows(&mut file, len, count, window)?;
+    let sampled: u64 = windows.iter().map(|window| window.len() as u64).sum();
+    match windows.as_slice() {
+        [_] if sampled == len => println!("input: {} bytes, compressed whole", len),
+        _                     => println!("input: {} bytes, {} windows of {} ({:.1}%)", len, windows.len(), window, sampled as f64 * 100.0 / len as f64),
+    }
+    let window = windows.iter().map(|window| window.len() as u64).max().unwrap_or(0);
+    let mut trials = plan::run_all(&windows, plan::candidates(window));
+    trials.sort_by_key(|trial| (trial.estimate(len), trial.elapsed));
+    println!("{:>12} {:>7} {:>9}  settings", "estimate", "ratio", "time");
+    for trial in &trials {
+        let estimate = trial.estimate(len);
+        println!("{:>12} {:>6.1}% {:>8.1}s  {}{}", estimate, estimate as f64 * 100.0 / len as f64, trial.time(len).as_secs_f64(),
+                 plan::args(trial.options), if estimate <= limit { "  fits" } else { "" });
+    }
+    // The quickest of those that fit, as the smallest may take far longer
+    let best = match trials.iter().filter(|trial| trial.estimate(len) <= limit).min_by_key(|trial| trial.elapsed) {
+        Some(best) => best,
+        None       => {
+            let smallest = &trials[0];
+            println!("no settings fit: the smallest, {}, comes to about {} bytes, {} over {}",
+                     plan::args(smallest.options), smallest.estimate(len), smallest.estimate(len) - limit, limit);
+            return Ok(false);
+        },
+    };
+    println!("recommended: {}, about {} bytes, {} under {}, in about {:.1}s",
+             plan::args(best.options), best.estimate(len), limit - best.estimate(len), limit, best.time(len).as_secs_f64());
+    if !matches.is_present("confirm") {
+        return Ok(true);
+    }
+    file.rewind()?;
+    let mut held = encode::Budget::new(limit);
+    encode::compress_from(io::BufReader::new(file), &mut held, best.options, None)?;
+    match held.finish() {
+        Ok(stream) => {
+            println!("confirmed: {} bytes, {} under", stream.len(), limit - stream.len() as u64);
+            Ok(true)
+        },
+        Err(len)   => {
+            println!("not confirmed: {}", encode::over_budget(len, limit));
+            Ok(false)
+        },
+    }
+}
+
+/// Runs `hpcmp cat`.
+fn cat_streams(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    use std::io::Write;
+    let paths: Vec<&OsStr> = matches.values_of_os("inputs").unwrap().collect();
+    let streams = paths.iter().map(fs::read).collect::<io::Result<Vec<_>>>()?;
+    let streams: Vec<&[u8]> = streams.iter().map(Vec::as_slice).collect();
+    let (joined, data) = cat::join(&streams).map_err(|(i, e)| format!("{}: {}", Path::new(paths[i]).display(), e))?;
+    if hpcmp::decompress(&joined).ok().as_ref() != Some(&data) {
+        return Err("the joined stream doesn't decode to the inputs' outputs".into());
+    }
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &joined)?,
+        None       => io::stdout().write_all(&joined)?,
+    }
+    Ok(())
+}
+
+/// Runs `hpcmp list`.
+fn list_members(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    use std::io::Write;
+
+    let data = fs::read(matches.value_of_os("archive").unwrap())?;
+    let members = container::read(&data)?;
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    writeln!(out, "{:>10} {:>12} {:>10} {:>8}  name", "compressed", "decompressed", "source", "crc32")?;
+    for member in &members {
+        writeln!(out, "{:>10} {:>12} {:#010x} {:08x}  {}",
+                 member.stream.len(), member.stored.len, member.source_offset, member.stored.crc32, member.name)?;
+    }
+    Ok(out.flush()?)
+}
+
+/// Runs `hpcmp extract`, returning whether every member asked for was
+/// there and extracted. A member that doesn't decode to what was stored
+/// with it isn't written.
+fn extract_members(matches: &ArgMatches) -> Result<bool, Box<dyn Error>> {
+    let path = Path::new(matches.value_of_os("archive").unwrap());
+    let dir = Path::new(matches.value_of_os("out-dir").unwrap());
+    let patterns = matches.values_of("member").into_iter().flatten()
+        .map(|name| glob::Pattern::new(name).map(|pattern| (name, pattern)).unwrap_or_else(|e| invalid("--member", e)))
+        .collect::<Vec<_>>();
+    let data = fs::read(path)?;
+    let members = container::read(&data)?;
+    fs::create_dir_all(dir)?;
+    let mut ok = true;
+    for (name, _) in patterns.iter().filter(|(_, pattern)| !members.iter().any(|member| pattern.matches(&member.name))) {
+        eprintln!("hpcmp: {}: no member {}", path.display(), name);
+        ok = false;
+    }
+    let wanted = |member: &container::Member| patterns.is_empty() || patterns.iter().any(|(_, pattern)| pattern.matches(&member.name));
+    for member in members.iter().filter(|member| wanted(member)) {
+        if let Err(e) = extract_member(member, dir) {
+            eprintln!("hpcmp: {}: {}: {}", path.display(), member.name, e);
+            ok = false;
+        }
+    }
+    Ok(ok)
+}
+
+/// Decompresses `member` into `dir` under its name, if it decodes to what
+/// was stored with it.
+fn extract_member(member: &container::Member, dir: &Path) -> Result<(), Box<dyn Error>> {
+    let name = Path::new(&member.name).file_name().ok_or("not a file name")?;
+    let output = hpcmp::decompress(member.stream).map_err(|e| diagnose::explain(e, hpcmp::Decoder::new(), member.stream))?;
+    if let Some(crcs) = &member.crcs {
+        crcs.check_data(&output)?;
+    }
+    member.stored.check_data(&output)?;
+    Ok(fs::write(dir.join(name), &output)?)
+}
+
+/// Runs `hpcmp grep`, returning its exit status: as grep's, 0 if anything
+/// matched, 1 if nothing did and 2 if an input couldn't be searched.
+fn grep_inputs(matches: &ArgMatches) -> i32 {
+    use std::io::Write;
+
+    let hex = matches.values_of("hex").into_iter().flatten().map(search::Pattern::hex);
+    let strings = matches.values_of("string").into_iter().flatten().map(search::Pattern::string);
+    let patterns = hex.chain(strings).collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| invalid("pattern", e));
+    let inputs: Vec<_> = matches.values_of_os("input").unwrap().map(Path::new).collect();
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let (mut matched, mut errors) = (false, false);
+    for input in &inputs {
+        let found = |offset, pattern: &search::Pattern| match inputs.len() {
+            1 => writeln!(out, "{:#010x} {}", offset, pattern.name),
+            _ => writeln!(out, "{}:{:#010x} {}", input.display(), offset, pattern.name),
+        };
+        match fs::File::open(input).and_then(|file| search::search(file, &patterns, found)) {
+            Ok(n)  => matched |= n > 0,
+            // Whatever reads the matches has seen enough, as with `| head`
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return 0,
+            Err(e) => {
+                let _ = out.flush();
+                eprintln!("hpcmp: {}: {}", input.display(), e);
+                errors = true;
+            },
+        }
+    }
+    match out.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return 0,
+        Err(e) => failed("writing matches", e),
+        Ok(()) => (),
+    }
+    match (matched, errors) {
+        (_, true)  => 2,
+        (true, _)  => 0,
+        (false, _) => 1,
+    }
+}
+
+/// Runs `hpcmp scan`, returning its exit status: as for `hpcmp grep`, 0 if
+/// any streams were found, 1 if none were and 2 if an image couldn't be read.
+fn scan_images(matches: &ArgMatches) -> i32 {
+    use std::io::Write;
+
+    let options = scan_options(matches);
+    let inputs: Vec<_> = matches.values_of_os("input").unwrap().map(Path::new).collect();
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let (mut any, mut errors) = (false, false);
+    for input in &inputs {
+        let image = match fs::read(input) {
+            Ok(image) => image,
+            Err(e) => {
+                let _ = out.flush();
+                eprintln!("hpcmp: {}: {}", input.display(), e);
+                errors = true;
+                continue;
+            },
+        };
+        for found in scan_image(matches, input, &image, options) {
+            any = true;
+            let prefix = match inputs.len() {
+                1 => String::new(),
+                _ => format!("{}:", input.display()),
+            };
+            let result = match &found.error {
+                None    => writeln!(out, "{}{:#010x} {} bytes, {} decompressed, score {}", prefix, found.offset, found.input_len, found.output_len,
+                                    found.score),
+                Some(e) => writeln!(out, "{}{:#010x} {} bytes, {} decompressed before: {}, score {}", prefix, found.offset, found.input_len,
+                                    found.output_len, e, found.score),
+            };
+            match result {
+                Ok(()) => (),
+                // Whatever reads them has seen enough, as with `| head`
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return 0,
+                Err(e) => failed("writing streams found", e),
+            }
+        }
+    }
+    match out.flush() {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return 0,
+        Err(e) => failed("writing streams found", e),
+        Ok(()) => (),
+    }
+    match (any, errors) {
+        (_, true)  => 2,
+        (true, _)  => 0,
+        (false, _) => 1,
+    }
+}
+
+/// What to look for, from `hpcmp scan`'s options or another subcommand's
+/// that shares them.
+fn scan_options(matches: &ArgMatches) -> scan::Options {
+    scan::Options{
+        align: match matches.value_of("align").map(parse_offset) {
+            Some(Ok(0))  => invalid("--align", "must be more than 0"),
+            Some(Ok(n))  => n.min(usize::MAX as u64) as usize,
+            Some(Err(e)) => invalid("--align", e),
+            None         => 1,
+        },
+        min_output: parse_size(matches.value_of("min-output").unwrap()).unwrap_or_else(|e| invalid("--min-output", e)),
+        partial: matches.is_present("partial"),
+        min_score: match matches.value_of("min-score").unwrap().parse() {
+            Ok(score) if score <= 100 => score,
+            Ok(_)  => invalid("--min-score", "must be 100 or less"),
+            Err(e) => invalid("--min-score", e),
+        },
+    }
+}
+
+/// The streams in `image`, read from `path`, from the scan cached next to it
+/// unless `--no-cache` is given.
+fn scan_image(matches: &ArgMatches, path: &Path, image: &[u8], options: scan::Options) -> Vec<scan::Found> {
+    match matches.is_present("no-cache") {
+        true  => scan::scan(image, options),
+        false => cache::scan(path, image, options),
+    }
+}
+
+/// Runs `hpcmp carve`, returning whether any streams were found. What was
+/// carved is listed in `carve.json` in the directory.
+fn carve_image(matches: &ArgMatches) -> Result<bool, Box<dyn Error>> {
+    use std::io::Write;
+
+    let options = scan_options(matches);
+    let template = Template::parse(matches.value_of("template").unwrap()).unwrap_or_else(|e| invalid("--template", e));
+    let duplicates = matches.value_of("duplicates").and_then(carve::Duplicates::from_name).unwrap();
+    let path = Path::new(matches.value_of_os("image").unwrap());
+    let dir = Path::new(matches.value_of_os("out-dir").unwrap());
+    let image = fs::read(path)?;
+    fs::create_dir_all(dir)?;
+    let load_base = parse_offset(matches.value_of("load-base").unwrap()).unwrap_or_else(|e| invalid("--load-base", e));
+    let found = scan_image(matches, path, &image, options);
+    let carved = carve::carve(&image, found, duplicates, load_base, |index, offset| dir.join(template.render(&Vars{
+        stem: path.file_stem().unwrap_or_default(),
+        index: index as u64,
+        offset,
+        out_offset: 0,
+    })))?;
+    fs::write(dir.join("carve.json"), serde_json::to_string_pretty(&carve::manifest(path, dir, &carved))?)?;
+    for format in matches.values_of("map").into_iter().flatten().filter_map(map::Format::from_name) {
+        fs::write(dir.join(format.file_name()), map::script(format, path, dir, &carved))?;
+    }
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let result = carved.iter().try_for_each(|carved| match (&carved.file, carved.duplicate_of) {
+        (Some(file), None)        => writeln!(out, "{:#010x} {} bytes -> {}", carved.offset, carved.decompressed_len, file.display()),
+        (Some(file), Some(first)) => writeln!(out, "{:#010x} {} bytes -> {}, same as {:#010x}", carved.offset, carved.decompressed_len, file.display(), first),
+        (None, Some(first))       => writeln!(out, "{:#010x} {} bytes, same as {:#010x}, skipped", carved.offset, carved.decompressed_len, first),
+        (None, None)              => unreachable!(),
+    });
+    match result.and_then(|()| out.flush()) {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => (),
+    }
+    Ok(!carved.is_empty())
+}
+
+/// Runs `hpcmp compare-images`, returning its exit status: as cmp's, 0 if
+/// the images' streams decode the same, 1 if they don't and 2 if an image
+/// couldn't be read or a stream in it decoded.
+fn compare_images(matches: &ArgMatches) -> i32 {
+    use std::io::Write;
+
+    let options = scan_options(matches);
+    let mut streams = ["old", "new"].iter().map(|name| {
+        let path = Path::new(matches.value_of_os(name).unwrap());
+        fs::read(path).map_err(Box::<dyn Error>::from)
+            .and_then(|image| Ok(compare::streams(&image, scan_image(matches, path, &image, options))?))
+            .unwrap_or_else(|e| {
+                eprintln!("hpcmp: {}: {}", path.display(), e);
+                std::process::exit(2);
+            })
+    });
+    let (old, new) = (streams.next().unwrap(), streams.next().unwrap());
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let mut same = true;
+    let result = compare::pair(&old, &new).into_iter().try_for_each(|pair| {
+        let describe = |stream: &compare::Stream| format!("{} bytes, crc32 {}", stream.output.len(), digest::crc32(&stream.output));
+        let (a, b) = match pair {
+            (Some(a), Some(b)) => (&old[a], &new[b]),
+            (Some(a), None) => {
+                same = false;
+                return writeln!(out, "{:#010x}: only in old, {}", old[a].offset, describe(&old[a]));
+            },
+            (None, Some(b)) => {
+                same = false;
+                return writeln!(out, "{:#010x}: only in new, {}", new[b].offset, describe(&new[b]));
+            },
+            (None, None) => unreachable!(),
+        };
+        if a.output == b.output {
+            return writeln!(out, "{:#010x} -> {:#010x}: identical, {}", a.offset, b.offset, describe(a));
+        }
+        same = false;
+        writeln!(out, "{:#010x} -> {:#010x}: changed, {} -> {} bytes, crc32 {} -> {}", a.offset, b.offset,
+                 a.output.len(), b.output.len(), digest::crc32(&a.output), digest::crc32(&b.output))?;
+        let changes = compare::blocks(a, b);
+        let mut index = 0;
+        while index < changes.len() {
+            let (block_a, block_b) = (a.block(index).unwrap_or_default(), b.block(index).unwrap_or_default());
+            match changes[index] {
+                compare::Change::Identical => {
+                    // Runs of them are only worth a line
+                    let run = changes[index..].iter().take_while(|&&change| change == compare::Change::Identical).count();
+                    let len: usize = (index..index + run).map(|i| a.blocks[i].len()).sum();
+                    match run {
+                        1 => writeln!(out, "  block {}: identical, {} bytes", index, len)?,
+                        _ => writeln!(out, "  blocks {}-{}: identical, {} bytes", index, index + run - 1, len)?,
+                    }
+                    index += run;
+                    continue;
+                },
+                compare::Change::Changed => writeln!(out, "  block {}: changed, {} -> {} bytes, crc32 {} -> {}", index,
+                                                     block_a.len(), block_b.len(), digest::crc32(block_a), digest::crc32(block_b))?,
+                compare::Change::OnlyInOld => writeln!(out, "  block {}: only in old, {} bytes, crc32 {}", index, block_a.len(), digest::crc32(block_a))?,
+                compare::Change::OnlyInNew => writeln!(out, "  block {}: only in new, {} bytes, crc32 {}", index, block_b.len(), digest::crc32(block_b))?,
+            }
+            index += 1;
+        }
+        Ok(())
+    });
+    match result.and_then(|()| out.flush()) {
+        // Whatever reads them has seen enough, as with `| head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(e) => failed("writing comparison", e),
+        Ok(()) => (),
+    }
+    match same {
+        true  => 0,
+        false => 1,
+    }
+}
+
+/// Runs `hpcmp patch`, returning whether the patched stream fits where the
+/// original was. The image is only written if it does.
+fn patch_image(matches: &ArgMatches) -> Result<bool, Box<dyn Error>> {
+    let fill = match matches.value_of("fill").map(parse_offset).unwrap() {
+        Ok(byte) if byte <= 0xff => byte as u8,
+        Ok(_)  => invalid("--fill", "must be a byte"),
+        Err(e) => invalid("--fill", e),
+    };
+    let path = Path::new(matches.value_of_os("image").unwrap());
+    let mut image = fs::read(path)?;
+    let offset = match matches.value_of("offset").map(parse_offset) {
+        Some(Ok(offset)) if offset < image.len() as u64 => offset as usize,
+        Some(Ok(_))  => invalid("--offset", "past the end of the image"),
+        Some(Err(e)) => invalid("--offset", e),
+        None => {
+            // As scan does by default
+            let options = scan::Options{ align: 1, min_output: 1 << 10, partial: false, min_score: 40 };
+            match &scan_image(matches, path, &image, options)[..] {
+                [found] => found.offset as usize,
+                []      => return Err("found no stream in the image; give its --offset".into()),
+                found   => {
+                    let offsets: Vec<_> = found.iter().map(|found| format!("{:#x}", found.offset)).collect();
+                    return Err(format!("found streams at {}; give the --offset of one", offsets.join(", ")).into());
+                },
+            }
+        },
+    };
+    let (old, report) = hpcmp::decompress_with_report(&image[offset..])?;
+    let slot = report.compressed_len as usize;
+    println!("{:#010x}: {} bytes, {} decompressed", offset, slot, old.len());
+
+    let new = patch::apply(&fs::read(matches.value_of_os("apply").unwrap())?, &old)?;
+    if new.len() < 2 {
+        return Err("the patched output is too short for a stream, which holds at least 2 bytes".into());
+    }
+    let options = encode::Options{
+        block_len: encode::block_len(&report),
+        when_full: matches.value_of("when-full").and_then(encode::WhenFull::from_name).unwrap(),
+        level: matches.value_of("level").and_then(encode::Level::from_name).unwrap(),
+    };
+    let stream = encode::compress(&new, options);
+    if hpcmp::decompress(&stream).ok().as_ref() != Some(&new) {
+        return Err("the recompressed stream doesn't decode to the patched output".into());
+    }
+    let fits = stream.len() <= slot;
+    match fits {
+        true  => println!("patched: {} bytes, {} decompressed; fits with {} to spare", stream.len(), new.len(), slot - stream.len()),
+        false => println!("patched: {} bytes, {} decompressed; {} over the {}-byte slot", stream.len(), new.len(), stream.len() - slot, slot),
+    }
+    if let (true, Some(path)) = (fits, matches.value_of_os("output")) {
+        image[offset..offset + stream.len()].copy_from_slice(&stream);
+        image[offset + stream.len()..offset + slot].fill(fill);
+        fs::write(path, &image)?;
+    }
+    Ok(fits)
+}
+
+/// Runs `hpcmp bruteforce`, printing the best layout as a variant spec.
+/// Returns whether any layout decoded the start of the stream at all.
+fn bruteforce_layout(matches: &ArgMatches) -> Result<bool, Box<dyn Error>> {
+    let path = Path::new(matches.value_of_os("input").unwrap());
+    let prefix_len = parse_size(matches.value_of("prefix").unwrap()).unwrap_or_else(|e| invalid("--prefix", e));
+    let data = fs::read(path)?;
+    let offset = match matches.value_of("offset").map(parse_offset) {
+        Some(Ok(offset)) if offset < data.len() as u64 => offset as usize,
+        Some(Ok(_))  => invalid("--offset", "past the end of the input"),
+        Some(Err(e)) => invalid("--offset", e),
+        None         => 0,
+    };
+    let prefix = &data[offset..data.len().min(offset.saturating_add(prefix_len.min(usize::MAX as u64) as usize))];
+    let attempts = bruteforce::rank(prefix);
+    if attempts.is_empty() {
+        eprintln!("hpcmp: {}: no layout tried starts with a reset", path.display());
+        return Ok(false);
+    }
+    print!("{}", bruteforce::spec(&path.display().to_string(), prefix.len(), &attempts));
+    Ok(true)
+}
+
+/// Runs `hpcmp extract-at`. Finding the reset points decodes the whole
+/// stream, so they are kept in the `--index` file for next time; a stream
+/// in an image that has been scanned has them in the scan's cache already.
+fn extract_at(matches: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let range = parse_range(matches.value_of("range").unwrap()).unwrap_or_else(|e| invalid("--range", e));
+    let path = Path::new(matches.value_of_os("input").unwrap());
+    let mut input = fs::File::open(path)?;
+    let len = input.metadata()?.len();
+    let offset = match matches.value_of("offset").map(parse_offset) {
+        Some(Ok(offset)) if offset < len => offset,
+        Some(Ok(_))  => invalid("--offset", "past the end of the input"),
+        Some(Err(e)) => invalid("--offset", e),
+        None         => 0,
+    };
+    let index = match matches.value_of_os("index") {
+        Some(path) if Path::new(path).exists() => {
+            let index = hpcmp::StreamIndex::read_from(io::BufReader::new(fs::File::open(path)?))?;
+            if index.compressed_len > len - offset {
+                return Err(format!("{} is the index of a longer stream", Path::new(path).display()).into());
+            }
+            index
+        },
+        index_path => match cached_index(matches, path, offset) {
+            Some(index) => index,
+            None => {
+                io::Seek::seek(&mut input, io::SeekFrom::Start(offset))?;
+                let index = hpcmp::build_index(io::BufReader::new(&input))?;
+                if let Some(path) = index_path {
+                    let mut file = io::BufWriter::new(fs::File::create(path)?);
+                    index.write_to(&mut file)?;
+                    io::Write::flush(&mut file)?;
+                }
+                index
+            },
+        },
+    };
+    io::Seek::seek(&mut input, io::SeekFrom::Start(offset))?;
+    if range.start >= index.decompressed_len {
+        return Err(format!("the range starts past the end of the {}-byte output", index.decompressed_len).into());
+    }
+    let data = hpcmp::decompress_range(input, &index, range)?;
+    match matches.value_of_os("output") {
+        Some(path) => fs::write(path, &data)?,
+        None       => io::Write::write_all(&mut io::stdout(), &data)?,
+    }
+    Ok(())
+}
+
+/// The index of the stream at `offset` in the image at `path`, if the image
+/// was scanned with it found whole. Without `--offset` the input is taken
+/// to be a stream of its own rather than an image.
+fn cached_index(matches: &ArgMatches, path: &Path, offset: u64) -> Option<hpcmp::StreamIndex> {
+    if !matches.is_present("offset") || !cache::path(path).exists() {
+        return None;
+    }
+    let cached = cache::load(path, &fs::read(path).ok()?)?;
+    let found = cached.found.into_iter().find(|found| found.offset == offset && found.error.is_none())?;
+    Some(hpcmp::StreamIndex{ resets: found.resets, compressed_len: found.input_len, decompressed_len: found.output_len })
+}
+
+/// Locks `mutex`, whether or not another job panicked holding it.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Exits after `what` failed, before logging is set up.
+fn failed(what: &str, e: impl std::fmt::Display) -> ! {
+    eprintln!("hpcmp: {}: {}", what, e);
+    std::process::exit(1);
+}
+
+/// Exits with a usage error about `arg`.
+fn invalid(arg: &str, e: impl std::fmt::Display) -> ! {
+    clap::Error::with_description(&format!("Invalid {}: {}", arg, e), clap::ErrorKind::InvalidValue).exit()
+}
+
+/// Whether io_uring can be used here, warning that standard IO will be used
+/// instead if not.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn uring_available() -> bool {
+    match uring::probe() {
+        Ok(())  => true,
+        Err(e) => {
+            warn!("can't use io_uring, using standard IO instead: {}", e);
+            false
+        },
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+fn uring_available() -> bool {
+    false
+}
+
+/// Parses a decimal or 0x-prefixed hex offset.
+fn parse_offset(s: &str) -> Result<u64, std::num::ParseIntError> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None      => s.parse(),
+    }
+}
+
+/// Parses a range of offsets, `start..end`.
+fn parse_range(s: &str) -> Result<std::ops::Range<u64>, String> {
+    let (start, end) = s.split_once("..").ok_or("expected START..END")?;
+    let start = parse_offset(start).map_err(|e| format!("{:?}: {}", start, e))?;
+    let end = parse_offset(end).map_err(|e| format!("{:?}: {}", end, e))?;
+    match start < end {
+        true  => Ok(start..end),
+        false => Err("the range is empty".to_string()),
+    }
+}
+
+/// `data` with the bytes of each `word`-byte word reversed, for ROMs stored
+/// word-swapped. A partial word at the end fails it.
+fn swap(data: &[u8], word: usize) -> Result<Vec<u8>, CheckFailed> {
+    if !data.len().is_multiple_of(word) {
+        return Err(CheckFailed{
+            code: "swap_partial_word",
+            message: format!("output is {} bytes, not a whole number of {}-bit words for --swap", data.len(), word * 8),
+        });
+    }
+    Ok(data.chunks_exact(word).flat_map(|word| word.iter().rev()).copied().collect())
+}
+
+/// `data` shifted down by `bits` bits, so that a stream starting that far
+/// into its first byte, counting from the least significant bit, starts on
+/// a byte. Its resets then realign to bytes of the stream rather than of the
+/// input, as they must for a stream packed into a bitfield.
+fn shift(data: &[u8], bits: u8) -> Vec<u8> {
+    let next = data.iter().skip(1).chain(&[0]);
+    data.iter().zip(next).map(|(&byte, &next)| byte >> bits | next << (8 - bits)).collect()
+}
+
+/// `e`, from decoding the stream `start` bytes into `input`, explained if
+/// it's a decode error rather than, say, a timeout.
+fn explained(e: Box<dyn Error>, decoding: &Decoding, input: &[u8], start: usize) -> Box<dyn Error> {
+    match e.downcast() {
+        Ok(e)  => diagnose::explain_in(*e, decoding.decoder(), input, start).into(),
+        Err(e) => e,
+    }
+}
+
+/// Parses a size in bytes, with an optional K, M or G suffix for KiB, MiB
+/// or GiB.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, shift) = match s.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&s[..i], 10),
+        Some((i, 'm' | 'M')) => (&s[..i], 20),
+        Some((i, 'g' | 'G')) => (&s[..i], 30),
+        _                    => (s, 0),
+    };
+    let n: u64 = digits.parse().map_err(|e| format!("{}", e))?;
+    n.checked_mul(1 << shift).ok_or_else(|| "too large".to_string())
+}
+
+/// Parses a duration, in seconds unless it ends in ms, s, m or h.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => s.split_at(i),
+        None    => (s, "s"),
+    };
+    let n: f64 = digits.parse().map_err(|e| format!("{}", e))?;
+    let seconds = match unit {
+        "ms" => n / 1000.0,
+        "s"  => n,
+        "m"  => n * 60.0,
+        "h"  => n * 3600.0,
+        _    => return Err(format!("unknown unit {:?}; expected ms, s, m or h", unit)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("{}", e))
+}
+
+/// Options shared by every job, and what has been gathered across them. Jobs
+/// may run on several threads at once.
+struct Runner<'a> {
+    matches: &'a ArgMatches<'a>,
+    out_dir: Option<PathBuf>,
+    /// Names outputs in `out_dir`.
+    template: Template,
+    /// Names blocks in split mode, or streams with `--multistream split`.
+    split: Option<Template>,
+    multistream: Option<multistream::Mode>,
+    output_length: Option<u64>,
+    /// Reads the length header of `--length-header`.
+    length_header: Option<fn([u8; 4]) -> u32>,
+    member: glob::Pattern,
+    codec: Option<Codec>,
+    preserve: bool,
+    max_output: Option<u64>,
+    /// Set for `--chunk-size`.
+    chunk_size: Option<usize>,
+    /// From `--expected-size`, or else `--index`.
+    expected_size: Option<u64>,
+    /// Set for `--index`.
+    index: Option<hpcmp::StreamIndex>,
+    /// Set for `--block-crcs`.
+    block_crcs: Option<checksums::Checksums>,
+    /// Set for `--mmap`.
+    mmap: bool,
+    /// Set for `--skip-existing`, to whether existing outputs are verified.
+    skip_existing: Option<bool>,
+    output: output::Options,
+    manifest: Option<Mutex<Manifest>>,
+    /// Set for `--strict`, which fails inputs with anomalies rather than
+    /// warning of them.
+    strict: bool,
+    /// Set for `--time`.
+    time: bool,
+    timeout: Option<Duration>,
+    /// What decoding does with an index that refers to nothing, for
+    /// `--invalid-index`.
+    index_policy: IndexPolicy,
+    quiet: bool,
+    /// Set for `--errors-json`, which also keeps summaries off stderr.
+    errors_json: bool,
+    csv: Option<Mutex<Csv>>,
+    reference: Option<Reference>,
+    /// Set for `--reverse-input`.
+    reverse_input: bool,
+    /// Where in each input the stream starts, from `--offset` and
+    /// `--bit-offset`.
+    offset: u64,
+    bit_offset: u8,
+    /// Bytes in each word swapped, for `--swap`.
+    swap: Option<usize>,
+    filters: Option<Filters>,
+    /// For every job that succeeded.
+    total: Mutex<Stats>,
+}
+
+impl<'a> Runner<'a> {
+    fn new(matches: &'a ArgMatches<'a>) -> Runner<'a> {
+        let template = |default| {
+            let template = matches.value_of("template").unwrap_or(default);
+            Template::parse(template).unwrap_or_else(|e| invalid("--template", e))
+        };
+        let index = matches.value_of_os("index").map(|path| {
+            fs::File::open(path)
+                .map_err(Box::<dyn Error>::from)
+                .and_then(|file| Ok(hpcmp::StreamIndex::read_from(io::BufReader::new(file))?))
+                .unwrap_or_else(|e| invalid("--index", e))
+        });
+        let length_header = matches.value_of("length-header").is_some_and(|format| format != "none");
+        if matches.is_present("mmap") && !matches.is_present("expected-size") && index.is_none() && !length_header {
+            invalid("--mmap", "needs the size of the output from --expected-size, --index or --length-header");
+        }
+        Runner{
+            matches,
+            out_dir: matches.value_of_os("out-dir").map(PathBuf::from),
+            // In split mode this only names the sidecar files
+            template: template("{stem}"),
+            split: (matches.is_present("split") || matches.value_of("multistream") == Some("split"))
+                .then(|| template("{stem}_{index}")),
+            multistream: matches.value_of("multistream").and_then(multistream::Mode::from_name),
+            length_header: match matches.value_of("length-header") {
+                Some("u32le") => Some(u32::from_le_bytes),
+                Some("u32be") => Some(u32::from_be_bytes),
+                _             => None,
+            },
+            output_length: matches.value_of("output-length")
+                .map(|size| parse_size(size).unwrap_or_else(|e| invalid("--output-length", e))),
+            member: glob::Pattern::new(matches.value_of("member").unwrap_or("*"))
+                .unwrap_or_else(|e| invalid("--member", e)),
+            codec: matches.value_of("post-compress").and_then(Codec::from_name),
+            preserve: matches.is_present("preserve"),
+            max_output: matches.value_of("max-output")
+                .map(|size| parse_size(size).unwrap_or_else(|e| invalid("--max-output", e))),
+            chunk_size: matches.value_of("chunk-size").map(|size| match parse_size(size) {
+                Ok(0)    => invalid("--chunk-size", "must be more than 0"),
+                Ok(size) => size.min(usize::MAX as u64) as usize,
+                Err(e)   => invalid("--chunk-size", e),
+            }),
+            expected_size: match (matches.value_of("expected-size"), &index) {
+                (Some(size), index) => {
+                    let size = parse_size(size).unwrap_or_else(|e| invalid("--expected-size", e));
+                    if index.as_ref().is_some_and(|index| index.decompressed_len != size) {
+                        invalid("--expected-size", "doesn't match the size in --index");
+                    }
+                    Some(size)
+                },
+                (None, index) => index.as_ref().map(|index| index.decompressed_len),
+            },
+            index,
+            block_crcs: matches.value_of_os("block-crcs").map(|path| {
+                fs::File::open(path)
+                    .and_then(|file| checksums::Checksums::read_from(io::BufReader::new(file)))
+                    .unwrap_or_else(|e| invalid("--block-crcs", e))
+            }),
+            mmap: matches.is_present("mmap"),
+            skip_existing: matches.is_present("skip-existing").then(|| {
+                let verify = matches.value_of("skip-existing") == Some("verify");
+                if verify && !matches.is_present("manifest") {
+                    invalid("--skip-existing", "=verify needs --manifest to check against");
+                }
+                verify
+            }),
+            output: output::Options{
+                sparse: matches.is_present("sparse"),
+                position: match matches.value_of("at") {
+                    Some(at) => Position::At(parse_offset(at).unwrap_or_else(|e| invalid("--at", e))),
+                    None if matches.is_present("append") => Position::Append,
+                    None => Position::Truncate,
+                },
+                lock: match matches.value_of("lock") {
+                    Some("wait") => Lock::Wait,
+                    Some("skip") => Lock::Skip,
+                    _            => Lock::None,
+                },
+                uring: matches.value_of("io") == Some("uring") && uring_available(),
+            },
+            manifest: matches.value_of_os("manifest")
+                .map(|path| Mutex::new(Manifest::new(path, matches.is_present("manifest-inputs")))),
+            strict: matches.is_present("strict"),
+            time: matches.is_present("time"),
+            timeout: matches.value_of("timeout").map(|timeout| match parse_duration(timeout) {
+                Ok(timeout) if timeout.is_zero() => invalid("--timeout", "must be more than 0"),
+                Ok(timeout) => timeout,
+                Err(e)      => invalid("--timeout", e),
+            }),
+            index_policy: match matches.value_of("invalid-index") {
+                Some("substitute") => IndexPolicy::Substitute,
+                _                  => IndexPolicy::Fail,
+            },
+            quiet: matches.is_present("quiet") || matches.is_present("errors-json"),
+            errors_json: matches.is_present("errors-json"),
+            csv: matches.value_of_os("summary-csv")
+                .map(|path| Mutex::new(Csv::open(Path::new(path)).unwrap_or_else(|e| invalid("--summary-csv", e)))),
+            reference: matches.value_of("reference-cmd").map(Reference::new),
+            reverse_input: matches.is_present("reverse-input"),
+            offset: matches.value_of("offset")
+                .map_or(0, |offset| parse_offset(offset).unwrap_or_else(|e| invalid("--offset", e))),
+            bit_offset: matches.value_of("bit-offset").map_or(0, |bits| bits.parse().unwrap()),
+            swap: matches.value_of("swap").map(|bits| bits.parse::<usize>().unwrap() / 8),
+            filters: matches.values_of("filter").map(|commands| Filters::new(commands.map(str::to_string).collect())),
+            total: Mutex::new(Stats::default()),
+        }
+    }
+
+    fn jobs(&self) -> Vec<Job> {
+        let files: Vec<&OsStr> = self.matches.values_of_os("files").unwrap().collect();
+        if self.out_dir.is_some() {
+            if self.block_crcs.is_some() && files.len() > 1 {
+                invalid("--block-crcs", "holds the CRCs of a single stream, so takes a single input");
+            }
+            return files.iter().enumerate().map(|(index, input)| Job{
+                input: input.into(),
+                output: self.output_for(Path::new(input), index),
+                tee: vec![],
+                stored: None,
+                crcs: self.block_crcs.clone(),
+            }).collect();
+        }
+        let mut outputs: Vec<PathBuf> = files.iter().skip(1).map(PathBuf::from).collect();
+        outputs.extend(self.matches.values_of_os("output").into_iter().flatten().map(PathBuf::from));
+        if files.len() > 2 || outputs.is_empty() {
+            clap::Error::with_description(
+                "Expected an input and an output, or --out-dir with any number of inputs",
+                clap::ErrorKind::WrongNumberOfValues,
+            ).exit();
+        }
+        let output = outputs.remove(0);
+        vec![Job{ input: files[0].into(), output, tee: outputs, stored: None, crcs: self.block_crcs.clone() }]
+    }
+
+    /// Where the output for the `index`th input, `input`, goes in
+    /// `--out-dir`.
+    fn output_for(&self, input: &Path, index: usize) -> PathBuf {
+        self.out_dir.as_ref().unwrap().join(self.template.render(&Vars{
+            stem: input.file_stem().unwrap_or_default(),
+            index: index as u64,
+            offset: 0,
+            out_offset: 0,
+        }))
+    }
+
+    fn run(&self, job: &Job) -> Result<(), Box<dyn Error>> {
+        logging::set_file(Some(&job.input));
+        let start = Instant::now();
+        let mut stats = Stats{ files: 1, ..Stats::default() };
+        let decoding = Decoding::new(self.index_policy, self.timeout);
+        let mut result = self.run_input(job, &decoding, &mut stats);
+        stats.elapsed = start.elapsed();
+        if let Some(csv) = &self.csv {
+            let status = match &result {
+                Err(e) => Status::Failed(diagnose::code(&**e)),
+                // Nothing was decoded for skipped outputs
+                Ok(()) if stats.blocks == 0 => Status::Skipped,
+                Ok(()) => Status::Ok,
+            };
+            if let Err(e) = lock(csv).row(&job.input, status, &stats) {
+                result = result.and(Err(format!("writing --summary-csv: {}", e).into()));
+            }
+        }
+        if result.is_ok() {
+            // Nothing to report for skipped outputs
+            progress::suspend(|| {
+                if !self.quiet && stats.blocks > 0 {
+                    eprintln!("{}: {}", job.input.display(), stats.summary());
+                }
+                if self.time {
+                    eprintln!("{}: {}", job.input.display(), stats.timing());
+                }
+            });
+            lock(&self.total).add(&stats);
+        }
+        result
+    }
+
+    /// Runs `job`, adding what it read and wrote to `stats`.
+    fn run_input(&self, job: &Job, decoding: &Decoding, stats: &mut Stats) -> Result<(), Box<dyn Error>> {
+        let source = fs::metadata(&job.input)?;
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        let compressed = match self.output.uring && source.is_file() {
+            true  => {
+                let mut input = uring::Input::open(&job.input)?;
+                // Decoded as it arrives unless it's an archive or compressed,
+                // which the first tar header is enough to tell
+                let head = input.head(512)?;
+                let plain = !archive::is_tar(head) && !container::is_container(head) && self.unwrapping(head).is_none();
+                if self.chunk_size.is_some() && plain && !self.read_whole() {
+                    return self.run_arriving(job, input, &source, decoding, stats);
+                }
+                input.into_data()?
+            },
+            false => fs::read(&job.input)?,
+        };
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        let compressed = fs::read(&job.input)?;
+        stats.input += compressed.len() as u64;
+        let unwrapped = match self.unwrapping(&compressed) {
+            Some(codec) => {
+                info!("{}: unwrapping {:?} compression", job.input.display(), codec);
+                Some(codec.decompress(&compressed)?)
+            },
+            None => None,
+        };
+        let stream = unwrapped.as_deref().unwrap_or(&compressed);
+        let reversed = self.reverse_input.then(|| stream.iter().rev().copied().collect::<Vec<u8>>());
+        let stream = reversed.as_deref().unwrap_or(stream);
+        if self.offset > 0 && self.offset >= stream.len() as u64 {
+            return Err(format!("--offset {:#x} is past the end of the {}-byte input", self.offset, stream.len()).into());
+        }
+        let stream = &stream[self.offset as usize..];
+        let shifted = (self.bit_offset > 0).then(|| shift(stream, self.bit_offset));
+        let stream = shifted.as_deref().unwrap_or(stream);
+
+        if let Some(manifest) = &self.manifest {
+            lock(manifest).add_input(&job.input, &compressed);
+        }
+        if archive::is_tar(stream) {
+            self.run_tar(job, stream, &source, decoding, stats)
+        } else if container::is_container(stream) {
+            self.run_container(job, stream, &source, decoding, stats)
+        } else {
+            self.decode(job, stream, &source, decoding, stats)
+        }
+    }
+
+    /// Whether inputs have to be read whole before decoding, rather than
+    /// decoded as they arrive.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn read_whole(&self) -> bool {
+        self.length_header.is_some() || self.reverse_input || self.offset > 0 || self.bit_offset > 0
+    }
+
+    /// The compression `compressed` is wrapped in, if it's to be unwrapped.
+    fn unwrapping(&self, compressed: &[u8]) -> Option<Codec> {
+        Codec::detect(compressed).filter(|_| self.matches.value_of("pre-decompress") == Some("auto"))
+    }
+
+    /// Like `run_input` for `--chunk-size` with `--io uring`, decoding the
+    /// input as it is read.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn run_arriving(&self, job: &Job, mut input: uring::Input, source: &Metadata, decoding: &Decoding, stats: &mut Stats) -> Result<(), Box<dyn Error>> {
+        if !self.skip(job)? {
+            self.decode_chunked(job, &mut input, source, decoding, stats, self.expected_size)?;
+        }
+        // Listed after its outputs rather than before, once it's all read
+        let compressed = decode::Source::all(&mut input)?;
+        stats.input += compressed.len() as u64;
+        if let Some(manifest) = &self.manifest {
+            lock(manifest).add_input(&job.input, compressed);
+        }
+        Ok(())
+    }
+
+    /// Decompresses each matching member of a tar archive into `--out-dir`.
+    /// `source` is the archive's metadata.
+    fn run_tar(&self, job: &Job, archive: &[u8], source: &Metadata, decoding: &Decoding, stats: &mut Stats) -> Result<(), Box<dyn Error>> {
+        if self.out_dir.is_none() {
+            return Err("tar archives can only be decompressed with --out-dir".into());
+        }
+        let pattern = self.member.clone();
+        let (mut count, mut failures) = (0, 0);
+        archive::tar_members(archive, &pattern, |name, stream| {
+            let member = Job{
+                input: job.input.join(name),
+                output: self.output_for(name, count),
+                tee: vec![],
+                stored: None,
+                crcs: None,
+            };
+            logging::set_file(Some(&member.input));
+            if let Err(e) = self.decode(&member, stream, source, decoding, stats) {
+                log_failure(&member.input, &*e, self.errors_json);
+                failures += 1;
+            }
+            count += 1;
+        })?;
+        self.members_done(job, count, failures)
+    }
+
+    /// Decompresses the members of a `.hpc` container: its only member to
+    /// the output, or each matching `--member` into `--out-dir`, named from
+    /// the file it was made from, with `{offset}` where that came from.
+    /// Each output is checked against the size and CRC-32 stored with it.
+    fn run_container(&self, job: &Job, data: &[u8], source: &Metadata, decoding: &Decoding, stats: &mut Stats) -> Result<(), Box<dyn Error>> {
+        let members = container::read(data)?;
+        let dir = match &self.out_dir {
+            Some(dir) => dir,
+            None => return match members.as_slice() {
+                [member] => {
+                    let job = Job{ stored: Some(member.stored), crcs: member.crcs.clone(), ..job.clone() };
+                    self.decode(&job, member.stream, source, decoding, stats)
+                },
+                _ => Err(format!("a container of {} members can only be decompressed with --out-dir", members.len()).into()),
+            },
+        };
+        let (mut count, mut failures) = (0, 0);
+        for member in members.iter().filter(|member| self.member.matches(&member.name)) {
+            let name = Path::new(&member.name);
+            let member_job = Job{
+                input: job.input.join(name),
+                output: dir.join(self.template.render(&Vars{
+                    stem: name.file_stem().unwrap_or_default(),
+                    index: count as u64,
+                    offset: member.source_offset,
+                    out_offset: 0,
+                })),
+                tee: vec![],
+                stored: Some(member.stored),
+                crcs: member.crcs.clone(),
+            };
+            logging::set_file(Some(&member_job.input));
+            if let Err(e) = self.decode(&member_job, member.stream, source, decoding, stats) {
+                log_failure(&member_job.input, &*e, self.errors_json);
+                failures += 1;
+            }
+            count += 1;
+        }
+        self.members_done(job, count, failures)
+    }
+
+    /// Finishes with an archive or container, `count` of whose members were
+    /// decompressed, `failures` of them failing.
+    fn members_done(&self, job: &Job, count: usize, failures: usize) -> Result<(), Box<dyn Error>> {
+        logging::set_file(Some(&job.input));
+        if count == 0 {
+            warn!("{}: no members match {}", job.input.display(), self.member);
+        }
+        if failures > 0 {
+            return Err(CheckFailed{
+                code: "members_failed",
+                message: format!("{} of {} members failed", failures, count),
+            }.into());
+        }
+        Ok(())
+    }
+
+    /// The files `job` writes to.
+    fn files(&self, job: &Job) -> Vec<PathBuf> {
+        std::iter::once(&job.output).chain(&job.tee)
+            .filter(|path| !is_stream(path))
+            .map(|path| self.written_path(path))
+            .collect()
+    }
+
+    /// The name `path` is written under, after any `--post-compress`
+    /// extension.
+    fn written_path(&self, path: &Path) -> PathBuf {
+        match self.codec {
+            Some(codec) if !is_stream(path) => codec.path(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Checks whether `job` can be skipped under `--skip-existing`, listing
+    /// its existing outputs in the manifest if so.
+    fn skip(&self, job: &Job) -> io::Result<bool> {
+        let verify = match self.skip_existing {
+            Some(verify) => verify,
+            None         => return Ok(false),
+        };
+        let files = self.files(job);
+        if files.is_empty() || !files.iter().all(|path| path.exists()) {
+            return Ok(false);
+        }
+
+        // Without verifying, the earlier manifest's digests are carried over
+        // so that a later verify still catches a damaged output
+        let mut digests = vec![];
+        if let Some(manifest) = &self.manifest {
+            for path in &files {
+                let previous = lock(manifest).previous(path).map(str::to_string);
+                let digest = match &previous {
+                    Some(digest) if !verify => digest.clone(),
+                    _ => digest::sha256(&fs::read(path)?),
+                };
+                if verify && previous.as_deref() != Some(digest.as_str()) {
+                    info!("{}: doesn't match the manifest, extracting again", path.display());
+                    return Ok(false);
+                }
+                digests.push(digest);
+            }
+        }
+        info!("{}: skipping, output already exists", job.input.display());
+        if let Some(manifest) = &self.manifest {
+            let mut manifest = lock(manifest);
+            for (path, digest) in files.iter().zip(&digests) {
+                manifest.add_digest(path, digest);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Decompresses `stream` for `job`, adding what it produced to `stats`.
+    /// `source` is the metadata of the file it came from, for `--preserve`.
+    fn decode(&self, job: &Job, stream: &[u8], source: &Metadata, decoding: &Decoding, stats: &mut Stats) -> Result<(), Box<dyn Error>> {
+        if self.skip(job)? {
+            return Ok(());
+        }
+        let (stream, expected_size) = self.length_header(stream)?;
+        if self.chunk_size.is_some() {
+            return self.decode_chunked(job, &mut { stream }, source, decoding, stats, expected_size);
+        }
+        let matches = self.matches;
+        if let Some(index) = self.index.as_ref().filter(|index| index.compressed_len > stream.len() as u64) {
+            return Err(format!("--index is of a {}-byte stream, longer than this one", index.compressed_len).into());
+        }
+        let diagnosed = |e| explained(e, decoding, stream, 0);
+        // Dropped without being finished, this removes the output again
+        let mut mapping = match expected_size.filter(|_| self.mmap) {
+            Some(len) => match output::Mapping::open(&job.output, len, self.output)? {
+                Some(mapping) => Some(mapping),
+                None => {
+                    warn!("{}: skipped, another process is writing it", job.output.display());
+                    return Ok(());
+                },
+            },
+            None => None,
+        };
+        let mut owned = vec![];
+        let mut streams = vec![];
+        let (len, report) = if let Some(mapping) = &mut mapping {
+            decoding.decompress_into_with_report(stream, mapping.data()).map_err(|e| match e.downcast_ref() {
+                Some(hpcmp::Error::OutputOverflow) => size_mismatch(None, expected_size),
+                _                                  => diagnosed(e),
+            })?
+        } else if matches.is_present("sidecar") {
+            let (data, report) = sidecar::decompress(stream, &job.output, decoding).map_err(diagnosed)?;
+            owned = data;
+            (owned.len(), report)
+        } else if let Some(len) = self.output_length {
+            let (data, report, ended) = decoding.decompress_exact_with_report(stream, len as usize).map_err(diagnosed)?;
+            if (data.len() as u64) < len {
+                return Err(CheckFailed{
+                    code: "size_mismatch",
+                    message: format!("stream ends after {} bytes, short of --output-length {}", data.len(), len),
+                }.into());
+            }
+            if !ended {
+                self.no_end_marker(&job.input, len, report.compressed_len);
+            }
+            owned = data;
+            (owned.len(), report)
+        } else if self.multistream.is_some() {
+            let decoded = multistream::decompress(stream, decoding).map_err(|(start, e)| explained(e, decoding, stream, start))?;
+            info!("{}: {} streams", job.input.display(), decoded.streams.len());
+            owned = decoded.output;
+            streams = decoded.streams;
+            (owned.len(), decoded.report)
+        } else {
+            // Only as much as could pass --max-output
+            let capacity = expected_size.unwrap_or(0).min(self.max_output.unwrap_or(u64::MAX));
+            let (data, report) = decoding.decompress_with_report(stream, capacity as usize).map_err(diagnosed)?;
+            owned = data;
+            (owned.len(), report)
+        };
+        let data = match &mapping {
+            Some(mapping) => &mapping.bytes()[..len],
+            None          => &owned[..],
+        };
+        if self.strict && !report.anomalies.is_empty() {
+            return Err(diagnose::reject(&report.anomalies, stream).into());
+        }
+        self.warn(&job.input, &report);
+        let digests = self.checks(job, expected_size).check(data)?;
+        self.add_stats(stats, &digests, report.blocks.len());
+        if let Some(reference) = &self.reference {
+            reference.check(stream, data, &report)?;
+        }
+        let swapped = self.swap.map(|word| swap(data, word)).transpose()?;
+        let data = swapped.as_deref().unwrap_or(data);
+
+        let parts = match &self.split {
+            Some(split) => {
+                let dir = self.out_dir.as_ref().unwrap();
+                let stem = job.input.file_stem().unwrap_or_default();
+                // As (input offset, output offset, output length)
+                let pieces: Vec<_> = match self.multistream {
+                    Some(multistream::Mode::Split) => streams.iter()
+                        .map(|stream| (stream.input_offset, stream.output_offset, stream.output_len))
+                        .collect(),
+                    _ => report.blocks.iter()
+                        .map(|block| (block.input_offset, block.output_offset, block.output_len))
+                        .collect(),
+                };
+                pieces.into_iter().enumerate().map(|(index, (offset, out_offset, len))| {
+                    let name = split.render(&Vars{ stem, index: index as u64, offset, out_offset });
+                    let start = out_offset as usize;
+                    (vec![dir.join(name)], &data[start..start + len as usize])
+                }).collect()
+            },
+            None => {
+                let paths = std::iter::once(&job.output).chain(&job.tee).cloned().collect();
+                vec![(paths, data)]
+            },
+        };
+
+        let pipeline = self.pipeline(parts.iter().flat_map(|(paths, _)| paths));
+        for (paths, part) in parts {
+            let checks::Part{ written, digests } = pipeline.run(part)?;
+            for path in paths {
+                let path = self.written_path(&path);
+                pipeline.print(&digests, &path);
+                let stream = is_stream(&path);
+                let result = match mapping.as_ref().filter(|_| path == job.output) {
+                    // Already decoded into it
+                    Some(mapping) => mapping.finish(written.len()).map(|()| true),
+                    None          => output::write(&path, &written, self.output),
+                };
+                match result {
+                    Ok(true)  => (),
+                    Ok(false) => {
+                        warn!("{}: skipped, another process is writing it", path.display());
+                        continue;
+                    },
+                    // Whatever reads it has seen enough, as with `| head`
+                    Err(e) if stream && e.kind() == io::ErrorKind::BrokenPipe => {
+                        info!("{}: closed before the end of the output", path.display());
+                        continue;
+                    },
+                    Err(e) => return Err(e.into()),
+                }
+                if self.preserve && !stream {
+                    output::preserve(&path, source)?;
+                }
+                if let Some(manifest) = self.manifest.as_ref().filter(|_| !stream) {
+                    lock(manifest).add_output(&path, &written);
+                }
+            }
+        }
+        self.save_trailer(job, stream, &report)
+    }
+
+    /// Keeps whatever followed the end of `stream` in `<output>.trailer`
+    /// for `--save-trailer`, or in `<input>.trailer` when the output is a
+    /// stream. Nothing is written if nothing followed it.
+    fn save_trailer(&self, job: &Job, stream: &[u8], report: &StreamReport) -> Result<(), Box<dyn Error>> {
+        let trailer = &stream[(report.compressed_len as usize).min(stream.len())..];
+        if !self.matches.is_present("save-trailer") || trailer.is_empty() {
+            return Ok(());
+        }
+        let mut path = std::ffi::OsString::from(match is_stream(&job.output) {
+            true  => &job.input,
+            false => &job.output,
+        });
+        path.push(".trailer");
+        fs::write(&path, trailer)?;
+        info!("{}: saved the {} bytes after the stream to {}", job.input.display(), trailer.len(), Path::new(&path).display());
+        Ok(())
+    }
+
+    /// `stream` without the header `--length-header` says it starts with,
+    /// and the size its output should be, from the header or else from
+    /// `--expected-size` or `--index`.
+    fn length_header<'s>(&self, stream: &'s [u8]) -> Result<(&'s [u8], Option<u64>), CheckFailed> {
+        let read = match self.length_header {
+            Some(read) => read,
+            None       => return Ok((stream, self.expected_size)),
+        };
+        let bad = |message| CheckFailed{ code: "bad_length_header", message };
+        if stream.len() < 4 {
+            return Err(bad(format!("input is {} bytes, too short for a length header", stream.len())));
+        }
+        let len = u64::from(read([stream[0], stream[1], stream[2], stream[3]]));
+        if let Some(expected) = self.expected_size.filter(|&expected| expected != len) {
+            return Err(bad(format!("length header says {} bytes, --expected-size {}", len, expected)));
+        }
+        Ok((&stream[4..], Some(len)))
+    }
+
+    /// Like `decode`, for `--chunk-size`: writes the output to every path
+    /// that many bytes at a time as it is decoded, so that no more than that
+    /// is held at once. Digests are worked out along the way, and an output
+    /// that fails part way, or turns out not to have the expected digest, is
+    /// removed as it would be after an interrupt.
+    fn decode_chunked(&self, job: &Job, stream: &mut impl decode::Source, source: &Metadata, decoding: &Decoding, stats: &mut Stats, expected_size: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let size = self.chunk_size.expect("decoding in chunks without --chunk-size");
+        let mut sinks = vec![];
+        for path in std::iter::once(&job.output).chain(&job.tee) {
+            match output::Sink::open(path, self.output)? {
+                Some(sink) => sinks.push((path, Some(sink))),
+                None       => warn!("{}: skipped, another process is writing it", path.display()),
+            }
+        }
+        let mut tally = Tally::new(self.checks(job, expected_size));
+        let result = decoding.decompress_chunks(stream, size, |chunk| {
+            tally.update(chunk)?;
+            for (path, sink) in &mut sinks {
+                let stream = sink.as_ref().is_some_and(output::Sink::is_stream);
+                match sink.as_mut().map(|sink| sink.put(chunk)) {
+                    None | Some(Ok(())) => (),
+                    // Whatever reads it has seen enough, as with `| head`
+                    Some(Err(e)) if stream && e.kind() == io::ErrorKind::BrokenPipe => {
+                        info!("{}: closed before the end of the output", path.display());
+                        *sink = None;
+                    },
+                    Some(Err(e)) => return Err(e.into()),
+                }
+            }
+            Ok(())
+        });
+        let abandon = |sinks: Vec<(&PathBuf, Option<output::Sink>)>| sinks.into_iter()
+            .filter_map(|(_, sink)| sink)
+            .for_each(output::Sink::abandon);
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                abandon(sinks);
+                return Err(match e.downcast() {
+                    // Around where it failed, which may not all have arrived
+                    Ok(e)  => diagnose::explain(*e, decoding.decoder(), stream.all()?).into(),
+                    Err(e) => e,
+                });
+            },
+        };
+        if self.strict && !report.anomalies.is_empty() {
+            abandon(sinks);
+            return Err(diagnose::reject(&report.anomalies, stream.all()?).into());
+        }
+        self.warn(&job.input, &report);
+        let digests = match tally.finish() {
+            Ok(digests) => digests,
+            Err(e) => {
+                abandon(sinks);
+                return Err(e);
+            },
+        };
+        self.add_stats(stats, &digests, report.blocks.len());
+
+        let pipeline = self.pipeline(sinks.iter().map(|(path, _)| *path));
+        for (path, _) in &sinks {
+            pipeline.print(&digests, path);
+        }
+        for (path, sink) in sinks {
+            let stream = match sink {
+                Some(sink) => {
+                    let stream = sink.is_stream();
+                    sink.finish()?;
+                    stream
+                },
+                None => continue,
+            };
+            if self.preserve && !stream {
+                output::preserve(path, source)?;
+            }
+            if let (Some(manifest), Some(digest)) = (self.manifest.as_ref().filter(|_| !stream), &digests.sha256) {
+                lock(manifest).add_digest(path, digest);
+            }
+        }
+        self.save_trailer(job, stream.all()?, &report)
+    }
+
+    /// What the output of `job` is checked against, where it should be
+    /// `expected_size` bytes.
+    fn checks<'j>(&'j self, job: &'j Job, expected_size: Option<u64>) -> Checks<'j> {
+        Checks{
+            expected_size,
+            max_output: self.max_output,
+            expect_sha256: self.matches.value_of("expect-sha256"),
+            crcs: job.crcs.as_ref(),
+            stored: job.stored,
+            sha256: self.matches.is_present("sha256") || self.manifest.is_some() || self.csv.is_some(),
+            crc32: self.matches.is_present("crc32"),
+        }
+    }
+
+    /// What is done to each part of an output written to `paths`.
+    fn pipeline<'p>(&self, paths: impl IntoIterator<Item = &'p PathBuf>) -> Pipeline<'_> {
+        Pipeline{
+            filters: self.filters.as_ref(),
+            codec: self.codec,
+            sha256: self.matches.is_present("sha256"),
+            crc32: self.matches.is_present("crc32"),
+            to_stdout: paths.into_iter().any(|path| is_stdout(path)),
+        }
+    }
+
+    /// Adds an output with `digests`, in `blocks` blocks, to `stats`.
+    fn add_stats(&self, stats: &mut Stats, digests: &checks::Digests, blocks: usize) {
+        if self.csv.is_some() {
+            // Only kept for an input with just the one output
+            stats.sha256 = digests.sha256.clone().filter(|_| stats.output == 0);
+        }
+        stats.output += digests.len;
+        stats.blocks += blocks as u64;
+    }
+
+    /// Warns of anything suspicious in the stream decoded for `input`,
+    /// printing each as JSON too with `--errors-json`.
+    fn warn(&self, input: &Path, report: &StreamReport) {
+        const SHOWN: usize = 10;
+        if self.errors_json {
+            progress::suspend(|| for anomaly in &report.anomalies {
+                let warning = serde_json::json!({
+                    "file": input.to_string_lossy(),
+                    "warning": diagnose::warning(&anomaly.kind),
+                    "message": anomaly.kind.to_string(),
+                    "byte_offset": anomaly.bit_offset / 8,
+                    "bit_offset": anomaly.bit_offset,
+                });
+                eprintln!("{}", warning);
+            });
+        }
+        for anomaly in report.anomalies.iter().take(SHOWN) {
+            let message = match anomaly.kind {
+                AnomalyKind::TrailingData(len) =>
+                    format!("the stream ends at byte 0x{:x}, {} bytes before the end of the input", anomaly.bit_offset / 8, len),
+                _ => format!("{} at bit {} (byte 0x{:x})", anomaly.kind, anomaly.bit_offset, anomaly.bit_offset / 8),
+            };
+            logging::with_bit_offset(Some(anomaly.bit_offset), || warn!("{}: {}", input.display(), message));
+        }
+        if report.anomalies.len() > SHOWN {
+            warn!("{}: and {} more", input.display(), report.anomalies.len() - SHOWN);
+        }
+    }
+
+    /// Warns that decoding `input` stopped at `--output-length` `len` with
+    /// no end marker there, `consumed` bytes into it.
+    fn no_end_marker(&self, input: &Path, len: u64, consumed: u64) {
+        let message = format!("no end marker where the output reaches --output-length {} bytes", len);
+        if self.errors_json {
+            progress::suspend(|| eprintln!("{}", serde_json::json!({
+                "file": input.to_string_lossy(),
+                "warning": "no_end_marker",
+                "message": message,
+                "byte_offset": consumed,
+                "bit_offset": consumed * 8,
+            })));
+        }
+        logging::with_bit_offset(Some(consumed * 8), || warn!("{}: {}, at byte 0x{:x}", input.display(), message, consumed));
+    }
+
+    /// Writes out anything gathered across all the jobs.
+    fn finish(self) -> io::Result<()> {
+        let total = self.total.into_inner().unwrap_or_else(|e| e.into_inner());
+        if total.files > 1 {
+            if !self.quiet {
+                eprintln!("total: {} files, {}", total.files, total.summary());
+            }
+            if self.time {
+                eprintln!("total: {} files, {}", total.files, total.timing());
+            }
+        }
+        match self.manifest {
+            Some(manifest) => manifest.into_inner().unwrap_or_else(|e| e.into_inner()).write(),
+            None           => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::digest;
+
+/// A `SHA256SUMS`-style list of digests, written once a run is complete.
+///
+/// Paths are written relative to the directory holding the manifest where
+/// possible, so that `sha256sum -c` can check it from there.
+pub struct Manifest {
+    path: PathBuf,
+    inputs: bool,
+    lines: Vec<u8>,
+    /// Digests listed by the manifest already at `path`, from an earlier run.
+    previous: HashMap<PathBuf, String>,
+}
+
+impl Manifest {
+    /// Creates a manifest to be written to `path`, listing inputs as well as
+    /// outputs if `inputs` is set.
+    pub fn new(path: impl Into<PathBuf>, inputs: bool) -> Manifest {
+        let path = path.into();
+        let previous = fs::read(&path).unwrap_or_default()
+            .split(|&b| b == b'\n')
+            .filter(|line| line.len() > 66 && matches!(&line[64..66], b"  " | b" *"))
+            .map(|line| (path_from_bytes(&line[66..]), String::from_utf8_lossy(&line[..64]).into_owned()))
+            .collect();
+        Manifest{ path, inputs, lines: vec![], previous }
+    }
+
+    pub fn add_input(&mut self, path: &Path, data: &[u8]) {
+        if self.inputs {
+            self.add_digest(path, &digest::sha256(data));
+        }
+    }
+
+    pub fn add_output(&mut self, path: &Path, data: &[u8]) {
+        self.add_digest(path, &digest::sha256(data));
+    }
+
+    /// Lists `path` with a SHA-256 digest worked out already.
+    pub fn add_digest(&mut self, path: &Path, digest: &str) {
+        let path = self.relative(path);
+        self.lines.extend_from_slice(digest.as_bytes());
+        self.lines.extend_from_slice(b"  ");
+        self.lines.extend_from_slice(&path_to_bytes(&path));
+        self.lines.push(b'\n');
+    }
+
+    /// The digest an earlier run's manifest gave for `path`.
+    pub fn previous(&self, path: &Path) -> Option<&str> {
+        self.previous.get(&self.relative(path)).map(|digest| digest.as_str())
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        let base = match self.path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        match (fs::canonicalize(base), fs::canonicalize(path)) {
+            (Ok(base), Ok(full)) => match full.strip_prefix(&base) {
+                Ok(rel) => rel.to_path_buf(),
+                Err(_)  => full,
+            },
+            _ => path.to_path_buf(),
+        }
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        fs::write(&self.path, &self.lines)
+    }
+}
+
+// Paths are written as raw bytes where the platform allows, as sha256sum
+// does, so that names that aren't UTF-8 survive
+#[cfg(unix)]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::OsStr::from_bytes(bytes).into()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    String::from_utf8_lossy(bytes).into_owned().into()
+}
@@ -0,0 +1,118 @@
+//! Rendering a roff man page from the command line, for `hpcmp manpage`.
+
+use std::io::{self, Write};
+
+use clap::App;
+
+/// What the options can't say for themselves.
+const DESCRIPTION: &str = "\
+hpcmp decompresses HP \"CMP\" files, the format used for firmware updates on the \
+54710A, 54720A, 54750A and 83480A.";
+
+const FORMAT: &[(&str, &str)] = &[
+    ("Codes",
+     "A stream is a sequence of codes packed least significant bit first, starting \
+      9 bits wide. Codes below 8 are commands, 8 to 0x107 are literal bytes (the \
+      code less 8), and codes from 0x108 on refer to dictionary entries."),
+    ("Commands",
+     "1 resets the dictionary and the code width, and also marks the start of the \
+      stream, so every valid stream begins with the byte 0x01. 2 widens codes by \
+      one bit. 3 ends the stream, and is followed by one last literal byte."),
+    ("Dictionary",
+     "Each code after the first in a block adds an entry: the previous code's \
+      output followed by the first byte of this one's. The dictionary holds up to \
+      0x1000 entries."),
+    ("Variants",
+     "Every instrument listed above uses the same code assignments and dictionary \
+      size; the library's CodeMap trait and Decoder's DICT parameter describe \
+      streams that differ."),
+];
+
+const FILES: &str = "\
+~/.config/hpcmp/config.toml, or $XDG_CONFIG_HOME/hpcmp/config.toml: defaults for \
+any option, keyed by long option name, with [presets.NAME] tables for --preset.";
+
+const ENVIRONMENT: &[(&str, &str)] = &[
+    ("HPCMP_LOG", "error, info, debug or trace, as a number of -v, or module=level pairs as given to -v"),
+    ("HPCMP_PRESET", "a preset from the config file, as --preset"),
+    ("HPCMP_MAX_OUTPUT", "as --max-output"),
+];
+
+const EXIT_STATUS: &[(&str, &str)] = &[
+    ("0", "every input decompressed"),
+    ("1", "every input failed, any did with --strict-exit, or the command line was wrong"),
+    ("2", "some inputs failed and the others decompressed"),
+    ("130", "interrupted"),
+];
+
+/// Writes the man page for `app` to `out`.
+pub fn write(app: &App, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, ".TH HPCMP 1 \"\" \"hpcmp {}\"", env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, ".SH NAME\nhpcmp \\- decompress HP CMP firmware files")?;
+
+    writeln!(out, ".SH SYNOPSIS")?;
+    for line in app.p.meta.usage_str.unwrap_or_default().lines() {
+        writeln!(out, "{}\n.br", escape(line.trim()))?;
+    }
+    writeln!(out, ".SH DESCRIPTION\n{}", escape(DESCRIPTION))?;
+
+    writeln!(out, ".SH OPTIONS")?;
+    let mut options: Vec<_> = app.p.flags.iter()
+        .map(|flag| (flag.s.unified_ord, &flag.b, &flag.s, None))
+        .chain(app.p.opts.iter().map(|opt| (opt.s.unified_ord, &opt.b, &opt.s, Some(&opt.v))))
+        .collect();
+    options.sort_by_key(|&(order, ..)| order);
+    for (_, base, switched, valued) in options {
+        let mut names = vec![];
+        if let Some(short) = switched.short {
+            names.push(format!("\\fB\\-{}\\fR", short));
+        }
+        if let Some(long) = switched.long {
+            names.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+        }
+        let mut tag = names.join(", ");
+        let mut help = escape(base.help.unwrap_or_default());
+        if let Some(valued) = valued {
+            let name = valued.val_names.as_ref()
+                .and_then(|names| names.values().next().copied())
+                .unwrap_or(base.name);
+            tag.push_str(&format!(" \\fI{}\\fR", escape(name)));
+            if let Some(values) = &valued.possible_vals {
+                help.push_str(&format!(" [values: {}]", escape(&values.join(", "))));
+            }
+            if let Some(default) = valued.default_val {
+                help.push_str(&format!(" [default: {}]", escape(&default.to_string_lossy())));
+            }
+        }
+        writeln!(out, ".TP\n{}\n{}", tag, help)?;
+    }
+
+    writeln!(out, ".SH COMMANDS")?;
+    for command in &app.p.subcommands {
+        writeln!(out, ".TP\n\\fB{}\\fR\n{}", escape(&command.p.meta.name), escape(command.p.meta.about.unwrap_or_default()))?;
+    }
+
+    writeln!(out, ".SH FORMAT")?;
+    for (heading, text) in FORMAT {
+        writeln!(out, ".SS {}\n{}", heading, escape(text))?;
+    }
+    writeln!(out, ".SH FILES\n{}", escape(FILES))?;
+    writeln!(out, ".SH ENVIRONMENT")?;
+    for (name, text) in ENVIRONMENT {
+        writeln!(out, ".TP\n\\fB{}\\fR\n{}", name, escape(text))?;
+    }
+    writeln!(out, ".SH EXIT STATUS")?;
+    for (status, text) in EXIT_STATUS {
+        writeln!(out, ".TP\n{}\n{}", status, escape(text))?;
+    }
+    Ok(())
+}
+
+/// Escapes `text` for roff, so it can't be taken for a request.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    match text.starts_with(['.', '\'']) {
+        true  => format!("\\&{}", text),
+        false => text,
+    }
+}
@@ -0,0 +1,112 @@
+//! Scripts that load what `hpcmp carve` wrote into Ghidra or IDA, each
+//! stream's output as a segment of its own at its suggested load address,
+//! commented with where in the image it came from.
+//!
+//! The segments are listed in the script as JSON, which reads the same in
+//! Ghidra's Jython as in IDA's Python 3, and the outputs are looked for next
+//! to it, so the directory can be moved as a whole.
+
+use std::path::Path;
+
+use crate::carve::Carved;
+
+pub const NAMES: &[&str] = &["ghidra", "ida"];
+
+/// What the script is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A script for Ghidra's Script Manager, run with the program to load
+    /// the segments into open.
+    Ghidra,
+    /// An IDAPython script, for File > Script file.
+    Ida,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "ghidra" => Some(Format::Ghidra),
+            "ida"    => Some(Format::Ida),
+            _        => None,
+        }
+    }
+
+    /// What the script is called in the directory carved into.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Format::Ghidra => "ghidra_import.py",
+            Format::Ida    => "ida_import.py",
+        }
+    }
+}
+
+/// The script loading everything `carved` from `image` into `dir`. Streams
+/// skipped as duplicates are loaded from the output of the first.
+pub fn script(format: Format, image: &Path, dir: &Path, carved: &[Carved]) -> String {
+    let segments: Vec<_> = carved.iter().filter_map(|stream| {
+        let file = match (&stream.file, stream.duplicate_of) {
+            (Some(file), _)     => file,
+            (None, Some(first)) => carved.iter().find(|other| other.offset == first)?.file.as_ref()?,
+            (None, None)        => return None,
+        };
+        Some(serde_json::json!({
+            "name": format!("hpcmp_{:08x}", stream.offset),
+            "file": file.strip_prefix(dir).unwrap_or(file).to_string_lossy(),
+            "load_address": stream.load_address,
+            "length": stream.decompressed_len,
+            "comment": format!("hpcmp: stream at {:#010x} in {}, {} bytes decompressed to {}, sha256 {}",
+                               stream.offset, image.display(), stream.compressed_len, stream.decompressed_len, stream.sha256),
+        }))
+    }).collect();
+    // JSON text never has three quotes in a row, so it can go in a raw
+    // string as it is
+    let segments = serde_json::to_string_pretty(&segments).unwrap();
+    let body = match format {
+        Format::Ghidra => GHIDRA,
+        Format::Ida    => IDA,
+    };
+    format!("{}\nimport json\nimport os\n\nSEGMENTS = json.loads(r\"\"\"\n{}\n\"\"\")\n{}", header(format, image), segments, body)
+}
+
+fn header(format: Format, image: &Path) -> String {
+    let how = match format {
+        Format::Ghidra => "Run it from Ghidra's Script Manager with the program to load them into open.",
+        Format::Ida    => "Run it in IDA with File > Script file.",
+    };
+    format!("# Written by hpcmp carve: loads the streams carved from {} as segments.\n# {}\n", image.display(), how)
+}
+
+const GHIDRA: &str = r#"
+from java.io import File, FileInputStream
+
+here = os.path.dirname(getSourceFile().getAbsolutePath())
+memory = currentProgram.getMemory()
+for segment in SEGMENTS:
+    stream = FileInputStream(File(os.path.join(here, segment["file"])))
+    try:
+        block = memory.createInitializedBlock(segment["name"], toAddr(segment["load_address"]), stream,
+                                              segment["length"], monitor, False)
+    finally:
+        stream.close()
+    block.setComment(segment["comment"])
+    block.setRead(True)
+    block.setWrite(True)
+    block.setExecute(True)
+    print("%s at %#x" % (segment["name"], segment["load_address"]))
+"#;
+
+const IDA: &str = r#"
+import ida_bytes
+import ida_segment
+
+here = os.path.dirname(os.path.abspath(__file__))
+for segment in SEGMENTS:
+    with open(os.path.join(here, segment["file"]), "rb") as f:
+        data = f.read()
+    start = segment["load_address"]
+    if not ida_segment.add_segm(0, start, start + len(data), segment["name"], "DATA"):
+        raise RuntimeError("can't add segment %s at %#x" % (segment["name"], start))
+    ida_bytes.put_bytes(start, data)
+    ida_segment.set_segment_cmt(ida_segment.getseg(start), segment["comment"], 0)
+    print("%s at %#x" % (segment["name"], start))
+"#;
@@ -0,0 +1,111 @@
+//! Decoding streams stored back to back, as several firmware sections are,
+//! for `--multistream`: once one stream has ended, another is taken to
+//! start at the next byte, until the input runs out or what's left doesn't
+//! start with a reset.
+
+use std::error::Error;
+
+use hpcmp::{Anomaly, AnomalyKind, StreamReport};
+
+use crate::decode::Decoding;
+
+pub const NAMES: &[&str] = &["concat", "split"];
+
+/// What becomes of the streams' outputs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// One after another in the one output.
+    Concat,
+    /// Each to a file of its own in `--out-dir`.
+    Split,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "concat" => Some(Mode::Concat),
+            "split"  => Some(Mode::Split),
+            _        => None,
+        }
+    }
+}
+
+/// Where one of the streams is, in the input and in the output.
+pub struct Stream {
+    pub input_offset: u64,
+    pub compressed_len: u64,
+    pub output_offset: u64,
+    pub output_len: u64,
+}
+
+/// Every stream's output, one after another, with a report covering them
+/// all as if they were one stream's blocks.
+pub struct Decoded {
+    pub output: Vec<u8>,
+    pub report: StreamReport,
+    pub streams: Vec<Stream>,
+}
+
+/// Decodes the streams in `input` as `decoding` says. A stream that fails
+/// fails them all, giving where it started along with the error.
+pub fn decompress(input: &[u8], decoding: &Decoding) -> Result<Decoded, (usize, Box<dyn Error>)> {
+    let mut decoded = Decoded{ output: vec![], report: StreamReport::default(), streams: vec![] };
+    let mut start = 0;
+    while start < input.len() {
+        let rest = &input[start..];
+        if start > 0 && !starts_stream(rest) {
+            let report = &mut decoded.report;
+            report.anomalies.push(Anomaly{
+                bit_offset: start as u64 * 8,
+                kind: AnomalyKind::TrailingData(rest.len() as u64),
+            });
+            break;
+        }
+        let (output, report) = decoding.decompress_with_report(rest, 0).map_err(|e| (start, e))?;
+        let stream = Stream{
+            input_offset: start as u64,
+            compressed_len: report.compressed_len,
+            output_offset: decoded.output.len() as u64,
+            output_len: output.len() as u64,
+        };
+        add(&mut decoded.report, report, &stream);
+        decoded.output.extend_from_slice(&output);
+        start += stream.compressed_len as usize;
+        decoded.streams.push(stream);
+    }
+    Ok(decoded)
+}
+
+/// Whether `data` starts with the 9-bit reset every stream starts with.
+fn starts_stream(data: &[u8]) -> bool {
+    match *data {
+        [first, second, ..] => u16::from(first) | u16::from(second & 1) << 8 == 1,
+        _ => false,
+    }
+}
+
+/// Adds the report on `stream` to `total`, moving its offsets to where the
+/// stream is. What followed it is the next stream, so isn't trailing data.
+fn add(total: &mut StreamReport, report: StreamReport, stream: &Stream) {
+    let bits = stream.input_offset * 8;
+    total.compressed_len = stream.input_offset + report.compressed_len;
+    total.decompressed_len += report.decompressed_len;
+    total.blocks.extend(report.blocks.into_iter().map(|mut block| {
+        block.bit_offset += bits;
+        block.input_offset += stream.input_offset;
+        block.output_offset += stream.output_offset;
+        block
+    }));
+    let histogram = &mut total.histogram;
+    for (total, count) in histogram.commands.iter_mut().zip(report.histogram.commands) {
+        *total += count;
+    }
+    histogram.values += report.histogram.values;
+    histogram.indices += report.histogram.indices;
+    for (width, count) in report.histogram.widths {
+        *histogram.widths.entry(width).or_insert(0) += count;
+    }
+    total.anomalies.extend(report.anomalies.into_iter()
+        .filter(|anomaly| !matches!(anomaly.kind, AnomalyKind::TrailingData(_)))
+        .map(|anomaly| Anomaly{ bit_offset: anomaly.bit_offset + bits, ..anomaly }));
+}
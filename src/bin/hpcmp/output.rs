@@ -0,0 +1,418 @@
+//! Writing outputs to files or stdout.
+
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fs::{self, File, FileTimes, Metadata, OpenOptions, TryLockError};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use log::info;
+
+use crate::interrupt;
+
+/// Names for `--io`.
+pub const IO_BACKENDS: &[&str] = match cfg!(all(target_os = "linux", feature = "io-uring")) {
+    true  => &["std", "uring"],
+    false => &["std"],
+};
+
+/// Zero runs are left as holes a block at a time.
+const SPARSE_BLOCK: usize = 4096;
+
+/// Where in an output file the data goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Position {
+    /// Replaces the file.
+    #[default]
+    Truncate,
+    /// After the end of the file.
+    Append,
+    /// At an offset, leaving the rest of the file alone.
+    At(u64),
+}
+
+/// Whether to take an advisory lock on an output file while writing it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Lock {
+    #[default]
+    None,
+    /// Waits for any other process holding the lock.
+    Wait,
+    /// Leaves the file alone if another process holds the lock.
+    Skip,
+}
+
+/// How an output is written.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Seek over blocks of zeros rather than writing them, leaving holes in
+    /// the file on filesystems that support them.
+    pub sparse: bool,
+    pub position: Position,
+    pub lock: Lock,
+    /// Writes regular files through io_uring, unless they're sparse.
+    pub uring: bool,
+}
+
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Whether `path` is stdout or something else that can only be written
+/// straight through, like a named pipe, a process substitution or a device,
+/// and so is never truncated, seeked, renamed or removed.
+pub fn is_stream(path: &Path) -> bool {
+    is_stdout(path) || fs::metadata(path).is_ok_and(|metadata| !metadata.is_file())
+}
+
+/// Writes `data` to `path`, or to stdout for `-`. Returns false if the write
+/// was skipped because another process holds the file's lock.
+///
+/// A stream is written as it is, whatever the options; its reader going
+/// away before the end fails with `ErrorKind::BrokenPipe`.
+pub fn write(path: &Path, data: &[u8], options: Options) -> io::Result<bool> {
+    match Sink::open(path, options)? {
+        Some(mut sink) => {
+            sink.put(data)?;
+            sink.finish()?;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
+}
+
+/// An output written a piece at a time, as `write` writes it in one go.
+pub struct Sink {
+    target: Target,
+    /// Set for a regular file, which can be seeked and cut back.
+    regular: bool,
+    sparse: bool,
+    /// Where the next piece goes.
+    pos: u64,
+    /// The file's length before writing.
+    len: u64,
+    writing: Option<interrupt::Guard>,
+}
+
+enum Target {
+    Stdout,
+    File(File),
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    Uring(Box<crate::uring::Output>),
+}
+
+impl Sink {
+    /// Opens `path` for writing as `options` say. Returns None if another
+    /// process holds the file's lock.
+    pub fn open(path: &Path, options: Options) -> io::Result<Option<Sink>> {
+        let stream = |target| Sink{ target, regular: false, sparse: false, pos: 0, len: 0, writing: None };
+        if is_stdout(path) {
+            return Ok(Some(stream(Target::Stdout)));
+        }
+        if is_stream(path) {
+            if let Position::At(_) = options.position {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "--at needs a regular file to write into"));
+            }
+            return Ok(Some(stream(Target::File(OpenOptions::new().write(true).open(path)?))));
+        }
+
+        let (file, pos, len, writing) = match open_file(path, false, options)? {
+            Some(opened) => opened,
+            None         => return Ok(None),
+        };
+        let target = file_target(file, pos, options)?;
+        Ok(Some(Sink{ target, regular: true, sparse: options.sparse, pos, len, writing: Some(writing) }))
+    }
+
+    /// Whether this is stdout or another stream rather than a regular file.
+    pub fn is_stream(&self) -> bool {
+        !self.regular
+    }
+
+    /// Writes `data` after whatever was written before.
+    pub fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        let Sink{ target, sparse, pos, len, writing, .. } = self;
+        let mut put = || match target {
+            Target::Stdout => io::stdout().lock().write_all(data),
+            Target::File(file) if *sparse => write_sparse(file, data, *pos, *len),
+            Target::File(file) => file.write_all(data),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Target::Uring(output) => output.put(data),
+        };
+        match writing {
+            Some(writing) => writing.write(put)?,
+            None          => put()?,
+        }
+        self.pos += data.len() as u64;
+        Ok(())
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.target {
+            Target::Stdout => io::stdout().lock().flush(),
+            // Extends the file over any hole at the end
+            Target::File(file) if self.sparse && self.len < self.pos => file.set_len(self.pos),
+            Target::File(mut file) => file.flush(),
+            #[cfg(all(target_os = "linux", feature = "io-uring"))]
+            Target::Uring(output) => output.finish(),
+        }
+    }
+
+    /// Gives up on the output, removing it, or cutting an appended-to file
+    /// back to its original length, as after an interrupt.
+    pub fn abandon(self) {
+        let Sink{ target, writing, .. } = self;
+        // Closed first, and with it any lock
+        drop(target);
+        if let Some(writing) = writing {
+            writing.undo();
+        }
+    }
+}
+
+/// Opens the regular file at `path`, for reading too if `read`, and moves
+/// to where `options` say to write, returning it with that position and its length before writing,
+/// and marking it as being written. Returns None if another process holds
+/// its lock.
+fn open_file(path: &Path, read: bool, options: Options) -> io::Result<Option<(File, u64, u64, interrupt::Guard)>> {
+    let existed = path.exists();
+    // Not truncated until any lock is held
+    let mut file = OpenOptions::new().read(read).write(true).create(true).truncate(false).open(path)?;
+    if options.lock != Lock::None {
+        match file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) if options.lock == Lock::Skip => return Ok(None),
+            Err(TryLockError::WouldBlock) => {
+                info!("{}: waiting for another process to finish writing it", path.display());
+                file.lock()?;
+            },
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+
+    let len = file.metadata()?.len();
+    let writing = interrupt::writing(
+        path,
+        Some(len).filter(|_| existed && options.position == Position::Append),
+        existed && matches!(options.position, Position::At(_)),
+    );
+    let (pos, len) = match options.position {
+        Position::Truncate => {
+            file.set_len(0)?;
+            (0, 0)
+        },
+        Position::Append   => (file.seek(SeekFrom::End(0))?, len),
+        Position::At(n)    => (file.seek(SeekFrom::Start(n))?, len),
+    };
+    Ok(Some((file, pos, len, writing)))
+}
+
+/// An output file with `len` bytes of it, from where `Options` say to write,
+/// mapped into memory to decode straight into. Until it is finished,
+/// dropping it cleans the file up as an interrupt would.
+pub struct Mapping {
+    map: Map,
+    /// Where the output starts in the map, which starts on a page boundary.
+    skip: usize,
+    len: usize,
+    file: File,
+    start: u64,
+    /// The least the file is left as long as: its old length, unless it's
+    /// replaced.
+    kept_len: u64,
+    finished: Cell<bool>,
+    writing: Option<interrupt::Guard>,
+}
+
+impl Mapping {
+    /// Opens `path` as `options` say, and maps the `len` bytes to be
+    /// written, allocating them on disk first so that running out of space
+    /// is an error here rather than a crash later. Returns None if another
+    /// process holds the file's lock.
+    pub fn open(path: &Path, len: u64, options: Options) -> io::Result<Option<Mapping>> {
+        if is_stream(path) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "--mmap needs a regular file to write into"));
+        }
+        let len = usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too large to map"))?;
+        // Shared mappings can't be written only
+        let (file, start, old_len, writing) = match open_file(path, true, options)? {
+            Some(opened) => opened,
+            None         => return Ok(None),
+        };
+        let kept_len = match options.position {
+            Position::Truncate => 0,
+            _                  => old_len,
+        };
+        let skip = (start % page_size()) as usize;
+        let map = allocate(&file, start, len as u64).and_then(|()| Map::new(&file, start - skip as u64, skip + len));
+        match map {
+            Ok(map) => Ok(Some(Mapping{ map, skip, len, file, start, kept_len, finished: Cell::new(false), writing: Some(writing) })),
+            Err(e) => {
+                writing.undo();
+                Err(e)
+            },
+        }
+    }
+
+    /// The bytes to be written.
+    pub fn data(&mut self) -> &mut [u8] {
+        &mut self.map.bytes_mut()[self.skip..self.skip + self.len]
+    }
+
+    /// The bytes to be written, as far as they have been.
+    pub fn bytes(&self) -> &[u8] {
+        &self.map.bytes()[self.skip..self.skip + self.len]
+    }
+
+    /// Ends the output after the first `used` bytes of `data`, which it
+    /// can still be read from.
+    pub fn finish(&self, used: usize) -> io::Result<()> {
+        self.file.set_len(self.kept_len.max(self.start + used as u64))?;
+        self.finished.set(true);
+        Ok(())
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        if !self.finished.get() {
+            // What was allocated past the end, even where the rest can't be
+            // undone
+            let _ = self.file.set_len(self.kept_len);
+            if let Some(writing) = self.writing.take() {
+                writing.undo();
+            }
+        }
+    }
+}
+
+/// Part of a file mapped into memory, shared with it.
+struct Map {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Map {
+    #[cfg(unix)]
+    fn new(file: &File, offset: u64, len: usize) -> io::Result<Map> {
+        use std::os::fd::AsRawFd;
+        if len == 0 {
+            return Ok(Map{ ptr: std::ptr::NonNull::dangling().as_ptr(), len });
+        }
+        let offset = libc::off_t::try_from(offset).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset too large to map"))?;
+        // SAFETY: a fresh shared mapping of the file, checked for failure
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, file.as_raw_fd(), offset)
+        };
+        match ptr {
+            libc::MAP_FAILED => Err(io::Error::last_os_error()),
+            ptr              => Ok(Map{ ptr: ptr.cast(), len }),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new(_file: &File, _offset: u64, _len: usize) -> io::Result<Map> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "--mmap is only supported on Unix"))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `len` bytes were mapped, and only this map refers to them
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as for `bytes`
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if self.len > 0 {
+            // SAFETY: mapped in `new`, and nothing borrows from it any more
+            unsafe { libc::munmap(self.ptr.cast(), self.len) };
+        }
+    }
+}
+
+/// Allocates `len` bytes of `file` from `start` on disk, extending it if
+/// need be, or just extends it where the filesystem can't allocate ahead.
+#[cfg(unix)]
+fn allocate(file: &File, start: u64, len: u64) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    if len == 0 {
+        return Ok(());
+    }
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "too large to allocate");
+    let (offset, size) = (libc::off_t::try_from(start).map_err(|_| too_large())?, libc::off_t::try_from(len).map_err(|_| too_large())?);
+    // SAFETY: only reads its arguments
+    match unsafe { libc::posix_fallocate(file.as_raw_fd(), offset, size) } {
+        0 => Ok(()),
+        libc::EOPNOTSUPP | libc::EINVAL => {
+            let end = start + len;
+            match file.metadata()?.len() < end {
+                true  => file.set_len(end),
+                false => Ok(()),
+            }
+        },
+        e => Err(io::Error::from_raw_os_error(e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn allocate(_file: &File, _start: u64, _len: u64) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn page_size() -> u64 {
+    // SAFETY: only reads a configuration value
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as u64 }
+}
+
+#[cfg(not(unix))]
+fn page_size() -> u64 {
+    4096
+}
+
+/// `file`, to be written from `pos` on, through io_uring if `options` say.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn file_target(file: File, pos: u64, options: Options) -> io::Result<Target> {
+    match options.uring && !options.sparse {
+        true  => Ok(Target::Uring(Box::new(crate::uring::Output::new(file, pos)?))),
+        false => Ok(Target::File(file)),
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+fn file_target(file: File, _pos: u64, options: Options) -> io::Result<Target> {
+    debug_assert!(!options.uring, "--io uring isn't built in");
+    Ok(Target::File(file))
+}
+
+// Writes `data` at `start` in a file that is `len` bytes long. Only zeros
+// past the end of the file can be skipped; anything before it must be
+// overwritten.
+fn write_sparse(file: &mut File, data: &[u8], start: u64, len: u64) -> io::Result<()> {
+    let mut pos = start;
+    for block in data.chunks(SPARSE_BLOCK) {
+        if pos >= len && block.iter().all(|&b| b == 0) {
+            file.seek(SeekFrom::Current(block.len() as i64))?;
+        } else {
+            file.write_all(block)?;
+        }
+        pos += block.len() as u64;
+    }
+    Ok(())
+}
+
+/// Gives the file at `path` the timestamps and permissions in `source`.
+pub fn preserve(path: &Path, source: &Metadata) -> io::Result<()> {
+    let times = FileTimes::new()
+        .set_accessed(source.accessed()?)
+        .set_modified(source.modified()?);
+    // Before the permissions, which may not allow writing
+    OpenOptions::new().write(true).open(path)?.set_times(times)?;
+    fs::set_permissions(path, source.permissions())
+}
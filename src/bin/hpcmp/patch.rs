@@ -0,0 +1,332 @@
+//! Applying binary patches to decompressed output, for `hpcmp patch`: VCDIFF
+//! as xdelta3 writes it, and bsdiff's original and endsley formats.
+
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::io::Read;
+
+/// Applies `patch`, whatever its format, to `old`.
+pub fn apply(patch: &[u8], old: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    match patch {
+        [0xd6, 0xc3, 0xc4, 0, ..]                   => vcdiff(&patch[4..], old),
+        _ if patch.starts_with(b"BSDIFF40")         => bsdiff(patch, old),
+        _ if patch.starts_with(b"ENDSLEY/BSDIFF43") => endsley(patch, old),
+        _                                           => Err("not an xdelta3 (VCDIFF) or bsdiff patch".into()),
+    }
+}
+
+/// Reads through a patch, failing if it ends early.
+struct Cursor<'a> {
+    data: &'a [u8],
+    what: &'static str,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: u64) -> Result<&'a [u8], Box<dyn Error>> {
+        if len > self.data.len() as u64 {
+            return Err(format!("patch ends in its {}", self.what).into());
+        }
+        let (bytes, rest) = self.data.split_at(len as usize);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, Box<dyn Error>> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// A VCDIFF integer: seven bits a byte, most significant first, with the
+    /// top bit set on all but the last.
+    fn varint(&mut self) -> Result<u64, Box<dyn Error>> {
+        let mut value = 0u64;
+        loop {
+            let byte = self.byte()?;
+            if value >> 57 != 0 {
+                return Err(format!("integer too large in patch {}", self.what).into());
+            }
+            value = value << 7 | u64::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn offtin(&mut self) -> Result<i64, Box<dyn Error>> {
+        self.bytes(8).map(offtin)
+    }
+}
+
+/// VCDIFF header indicator bits.
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+const VCD_APPHEADER: u8 = 0x04;
+/// Window indicator bits, the last of them xdelta3's.
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+const VCD_ADLER32: u8 = 0x04;
+/// The most a window decodes to, as xdelta3 limits them.
+const MAX_WINDOW: u64 = 1 << 24;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Inst {
+    Noop,
+    Add,
+    Run,
+    Copy(u8),
+}
+
+/// RFC 3284's default code table: each instruction code's one or two
+/// instructions, with their sizes, 0 for one given separately.
+fn code_table() -> Vec<[(Inst, u8); 2]> {
+    use Inst::*;
+    let mut table = vec![[(Run, 0), (Noop, 0)]];
+    table.extend((0..18).map(|size| [(Add, size), (Noop, 0)]));
+    for mode in 0..9 {
+        table.push([(Copy(mode), 0), (Noop, 0)]);
+        table.extend((4..19).map(|size| [(Copy(mode), size), (Noop, 0)]));
+    }
+    for mode in 0..6 {
+        for add in 1..5 {
+            table.extend((4..7).map(|copy| [(Add, add), (Copy(mode), copy)]));
+        }
+    }
+    for mode in 6..9 {
+        table.extend((1..5).map(|add| [(Add, add), (Copy(mode), 4)]));
+    }
+    table.extend((0..9).map(|mode| [(Copy(mode), 4), (Add, 1)]));
+    table
+}
+
+/// Addresses of the latest copies, which later ones can be given relative to.
+struct AddressCache {
+    near: [u64; 4],
+    next: usize,
+    same: Vec<u64>,
+}
+
+impl AddressCache {
+    fn new() -> AddressCache {
+        AddressCache{ near: [0; 4], next: 0, same: vec![0; 3 * 256] }
+    }
+
+    /// Reads the address of a copy in `mode`, from `here` in the window.
+    fn decode(&mut self, mode: u8, here: u64, addresses: &mut Cursor) -> Result<u64, Box<dyn Error>> {
+        let address = match mode {
+            0 => addresses.varint()?,
+            1 => here.checked_sub(addresses.varint()?).ok_or("copy from before the window in patch")?,
+            2..=5 => self.near[usize::from(mode - 2)].wrapping_add(addresses.varint()?),
+            _ => self.same[usize::from(mode - 6) * 256 + usize::from(addresses.byte()?)],
+        };
+        self.near[self.next] = address;
+        self.next = (self.next + 1) % self.near.len();
+        let slots = self.same.len();
+        self.same[(address % slots as u64) as usize] = address;
+        Ok(address)
+    }
+}
+
+/// Applies a VCDIFF patch, given what follows its magic number and version.
+fn vcdiff(patch: &[u8], old: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header = Cursor{ data: patch, what: "header" };
+    let indicator = header.byte()?;
+    if indicator & VCD_DECOMPRESS != 0 {
+        return Err("patch uses secondary compression, which isn't supported; make it with xdelta3 -S none".into());
+    }
+    if indicator & VCD_CODETABLE != 0 {
+        return Err("patch uses its own code table, which isn't supported".into());
+    }
+    if indicator & VCD_APPHEADER != 0 {
+        let len = header.varint()?;
+        header.bytes(len)?;
+    }
+
+    let table = code_table();
+    let mut new = vec![];
+    let mut windows = Cursor{ data: header.data, what: "windows" };
+    while !windows.data.is_empty() {
+        let indicator = windows.byte()?;
+        let source = match indicator & (VCD_SOURCE | VCD_TARGET) {
+            0 => &[][..],
+            bits => {
+                let len = windows.varint()?;
+                let pos = windows.varint()?;
+                let from = match bits {
+                    VCD_SOURCE => old,
+                    VCD_TARGET => &new[..],
+                    _ => return Err("patch window copies from both source and target".into()),
+                };
+                pos.checked_add(len).filter(|&end| end <= from.len() as u64)
+                    .map(|end| &from[pos as usize..end as usize])
+                    .ok_or("patch window's source is past the end of the data")?
+            },
+        };
+        let len = windows.varint()?;
+        let mut delta = Cursor{ data: windows.bytes(len)?, what: "window" };
+        let target_len = delta.varint()?;
+        if target_len > MAX_WINDOW {
+            return Err("patch window is larger than xdelta3 writes".into());
+        }
+        if delta.byte()? != 0 {
+            return Err("patch window is compressed, which isn't supported; make it with xdelta3 -S none".into());
+        }
+        let data_len = delta.varint()?;
+        let inst_len = delta.varint()?;
+        let addr_len = delta.varint()?;
+        let checksum = match indicator & VCD_ADLER32 {
+            0 => None,
+            _ => Some(u32::from_be_bytes(delta.bytes(4)?.try_into().unwrap())),
+        };
+        let mut data = Cursor{ data: delta.bytes(data_len)?, what: "window's data" };
+        let mut insts = Cursor{ data: delta.bytes(inst_len)?, what: "window's instructions" };
+        let mut addresses = Cursor{ data: delta.bytes(addr_len)?, what: "window's addresses" };
+
+        let mut target = Vec::with_capacity(target_len as usize);
+        let mut cache = AddressCache::new();
+        while !insts.data.is_empty() {
+            for &(inst, size) in &table[usize::from(insts.byte()?)] {
+                let size = match (inst, size) {
+                    (Inst::Noop, _) => continue,
+                    (_, 0)          => insts.varint()?,
+                    (_, size)       => u64::from(size),
+                };
+                if target.len() as u64 + size > target_len {
+                    return Err("patch window writes past its end".into());
+                }
+                match inst {
+                    Inst::Noop => (),
+                    Inst::Add  => target.extend_from_slice(data.bytes(size)?),
+                    Inst::Run  => {
+                        let byte = data.byte()?;
+                        target.resize(target.len() + size as usize, byte);
+                    },
+                    Inst::Copy(mode) => {
+                        let here = source.len() as u64 + target.len() as u64;
+                        let address = cache.decode(mode, here, &mut addresses)?;
+                        if address >= here {
+                            return Err("patch copies from past where it has got to".into());
+                        }
+                        // Copies from the target can overlap what they write
+                        for address in address..address + size {
+                            let byte = match address.checked_sub(source.len() as u64) {
+                                None         => source[address as usize],
+                                Some(offset) => target[offset as usize],
+                            };
+                            target.push(byte);
+                        }
+                    },
+                }
+            }
+        }
+        if target.len() as u64 != target_len {
+            return Err("patch window is shorter than it says".into());
+        }
+        if checksum.is_some_and(|checksum| checksum != adler32(&target)) {
+            return Err("patched data fails the patch's checksum; is it for different data?".into());
+        }
+        new.extend_from_slice(&target);
+    }
+    Ok(new)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+/// Applies a BSDIFF40 patch: a header, then the control, diff and extra
+/// sections, each compressed with bzip2 on its own.
+fn bsdiff(patch: &[u8], old: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header = Cursor{ data: &patch[8..], what: "header" };
+    let control_len = header.offtin()?;
+    let diff_len = header.offtin()?;
+    let new_len = header.offtin()?;
+    let mut sections = Cursor{ data: header.data, what: "sections" };
+    let mut section = |len: i64| match u64::try_from(len) {
+        Ok(len) => sections.bytes(len).and_then(bunzip2),
+        Err(_)  => Err("negative section length in patch".into()),
+    };
+    let (control, diff) = (section(control_len)?, section(diff_len)?);
+    let extra = bunzip2(sections.data)?;
+    let mut control = Cursor{ data: &control, what: "control" };
+    let mut diff = Cursor{ data: &diff, what: "diff" };
+    let mut extra = Cursor{ data: &extra, what: "extra" };
+    bspatch(new_len, old, |part| match part {
+        Part::Control  => control.bytes(8),
+        Part::Diff(n)  => diff.bytes(n),
+        Part::Extra(n) => extra.bytes(n),
+    })
+}
+
+/// Applies an ENDSLEY/BSDIFF43 patch: a header, then one bzip2 stream with
+/// the control, diff and extra data interleaved.
+fn endsley(patch: &[u8], old: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header = Cursor{ data: &patch[16..], what: "header" };
+    let new_len = header.offtin()?;
+    let body = bunzip2(header.data)?;
+    let mut body = Cursor{ data: &body, what: "body" };
+    bspatch(new_len, old, |part| match part {
+        Part::Control                  => body.bytes(8),
+        Part::Diff(n) | Part::Extra(n) => body.bytes(n),
+    })
+}
+
+/// The next thing bsdiff's loop reads.
+enum Part {
+    /// One of a control triple's numbers.
+    Control,
+    Diff(u64),
+    Extra(u64),
+}
+
+/// Builds bsdiff's `new_len` bytes from `old` and what `read` reads: for
+/// each control triple, bytes to add to `old`'s, bytes to insert, and how
+/// far to move through `old`.
+fn bspatch<'a>(new_len: i64, old: &[u8], mut read: impl FnMut(Part) -> Result<&'a [u8], Box<dyn Error>>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let new_len = u64::try_from(new_len).map_err(|_| "negative output length in patch")?;
+    let mut new = Vec::with_capacity(new_len.min(1 << 24) as usize);
+    let mut old_pos = 0i64;
+    while (new.len() as u64) < new_len {
+        let mut control = || read(Part::Control).map(offtin);
+        let (diff_len, extra_len, seek) = (control()?, control()?, control()?);
+        let fits = |len: i64, new: &[u8]| u64::try_from(len).ok().filter(|&len| new.len() as u64 + len <= new_len)
+            .ok_or("patch control goes past the end of the output");
+        let diff_len = fits(diff_len, &new)?;
+        for (i, &byte) in read(Part::Diff(diff_len))?.iter().enumerate() {
+            let old_byte = old_pos.checked_add(i as i64)
+                .and_then(|pos| usize::try_from(pos).ok())
+                .and_then(|pos| old.get(pos));
+            new.push(byte.wrapping_add(old_byte.copied().unwrap_or(0)));
+        }
+        let extra_len = fits(extra_len, &new)?;
+        new.extend_from_slice(read(Part::Extra(extra_len))?);
+        old_pos = old_pos.checked_add(diff_len as i64).and_then(|pos| pos.checked_add(seek))
+            .ok_or("patch control seeks too far")?;
+    }
+    Ok(new)
+}
+
+/// A bsdiff integer: eight bytes of magnitude, least significant first,
+/// with the top bit for the sign.
+fn offtin(bytes: &[u8]) -> i64 {
+    let bytes: [u8; 8] = bytes.try_into().unwrap();
+    let magnitude = (u64::from_le_bytes(bytes) & !(1 << 63)) as i64;
+    match bytes[7] & 0x80 {
+        0 => magnitude,
+        _ => -magnitude,
+    }
+}
+
+/// Decompresses bzip2 data, of as many streams as follow one another.
+fn bunzip2(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = vec![];
+    bzip2::read::MultiBzDecoder::new(data).read_to_end(&mut out).map_err(|e| format!("bad bzip2 data: {}", e))?;
+    Ok(out)
+}
@@ -0,0 +1,109 @@
+//! Finding encoder settings that bring an input under a compressed-size
+//! budget, for `hpcmp plan`, without compressing all of it with each.
+//!
+//! Windows are read from across the input and each compressed on its own
+//! with every combination of level, reset policy and block length, and how
+//! much they came to, and how long that took, scaled up to the whole input.
+//! Each window starts a block of its own, so a setting whose blocks run on
+//! for longer than a window is judged by how it does on the window's worth,
+//! which flatters one that freezes a dictionary for the rest of a long block.
+//! With the `rayon` feature, settings are tried in parallel.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+
+use crate::encode::{self, Level, Options, WhenFull};
+
+/// Block lengths tried, besides running blocks on to the end.
+const BLOCK_LENS: &[u64] = &[4 << 10, 16 << 10, 64 << 10, 256 << 10, 1 << 20];
+
+/// Windows of `file`, `len` bytes long, to judge settings by: `count` of
+/// them `window` bytes long spread evenly from its start to its end, or all
+/// of it if that's no more.
+pub fn windows(file: &mut File, len: u64, count: u64, window: u64) -> io::Result<Vec<Vec<u8>>> {
+    if count.saturating_mul(window) >= len {
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+        return Ok(vec![data]);
+    }
+    (0..count).map(|i| {
+        let offset = match count {
+            1 => (len - window) / 2,
+            _ => i * (len - window) / (count - 1),
+        };
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; window as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }).collect()
+}
+
+/// Every combination of settings worth trying on windows `window` bytes
+/// long: blocks shorter than a window, or running on to the end.
+pub fn candidates(window: u64) -> Vec<Options> {
+    let block_lens = std::iter::once(None).chain(BLOCK_LENS.iter().filter(|&&len| len < window).map(|&len| Some(len)));
+    let mut all = vec![];
+    for block_len in block_lens {
+        for when_full in [WhenFull::Freeze, WhenFull::Reset, WhenFull::Adaptive] {
+            for level in [Level::Greedy, Level::Max] {
+                all.push(Options{ block_len, when_full, level });
+            }
+        }
+    }
+    all
+}
+
+/// How a setting did on the windows.
+pub struct Trial {
+    pub options: Options,
+    /// Bytes of the windows, and what they compressed to.
+    pub sampled: u64,
+    pub compressed: u64,
+    pub elapsed: Duration,
+}
+
+impl Trial {
+    /// Compresses each of `windows` with `options`.
+    pub fn run(windows: &[Vec<u8>], options: Options) -> Trial {
+        let start = Instant::now();
+        let compressed = windows.iter().filter(|window| window.len() >= 2).map(|window| encode::compress(window, options).len() as u64).sum();
+        let sampled = windows.iter().filter(|window| window.len() >= 2).map(|window| window.len() as u64).sum();
+        Trial{ options, sampled, compressed, elapsed: start.elapsed() }
+    }
+
+    /// What an input `len` bytes long would come to.
+    pub fn estimate(&self, len: u64) -> u64 {
+        (self.compressed as f64 / self.sampled.max(1) as f64 * len as f64).ceil() as u64
+    }
+
+    /// How long compressing an input `len` bytes long would take.
+    pub fn time(&self, len: u64) -> Duration {
+        self.elapsed.mul_f64(len as f64 / self.sampled.max(1) as f64)
+    }
+}
+
+/// A trial of each of `candidates`.
+#[cfg(feature = "rayon")]
+pub fn run_all(windows: &[Vec<u8>], candidates: Vec<Options>) -> Vec<Trial> {
+    candidates.into_par_iter().map(|options| Trial::run(windows, options)).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub fn run_all(windows: &[Vec<u8>], candidates: Vec<Options>) -> Vec<Trial> {
+    candidates.into_iter().map(|options| Trial::run(windows, options)).collect()
+}
+
+/// `options` as the arguments to `compress` that give them.
+pub fn args(options: Options) -> String {
+    let block_len = match options.block_len {
+        Some(len) if len % (1 << 20) == 0 => format!(" --block-len {}M", len >> 20),
+        Some(len) if len % (1 << 10) == 0 => format!(" --block-len {}K", len >> 10),
+        Some(len)                         => format!(" --block-len {}", len),
+        None                              => String::new(),
+    };
+    format!("--level {} --when-full {}{}", options.level.name(), options.when_full.name(), block_len)
+}
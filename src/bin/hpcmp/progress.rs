@@ -0,0 +1,150 @@
+//! Progress through a parallel batch: a bar for the batch and one for each
+//! worker on a terminal, or a status line now and then otherwise.
+
+use std::cell::RefCell;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use hpcmp::{Code, DecodeObserver};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// How often a status line is printed when stderr isn't a terminal.
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+/// Bytes of input between updates to a worker's bar.
+const UPDATE_BYTES: u64 = 1 << 16;
+
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+thread_local! {
+    /// The bar for the worker on this thread.
+    static WORKER: RefCell<Option<ProgressBar>> = const { RefCell::new(None) };
+}
+
+pub struct Progress {
+    batch: ProgressBar,
+    workers: Vec<ProgressBar>,
+    /// Stops the status lines.
+    status: Option<(Sender<()>, JoinHandle<()>)>,
+}
+
+impl Progress {
+    /// Starts showing progress through `files` inputs for `workers` workers.
+    pub fn start(files: usize, workers: usize) -> Progress {
+        let terminal = std::io::stderr().is_terminal();
+        let bars = MultiProgress::with_draw_target(match terminal {
+            true  => ProgressDrawTarget::stderr(),
+            false => ProgressDrawTarget::hidden(),
+        });
+        let batch = bars.add(ProgressBar::new(files as u64).with_style(
+            ProgressStyle::with_template("{bar:40} {pos}/{len} files, {elapsed}").unwrap(),
+        ));
+        let workers = (0..workers).map(|_| bars.add(ProgressBar::new(0).with_style(
+            ProgressStyle::with_template("  {bar:40} {bytes}/{total_bytes} {wide_msg}").unwrap(),
+        ))).collect::<Vec<_>>();
+
+        let status = (!terminal).then(|| {
+            let (stop, stopped) = mpsc::channel();
+            let (batch, workers) = (batch.clone(), workers.clone());
+            let thread = thread::spawn(move || {
+                while stopped.recv_timeout(STATUS_INTERVAL) == Err(RecvTimeoutError::Timeout) {
+                    eprintln!("{}", status_line(&batch, &workers));
+                }
+            });
+            (stop, thread)
+        });
+        // Only one batch runs, so this can't already be set
+        let _ = BARS.set(bars);
+        Progress{ batch, workers, status }
+    }
+
+    /// Makes this thread the `index`th worker.
+    pub fn attach(&self, index: usize) {
+        let bar = self.workers[index].clone();
+        WORKER.with(|worker| *worker.borrow_mut() = Some(bar));
+    }
+
+    /// Counts an input as done.
+    pub fn file_done(&self) {
+        self.batch.inc(1);
+    }
+
+    pub fn finish(self) {
+        if let Some((stop, thread)) = self.status {
+            let _ = stop.send(());
+            let _ = thread.join();
+        }
+        for bar in self.workers.iter().chain([&self.batch]) {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// E.g. `3/10 files; decoding a.cmp (45%), b.cmp (12%)`.
+fn status_line(batch: &ProgressBar, workers: &[ProgressBar]) -> String {
+    let busy: Vec<String> = workers.iter()
+        .filter(|bar| !bar.message().is_empty())
+        .map(|bar| format!("{} ({}%)", bar.message(), bar.position() * 100 / bar.length().unwrap_or(0).max(1)))
+        .collect();
+    match busy.is_empty() {
+        true  => format!("{}/{} files", batch.position(), batch.length().unwrap_or(0)),
+        false => format!("{}/{} files; decoding {}", batch.position(), batch.length().unwrap_or(0), busy.join(", ")),
+    }
+}
+
+/// Shows this thread's worker as working on `input`, or as idle.
+pub fn set_file(input: Option<&Path>) {
+    with_worker(|bar| {
+        bar.set_message(input.map(|input| input.display().to_string()).unwrap_or_default());
+        bar.set_length(0);
+        bar.set_position(0);
+    });
+}
+
+/// Starts this thread's worker's bar over a `len`-byte stream, and returns an
+/// observer that moves it along.
+pub fn observer(len: usize) -> Observer {
+    let bar = WORKER.with(|worker| worker.borrow().clone());
+    if let Some(bar) = &bar {
+        bar.set_length(len as u64);
+        bar.set_position(0);
+    }
+    Observer{ bar, next: UPDATE_BYTES }
+}
+
+fn with_worker(f: impl FnOnce(&ProgressBar)) {
+    WORKER.with(|worker| {
+        if let Some(bar) = worker.borrow().as_ref() {
+            f(bar);
+        }
+    });
+}
+
+/// Runs `f`, which writes to the terminal, with the bars out of the way.
+pub fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    match BARS.get() {
+        Some(bars) => bars.suspend(f),
+        None       => f(),
+    }
+}
+
+/// Moves a worker's bar along with the input read.
+pub struct Observer {
+    bar: Option<ProgressBar>,
+    next: u64,
+}
+
+impl DecodeObserver for Observer {
+    fn code(&mut self, bit_offset: u64, _width: u8, _code: Code) {
+        let byte = bit_offset / 8;
+        if byte >= self.next {
+            if let Some(bar) = &self.bar {
+                bar.set_position(byte);
+            }
+            self.next = byte + UPDATE_BYTES;
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Checking outputs against another decompressor, for `--reference-cmd`.
+
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+use hpcmp::StreamReport;
+
+use crate::diagnose::CheckFailed;
+
+/// Numbers the temporary files of jobs running at once.
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+/// A shell command decompressing `{in}` into `{out}`. Without `{in}` the
+/// input goes to its stdin, and without `{out}` its stdout is the output.
+pub struct Reference {
+    command: String,
+}
+
+impl Reference {
+    pub fn new(command: &str) -> Reference {
+        Reference{ command: command.to_string() }
+    }
+
+    /// Runs the command on `stream` and compares what it produces with
+    /// `data`, which hpcmp decoded with `report`.
+    pub fn check(&self, stream: &[u8], data: &[u8], report: &StreamReport) -> Result<(), Box<dyn Error>> {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let temp = |suffix| std::env::temp_dir().join(format!("hpcmp-reference-{}-{}.{}", std::process::id(), n, suffix));
+        let (input, output) = (Temp(temp("in")), Temp(temp("out")));
+        let command = self.command
+            .replace("{in}", &quote(&input.0))
+            .replace("{out}", &quote(&output.0));
+        let reads_stdin = !self.command.contains("{in}");
+        let writes_stdout = !self.command.contains("{out}");
+        if !reads_stdin {
+            fs::write(&input.0, stream)?;
+        }
+
+        let mut child = shell(&command)
+            .stdin(if reads_stdin { Stdio::piped() } else { Stdio::null() })
+            .stdout(if writes_stdout { Stdio::piped() } else { Stdio::null() })
+            .spawn()?;
+        // Fed from another thread, so a command writing as it reads can't
+        // fill its stdout while this waits on its stdin
+        let stdin = child.stdin.take();
+        let result = thread::scope(|scope| {
+            if let Some(mut stdin) = stdin {
+                // The command may not read all of it
+                scope.spawn(move || { let _ = stdin.write_all(stream); });
+            }
+            child.wait_with_output()
+        })?;
+        if !result.status.success() {
+            return Err(CheckFailed{
+                code: "reference_failed",
+                message: format!("reference command failed: {}", result.status),
+            }.into());
+        }
+        let expected = match writes_stdout {
+            true  => result.stdout,
+            false => fs::read(&output.0)?,
+        };
+        match divergence(data, &expected, report) {
+            Some(message) => Err(CheckFailed{ code: "reference_mismatch", message }.into()),
+            None          => Ok(()),
+        }
+    }
+}
+
+/// Where `data` first differs from the reference's `expected`, if it does.
+fn divergence(data: &[u8], expected: &[u8], report: &StreamReport) -> Option<String> {
+    let offset = data.iter().zip(expected).position(|(a, b)| a != b);
+    let offset = match offset {
+        Some(offset) => offset,
+        None if data.len() == expected.len() => return None,
+        None => return Some(format!(
+            "output is {} bytes and the reference's {}, but the same up to there",
+            data.len(), expected.len(),
+        )),
+    };
+    let mut message = format!(
+        "output differs from the reference at byte 0x{:x}: 0x{:02x}, the reference 0x{:02x}",
+        offset, data[offset], expected[offset],
+    );
+    let block = report.blocks.iter().enumerate()
+        .find(|(_, block)| (block.output_offset..block.output_offset + block.output_len).contains(&(offset as u64)));
+    if let Some((index, block)) = block {
+        message += &format!(", in block {} from input byte 0x{:x}", index, block.input_offset);
+    }
+    Some(message)
+}
+
+/// `command` run through the shell.
+#[cfg(unix)]
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// `path` quoted for the shell.
+fn quote(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    match cfg!(unix) {
+        true  => format!("'{}'", path.replace('\'', "'\\''")),
+        false => format!("\"{}\"", path),
+    }
+}
+
+/// A temporary file, removed when dropped.
+struct Temp(PathBuf);
+
+impl Drop for Temp {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
@@ -0,0 +1,70 @@
+//! Replaying inputs found by fuzzing, for `hpcmp run-corpus`.
+
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use hpcmp::Decoder;
+
+/// Output allowed per input, as in the fuzz targets.
+const LIMIT: usize = 1 << 20;
+
+/// Decodes every file in `dir` as the fuzz targets do, returning the
+/// number that panicked or decoded differently when fed a byte at a time.
+pub fn run(dir: &Path) -> io::Result<usize> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let mut failures = 0;
+    for path in &paths {
+        let input = fs::read(path)?;
+        match panic::catch_unwind(AssertUnwindSafe(|| check(&input))) {
+            Ok(Ok(outcome)) => println!("{}: {}", path.display(), outcome),
+            Ok(Err(e))      => {
+                println!("{}: FAILED, {}", path.display(), e);
+                failures += 1;
+            },
+            Err(_) => {
+                println!("{}: FAILED, panicked", path.display());
+                failures += 1;
+            },
+        }
+    }
+    println!("{} inputs, {} failed", paths.len(), failures);
+    Ok(failures)
+}
+
+/// What `input` decodes to, or how decoding it in one go and a byte at a
+/// time disagree.
+fn check(input: &[u8]) -> Result<String, String> {
+    let mut out = vec![0; LIMIT];
+    let whole = hpcmp::decompress_into(input, &mut out).map(|len| out[..len].to_vec());
+
+    let mut decoder = Decoder::new();
+    let mut data = vec![];
+    let mut chunked = input.chunks(1)
+        .try_for_each(|byte| {
+            decoder.decode_to_vec(byte, &mut data)?;
+            match data.len() > LIMIT {
+                true  => Err(hpcmp::Error::OutputOverflow),
+                false => Ok(()),
+            }
+        })
+        .map(|()| data);
+    if chunked.is_ok() && !decoder.is_done() {
+        chunked = Err(hpcmp::Error::UnexpectedEof);
+    }
+
+    match (whole, chunked) {
+        (Ok(whole), Ok(chunked)) if whole == chunked => Ok(format!("ok, {} bytes", whole.len())),
+        (Err(whole), Err(chunked)) if whole == chunked => Ok(format!("ok, {}", whole)),
+        (whole, chunked) => Err(format!(
+            "{:?} in one go, but {:?} a byte at a time",
+            whole.map(|data| data.len()), chunked.map(|data| data.len()),
+        )),
+    }
+}
@@ -0,0 +1,298 @@
+//! Uncompressed data shaped like what firmware images hold, for
+//! `hpcmp gen-data`, so that the encoder can be measured the same way
+//! anywhere without sharing real firmware.
+//!
+//! Each profile makes its data a unit at a time: a function with its literal
+//! pool, a NUL-terminated message, or an image. The redundancy comes from
+//! where it does in firmware rather than from repeating a pattern: a few
+//! idioms and globals that code keeps coming back to, a vocabulary that
+//! messages mostly draw from the start of, and images of flat colours,
+//! gradients and glyphs. A seed gives the same data on every platform.
+
+use std::io::{self, Write};
+
+use crate::generate::Rng;
+
+pub const PROFILE_NAMES: &[&str] = &["code", "text", "raster"];
+
+/// Bytes made before they're written out.
+const CHUNK: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// Thumb-like machine code with literal pools.
+    Code,
+    /// Log, error and menu strings.
+    Text,
+    /// Icons, gradients and font bitmaps.
+    Raster,
+}
+
+impl Profile {
+    pub fn from_name(name: &str) -> Option<Profile> {
+        match name {
+            "code"   => Some(Profile::Code),
+            "text"   => Some(Profile::Text),
+            "raster" => Some(Profile::Raster),
+            _        => None,
+        }
+    }
+}
+
+/// Writes `size` bytes of `profile`'s data from `seed` to `w`.
+pub fn write(profile: Profile, seed: u64, size: u64, w: &mut impl Write) -> io::Result<()> {
+    let mut rng = Rng(seed);
+    let mut source: Box<dyn Source> = match profile {
+        Profile::Code   => Box::new(Code::new(&mut rng)),
+        Profile::Text   => Box::new(Text::new(&mut rng)),
+        Profile::Raster => Box::new(Raster::new(&mut rng)),
+    };
+    let mut buf = Vec::with_capacity(CHUNK * 2);
+    let mut left = size;
+    while left > 0 {
+        source.unit(&mut rng, &mut buf);
+        // Whole chunks, so that what's aligned in `buf` is in the output
+        while buf.len() >= CHUNK || (left > 0 && buf.len() as u64 >= left) {
+            let len = buf.len().min(CHUNK).min(left as usize);
+            w.write_all(&buf[..len])?;
+            left -= len as u64;
+            buf.drain(..len);
+        }
+    }
+    Ok(())
+}
+
+/// Makes one profile's data.
+trait Source {
+    /// Appends a unit of data, at least a byte, to `out`.
+    fn unit(&mut self, rng: &mut Rng, out: &mut Vec<u8>);
+}
+
+/// An index below `n`, mostly near the start, as a few of anything are used
+/// far more than the rest.
+fn skewed(rng: &mut Rng, n: usize) -> usize {
+    let limit = rng.below(n) + 1;
+    rng.below(limit)
+}
+
+struct Code {
+    /// Short runs of instructions compilers keep emitting.
+    idioms: Vec<Vec<u16>>,
+    /// Addresses of functions called and of the data they load.
+    functions: Vec<u32>,
+    globals: Vec<u32>,
+}
+
+impl Code {
+    fn new(rng: &mut Rng) -> Code {
+        let idioms = (0..64).map(|_| (0..2 + rng.below(5)).map(|_| instruction(rng)).collect()).collect();
+        let functions = (0..256).map(|_| 0x0800_0000 + (rng.below(0x10_0000) as u32 & !1) + 1).collect();
+        let globals = (0..128).map(|_| match rng.below(3) {
+            0 => (0x4000_0000 + rng.below(0x3_0000) as u32) & !3,
+            _ => (0x2000_0000 + rng.below(0x2_0000) as u32) & !3,
+        }).collect();
+        Code{ idioms, functions, globals }
+    }
+}
+
+/// A 16-bit instruction of a common form, with registers mostly low.
+fn instruction(rng: &mut Rng) -> u16 {
+    let reg = |rng: &mut Rng| skewed(rng, 8) as u16;
+    let imm = |rng: &mut Rng, bits: u32| skewed(rng, 1 << bits) as u16;
+    match rng.below(9) {
+        0 => 0x2000 | reg(rng) << 8 | imm(rng, 8),                     // movs rd, #imm
+        1 => 0x2800 | reg(rng) << 8 | imm(rng, 8),                     // cmp rn, #imm
+        2 => 0x6800 | imm(rng, 5) << 6 | reg(rng) << 3 | reg(rng),     // ldr rd, [rn, #imm]
+        3 => 0x6000 | imm(rng, 5) << 6 | reg(rng) << 3 | reg(rng),     // str rd, [rn, #imm]
+        4 => 0x1800 | reg(rng) << 6 | reg(rng) << 3 | reg(rng),        // adds rd, rn, rm
+        5 => 0x4600 | reg(rng) << 3 | reg(rng),                        // mov rd, rm
+        6 => 0x4800 | reg(rng) << 8 | imm(rng, 8),                     // ldr rd, [pc, #imm]
+        7 => 0xd000 | (rng.below(14) as u16) << 8 | imm(rng, 8),       // b<cond>
+        _ => 0x3000 | reg(rng) << 8 | imm(rng, 8),                     // adds rd, #imm
+    }
+}
+
+impl Source for Code {
+    fn unit(&mut self, rng: &mut Rng, out: &mut Vec<u8>) {
+        let mut halves = vec![0xb500 | [0xf0, 0x70, 0x30, 0x10][rng.below(4)]];
+        for _ in 0..4 + skewed(rng, 120) {
+            match rng.below(8) {
+                0..=4 => halves.extend_from_slice(&self.idioms[skewed(rng, self.idioms.len())]),
+                5     => {
+                    // bl, to somewhere near enough to encode
+                    let offset = (self.functions[skewed(rng, self.functions.len())] >> 1) & 0x3f_ffff;
+                    halves.push(0xf000 | (offset >> 11) as u16);
+                    halves.push(0xf800 | (offset & 0x7ff) as u16);
+                },
+                _     => halves.push(instruction(rng)),
+            }
+        }
+        halves.push(halves[0] | 0x0800);
+        if halves.len() % 2 == 1 {
+            halves.push(0xbf00);
+        }
+        out.extend(halves.iter().flat_map(|half| half.to_le_bytes()));
+        for _ in 0..rng.below(8) {
+            let word = match rng.below(3) {
+                0 => self.functions[skewed(rng, self.functions.len())],
+                1 => {
+                    let bits = skewed(rng, 32);
+                    rng.below(1 << bits) as u32
+                },
+                _ => self.globals[skewed(rng, self.globals.len())],
+            };
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        // Functions aligned to 16 bytes, the gaps erased flash
+        while !out.len().is_multiple_of(16) {
+            out.push(0xff);
+        }
+    }
+}
+
+const MODULES: &[&str] = &[
+    "usb", "flash", "i2c", "spi", "uart", "adc", "dma", "gpio", "rtc", "pwr", "sensor", "display", "bt", "wdt",
+];
+
+const WORDS: &[&str] = &[
+    "failed", "error", "init", "timeout", "invalid", "buffer", "ready", "state", "write", "read", "config",
+    "request", "overflow", "retry", "calibration", "channel", "device", "not", "found", "status", "value",
+    "expected", "got", "address", "length", "mode", "enabled", "disabled", "start", "stop", "reset", "busy",
+    "checksum", "mismatch", "version", "update", "sector", "erase", "queue", "full", "empty", "unsupported",
+    "command", "response", "power", "low", "battery", "temperature", "range", "out", "of", "memory",
+];
+
+const FORMATS: &[&str] = &["%d", "%u", "0x%08x", "%s", "%02x", "%ld", "%p"];
+
+const MENU: &[&str] = &[
+    "Settings", "Language", "Brightness", "Volume", "About", "Firmware Update", "Factory Reset", "Back",
+    "Cancel", "OK", "Save", "Calibrate", "Date & Time", "Units", "Bluetooth", "Display", "Sleep Timer",
+];
+
+struct Text {
+    /// Messages logged from more than one place.
+    common: Vec<Vec<u8>>,
+}
+
+impl Text {
+    fn new(rng: &mut Rng) -> Text {
+        Text{ common: (0..64).map(|_| message(rng)).collect() }
+    }
+}
+
+fn message(rng: &mut Rng) -> Vec<u8> {
+    let mut text = format!("{}: ", MODULES[skewed(rng, MODULES.len())]);
+    for i in 0..2 + rng.below(6) {
+        if i > 0 {
+            text.push(' ');
+        }
+        if rng.one_in(5) {
+            text.push_str(FORMATS[skewed(rng, FORMATS.len())]);
+        } else {
+            text.push_str(WORDS[skewed(rng, WORDS.len())]);
+        }
+    }
+    if rng.one_in(3) {
+        text.push('\n');
+    }
+    text.into_bytes()
+}
+
+impl Source for Text {
+    fn unit(&mut self, rng: &mut Rng, out: &mut Vec<u8>) {
+        match rng.below(8) {
+            0     => out.extend_from_slice(MENU[rng.below(MENU.len())].as_bytes()),
+            1..=3 => out.extend_from_slice(&self.common[skewed(rng, self.common.len())]),
+            _     => out.extend(message(rng)),
+        }
+        out.push(0);
+        // The odd string table aligned for the code that indexes it
+        if rng.one_in(16) {
+            while !out.len().is_multiple_of(4) {
+                out.push(0);
+            }
+        }
+    }
+}
+
+struct Raster {
+    /// The UI's colours, as indices into its 8-bit palette.
+    palette: Vec<u8>,
+    /// A font's 8×16 glyphs, a byte a row.
+    glyphs: Vec<[u8; 16]>,
+}
+
+impl Raster {
+    fn new(rng: &mut Rng) -> Raster {
+        let palette = (0..8).map(|_| rng.below(256) as u8).collect();
+        let glyphs = (0..96).map(|_| {
+            let mut glyph = [0; 16];
+            // Strokes of a few columns down most of the cell
+            for _ in 0..1 + rng.below(3) {
+                let (mask, top) = (0xc0u8 >> rng.below(7), 2 + rng.below(4));
+                for row in &mut glyph[top..12 + rng.below(3)] {
+                    *row |= mask;
+                }
+            }
+            let bar = 3 + rng.below(9);
+            glyph[bar] |= 0x7e;
+            glyph
+        }).collect();
+        Raster{ palette, glyphs }
+    }
+}
+
+impl Source for Raster {
+    fn unit(&mut self, rng: &mut Rng, out: &mut Vec<u8>) {
+        match rng.below(4) {
+            // An icon: flat shapes on a background, in palette indices
+            0 | 1 => {
+                let (width, height) = ([16, 24, 32, 48][rng.below(4)], [16, 24, 32, 48][rng.below(4)]);
+                header(out, width, height, 8);
+                let background = self.palette[0];
+                let shapes: Vec<(usize, usize, usize, usize, u8)> = (0..1 + rng.below(4)).map(|_| {
+                    let (x, y) = (rng.below(width), rng.below(height));
+                    (x, y, x + 1 + rng.below(width - x), y + 1 + rng.below(height - y), self.palette[1 + rng.below(7)])
+                }).collect();
+                for y in 0..height {
+                    for x in 0..width {
+                        let inside = shapes.iter().rev().find(|s| (s.0..s.2).contains(&x) && (s.1..s.3).contains(&y));
+                        out.push(inside.map_or(background, |s| s.4));
+                    }
+                }
+            },
+            // A gradient in RGB565, with a little dither
+            2 => {
+                let (width, height) = (64 + rng.below(4) * 32, 8 + rng.below(24));
+                header(out, width, height, 16);
+                let (from, to) = ([rng.below(32), rng.below(64), rng.below(32)], [rng.below(32), rng.below(64), rng.below(32)]);
+                for _ in 0..height {
+                    for x in 0..width {
+                        let at = |c: usize| (from[c] as isize + (to[c] as isize - from[c] as isize) * x as isize / width as isize) as usize;
+                        let mut pixel = (at(0) << 11 | at(1) << 5 | at(2)) as u16;
+                        if rng.one_in(40) {
+                            pixel ^= 1;
+                        }
+                        out.extend_from_slice(&pixel.to_le_bytes());
+                    }
+                }
+            },
+            // A run of the font's glyphs, as a string would be drawn
+            _ => {
+                let len = 1 + rng.below(24);
+                header(out, len * 8, 16, 1);
+                let text: Vec<usize> = (0..len).map(|_| skewed(rng, self.glyphs.len())).collect();
+                for row in 0..16 {
+                    out.extend(text.iter().map(|&glyph| self.glyphs[glyph][row]));
+                }
+            },
+        }
+    }
+}
+
+/// The width, height and bits per pixel each image starts with.
+fn header(out: &mut Vec<u8>, width: usize, height: usize, bpp: u8) {
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    out.push(bpp);
+}
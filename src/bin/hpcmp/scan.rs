@@ -0,0 +1,212 @@
+//! Finding compressed streams in a larger image, like a flash dump, for
+//! `hpcmp scan`.
+//!
+//! Every offset that could start a stream is tried on its own, so with the
+//! `rayon` feature they are tried in parallel.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use hpcmp::{Decoder, ReportBuilder, ResetPoint, StreamReport};
+
+/// A stream found in an image.
+pub struct Found {
+    pub offset: u64,
+    /// Bytes of the image it took up, or decoded before failing.
+    pub input_len: u64,
+    pub output_len: u64,
+    /// Set if it failed before the end of the stream.
+    pub error: Option<hpcmp::Error>,
+    /// Where each block it decoded starts, relative to `offset`.
+    pub resets: Vec<ResetPoint>,
+    /// How likely it is to be a stream rather than noise, from 0 to 100; see
+    /// [`score`].
+    pub score: u32,
+}
+
+/// What to look for.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Options {
+    /// Only offsets that are a multiple of this are tried.
+    pub align: usize,
+    /// Streams that decode to less than this aren't reported, as noise
+    /// decodes to a few bytes now and then.
+    pub min_output: u64,
+    /// Whether streams that fail before their end are reported.
+    pub partial: bool,
+    /// Streams scoring less than this aren't reported.
+    pub min_score: u32,
+}
+
+/// Finds the streams in `image`, in order of offset. Any that start inside
+/// one already found, reported or not, are taken to be part of it and left
+/// out.
+///
+/// A reset inside a stream can look like the start of one, and decoding from
+/// there would decode the rest of the stream again, so rather than trying
+/// all the offsets at once they are tried in order a batch at a time,
+/// skipping any inside what has been found so far.
+pub fn scan(image: &[u8], options: Options) -> Vec<Found> {
+    let mut offsets = (0..image.len()).step_by(options.align.max(1)).filter(|&offset| could_start(&image[offset..]));
+    let mut found = vec![];
+    let mut end = 0;
+    loop {
+        let batch: Vec<usize> = offsets.by_ref().filter(|&offset| offset as u64 >= end).take(batch_len()).collect();
+        if batch.is_empty() {
+            return found;
+        }
+        for probed in probe_all(image, batch, options) {
+            if probed.offset >= end {
+                end = probed.offset + probed.input_len;
+                if probed.error.is_none() || options.partial {
+                    found.push(probed);
+                }
+            }
+        }
+    }
+}
+
+/// Offsets tried at once: one for each thread, as any more are as likely to
+/// be inside a stream found in the same batch as to keep a thread busy.
+#[cfg(feature = "rayon")]
+fn batch_len() -> usize {
+    rayon::current_num_threads()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn batch_len() -> usize {
+    1
+}
+
+#[cfg(feature = "rayon")]
+fn probe_all(image: &[u8], offsets: Vec<usize>, options: Options) -> Vec<Found> {
+    offsets.into_par_iter().filter_map(|offset| probe(image, offset, options)).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn probe_all(image: &[u8], offsets: Vec<usize>, options: Options) -> Vec<Found> {
+    offsets.into_iter().filter_map(|offset| probe(image, offset, options)).collect()
+}
+
+/// Whether `data` starts with a 9-bit reset code, whose byte is then
+/// dropped, followed by a 9-bit literal, as every stream does. All but about
+/// one offset in a thousand of anything else fail this.
+fn could_start(data: &[u8]) -> bool {
+    match *data {
+        [first, second, third, fourth, ..] => {
+            let reset = u16::from(first) | u16::from(second & 1) << 8;
+            let literal = u16::from(third) | u16::from(fourth & 1) << 8;
+            reset == 1 && (0x8..0x108).contains(&literal)
+        },
+        _ => false,
+    }
+}
+
+/// Decodes from `offset` in `image` to the end of the stream there, or as
+/// far as it gets, throwing the output away. Returns what was found if it
+/// decoded enough, and well enough, to be more than noise.
+fn probe(image: &[u8], offset: usize, options: Options) -> Option<Found> {
+    let mut decoder = Decoder::new();
+    decoder.record_resets(true);
+    let mut builder = ReportBuilder::new();
+    let mut output = Output::new();
+    let mut discard = [0; 1 << 14];
+    let mut pos = offset;
+    let error = loop {
+        let before = decoder.total_out();
+        let result = decoder.decode_with(&image[pos..], &mut discard, &mut builder);
+        // Including what came before an error
+        output.add(&discard[..(decoder.total_out() - before) as usize]);
+        match result {
+            _ if decoder.is_done() => break None,
+            Ok((0, 0))             => break Some(hpcmp::Error::UnexpectedEof),
+            Ok((consumed, _))      => pos += consumed,
+            Err(e)                 => break Some(e),
+        }
+    };
+    if decoder.total_out() < options.min_output {
+        return None;
+    }
+    let report = builder.finish(&decoder);
+    let found = Found{
+        offset: offset as u64,
+        input_len: decoder.total_in(),
+        output_len: decoder.total_out(),
+        score: score(&report, &output, error.is_none()),
+        error,
+        resets: decoder.drain_resets(),
+    };
+    (found.score >= options.min_score).then_some(found)
+}
+
+/// What a stream decoded to, as far as scoring it goes.
+pub struct Output {
+    counts: [u64; 256],
+    printable: u64,
+    len: u64,
+}
+
+impl Output {
+    pub fn new() -> Output {
+        Output{ counts: [0; 256], printable: 0, len: 0 }
+    }
+
+    pub fn add(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.counts[byte as usize] += 1;
+        }
+        self.printable += data.iter().filter(|&&byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace()).count() as u64;
+        self.len += data.len() as u64;
+    }
+
+    /// How much it looks like data rather than noise, from 0 to 1: the larger
+    /// of the share of it that is printable text and how far its entropy is
+    /// below that of as many random bytes, as noise decodes to bytes of every
+    /// value about equally often.
+    pub fn structure(&self) -> f64 {
+        let printable = self.printable as f64 / self.len.max(1) as f64;
+        // The most entropy as many bytes can show, however random
+        let random = (self.len.max(1) as f64).min(256.0).log2();
+        match random > 0.0 {
+            true  => printable.max(1.0 - self.entropy() / random),
+            false => printable,
+        }
+    }
+
+    /// Shannon entropy, in bits per byte.
+    fn entropy(&self) -> f64 {
+        let len = self.len as f64;
+        self.counts.iter().filter(|&&count| count > 0).map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        }).sum()
+    }
+}
+
+/// How likely a stream decoded to `output` is to be one, rather than noise
+/// that happened to decode, from 0 to 100. It is the sum of
+///
+/// - up to 40 for decoding: at least 20 for reaching the end of the stream,
+///   and the rest the more it decoded, or for failing before the end up to
+///   30 the more it decoded;
+/// - up to 30 for looking like data, the more it decoded, going by
+///   [`Output::structure`];
+/// - up to 30 for decoding cleanly, the more it decoded: less for every
+///   anomaly, such as an unknown command, and none once there is one every
+///   hundred codes.
+///
+/// However it ends, noise hardly ever decodes to more than a few bytes,
+/// so how much was decoded counts for a lot: all that it can for 64K.
+fn score(report: &StreamReport, output: &Output, complete: bool) -> u32 {
+    let len = output.len.max(1) as f64;
+    let decoded = (len.log2() / 16.0).min(1.0);
+    let decoding = match complete {
+        true  => 0.5 + 0.5 * decoded,
+        false => 0.75 * decoded,
+    };
+    let data = decoded * output.structure();
+    let histogram = &report.histogram;
+    let codes = histogram.commands.iter().sum::<u64>() + histogram.values + histogram.indices;
+    let clean = decoded * (1.0 - (report.anomalies.len() as f64 * 100.0 / codes.max(1) as f64).min(1.0));
+    (40.0 * decoding + 30.0 * data + 30.0 * clean).round() as u32
+}
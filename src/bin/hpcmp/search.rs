@@ -0,0 +1,84 @@
+//! Searching decompressed output as it is decoded, for `hpcmp grep`.
+
+use std::io::{self, Read};
+
+/// Decompressed bytes searched at a time.
+const CHUNK: usize = 64 << 10;
+
+/// Bytes to look for, and how to show them.
+pub struct Pattern {
+    pub bytes: Vec<u8>,
+    pub name: String,
+}
+
+impl Pattern {
+    /// Hex digits, optionally separated by whitespace: `DEADBEEF` or
+    /// `de ad be ef`.
+    pub fn hex(s: &str) -> Result<Pattern, String> {
+        let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        if digits.is_empty() || !digits.len().is_multiple_of(2) {
+            return Err(format!("{:?} isn't a whole number of bytes", s));
+        }
+        let bytes = digits.chunks(2)
+            .map(|pair| std::str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| format!("{:?} isn't hex", s))?;
+        let name = bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+        Ok(Pattern{ bytes, name })
+    }
+
+    pub fn string(s: &str) -> Result<Pattern, String> {
+        match s.is_empty() {
+            true  => Err("an empty string matches everywhere".to_string()),
+            false => Ok(Pattern{ bytes: s.as_bytes().to_vec(), name: format!("{:?}", s) }),
+        }
+    }
+}
+
+/// Decompresses `input`, calling `found` with the offset in the output of
+/// every occurrence of each pattern, in order, overlapping ones included,
+/// and stopping if it fails. Only as much output as the longest pattern
+/// spans is kept between chunks. Returns the number of matches.
+pub fn search(input: impl Read, patterns: &[Pattern], mut found: impl FnMut(u64, &Pattern) -> io::Result<()>) -> io::Result<u64> {
+    let mut decompressor = hpcmp::Decompressor::new(input);
+    let keep = patterns.iter().map(|pattern| pattern.bytes.len()).max().unwrap_or(1) - 1;
+    let mut buf = vec![];
+    // Offset in the output of `buf[0]`
+    let mut base = 0;
+    let mut matches = 0;
+    let mut hits = vec![];
+    loop {
+        let tail = buf.len();
+        buf.resize(tail + CHUNK, 0);
+        let read = match decompressor.read(&mut buf[tail..]) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                buf.truncate(tail);
+                continue;
+            },
+            Err(e) => return Err(e),
+        };
+        buf.truncate(tail + read);
+        if read == 0 {
+            return Ok(matches);
+        }
+
+        // Anything ending within the tail was found last time round
+        for (p, pattern) in patterns.iter().enumerate() {
+            let len = pattern.bytes.len();
+            let first = (tail + 1).saturating_sub(len);
+            hits.extend(buf.windows(len).enumerate().skip(first)
+                .filter(|(_, window)| *window == &pattern.bytes[..])
+                .map(|(i, _)| (i, p)));
+        }
+        hits.sort_unstable();
+        for (i, p) in hits.drain(..) {
+            found(base + i as u64, &patterns[p])?;
+            matches += 1;
+        }
+
+        let drop = buf.len().saturating_sub(keep);
+        buf.drain(..drop);
+        base += drop as u64;
+    }
+}
@@ -0,0 +1,95 @@
+//! `.trace` and `.stats.json` files written alongside an output.
+
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use hpcmp::{Code, DecodeObserver, ReportBuilder, ResetPoint, StreamReport, Suppression};
+
+use crate::decode::Decoding;
+
+/// Decompresses `input` as `decoding` says, writing a trace of the decode
+/// and a report on the stream next to `output` as it goes. The report is
+/// returned too.
+///
+/// The trace is kept even if decoding fails, as that is when it is most
+/// useful; the report is only written for complete streams.
+pub fn decompress(input: &[u8], output: &Path, decoding: &Decoding) -> Result<(Vec<u8>, StreamReport), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path(output, ".trace"))?);
+    let mut observer = (ReportBuilder::new(), Trace::new(file));
+    let mut decoder = decoding.decoder();
+    let mut data = vec![];
+    let result = decoding.decode_to_vec_with(&mut decoder, input, &mut data, &mut observer);
+    let (builder, trace) = observer;
+    let flushed = trace.finish();
+    result?;
+    flushed?;
+    if !decoder.is_done() {
+        return Err(hpcmp::Error::UnexpectedEof.into());
+    }
+
+    let report = builder.finish_input(&decoder, input.len() as u64);
+    fs::write(path(output, ".stats.json"), serde_json::to_string_pretty(&report)?)?;
+    Ok((data, report))
+}
+
+/// `output` with `suffix` appended to its file name.
+fn path(output: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(output);
+    name.push(suffix);
+    name.into()
+}
+
+/// Observer that writes a line for each decode event.
+struct Trace<W> {
+    out: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Trace<W> {
+    fn new(out: W) -> Trace<W> {
+        Trace{ out, error: None }
+    }
+
+    // Observer methods can't fail, so the first error is kept for `finish`
+    fn line(&mut self, args: fmt::Arguments) {
+        if self.error.is_none() {
+            if let Err(e) = self.out.write_fmt(args) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None    => self.out.flush(),
+        }
+    }
+}
+
+impl<W: Write> DecodeObserver for Trace<W> {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        self.line(format_args!("code {} w{} {:?}\n", bit_offset, width, code));
+    }
+
+    fn insert(&mut self, index: usize, value: u8, next: Code) {
+        self.line(format_args!("insert {} {:#04x} {:?}\n", index, value, next));
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        self.line(format_args!("suppressed {:?}\n", reason));
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        self.line(format_args!("reset {} in={} out={} prev_len={}\n",
+            point.bit_offset, point.input_offset, point.output_offset, point.prev_len));
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        self.line(format_args!("block_end out={} dict={}\n", output_offset, dictionary_len));
+    }
+}
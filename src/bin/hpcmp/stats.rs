@@ -0,0 +1,133 @@
+//! Sizes and timings of decodes, for the summary, `--time` and
+//! `--summary-csv`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// What went into and came out of one or more decodes, and how long they
+/// took.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub files: u64,
+    /// Bytes read, as stored.
+    pub input: u64,
+    /// Bytes decompressed.
+    pub output: u64,
+    pub blocks: u64,
+    pub elapsed: Duration,
+    /// Of the output, for `--summary-csv`, if there was just one.
+    pub sha256: Option<String>,
+}
+
+impl Stats {
+    pub fn add(&mut self, other: &Stats) {
+        self.files += other.files;
+        self.input += other.input;
+        self.output += other.output;
+        self.blocks += other.blocks;
+        self.elapsed += other.elapsed;
+    }
+
+    /// Sizes, the ratio, the block count and the duration, e.g. `12.3 KiB ->
+    /// 1.5 MiB (125.2x), 4 blocks, 38 ms`.
+    pub fn summary(&self) -> String {
+        let ratio = match self.input {
+            0     => "-".to_string(),
+            input => format!("{:.1}x", self.output as f64 / input as f64),
+        };
+        format!(
+            "{} -> {} ({}), {} block{}, {}",
+            size(self.input), size(self.output), ratio,
+            self.blocks, if self.blocks == 1 { "" } else { "s" }, duration(self.elapsed),
+        )
+    }
+
+    /// The duration and throughput, e.g. `0.125 s, in 2.4 MB/s, out 6.0 MB/s`.
+    pub fn timing(&self) -> String {
+        let seconds = self.elapsed.as_secs_f64();
+        let rate = |bytes: u64| match seconds {
+            s if s > 0.0 => format!("{:.1} MB/s", bytes as f64 / s / 1e6),
+            _            => "- MB/s".to_string(),
+        };
+        format!("{:.3} s, in {}, out {}", seconds, rate(self.input), rate(self.output))
+    }
+}
+
+/// `bytes` in binary units.
+fn size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn duration(elapsed: Duration) -> String {
+    match elapsed.as_secs_f64() {
+        s if s < 1.0 => format!("{} ms", elapsed.as_millis()),
+        s            => format!("{:.2} s", s),
+    }
+}
+
+/// How a job turned out, for `--summary-csv`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    Ok,
+    Skipped,
+    /// With the kind of failure, as for `--errors-json`.
+    Failed(&'static str),
+}
+
+/// A CSV file with a row appended for each job.
+pub struct Csv {
+    file: LineWriter<File>,
+}
+
+impl Csv {
+    const HEADER: &'static str = "path,status,error,input_bytes,output_bytes,ratio,blocks,seconds,sha256";
+
+    /// Opens the file at `path` for appending, starting it with a header if
+    /// it's new.
+    pub fn open(path: &Path) -> io::Result<Csv> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut csv = Csv{ file: LineWriter::new(file) };
+        if empty {
+            writeln!(csv.file, "{}", Csv::HEADER)?;
+        }
+        Ok(csv)
+    }
+
+    pub fn row(&mut self, path: &Path, status: Status, stats: &Stats) -> io::Result<()> {
+        let (status, error) = match status {
+            Status::Ok           => ("ok", ""),
+            Status::Skipped      => ("skipped", ""),
+            Status::Failed(code) => ("failed", code),
+        };
+        let ratio = match stats.input {
+            0     => String::new(),
+            input => format!("{:.4}", stats.output as f64 / input as f64),
+        };
+        writeln!(
+            self.file, "{},{},{},{},{},{},{},{:.6},{}",
+            quote(&path.to_string_lossy()), status, error, stats.input, stats.output, ratio, stats.blocks,
+            stats.elapsed.as_secs_f64(), stats.sha256.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+/// `field`, quoted if it needs to be.
+fn quote(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true  => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
@@ -0,0 +1,137 @@
+//! Output file name templates, e.g. `{stem}_{offset:#x}_{index}.bin`.
+//!
+//! Fields are `{stem}`, the input's file name without its extension,
+//! `{index}`, the number of the output, and `{offset}` and `{out_offset}`,
+//! where the output's data begins in the input and in the decompressed
+//! stream. Numbers take a format spec after a colon like Rust's: `#` for a
+//! `0x` prefix, a width, optionally zero-padded, and `x` or `X` for hex.
+//! `{{` and `}}` are literal braces.
+
+use std::ffi::{OsStr, OsString};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Stem,
+    Index,
+    Offset,
+    OutOffset,
+}
+
+#[derive(Clone, Debug)]
+enum Piece {
+    Literal(String),
+    Field(Field, String),
+}
+
+/// Values substituted into a [`Template`].
+pub struct Vars<'a> {
+    pub stem: &'a OsStr,
+    pub index: u64,
+    pub offset: u64,
+    pub out_offset: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Template, String> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                },
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                },
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or("unterminated '{' in template")?;
+                    let (name, spec) = match rest[..end].split_once(':') {
+                        Some((name, spec)) => (name, spec),
+                        None               => (&rest[..end], ""),
+                    };
+                    let field = match name {
+                        "stem"       => Field::Stem,
+                        "index"      => Field::Index,
+                        "offset"     => Field::Offset,
+                        "out_offset" => Field::OutOffset,
+                        _ => return Err(format!("unknown template field '{}'", name)),
+                    };
+                    if field == Field::Stem && !spec.is_empty() {
+                        return Err("{stem} takes no format spec".into());
+                    }
+                    format_number(0, spec)?;
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    pieces.push(Piece::Field(field, spec.into()));
+                    chars = rest[end + 1..].chars();
+                },
+                '}' => return Err("unmatched '}' in template".into()),
+                c => literal.push(c),
+            }
+        }
+        pieces.push(Piece::Literal(literal));
+        Ok(Template{ pieces })
+    }
+
+    pub fn render(&self, vars: &Vars) -> OsString {
+        let mut name = OsString::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(s) => name.push(s),
+                Piece::Field(Field::Stem, _) => name.push(vars.stem),
+                Piece::Field(field, spec) => {
+                    let n = match field {
+                        Field::Index     => vars.index,
+                        Field::Offset    => vars.offset,
+                        Field::OutOffset => vars.out_offset,
+                        Field::Stem      => unreachable!(),
+                    };
+                    // Specs were checked when parsing
+                    name.push(format_number(n, spec).unwrap());
+                },
+            }
+        }
+        name
+    }
+}
+
+fn format_number(n: u64, spec: &str) -> Result<String, String> {
+    let bad = || format!("bad format spec '{}'", spec);
+    let (alternate, spec_rest) = match spec.strip_prefix('#') {
+        Some(rest) => (true, rest),
+        None       => (false, spec),
+    };
+    let (zero, spec_rest) = match spec_rest.strip_prefix('0') {
+        Some(rest) => (true, rest),
+        None       => (false, spec_rest),
+    };
+    let (digits, width) = match spec_rest.strip_suffix('x') {
+        Some(width) => (format!("{:x}", n), width),
+        None => match spec_rest.strip_suffix('X') {
+            Some(width) => (format!("{:X}", n), width),
+            None        => (n.to_string(), spec_rest),
+        },
+    };
+    let hex = spec_rest.ends_with(['x', 'X']);
+    if alternate && !hex {
+        return Err(bad());
+    }
+    let width: usize = if width.is_empty() { 0 } else { width.parse().map_err(|_| bad())? };
+
+    let prefix = if alternate { "0x" } else { "" };
+    let len = prefix.len() + digits.len();
+    let padding = width.saturating_sub(len);
+    Ok(if zero {
+        format!("{}{}{}", prefix, "0".repeat(padding), digits)
+    } else {
+        format!("{}{}{}", " ".repeat(padding), prefix, digits)
+    })
+}
@@ -0,0 +1,329 @@
+//! File IO through io_uring on Linux, for `--io uring`. Reads of an input
+//! are queued ahead of the decoder and writes of an output behind it, so
+//! neither waits on the other, all from the thread doing the decoding.
+//!
+//! A ring per file, from the `io-uring` crate, with reads and writes of a
+//! block at a time.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+/// Bytes read or written by each operation.
+const BLOCK: usize = 1 << 20;
+/// Operations kept in flight on each ring.
+const DEPTH: u32 = 8;
+
+/// Checks that io_uring can be used here, as it may be turned off or
+/// filtered out in a container.
+pub fn probe() -> io::Result<()> {
+    Ring::new(1).map(drop)
+}
+
+/// A submission and a completion queue shared with the kernel.
+///
+/// Whatever owns the buffers operations point into must `drain` its ring
+/// before freeing them, and leak them instead if that fails.
+struct Ring {
+    ring: IoUring,
+    /// Queued or handed to the kernel, and not yet completed.
+    pending: u32,
+}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Ring> {
+        Ok(Ring{ ring: IoUring::new(entries)?, pending: 0 })
+    }
+
+    /// Queues `entry`. No more than `DEPTH` operations may be pending, so
+    /// there is always room for it.
+    ///
+    /// # Safety
+    ///
+    /// Any buffer it points to must stay put until it completes.
+    unsafe fn push(&mut self, entry: squeue::Entry) {
+        debug_assert!(self.pending < DEPTH);
+        // SAFETY: the caller keeps the buffer alive, and the queue has room
+        // for every operation that can be pending
+        unsafe { self.ring.submission().push(&entry) }.expect("submission queue full");
+        self.pending += 1;
+    }
+
+    /// Hands the kernel everything queued, waiting until at least `wait`
+    /// operations have completed.
+    fn enter(&mut self, wait: u32) -> io::Result<()> {
+        loop {
+            match self.ring.submit_and_wait(wait as usize) {
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The next completed operation's user data and result.
+    fn pop(&mut self) -> Option<(u64, i32)> {
+        let entry = self.ring.completion().next()?;
+        self.pending -= 1;
+        Some((entry.user_data(), entry.result()))
+    }
+
+    /// Waits for every operation queued or in flight to complete. Returns
+    /// false if the kernel refused to take or finish them, when they may
+    /// still read or write the buffers they point to at any time.
+    fn drain(&mut self) -> bool {
+        while self.pending > 0 {
+            if self.enter(1).is_err() {
+                return false;
+            }
+            while self.pop().is_some() {}
+        }
+        true
+    }
+}
+
+/// What a failed operation's result means.
+fn failure(res: i32) -> io::Error {
+    io::Error::from_raw_os_error(-res)
+}
+
+fn retry(res: i32) -> bool {
+    -res == libc::EINTR || -res == libc::EAGAIN
+}
+
+/// A file being read into memory with reads queued ahead of what has been
+/// asked for.
+pub struct Input {
+    // Drained on drop, so that no read is left writing into `data`
+    ring: Ring,
+    file: File,
+    data: Vec<u8>,
+    /// Where `data` starts, which reads write to without going through it,
+    /// so that nothing here borrows what the kernel is still writing.
+    base: *mut u8,
+    /// The end of the data read from the start of the file so far.
+    ready: usize,
+    /// Where the next read starts.
+    next: usize,
+    /// Reads done past `ready`, by where they start and end.
+    done: BTreeMap<usize, usize>,
+}
+
+impl Input {
+    /// Starts reading the file at `path`.
+    pub fn open(path: &Path) -> io::Result<Input> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        let mut data = vec![0; len];
+        let base = data.as_mut_ptr();
+        let mut input = Input{ ring: Ring::new(DEPTH)?, file, data, base, ready: 0, next: 0, done: BTreeMap::new() };
+        input.fill()?;
+        Ok(input)
+    }
+
+    /// As much of the file as `wait` has said has been read.
+    pub fn data(&self) -> &[u8] {
+        self.read_so_far(self.ready)
+    }
+
+    /// The length of the whole file.
+    pub fn total_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Waits until more than `pos` bytes have been read, and returns how
+    /// many have, or `pos` if that's the whole file.
+    pub fn wait(&mut self, pos: usize) -> io::Result<usize> {
+        loop {
+            self.reap()?;
+            self.fill()?;
+            if self.ready > pos || self.ready == self.data.len() {
+                return Ok(self.ready);
+            }
+            self.ring.enter(1)?;
+        }
+    }
+
+    /// The start of the file, `len` bytes of it or all of it if shorter.
+    pub fn head(&mut self, len: usize) -> io::Result<&[u8]> {
+        let len = len.min(self.data.len());
+        while self.ready < len {
+            self.wait(self.ready)?;
+        }
+        Ok(self.read_so_far(len))
+    }
+
+    /// The first `len` bytes, which must have been read.
+    fn read_so_far(&self, len: usize) -> &[u8] {
+        assert!(len <= self.ready);
+        // SAFETY: reads are only queued past `ready`, so nothing writes to
+        // what's before it
+        unsafe { std::slice::from_raw_parts(self.base, len) }
+    }
+
+    /// The whole file, once read.
+    pub fn into_data(mut self) -> io::Result<Vec<u8>> {
+        self.head(usize::MAX)?;
+        Ok(mem::take(&mut self.data))
+    }
+
+    /// Queues reads until `DEPTH` are in flight or the file is covered.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.ring.pending < DEPTH && self.next < self.data.len() {
+            let end = (self.next + BLOCK).min(self.data.len());
+            self.read(self.next, end);
+            self.next = end;
+        }
+        self.ring.enter(0)
+    }
+
+    fn read(&mut self, start: usize, end: usize) {
+        let entry = opcode::Read::new(types::Fd(self.file.as_raw_fd()), self.base.wrapping_add(start), (end - start) as u32)
+            .offset(start as u64)
+            .build()
+            .user_data(start as u64);
+        // SAFETY: `data` isn't resized until the ring is dropped
+        unsafe { self.ring.push(entry) };
+    }
+
+    /// Takes in completed reads, queueing again any that came up short.
+    fn reap(&mut self) -> io::Result<()> {
+        while let Some((start, res)) = self.ring.pop() {
+            let start = start as usize;
+            // Reads are of a block at most, so where this one was to end
+            let end = ((start / BLOCK + 1) * BLOCK).min(self.data.len());
+            match res {
+                res if res < 0 && retry(res) => self.read(start, end),
+                res if res < 0 => return Err(failure(res)),
+                // The file has been cut short since it was opened
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                res => {
+                    let read = start + res as usize;
+                    if read < end {
+                        self.read(read, end);
+                    }
+                    self.done.insert(start, read);
+                },
+            }
+        }
+        while let Some(end) = self.done.remove(&self.ready) {
+            self.ready = end;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if !self.ring.drain() {
+            // Leaked rather than freed while the kernel may write into it
+            mem::forget(mem::take(&mut self.data));
+        }
+    }
+}
+
+/// A buffer being written, or free to be.
+struct Slot {
+    buf: Vec<u8>,
+    /// Where in the file the buffer goes.
+    offset: u64,
+    /// How much of it has been written.
+    written: usize,
+    busy: bool,
+}
+
+/// A file written with writes queued behind what has been handed over.
+pub struct Output {
+    // Drained on drop, so that no write is left reading from `slots`
+    ring: Ring,
+    slots: Vec<Slot>,
+    file: File,
+    /// Where the next write goes.
+    pos: u64,
+}
+
+impl Output {
+    /// Writes to `file` from `pos` on.
+    pub fn new(file: File, pos: u64) -> io::Result<Output> {
+        let slots = (0..DEPTH).map(|_| Slot{ buf: Vec::with_capacity(BLOCK), offset: 0, written: 0, busy: false }).collect();
+        Ok(Output{ ring: Ring::new(DEPTH)?, slots, file, pos })
+    }
+
+    /// Queues `data` to be written after whatever was before, waiting only
+    /// for a buffer to copy it into.
+    pub fn put(&mut self, data: &[u8]) -> io::Result<()> {
+        for piece in data.chunks(BLOCK) {
+            let slot = loop {
+                self.reap()?;
+                match self.slots.iter().position(|slot| !slot.busy) {
+                    Some(slot) => break slot,
+                    None       => self.ring.enter(1)?,
+                }
+            };
+            let slot_ref = &mut self.slots[slot];
+            slot_ref.buf.clear();
+            slot_ref.buf.extend_from_slice(piece);
+            slot_ref.offset = self.pos;
+            slot_ref.written = 0;
+            slot_ref.busy = true;
+            self.pos += piece.len() as u64;
+            self.write(slot);
+        }
+        self.ring.enter(0)
+    }
+
+    /// Waits for every write to finish.
+    pub fn finish(mut self) -> io::Result<()> {
+        while self.slots.iter().any(|slot| slot.busy) {
+            self.ring.enter(1)?;
+            self.reap()?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, slot: usize) {
+        let Slot{ buf, offset, written, .. } = &self.slots[slot];
+        let entry = opcode::Write::new(types::Fd(self.file.as_raw_fd()), buf[*written..].as_ptr(), (buf.len() - written) as u32)
+            .offset(offset + *written as u64)
+            .build()
+            .user_data(slot as u64);
+        // SAFETY: a busy slot's buffer isn't touched until it's written
+        unsafe { self.ring.push(entry) };
+    }
+
+    /// Frees the buffers of completed writes, queueing again any that came
+    /// up short.
+    fn reap(&mut self) -> io::Result<()> {
+        while let Some((slot, res)) = self.ring.pop() {
+            let slot = slot as usize;
+            match res {
+                res if res < 0 && retry(res) => self.write(slot),
+                res if res < 0 => return Err(failure(res)),
+                0 => return Err(io::ErrorKind::WriteZero.into()),
+                res => {
+                    let done = &mut self.slots[slot];
+                    done.written += res as usize;
+                    match done.written < done.buf.len() {
+                        true  => self.write(slot),
+                        false => done.busy = false,
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.ring.drain() {
+            // Leaked rather than freed while the kernel may read from them
+            mem::forget(mem::take(&mut self.slots));
+        }
+    }
+}
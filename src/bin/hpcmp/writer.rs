@@ -0,0 +1,108 @@
+//! Writing codes as the decoder reads them, for building streams.
+
+/// Packs codes least significant bit first, as the decoder reads them.
+pub struct Writer {
+    out: Vec<u8>,
+    /// Bytes taken from `out` already.
+    taken: u64,
+    bits: u64,
+    len: u32,
+    width: u32,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer{ out: vec![], taken: 0, bits: 0, len: 0, width: 9 }
+    }
+
+    /// Bits in a code.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Bits written so far, counting the padding of any byte dropped to.
+    pub fn bits(&self) -> u64 {
+        (self.taken + self.out.len() as u64) * 8 + u64::from(self.len)
+    }
+
+    /// Whole bytes written since they were last taken.
+    pub fn pending(&self) -> usize {
+        self.out.len()
+    }
+
+    /// Takes the whole bytes written so far, for writing out a long stream
+    /// as it goes.
+    pub fn take(&mut self) -> Vec<u8> {
+        self.taken += self.out.len() as u64;
+        std::mem::take(&mut self.out)
+    }
+
+    /// Writes `code` in the current width, which it must fit.
+    pub fn put(&mut self, code: u32) {
+        debug_assert!(code < 1 << self.width, "code 0x{:x} too wide for {} bits", code, self.width);
+        self.bits |= u64::from(code) << self.len;
+        self.len += self.width;
+        while self.len >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Drops to the next byte, as the decoder does after a reset and the
+    /// end-of-file command.
+    pub fn pad(&mut self) {
+        if self.len > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.bits = 0;
+        self.len = 0;
+    }
+
+    pub fn reset(&mut self) {
+        self.put(1);
+        self.pad();
+        self.width = 9;
+    }
+
+    pub fn widen(&mut self) {
+        self.put(2);
+        self.width += 1;
+    }
+
+    /// Writes bits `from` up to `to` of `stream`, codes `width` bits wide by
+    /// the end of them, as they are. Any reset among them pads as it did
+    /// there only if they start where they did in a byte.
+    pub fn copy(&mut self, stream: &[u8], from: u64, to: u64, width: u32) {
+        debug_assert_eq!(from % 8, u64::from(self.len), "bits copied out of alignment");
+        let mut at = from;
+        while at < to {
+            let n = (8 - at % 8).min(to - at);
+            let bits = u64::from(stream[(at / 8) as usize] >> (at % 8)) & ((1 << n) - 1);
+            self.bits |= bits << self.len;
+            self.len += n as u32;
+            if self.len >= 8 {
+                self.out.push(self.bits as u8);
+                self.bits >>= 8;
+                self.len -= 8;
+            }
+            at += n;
+        }
+        self.width = width;
+    }
+
+    /// Writes `code`, widening first if it needs more bits.
+    pub fn code(&mut self, code: u32) {
+        while code >= 1 << self.width {
+            self.widen();
+        }
+        self.put(code);
+    }
+
+    /// The stream written, or what's left of it to take, with any partial
+    /// byte.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.pad();
+        self.out
+    }
+}
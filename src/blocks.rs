@@ -0,0 +1,87 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::code::CodeMap;
+use crate::decoder::Decoder;
+use crate::error::Error;
+use crate::report::{BlockReport, ReportBuilder};
+
+/// One reset-delimited block of a stream, decoded on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    /// The block's decompressed bytes.
+    pub data: Vec<u8>,
+    /// Bits of the input the block takes up, from the reset that opened it
+    /// to the one that closed it, or to the end of the stream's final
+    /// literal.
+    pub bits: Range<u64>,
+    /// Where the block sits in the stream and its dictionary statistics, as
+    /// a [`StreamReport`](crate::StreamReport) would give them.
+    pub report: BlockReport,
+}
+
+/// Iterator over the blocks of a stream, returned by [`Decoder::blocks`].
+pub struct Blocks<'a, M, const DICT: usize> {
+    decoder: &'a mut Decoder<M, DICT>,
+    input: &'a [u8],
+    /// Reports on each block as it ends, as it would for the whole stream.
+    builder: ReportBuilder,
+    finished: bool,
+}
+
+impl<M: CodeMap, const DICT: usize> Decoder<M, DICT> {
+    /// Decodes `input`, the rest of a stream held in memory, a block at a
+    /// time, for callers that treat blocks as independent objects rather
+    /// than splitting the whole output up again.
+    ///
+    /// A decoder already part way into a block yields what is left of it
+    /// first. Once the input runs dry before the end of the stream, the
+    /// iterator yields [`Error::UnexpectedEof`]; after any error it ends.
+    pub fn blocks<'a>(&'a mut self, input: &'a [u8]) -> Blocks<'a, M, DICT> {
+        let mut builder = ReportBuilder::with_code_map(self.code_map());
+        if self.has_started() && !self.is_done() {
+            builder.resume_block(self);
+        }
+        Blocks{ decoder: self, input, builder, finished: false }
+    }
+}
+
+impl<M: CodeMap, const DICT: usize> Iterator for Blocks<'_, M, DICT> {
+    type Item = Result<Block, Error>;
+
+    fn next(&mut self) -> Option<Result<Block, Error>> {
+        if self.finished {
+            return None;
+        }
+        let mut data = vec![];
+        self.decoder.drain_scratch(&mut data);
+        loop {
+            if self.decoder.is_done() {
+                self.finished = true;
+                return None;
+            }
+            match self.decoder.step(&mut self.input, &mut self.builder) {
+                Ok(true)  => (),
+                Ok(false) => {
+                    self.finished = true;
+                    return Some(Err(Error::UnexpectedEof));
+                },
+                Err(e)    => {
+                    self.finished = true;
+                    return Some(Err(e));
+                },
+            }
+            self.decoder.drain_scratch(&mut data);
+            if let Some(report) = self.builder.take_block() {
+                // Closed by the reset just read, which opened the next, or
+                // by the final literal
+                let end = match self.builder.open_block() {
+                    Some(next) => next.bit_offset,
+                    None       => self.decoder.bit_position(),
+                };
+                return Some(Ok(Block{ data, bits: report.bit_offset..end, report }));
+            }
+        }
+    }
+}
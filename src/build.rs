@@ -0,0 +1,66 @@
+//! Helpers for decompressing resources from a build script.
+//!
+//! ```no_run
+//! // In build.rs:
+//! hpcmp::build::decompress_file("res/tables.cmp").unwrap();
+//! ```
+//!
+//! The decompressed file can then be embedded with
+//! `include_bytes!(concat!(env!("OUT_DIR"), "/tables"))`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Decompresses `input` into `OUT_DIR`, naming the output after the input
+/// with its extension removed, and returns the output path.
+pub fn decompress_file(input: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let input = input.as_ref();
+    let name = input.file_stem().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("{} has no file name", input.display()))
+    })?;
+    decompress_file_as(input, name)
+}
+
+/// Decompresses `input` to `name` inside `OUT_DIR` and returns the output
+/// path.
+///
+/// Cargo is told to rerun the build script when `input` changes, and the
+/// output is only rewritten when its contents differ, so crates embedding it
+/// are not rebuilt needlessly.
+pub fn decompress_file_as(input: impl AsRef<Path>, name: impl AsRef<Path>) -> io::Result<PathBuf> {
+    let input = input.as_ref();
+    let out_dir = env::var_os("OUT_DIR").ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "OUT_DIR not set; not running from a build script?")
+    })?;
+    let output = Path::new(&out_dir).join(name);
+
+    println!("cargo:rerun-if-changed={}", input.display());
+    let data = crate::decompress(&fs::read(input)?)?;
+    match fs::read(&output) {
+        Ok(existing) if existing == data => {},
+        _ => fs::write(&output, data)?,
+    }
+    Ok(output)
+}
+
+/// Decompresses every file in `dir` with the extension `extension` (e.g.
+/// `"cmp"`) into `OUT_DIR`, returning the output paths.
+///
+/// Cargo is also told to rerun the build script when files are added to or
+/// removed from `dir`.
+pub fn decompress_dir(dir: impl AsRef<Path>, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut inputs = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == extension) {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+    inputs.iter().map(decompress_file).collect()
+}
@@ -0,0 +1,76 @@
+/// A single code read from the stream.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Code {
+    /// Control codes: 1 resets, 2 widens codes by a bit and 3 ends the
+    /// stream.
+    Command(u8),
+    /// A literal byte.
+    Value(u8),
+    /// A dictionary entry.
+    Index(usize),
+}
+
+/// How raw codes read from the stream map onto commands, literals and
+/// dictionary indices.
+///
+/// [`HpCodeMap`] is the mapping used by the instrument firmware. Closely
+/// related variants can be decoded by implementing this trait, either
+/// overriding the three parameters or `classify` itself.
+pub trait CodeMap {
+    /// Codes below this are commands. At most 0x100, so that every command
+    /// is a byte.
+    fn command_limit(&self) -> u32 {
+        0x8
+    }
+
+    /// Added to a byte to give its literal code. At most `command_limit`,
+    /// and at most 0x100 below `index_base`, so that every code between the
+    /// two is a byte's literal.
+    fn value_bias(&self) -> u32 {
+        8
+    }
+
+    /// Code of the first dictionary entry.
+    fn index_base(&self) -> u32 {
+        0x108
+    }
+
+    /// What `code` is. The default goes by the three parameters, checking
+    /// their limits in debug builds.
+    fn classify(&self, code: u32) -> Code {
+        use Code::*;
+        debug_assert!(self.command_limit() <= 0x100, "command limit {:#x} beyond a byte", self.command_limit());
+        debug_assert!(self.value_bias() <= self.command_limit() && self.index_base().saturating_sub(self.value_bias()) <= 0x100,
+                      "literals from {:#x} to {:#x} biased by {:#x} beyond a byte", self.command_limit(), self.index_base(), self.value_bias());
+        match code {
+            c if c < self.command_limit() => Command(c as u8),
+            c if c >= self.index_base()   => Index((c - self.index_base()) as usize),
+            c                             => Value((c - self.value_bias()) as u8),
+        }
+    }
+}
+
+/// The standard code mapping: commands 0-7, literals 0x8-0x107 and
+/// dictionary entries from 0x108.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct HpCodeMap;
+
+impl CodeMap for HpCodeMap {}
+
+impl<M: CodeMap + ?Sized> CodeMap for &M {
+    fn command_limit(&self) -> u32 {
+        (**self).command_limit()
+    }
+
+    fn value_bias(&self) -> u32 {
+        (**self).value_bias()
+    }
+
+    fn index_base(&self) -> u32 {
+        (**self).index_base()
+    }
+
+    fn classify(&self, code: u32) -> Code {
+        (**self).classify(code)
+    }
+}
@@ -0,0 +1,569 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use log::{debug, trace};
+
+use crate::code::{Code, CodeMap, HpCodeMap};
+use crate::error::Error;
+use crate::observer::{DecodeObserver, Suppression};
+use crate::reader::Reader;
+use crate::telemetry::Telemetry;
+
+/// Widest code the 32-bit bit buffer can hold while refilling a byte at a time.
+pub const MAX_WIDTH: u8 = 24;
+/// Dictionary entries per block in the firmware's streams, and `Decoder`'s
+/// default `DICT`.
+pub const DICT_LEN: usize = 0x1000;
+/// Longest previous string for which a dictionary entry is still added.
+pub const MAX_PREV_LEN: usize = 0x80;
+
+/// Whether to check the dictionary's invariants after every code, which
+/// debug builds and the `paranoid` feature do.
+const CHECK_INVARIANTS: bool = cfg!(any(debug_assertions, feature = "paranoid"));
+
+#[derive(Clone, Debug)]
+struct DictionaryEntry {
+    value: u8,
+    next:  Code,
+}
+
+/// Where a block begins, recorded when the decoder sees a reset.
+///
+/// Decoding can resume from here with [`Decoder::resume_at`], with the input
+/// positioned at `input_offset`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResetPoint {
+    /// Bit offset in the input of the reset code itself.
+    pub bit_offset: u64,
+    /// Byte offset in the input at which the block's first code begins.
+    pub input_offset: u64,
+    /// Number of decompressed bytes preceding the block.
+    pub output_offset: u64,
+    /// Length of the string decoded just before the reset. The first
+    /// dictionary insertion of the block depends on it.
+    pub prev_len: usize,
+}
+
+/// What the decoder does with an index past the entry about to be added,
+/// which refers to nothing.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum IndexPolicy {
+    /// Fails with [`Error::InvalidIndex`].
+    #[default]
+    Fail,
+    /// Decodes it as if it referred to the entry about to be added, which
+    /// sometimes gets usable output from a damaged stream. A report notes
+    /// each one as an [`AnomalyKind::IndexOutOfRange`](crate::AnomalyKind::IndexOutOfRange).
+    Substitute,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    /// Waiting for the initial reset command.
+    Start,
+    /// Waiting for the literal that opens a block.
+    BlockStart,
+    Block,
+    /// Waiting for the literal that follows the end-of-file command.
+    Final,
+    Done,
+}
+
+/// Incremental decoder for a single compressed stream.
+///
+/// The decoder does no IO of its own: feed it input with [`Decoder::decode`]
+/// and it writes as much output as it can into the slice provided.
+///
+/// Its entire state, including the dictionary and any partially read code,
+/// lives in the struct, so cloning a decoder takes a checkpoint that can be
+/// resumed from, or restored after a speculative decode goes wrong.
+///
+/// Codes are classified by a [`CodeMap`], [`HpCodeMap`] unless another is
+/// given with [`Decoder::with_code_map`].
+///
+/// `DICT` is the dictionary capacity: once it holds that many entries no
+/// more are added until the next reset. Working memory is allocated up front
+/// in proportion to it and never grows, so a decoder for streams known to
+/// stay small can be given a fixed footprint, e.g.
+/// `Decoder::<HpCodeMap, 0x200>::with_code_map(HpCodeMap)`.
+#[derive(Clone)]
+pub struct Decoder<M = HpCodeMap, const DICT: usize = DICT_LEN> {
+    map: M,
+    reader: Reader,
+    state: State,
+    dictionary: Vec<DictionaryEntry>,
+    prev: Code,
+    prev_data: u8,
+    prev_scratch_len: usize,
+    // Output of the most recent code, of which `scratch_pos` bytes have
+    // already been handed out
+    scratch: Vec<u8>,
+    scratch_pos: usize,
+    total_in: u64,
+    produced: u64,
+    resets: Option<Vec<ResetPoint>>,
+    index_policy: IndexPolicy,
+    telemetry: Telemetry,
+}
+
+// Checkpoints are handed between threads when exploring variants in parallel
+const _: fn() = || {
+    fn assert_send<T: Send>() {}
+    assert_send::<Decoder>();
+};
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::with_code_map(HpCodeMap)
+    }
+
+    /// Creates a decoder that picks up at the start of the block described by
+    /// `point`. The first input it is given must be the byte at
+    /// `point.input_offset`.
+    pub fn resume_at(point: &ResetPoint) -> Decoder {
+        Decoder::resume_at_with_code_map(point, HpCodeMap)
+    }
+}
+
+impl<M: CodeMap, const DICT: usize> Decoder<M, DICT> {
+    pub fn with_code_map(map: M) -> Decoder<M, DICT> {
+        Decoder{
+            map,
+            reader: Reader::new(),
+            state: State::Start,
+            dictionary: Vec::with_capacity(DICT),
+            prev: Code::Command(0),
+            prev_data: 0,
+            prev_scratch_len: 0,
+            // A chain visits each entry at most once, plus its final literal
+            // and the extra byte of a not-yet-inserted entry
+            scratch: Vec::with_capacity(DICT + 2),
+            scratch_pos: 0,
+            total_in: 0,
+            produced: 0,
+            resets: None,
+            index_policy: IndexPolicy::Fail,
+            telemetry: Telemetry::new(),
+        }
+    }
+
+    /// Like [`Decoder::resume_at`], classifying codes with `map`.
+    pub fn resume_at_with_code_map(point: &ResetPoint, map: M) -> Decoder<M, DICT> {
+        let mut decoder = Decoder::with_code_map(map);
+        decoder.prev_scratch_len = point.prev_len;
+        decoder.total_in = point.input_offset;
+        decoder.produced = point.output_offset;
+        decoder.start_block();
+        decoder
+    }
+
+    /// Starts or stops recording a [`ResetPoint`] for every reset decoded,
+    /// including the start marker. Recorded points are collected with
+    /// [`Decoder::drain_resets`].
+    pub fn record_resets(&mut self, enable: bool) {
+        self.resets = if enable { Some(vec![]) } else { None };
+    }
+
+    /// Sets what to do with an index that refers to nothing, by default
+    /// [`IndexPolicy::Fail`].
+    pub fn set_index_policy(&mut self, policy: IndexPolicy) {
+        self.index_policy = policy;
+    }
+
+    /// Returns the reset points recorded since the last call.
+    pub fn drain_resets(&mut self) -> Vec<ResetPoint> {
+        self.resets.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    /// Returns true once the end-of-file sequence has been decoded and all
+    /// output handed out.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done && self.scratch_pos == self.scratch.len()
+    }
+
+    /// Total number of input bytes consumed so far.
+    pub fn total_in(&self) -> u64 {
+        self.total_in
+    }
+
+    /// Total number of decompressed bytes written out so far.
+    pub fn total_out(&self) -> u64 {
+        self.produced - (self.scratch.len() - self.scratch_pos) as u64
+    }
+
+    /// Bit offset in the input of the next code to be read.
+    pub fn bit_position(&self) -> u64 {
+        self.total_in * 8 - self.reader.available() as u64
+    }
+
+    /// Decodes from `input` into `output`, returning the number of bytes
+    /// consumed from `input` and written to `output`.
+    ///
+    /// Decoding stops when the input runs dry, the output fills up or the
+    /// stream ends, whichever comes first.
+    pub fn decode(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize), Error> {
+        self.decode_with(input, output, ())
+    }
+
+    /// Like [`Decoder::decode`], reporting decode events to `observer`.
+    pub fn decode_with(&mut self, input: &[u8], output: &mut [u8], mut observer: impl DecodeObserver) -> Result<(usize, usize), Error> {
+        let mut span = self.telemetry.enter();
+        let mut remaining = input;
+        let mut written = 0;
+        loop {
+            let pending = &self.scratch[self.scratch_pos..];
+            let n = pending.len().min(output.len() - written);
+            output[written..written + n].copy_from_slice(&pending[..n]);
+            self.scratch_pos += n;
+            written += n;
+
+            if written == output.len() || self.state == State::Done {
+                break;
+            }
+            if !self.step(&mut remaining, &mut observer)? {
+                break;
+            }
+            self.telemetry.follow(&mut span);
+        }
+        let consumed = input.len() - remaining.len();
+        self.telemetry.refill(consumed, written);
+        Ok((consumed, written))
+    }
+
+    /// Decodes from `input`, appending everything produced to `output`, and
+    /// returns the number of bytes consumed from `input`.
+    pub fn decode_to_vec(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize, Error> {
+        self.decode_to_vec_with(input, output, ())
+    }
+
+    /// Like [`Decoder::decode_to_vec`], reporting decode events to
+    /// `observer`.
+    pub fn decode_to_vec_with(&mut self, input: &[u8], output: &mut Vec<u8>, mut observer: impl DecodeObserver) -> Result<usize, Error> {
+        let mut span = self.telemetry.enter();
+        let start = output.len();
+        let mut remaining = input;
+        self.drain_scratch(output);
+        while self.state != State::Done && self.step(&mut remaining, &mut observer)? {
+            self.telemetry.follow(&mut span);
+            self.drain_scratch(output);
+        }
+        let consumed = input.len() - remaining.len();
+        self.telemetry.refill(consumed, output.len() - start);
+        Ok(consumed)
+    }
+
+    // Like `decode_to_vec_with`, stopping once `output` holds `limit` bytes
+    // or `ends` holds `max_chains` entries, and pushing where each code's
+    // chain ends in `output` onto `ends`.
+    #[cfg(feature = "std")]
+    pub(crate) fn decode_chains_with(&mut self, input: &mut &[u8], output: &mut Vec<u8>, ends: &mut Vec<usize>, limit: usize, max_chains: usize, observer: &mut impl DecodeObserver) -> Result<(), Error> {
+        let mut span = self.telemetry.enter();
+        let start = output.len();
+        let available = input.len();
+        loop {
+            if self.scratch_pos < self.scratch.len() {
+                self.drain_scratch(output);
+                ends.push(output.len());
+            }
+            if output.len() >= limit || ends.len() >= max_chains || self.state == State::Done || !self.step(input, observer)? {
+                break;
+            }
+            self.telemetry.follow(&mut span);
+        }
+        self.telemetry.refill(available - input.len(), output.len() - start);
+        Ok(())
+    }
+
+    // Appends whatever of the most recent code's output hasn't been handed
+    // out yet.
+    pub(crate) fn drain_scratch(&mut self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.scratch[self.scratch_pos..]);
+        self.scratch_pos = self.scratch.len();
+    }
+
+    // Drops whatever of the most recent code's output hasn't been handed
+    // out yet, for callers that only follow the stream.
+    pub(crate) fn discard_scratch(&mut self) {
+        self.scratch_pos = self.scratch.len();
+    }
+
+    // How codes are classified.
+    pub(crate) fn code_map(&self) -> &M {
+        &self.map
+    }
+
+    // Bits in the next code to be read.
+    pub(crate) fn width(&self) -> u8 {
+        self.reader.width()
+    }
+
+    // Whether the start marker has been decoded, or decoding resumed after
+    // one.
+    pub(crate) fn has_started(&self) -> bool {
+        self.state != State::Start
+    }
+
+    // Reads and handles one code, leaving its output in the scratch buffer.
+    // Returns false if the input ran dry first.
+    pub(crate) fn step(&mut self, input: &mut &[u8], observer: &mut impl DecodeObserver) -> Result<bool, Error> {
+        let bit_offset = self.bit_position();
+        let width = self.reader.width();
+        let available = input.len();
+        let code = self.reader.read(input, &self.map);
+        self.total_in += (available - input.len()) as u64;
+        let code = match code {
+            Some(code) => code,
+            None       => return Ok(false),
+        };
+        observer.code(bit_offset, width, code);
+        if self.reader.width() > MAX_WIDTH {
+            return Err(Error::WidthOverflow(self.reader.width()));
+        }
+
+        self.scratch.clear();
+        self.scratch_pos = 0;
+
+        use Code::*;
+        match self.state {
+            State::Start => {
+                if code != Command(1) {
+                    return Err(Error::MissingStartMarker);
+                }
+                self.reset(bit_offset, observer);
+            },
+            State::BlockStart => {
+                if let Value(data) = code {
+                    self.scratch.push(data);
+                    self.prev_data = data;
+                } else {
+                    return Err(Error::FirstCodeNotValue);
+                }
+                self.prev = code;
+                self.state = State::Block;
+            },
+            State::Block => {
+                match code {
+                    // Reset
+                    Command(1) => {
+                        observer.block_end(self.produced, self.dictionary.len());
+                        self.reset(bit_offset, observer);
+                    },
+                    // End of file
+                    Command(3) => self.state = State::Final,
+                    Command(_) => (),
+                    c => self.expand(c, observer)?,
+                }
+            },
+            State::Final => {
+                if let Value(last) = code {
+                    self.scratch.push(last);
+                    self.state = State::Done;
+                } else {
+                    return Err(Error::FinalCodeNotValue);
+                }
+            },
+            State::Done => unreachable!(),
+        }
+
+        if CHECK_INVARIANTS {
+            self.check_invariants(code, width);
+        }
+        self.produced += self.scratch.len() as u64;
+        self.telemetry.code();
+        if self.state == State::Done {
+            observer.block_end(self.produced, self.dictionary.len());
+            self.telemetry.block_end(self.produced);
+        }
+        Ok(true)
+    }
+
+    // Checks what decoding relies on of the dictionary: that it stays within
+    // its capacity, and that entries only chain to earlier ones, so that
+    // every walk ends. With the `paranoid` feature every entry is checked
+    // after every code, otherwise just the newest. Also checks that `code`,
+    // read `width` bits wide, fits in that many bits as the map encodes it.
+    fn check_invariants(&self, code: Code, width: u8) {
+        let len = self.dictionary.len();
+        assert!(len <= DICT, "dictionary of {} entries, beyond its capacity of {}", len, DICT);
+        let start = match cfg!(feature = "paranoid") {
+            true  => 0,
+            false => len.saturating_sub(1),
+        };
+        for (i, entry) in self.dictionary.iter().enumerate().skip(start) {
+            match entry.next {
+                Code::Value(_)   => (),
+                Code::Index(p)   => assert!(p < i, "entry {} chains to entry {}, not an earlier one", i, p),
+                Code::Command(c) => panic!("entry {} chains to command {}", i, c),
+            }
+        }
+        assert!((9..=MAX_WIDTH).contains(&self.reader.width()), "code width {}", self.reader.width());
+        let raw = match code {
+            Code::Command(c) => c as u64,
+            Code::Value(v)   => self.map.value_bias() as u64 + v as u64,
+            Code::Index(p)   => self.map.index_base() as u64 + p as u64,
+        };
+        assert!(raw >> width == 0, "code {:?} is {:#x}, read only {} bits wide", code, raw, width);
+    }
+
+    fn reset(&mut self, bit_offset: u64, observer: &mut impl DecodeObserver) {
+        let point = ResetPoint{
+            bit_offset,
+            input_offset: self.total_in,
+            output_offset: self.produced,
+            prev_len: self.prev_scratch_len,
+        };
+        observer.reset(&point);
+        if let Some(resets) = &mut self.resets {
+            resets.push(point);
+        }
+        self.start_block();
+    }
+
+    fn start_block(&mut self) {
+        debug!(target: "hpcmp::dict", "dict: reset");
+        self.dictionary.clear();
+        self.telemetry.block_start(self.produced);
+        self.state = State::BlockStart;
+    }
+
+    // Walks the chain for `code`, building its bytes back to front.
+    fn expand(&mut self, code: Code, observer: &mut impl DecodeObserver) -> Result<(), Error> {
+        use Code::*;
+        let code = match code {
+            Index(p) if p > self.dictionary.len() && self.index_policy == IndexPolicy::Substitute => {
+                debug!("index {} beyond the dictionary of {} entries, taken as the next", p, self.dictionary.len());
+                Index(self.dictionary.len())
+            },
+            code => code,
+        };
+        let mut c = code;
+        if let Index(p) = c {
+            if p == self.dictionary.len() {
+                self.scratch.push(self.prev_data);
+                c = self.prev;
+            }
+        }
+        let c = self.walk(code, c)?;
+        if let Value(d) = c {
+            self.scratch.push(d);
+            self.prev_data = d;
+            if self.prev_scratch_len < MAX_PREV_LEN && self.dictionary.len() != DICT {
+                // A reference to an entry that wasn't added, as a block's
+                // first can be, would leave the new one chaining to itself
+                if let Index(p) = self.prev {
+                    if p >= self.dictionary.len() {
+                        return Err(Error::InvalidIndex{ index: p, dictionary_len: self.dictionary.len() });
+                    }
+                }
+                self.dictionary.push(DictionaryEntry{ value: d, next: self.prev });
+                observer.insert(self.dictionary.len()-1, d, self.prev);
+                debug!(target: "hpcmp::dict", "dict: insert {} {:?}", self.dictionary.len()-1, self.dictionary[self.dictionary.len()-1]);
+            } else if self.dictionary.len() == DICT {
+                observer.suppressed(Suppression::DictionaryFull);
+            } else {
+                observer.suppressed(Suppression::LongString);
+            }
+        } else {
+            unreachable!("Index to non-Value");
+        }
+        self.scratch.reverse();
+        trace!("0x{:x} {:?}", self.produced, self.scratch);
+        self.prev_scratch_len = self.scratch.len();
+
+        self.prev = code;
+        Ok(())
+    }
+
+    // Pushes the bytes of the entries from `c`, on the way to expanding
+    // `code`, onto the scratch buffer, returning the literal the chain ends
+    // in.
+    #[cfg(not(feature = "fast-unsafe"))]
+    fn walk(&mut self, code: Code, mut c: Code) -> Result<Code, Error> {
+        let mut chain = 0;
+        while let Code::Index(p) = c {
+            let entry = self.dictionary.get(p).ok_or(Error::InvalidIndex{
+                index: p,
+                dictionary_len: self.dictionary.len(),
+            })?;
+            self.scratch.push(entry.value);
+            c = entry.next;
+            chain += 1;
+            if CHECK_INVARIANTS {
+                assert!(chain <= self.dictionary.len(), "chain from {:?} runs past {} entries", code, self.dictionary.len());
+            }
+        }
+        Ok(c)
+    }
+
+    // As above, checking only where the chain starts. Entries only chain to
+    // earlier ones, as insertion makes sure, so a walk that starts within the
+    // dictionary stays within it, and visits each entry at most once.
+    #[cfg(feature = "fast-unsafe")]
+    fn walk(&mut self, code: Code, mut c: Code) -> Result<Code, Error> {
+        if let Code::Index(p) = c {
+            if p >= self.dictionary.len() {
+                return Err(Error::InvalidIndex{ index: p, dictionary_len: self.dictionary.len() });
+            }
+        }
+        self.scratch.reserve(self.dictionary.len());
+        let out = self.scratch.as_mut_ptr();
+        let mut len = self.scratch.len();
+        while let Code::Index(p) = c {
+            if CHECK_INVARIANTS {
+                assert!(p < self.dictionary.len(), "chain from {:?} reaches entry {} of {}", code, p, self.dictionary.len());
+            }
+            // SAFETY: the chain started within the dictionary and only goes
+            // to earlier entries, so `p` is within it, and it is no longer
+            // than the room reserved
+            unsafe {
+                let entry = self.dictionary.get_unchecked(p);
+                out.add(len).write(entry.value);
+                c = entry.next;
+            }
+            len += 1;
+        }
+        // SAFETY: every byte up to `len` was written above or before
+        unsafe { self.scratch.set_len(len) };
+        Ok(c)
+    }
+}
+
+/// Decompresses a complete stream held in memory.
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut decoder = Decoder::new();
+    let mut out = vec![];
+    decoder.decode_to_vec(input, &mut out)?;
+    if !decoder.is_done() {
+        return Err(Error::UnexpectedEof);
+    }
+    Ok(out)
+}
+
+/// Decompresses a complete stream held in memory into `output`, returning
+/// the decompressed length.
+///
+/// Fails with [`Error::OutputOverflow`] if the output would not fit. Nothing
+/// is allocated for the output; the decoder's own working state is the only
+/// allocation.
+pub fn decompress_into(input: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+    let mut decoder = Decoder::new();
+    let (consumed, written) = decoder.decode(input, output)?;
+    if !decoder.is_done() {
+        // Either the input ran out, or the output is full and the stream may
+        // still end without producing anything more
+        let (_, extra) = decoder.decode(&input[consumed..], &mut [0])?;
+        if extra > 0 {
+            return Err(Error::OutputOverflow);
+        }
+        if !decoder.is_done() {
+            return Err(Error::UnexpectedEof);
+        }
+    }
+    Ok(written)
+}
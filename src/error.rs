@@ -0,0 +1,50 @@
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The stream did not begin with a reset command.
+    MissingStartMarker,
+    /// A block began with something other than a literal byte.
+    FirstCodeNotValue,
+    /// The end-of-file command was not followed by a literal byte.
+    FinalCodeNotValue,
+    /// An index code referred past the end of the dictionary.
+    InvalidIndex { index: usize, dictionary_len: usize },
+    /// The code width grew beyond what the reader supports.
+    WidthOverflow(u8),
+    /// The input ended before the end-of-file command.
+    UnexpectedEof,
+    /// The decompressed data did not fit in the buffer provided.
+    OutputOverflow,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use Error::*;
+        match self {
+            MissingStartMarker => write!(f, "Start marker not found."),
+            FirstCodeNotValue  => write!(f, "First byte not Value"),
+            FinalCodeNotValue  => write!(f, "Final byte not Value"),
+            InvalidIndex { index, dictionary_len } =>
+                write!(f, "Index {} beyond dictionary of {} entries", index, dictionary_len),
+            WidthOverflow(width) => write!(f, "Code width {} too large", width),
+            UnexpectedEof      => write!(f, "Unexpected end of input"),
+            OutputOverflow     => write!(f, "Output buffer too small"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> std::io::Error {
+        use std::io::ErrorKind;
+        let kind = match e {
+            Error::UnexpectedEof => ErrorKind::UnexpectedEof,
+            _                    => ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
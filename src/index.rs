@@ -0,0 +1,117 @@
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use crate::decoder::{Decoder, ResetPoint};
+use crate::error::Error;
+use crate::read::Decompressor;
+
+const MAGIC: &str = "hpcmp-index 1";
+
+/// Reset points of a complete stream, for random access into its output.
+///
+/// Indexes are saved as plain text: a header line, one line per reset point
+/// giving its bit offset, input offset, output offset and carried string
+/// length, then a line with the compressed and decompressed lengths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamIndex {
+    /// Every reset in the stream, including the start marker, in order.
+    pub resets: Vec<ResetPoint>,
+    /// Bytes of input up to and including the end-of-file sequence.
+    pub compressed_len: u64,
+    /// Length of the decompressed output.
+    pub decompressed_len: u64,
+}
+
+impl StreamIndex {
+    /// Returns the last reset point at or before `output_offset`.
+    pub fn reset_before(&self, output_offset: u64) -> Option<&ResetPoint> {
+        self.resets.iter().take_while(|point| point.output_offset <= output_offset).last()
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "{}", MAGIC)?;
+        for point in &self.resets {
+            writeln!(w, "{} {} {} {}", point.bit_offset, point.input_offset, point.output_offset, point.prev_len)?;
+        }
+        writeln!(w, "end {} {}", self.compressed_len, self.decompressed_len)
+    }
+
+    pub fn read_from(r: impl BufRead) -> io::Result<StreamIndex> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("bad index: {}", what));
+        let number = |field: Option<&str>| -> io::Result<u64> {
+            field.and_then(|f| f.parse().ok()).ok_or_else(|| invalid("malformed line"))
+        };
+
+        let mut lines = r.lines();
+        if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+            return Err(invalid("missing header"));
+        }
+        let mut resets = vec![];
+        for line in lines {
+            let line = line?;
+            let mut fields = line.split_whitespace();
+            if line.starts_with("end ") {
+                fields.next();
+                return Ok(StreamIndex{
+                    resets,
+                    compressed_len:   number(fields.next())?,
+                    decompressed_len: number(fields.next())?,
+                });
+            }
+            resets.push(ResetPoint{
+                bit_offset:    number(fields.next())?,
+                input_offset:  number(fields.next())?,
+                output_offset: number(fields.next())?,
+                prev_len:      number(fields.next())? as usize,
+            });
+        }
+        Err(invalid("truncated"))
+    }
+}
+
+/// Decodes a whole stream, discarding the output, and records where each
+/// block begins.
+pub fn build_index(mut input: impl Read) -> Result<StreamIndex, io::Error> {
+    let mut decoder = Decoder::new();
+    decoder.record_resets(true);
+    let mut resets = vec![];
+    let mut buf = vec![0; 65536];
+    let mut out = vec![0; 65536];
+
+    while !decoder.is_done() {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            return Err(Error::UnexpectedEof.into());
+        }
+        let mut pos = 0;
+        while pos < n && !decoder.is_done() {
+            let (consumed, _) = decoder.decode(&buf[pos..n], &mut out)?;
+            pos += consumed;
+        }
+        resets.append(&mut decoder.drain_resets());
+    }
+
+    Ok(StreamIndex{
+        resets,
+        compressed_len: decoder.total_in(),
+        decompressed_len: decoder.total_out(),
+    })
+}
+
+/// Decompresses just the bytes in `range` of the output, decoding only the
+/// blocks that cover it. The range is clipped to the length of the output.
+///
+/// `input` must be positioned at the start of the stream that `index` was
+/// built from.
+pub fn decompress_range(input: impl Read + Seek, index: &StreamIndex, range: Range<u64>) -> Result<Vec<u8>, io::Error> {
+    let end = range.end.min(index.decompressed_len);
+    if range.start >= end {
+        return Ok(vec![]);
+    }
+
+    let mut decompressor = Decompressor::with_index(input, index);
+    decompressor.seek(SeekFrom::Start(range.start))?;
+    let mut out = vec![0; (end - range.start) as usize];
+    decompressor.read_exact(&mut out)?;
+    Ok(out)
+}
@@ -0,0 +1,49 @@
+//! Decompressor for HP "CMP" file format used for firmware updates on the
+//! 54710A / 54720A / 54750A / 83480A.
+//!
+//! The decoder itself only needs `alloc`; build with `default-features =
+//! false` for `no_std` targets.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod blocks;
+#[cfg(feature = "std")]
+pub mod build;
+mod code;
+mod decoder;
+mod error;
+#[cfg(feature = "std")]
+mod index;
+mod observer;
+#[cfg(feature = "std")]
+mod read;
+mod reader;
+mod report;
+mod telemetry;
+#[cfg(feature = "tokio")]
+mod tokio_io;
+#[cfg(feature = "std")]
+mod validate;
+mod vectors;
+#[cfg(feature = "std")]
+mod write;
+
+pub use blocks::{Block, Blocks};
+pub use code::{Code, CodeMap, HpCodeMap};
+pub use decoder::{decompress, decompress_into, Decoder, IndexPolicy, ResetPoint, DICT_LEN, MAX_PREV_LEN, MAX_WIDTH};
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use index::{build_index, decompress_range, StreamIndex};
+pub use observer::{DecodeObserver, Suppression};
+#[cfg(feature = "std")]
+pub use read::Decompressor;
+pub use report::{decompress_with_report, Anomaly, AnomalyKind, BlockReport, CodeHistogram, ReportBuilder, StreamReport};
+#[cfg(feature = "tokio")]
+pub use tokio_io::AsyncDecompressor;
+#[cfg(feature = "std")]
+pub use validate::{validate, ValidationReport, Violation};
+pub use vectors::{Vector, VECTORS};
+#[cfg(feature = "std")]
+pub use write::{decompress_to_writer, decompress_to_writer_with};
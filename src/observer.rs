@@ -0,0 +1,92 @@
+use crate::code::Code;
+use crate::decoder::ResetPoint;
+
+/// Why a string added no dictionary entry, as every other in a block but
+/// its first does.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Suppression {
+    /// The dictionary was full.
+    DictionaryFull,
+    /// The string before it was 0x80 bytes or longer.
+    LongString,
+}
+
+/// Hooks into the decode loop, for collecting statistics or tracing a
+/// stream without forking the decoder.
+///
+/// Pass an observer to [`Decoder::decode_with`](crate::Decoder::decode_with)
+/// or [`Decoder::decode_to_vec_with`](crate::Decoder::decode_to_vec_with).
+/// Every method has an empty default, so implementors only override the
+/// events they care about.
+pub trait DecodeObserver {
+    /// A code of `width` bits was read starting at `bit_offset`.
+    fn code(&mut self, _bit_offset: u64, _width: u8, _code: Code) {}
+
+    /// A dictionary entry was added at `index`, extending the string for
+    /// `next` by `value`.
+    fn insert(&mut self, _index: usize, _value: u8, _next: Code) {}
+
+    /// A string was decoded without adding a dictionary entry, for
+    /// `reason`.
+    fn suppressed(&mut self, _reason: Suppression) {}
+
+    /// A reset was decoded, including the start marker.
+    fn reset(&mut self, _point: &ResetPoint) {}
+
+    /// A block ended, by a reset or the end of the stream, after
+    /// `output_offset` bytes of total output and with `dictionary_len`
+    /// dictionary entries in use.
+    fn block_end(&mut self, _output_offset: u64, _dictionary_len: usize) {}
+}
+
+impl DecodeObserver for () {}
+
+/// Reports every event to both observers in turn.
+impl<A: DecodeObserver, B: DecodeObserver> DecodeObserver for (A, B) {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        self.0.code(bit_offset, width, code);
+        self.1.code(bit_offset, width, code);
+    }
+
+    fn insert(&mut self, index: usize, value: u8, next: Code) {
+        self.0.insert(index, value, next);
+        self.1.insert(index, value, next);
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        self.0.suppressed(reason);
+        self.1.suppressed(reason);
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        self.0.reset(point);
+        self.1.reset(point);
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        self.0.block_end(output_offset, dictionary_len);
+        self.1.block_end(output_offset, dictionary_len);
+    }
+}
+
+impl<T: DecodeObserver + ?Sized> DecodeObserver for &mut T {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        (**self).code(bit_offset, width, code)
+    }
+
+    fn insert(&mut self, index: usize, value: u8, next: Code) {
+        (**self).insert(index, value, next)
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        (**self).suppressed(reason)
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        (**self).reset(point)
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        (**self).block_end(output_offset, dictionary_len)
+    }
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::decoder::{Decoder, ResetPoint};
+use crate::error::Error;
+use crate::index::StreamIndex;
+
+/// Decompresses a stream read from any `io::Read` as it is consumed.
+///
+/// Reads return EOF once the stream's end-of-file marker has been decoded.
+/// An input that ends before the marker fails with
+/// `ErrorKind::UnexpectedEof`.
+///
+/// When the inner reader is also `Seek`, so is the decompressor. Every reset
+/// decoded along the way is remembered, and a seek restarts from the nearest
+/// reset at or before the target rather than from the start of the stream.
+/// Seeking past anything decoded so far, or relative to the end, decodes
+/// forward to find it; a seek beyond the end of the stream stops there and
+/// returns the length of the stream.
+pub struct Decompressor<R> {
+    inner: R,
+    decoder: Decoder,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+    resets: Vec<ResetPoint>,
+    // Position in `inner` at which the compressed stream starts, worked out
+    // on the first seek
+    base: Option<u64>,
+}
+
+impl<R: Read> Decompressor<R> {
+    pub fn new(inner: R) -> Decompressor<R> {
+        let mut decoder = Decoder::new();
+        decoder.record_resets(true);
+        Decompressor{
+            inner,
+            decoder,
+            buf: vec![0; 8192].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            resets: vec![],
+            base: None,
+        }
+    }
+
+    /// Creates a decompressor that already knows every reset point in the
+    /// stream, so that seeks go straight to the right block.
+    pub fn with_index(inner: R, index: &StreamIndex) -> Decompressor<R> {
+        let mut decompressor = Decompressor::new(inner);
+        decompressor.resets = index.resets.clone();
+        decompressor
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn collect_resets(&mut self) {
+        for point in self.decoder.drain_resets() {
+            if self.resets.last().is_none_or(|last| point.input_offset > last.input_offset) {
+                self.resets.push(point);
+            }
+        }
+    }
+
+    // Reads and discards up to `n` bytes, stopping early at the end of the
+    // stream.
+    fn skip(&mut self, mut n: u64) -> io::Result<()> {
+        let mut discard = [0u8; 4096];
+        while n > 0 {
+            let len = n.min(discard.len() as u64) as usize;
+            let read = self.read(&mut discard[..len])?;
+            if read == 0 {
+                break;
+            }
+            n -= read as u64;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decompressor<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if out.is_empty() || self.decoder.is_done() {
+                return Ok(0);
+            }
+
+            let input = &self.buf[self.pos..self.filled];
+            let result = self.decoder.decode(input, out);
+            self.collect_resets();
+            let (consumed, produced) = result?;
+            self.pos += consumed;
+            if produced > 0 || self.decoder.is_done() {
+                return Ok(produced);
+            }
+
+            // Everything buffered has been consumed without finishing a code
+            let n = self.inner.read(&mut self.buf)?;
+            if n == 0 {
+                return Err(Error::UnexpectedEof.into());
+            }
+            self.pos = 0;
+            self.filled = n;
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for Decompressor<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let current = self.decoder.total_out();
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(d) => current.checked_add_signed(d),
+            SeekFrom::End(d) => {
+                self.skip(u64::MAX)?;
+                self.decoder.total_out().checked_add_signed(d)
+            },
+        };
+        let target = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+
+        let current = self.decoder.total_out();
+        let restart = self.resets.iter()
+            .take_while(|point| point.output_offset <= target)
+            .last()
+            .copied()
+            .filter(|point| target < current || point.output_offset > current);
+        if let Some(point) = restart {
+            let base = match self.base {
+                Some(base) => base,
+                None => {
+                    let unread = (self.filled - self.pos) as u64;
+                    let base = self.inner.stream_position()? - unread - self.decoder.total_in();
+                    self.base = Some(base);
+                    base
+                },
+            };
+            self.inner.seek(SeekFrom::Start(base + point.input_offset))?;
+            self.decoder = Decoder::resume_at(&point);
+            self.decoder.record_resets(true);
+            self.pos = 0;
+            self.filled = 0;
+        }
+
+        let current = self.decoder.total_out();
+        self.skip(target - current)?;
+        Ok(self.decoder.total_out())
+    }
+}
@@ -0,0 +1,76 @@
+use log::{debug, trace};
+
+use crate::code::{Code, CodeMap};
+
+#[derive(Clone)]
+pub(crate) struct Reader {
+    bit_buffer: u32,
+    available: u8,
+    read_width: u8,
+}
+
+impl Reader {
+    pub(crate) fn new() -> Reader {
+        Reader{
+            bit_buffer: 0,
+            available: 0,
+            read_width: 9,
+        }
+    }
+
+    pub(crate) fn width(&self) -> u8 {
+        self.read_width
+    }
+
+    /// Bits read from the input but not yet used.
+    pub(crate) fn available(&self) -> u8 {
+        self.available
+    }
+
+    /// Reads the next code, consuming bytes from the front of `input`.
+    ///
+    /// Returns `None` once `input` runs dry; any bits taken so far are kept
+    /// and the read picks up where it left off on the next call.
+    pub(crate) fn read(&mut self, input: &mut &[u8], map: &impl CodeMap) -> Option<Code> {
+        // Read from the input stream until enough bits are available
+        while self.available < self.read_width {
+            let (&byte, rest) = input.split_first()?;
+            *input = rest;
+            self.bit_buffer |= (byte as u32) << self.available;
+            self.available += 8;
+        }
+
+        // Read n bits
+        let width = self.read_width;
+        let data = self.bit_buffer & ((1 << self.read_width) - 1);
+        self.bit_buffer >>= self.read_width;
+        self.available -= self.read_width;
+
+        let code = map.classify(data);
+        use Code::*;
+        match code {
+            // Reset
+            Command(1) => {
+                self.bit_buffer = 0;
+                self.available = 0;
+                self.read_width = 9;
+            },
+            // Increase code width
+            Command(2) => {
+                self.read_width += 1;
+            },
+            Command(3) => {
+                self.bit_buffer = 0;
+                self.available = 0;
+            },
+            _ => {}
+        }
+
+        match code {
+            Command(_) => debug!("read: {:?}, now {} bits wide", code, self.read_width),
+            _          => trace!("read: {:?} from 0x{:x}, {} bits wide", code, data, width),
+        }
+
+        Some(code)
+    }
+}
@@ -0,0 +1,286 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::code::{Code, CodeMap, HpCodeMap};
+use crate::decoder::{Decoder, ResetPoint};
+use crate::error::Error;
+use crate::observer::{DecodeObserver, Suppression};
+
+/// Summary of a decoded stream, for comparing analyses across files.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StreamReport {
+    /// Input bytes consumed, up to and including the end-of-file sequence.
+    pub compressed_len: u64,
+    pub decompressed_len: u64,
+    pub blocks: Vec<BlockReport>,
+    pub histogram: CodeHistogram,
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// One reset-delimited block of a stream.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockReport {
+    /// Bit offset in the input of the reset that opened the block.
+    pub bit_offset: u64,
+    /// Byte offset in the input of the block's first code.
+    pub input_offset: u64,
+    pub output_offset: u64,
+    pub output_len: u64,
+    pub codes: u64,
+    /// Dictionary entries in use when the block ended.
+    pub dictionary_len: usize,
+    pub max_width: u8,
+    /// Strings that added no dictionary entry because it was full.
+    pub suppressed_full: u64,
+    /// Strings that added no dictionary entry because the string before
+    /// them was too long.
+    pub suppressed_long: u64,
+}
+
+/// How often each kind of code appeared.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CodeHistogram {
+    /// Count of each command code, 0 to 7.
+    pub commands: [u64; 8],
+    pub values: u64,
+    pub indices: u64,
+    /// Count of codes read at each width.
+    pub widths: BTreeMap<u8, u64>,
+}
+
+/// Something decodable but suspicious in a stream.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Anomaly {
+    /// Bit offset in the input of the code concerned.
+    pub bit_offset: u64,
+    pub kind: AnomalyKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AnomalyKind {
+    /// A command code with no known meaning, which the decoder ignores.
+    UnknownCommand(u8),
+    /// Codes were widened from `width` bits while every code the dictionary
+    /// allowed still fitted.
+    EarlyWiden{ width: u8, dictionary_len: usize },
+    /// A reset closed a block holding nothing but its opening literal, as
+    /// if resets had been repeated. This is as close as a stream that
+    /// decodes comes to redundant commands: a reset straight after a reset
+    /// fails with [`Error::FirstCodeNotValue`], and the end-of-file command
+    /// can't be repeated, as the code after it must be the final literal.
+    RedundantReset,
+    /// Bytes followed the end of the stream.
+    TrailingData(u64),
+    /// The dictionary filled after only `codes` of a block of
+    /// `block_codes`, which a variant with a smaller dictionary or a
+    /// different limit on the strings it adds entries for may explain.
+    EarlySaturation{ codes: u64, block_codes: u64 },
+    /// An index past the entry about to be added, which only decodes with
+    /// [`IndexPolicy::Substitute`](crate::IndexPolicy::Substitute).
+    IndexOutOfRange{ index: usize, dictionary_len: usize },
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use AnomalyKind::*;
+        match self {
+            UnknownCommand(c) => write!(f, "Unknown command {}", c),
+            EarlyWiden{ width, dictionary_len } =>
+                write!(f, "Widened from {} bits with only {} dictionary entries", width, dictionary_len),
+            RedundantReset    => write!(f, "Reset after a block of one literal"),
+            TrailingData(len) => write!(f, "{} bytes after the end of the stream", len),
+            EarlySaturation{ codes, block_codes } =>
+                write!(f, "Dictionary full after {} of the block's {} codes", codes, block_codes),
+            IndexOutOfRange{ index, dictionary_len } =>
+                write!(f, "Index {} beyond dictionary of {} entries, taken as the next entry", index, dictionary_len),
+        }
+    }
+}
+
+/// Observer that assembles a [`StreamReport`] as a stream is decoded.
+pub struct ReportBuilder {
+    report: StreamReport,
+    block: Option<BlockReport>,
+    /// Code of the first dictionary entry, for spotting early widening.
+    index_base: u32,
+    dictionary_len: usize,
+    /// Where the last code started.
+    bit_offset: u64,
+    /// Where the current block's dictionary filled, and after how many of
+    /// its codes.
+    saturated: Option<(u64, u64)>,
+}
+
+impl Default for ReportBuilder {
+    fn default() -> ReportBuilder {
+        ReportBuilder::with_code_map(&HpCodeMap)
+    }
+}
+
+impl ReportBuilder {
+    pub fn new() -> ReportBuilder {
+        ReportBuilder::default()
+    }
+
+    /// A builder for a stream decoded with `map`.
+    pub fn with_code_map(map: &impl CodeMap) -> ReportBuilder {
+        ReportBuilder{
+            report: StreamReport::default(),
+            block: None,
+            index_base: map.index_base(),
+            dictionary_len: 0,
+            bit_offset: 0,
+            saturated: None,
+        }
+    }
+
+    /// Completes the report using the totals from `decoder`.
+    pub fn finish<M: CodeMap, const DICT: usize>(mut self, decoder: &Decoder<M, DICT>) -> StreamReport {
+        self.report.compressed_len = decoder.total_in();
+        self.report.decompressed_len = decoder.total_out();
+        self.report
+    }
+
+    /// As [`finish`](ReportBuilder::finish), for a stream decoded from
+    /// `input_len` bytes, noting any that followed its end.
+    pub fn finish_input<M: CodeMap, const DICT: usize>(self, decoder: &Decoder<M, DICT>, input_len: u64) -> StreamReport {
+        let mut report = self.finish(decoder);
+        if input_len > report.compressed_len {
+            report.anomalies.push(Anomaly{
+                bit_offset: report.compressed_len * 8,
+                kind: AnomalyKind::TrailingData(input_len - report.compressed_len),
+            });
+        }
+        report
+    }
+}
+
+impl ReportBuilder {
+    // Opens a block for one `decoder` is already part way into, for
+    // reporting on the rest of it.
+    pub(crate) fn resume_block<M: CodeMap, const DICT: usize>(&mut self, decoder: &Decoder<M, DICT>) {
+        self.block = Some(BlockReport{
+            bit_offset: decoder.bit_position(),
+            input_offset: decoder.total_in(),
+            output_offset: decoder.total_out(),
+            max_width: decoder.width(),
+            ..Default::default()
+        });
+    }
+
+    // The block still being decoded.
+    pub(crate) fn open_block(&self) -> Option<&BlockReport> {
+        self.block.as_ref()
+    }
+
+    // Takes the report of the block that ended last, if it hasn't been
+    // taken already.
+    pub(crate) fn take_block(&mut self) -> Option<BlockReport> {
+        self.report.blocks.pop()
+    }
+}
+
+impl DecodeObserver for ReportBuilder {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        self.bit_offset = bit_offset;
+        let histogram = &mut self.report.histogram;
+        *histogram.widths.entry(width).or_insert(0) += 1;
+        match code {
+            Code::Command(c) => {
+                if let Some(count) = histogram.commands.get_mut(c as usize) {
+                    *count += 1;
+                }
+                if !(1..=3).contains(&c) {
+                    self.report.anomalies.push(Anomaly{ bit_offset, kind: AnomalyKind::UnknownCommand(c) });
+                }
+                // Even the entry about to be defined would have fitted
+                let largest = u64::from(self.index_base) + self.dictionary_len as u64 + 1;
+                if c == 2 && largest < 1 << width {
+                    let kind = AnomalyKind::EarlyWiden{ width, dictionary_len: self.dictionary_len };
+                    self.report.anomalies.push(Anomaly{ bit_offset, kind });
+                }
+            },
+            Code::Value(_) => histogram.values += 1,
+            Code::Index(index) => {
+                histogram.indices += 1;
+                if index > self.dictionary_len {
+                    let kind = AnomalyKind::IndexOutOfRange{ index, dictionary_len: self.dictionary_len };
+                    self.report.anomalies.push(Anomaly{ bit_offset, kind });
+                }
+            },
+        }
+        if let Some(block) = &mut self.block {
+            block.codes += 1;
+            block.max_width = block.max_width.max(width);
+        }
+    }
+
+    fn insert(&mut self, index: usize, _value: u8, _next: Code) {
+        self.dictionary_len = index + 1;
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        if let Some(block) = &mut self.block {
+            match reason {
+                Suppression::DictionaryFull => {
+                    block.suppressed_full += 1;
+                    // Full since the code before this one
+                    self.saturated.get_or_insert((self.bit_offset, block.codes - 1));
+                },
+                Suppression::LongString => block.suppressed_long += 1,
+            }
+        }
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        // The block this one follows has just ended
+        if self.report.blocks.last().is_some_and(|previous| previous.output_len <= 1) {
+            self.report.anomalies.push(Anomaly{ bit_offset: point.bit_offset, kind: AnomalyKind::RedundantReset });
+        }
+        self.dictionary_len = 0;
+        self.saturated = None;
+        self.block = Some(BlockReport{
+            bit_offset: point.bit_offset,
+            input_offset: point.input_offset,
+            output_offset: point.output_offset,
+            max_width: 9,
+            ..Default::default()
+        });
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        if let Some(mut block) = self.block.take() {
+            block.output_len = output_offset - block.output_offset;
+            block.dictionary_len = dictionary_len;
+            // Most of the block decoded with no more room in the dictionary
+            if let Some((bit_offset, codes)) = self.saturated.take().filter(|&(_, codes)| codes * 2 < block.codes) {
+                let kind = AnomalyKind::EarlySaturation{ codes, block_codes: block.codes };
+                self.report.anomalies.push(Anomaly{ bit_offset, kind });
+            }
+            self.report.blocks.push(block);
+        }
+    }
+}
+
+/// Decompresses a complete stream held in memory, along with a report on
+/// its structure.
+pub fn decompress_with_report(input: &[u8]) -> Result<(Vec<u8>, StreamReport), Error> {
+    let mut decoder = Decoder::new();
+    let mut builder = ReportBuilder::new();
+    let mut out = vec![];
+    decoder.decode_to_vec_with(input, &mut out, &mut builder)?;
+    if !decoder.is_done() {
+        return Err(Error::UnexpectedEof);
+    }
+    Ok((out, builder.finish_input(&decoder, input.len() as u64)))
+}
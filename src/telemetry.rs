@@ -0,0 +1,97 @@
+//! Optional `tracing` spans and events. Without the `tracing` feature all of
+//! this compiles away to nothing.
+
+#[cfg(feature = "tracing")]
+use tracing::{debug_span, field, trace, Span};
+
+#[cfg(feature = "tracing")]
+pub(crate) type Guard = tracing::span::EnteredSpan;
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Guard;
+
+/// Tracks the span covering the block currently being decoded.
+///
+/// Each block gets a `block` span that is entered whenever the decoder is
+/// working on it, so subscribers see its busy time, along with the number of
+/// codes and bytes it produced once it closes. Refills, i.e. each call into
+/// the decoder with fresh input, are logged as `refill` events.
+#[derive(Clone)]
+pub(crate) struct Telemetry {
+    #[cfg(feature = "tracing")]
+    span: Span,
+    #[cfg(feature = "tracing")]
+    block: u64,
+    #[cfg(feature = "tracing")]
+    codes: u64,
+    #[cfg(feature = "tracing")]
+    block_start: u64,
+}
+
+#[cfg(feature = "tracing")]
+impl Telemetry {
+    pub(crate) fn new() -> Telemetry {
+        Telemetry{ span: Span::none(), block: 0, codes: 0, block_start: 0 }
+    }
+
+    pub(crate) fn enter(&self) -> Guard {
+        self.span.clone().entered()
+    }
+
+    /// Moves `guard` onto the current block's span if a new block has begun.
+    pub(crate) fn follow(&self, guard: &mut Guard) {
+        if guard.id() != self.span.id() {
+            *guard = self.enter();
+        }
+    }
+
+    pub(crate) fn block_start(&mut self, total_out: u64) {
+        self.block_end(total_out);
+        self.span = debug_span!(parent: None, "block",
+            index = self.block,
+            offset = total_out,
+            codes = field::Empty,
+            bytes = field::Empty,
+        );
+        self.span.in_scope(|| trace!("dictionary reset"));
+        self.block += 1;
+        self.codes = 0;
+        self.block_start = total_out;
+    }
+
+    pub(crate) fn code(&mut self) {
+        self.codes += 1;
+    }
+
+    pub(crate) fn block_end(&mut self, total_out: u64) {
+        if !self.span.is_none() {
+            self.span.record("codes", self.codes);
+            self.span.record("bytes", total_out - self.block_start);
+            self.span = Span::none();
+        }
+    }
+
+    pub(crate) fn refill(&self, consumed: usize, produced: usize) {
+        trace!(consumed, produced, "refill");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Telemetry {
+    pub(crate) fn new() -> Telemetry {
+        Telemetry{}
+    }
+
+    pub(crate) fn enter(&self) -> Guard {
+        Guard
+    }
+
+    pub(crate) fn follow(&self, _guard: &mut Guard) {}
+
+    pub(crate) fn block_start(&mut self, _total_out: u64) {}
+
+    pub(crate) fn code(&mut self) {}
+
+    pub(crate) fn block_end(&mut self, _total_out: u64) {}
+
+    pub(crate) fn refill(&self, _consumed: usize, _produced: usize) {}
+}
@@ -0,0 +1,86 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::decoder::Decoder;
+use crate::error::Error;
+
+/// Decompresses a stream read from a tokio `AsyncRead` as it arrives.
+///
+/// Reads return EOF once the stream's end-of-file marker has been decoded.
+/// Input is read ahead a buffer at a time, so whatever followed the stream
+/// may already have been taken from the inner reader; [`buffer`] gives it.
+/// An input that ends before the marker fails with
+/// `ErrorKind::UnexpectedEof`.
+///
+/// [`buffer`]: AsyncDecompressor::buffer
+pub struct AsyncDecompressor<R> {
+    inner: R,
+    decoder: Decoder,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncDecompressor<R> {
+    pub fn new(inner: R) -> AsyncDecompressor<R> {
+        AsyncDecompressor{
+            inner,
+            decoder: Decoder::new(),
+            buf: vec![0; 8192].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Input read from the inner reader but not yet decoded, which once the
+    /// stream has ended is what came after it.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncDecompressor<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.decoder.is_done() || out.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let input = &this.buf[this.pos..this.filled];
+            let (consumed, produced) = this.decoder.decode(input, out.initialize_unfilled())?;
+            this.pos += consumed;
+            out.advance(produced);
+            if produced > 0 || this.decoder.is_done() {
+                return Poll::Ready(Ok(()));
+            }
+
+            // Everything buffered has been consumed without finishing a code
+            let mut read_buf = ReadBuf::new(&mut this.buf);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {},
+                other               => return other,
+            }
+            if read_buf.filled().is_empty() {
+                return Poll::Ready(Err(Error::UnexpectedEof.into()));
+            }
+            this.filled = read_buf.filled().len();
+            this.pos = 0;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{self, Read};
+
+use crate::code::{Code, CodeMap};
+use crate::decoder::{Decoder, ResetPoint};
+use crate::error::Error;
+use crate::observer::DecodeObserver;
+
+/// A structural problem found by [`validate`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// Bit offset in the input of the offending code.
+    pub bit_offset: u64,
+    /// Decompressed bytes preceding the offending code.
+    pub output_offset: u64,
+    pub error: Error,
+}
+
+/// Outcome of [`validate`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// Violations found, in stream order. Validation stops at the first
+    /// one, as nothing after it can be interpreted reliably.
+    pub violations: Vec<Violation>,
+    /// Input bytes consumed.
+    pub compressed_len: u64,
+    /// Bytes the stream decompresses to, up to any violation.
+    pub decompressed_len: u64,
+    pub codes: u64,
+    pub blocks: u64,
+    /// Widest code width reached.
+    pub max_width: u8,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+// Counts what the report needs of the codes the decoder reads.
+struct Counter<'a>(&'a mut ValidationReport);
+
+impl DecodeObserver for Counter<'_> {
+    fn code(&mut self, _bit_offset: u64, _width: u8, _code: Code) {
+        self.0.codes += 1;
+    }
+
+    fn reset(&mut self, _point: &ResetPoint) {
+        self.0.blocks += 1;
+    }
+}
+
+impl<M: CodeMap, const DICT: usize> Decoder<M, DICT> {
+    /// Checks the rest of a stream read from `input` as [`validate`] does,
+    /// following this decoder's code map, dictionary capacity and index
+    /// policy, and leaving it where the check stopped.
+    pub fn validate(&mut self, mut input: impl Read) -> io::Result<ValidationReport> {
+        let mut report = ValidationReport{ decompressed_len: self.total_out(), max_width: self.width(), ..Default::default() };
+        let mut buf = vec![0; 65536];
+        while !self.is_done() {
+            let n = input.read(&mut buf)?;
+            if n == 0 {
+                report.violations.push(Violation{
+                    bit_offset: self.total_in() * 8,
+                    output_offset: report.decompressed_len,
+                    error: Error::UnexpectedEof,
+                });
+                break;
+            }
+            let mut chunk = &buf[..n];
+            while !self.is_done() {
+                let (bit_offset, output_offset) = (self.bit_position(), self.total_out());
+                let result = self.step(&mut chunk, &mut Counter(&mut report));
+                report.max_width = report.max_width.max(self.width());
+                match result {
+                    Ok(true)  => {
+                        self.discard_scratch();
+                        report.decompressed_len = self.total_out();
+                    },
+                    Ok(false) => break,
+                    Err(error) => {
+                        report.violations.push(Violation{ bit_offset, output_offset, error });
+                        break;
+                    },
+                }
+            }
+            if !report.is_valid() {
+                break;
+            }
+        }
+        report.compressed_len = self.total_in();
+        Ok(report)
+    }
+}
+
+/// Checks that `input` holds a well-formed stream without keeping any
+/// output: it must open with a start marker, keep its indices within the
+/// dictionary and its code width within limits, and run to a clean
+/// end-of-file sequence. Codes are followed by a [`Decoder`], so a stream
+/// validates exactly when it decodes; [`Decoder::validate`] checks with
+/// another code map, dictionary capacity or index policy.
+pub fn validate(input: impl Read) -> io::Result<ValidationReport> {
+    Decoder::new().validate(input)
+}
@@ -0,0 +1,64 @@
+//! Small streams with known outputs, for checking that a build or port
+//! decodes correctly.
+
+/// A stream and what it decodes to.
+#[derive(Clone, Copy, Debug)]
+pub struct Vector {
+    pub name: &'static str,
+    pub stream: &'static [u8],
+    pub output_len: usize,
+    /// SHA-256 of the output, in lowercase hex.
+    pub sha256: &'static str,
+}
+
+pub const VECTORS: &[Vector] = &[
+    // Nothing but literals and short references
+    Vector{
+        name: "literals",
+        stream: include_bytes!("vectors/literals.cmp"),
+        output_len: 9,
+        sha256: "49fecfcfff582f1055a41ffc7b1d56c96748c3cdee30634cd576937aebe0b884",
+    },
+    // References to the entry being defined
+    Vector{
+        name: "kwkwk",
+        stream: include_bytes!("vectors/kwkwk.cmp"),
+        output_len: 40,
+        sha256: "e33cdf9c7f7120b98e8c78408953e07f2ecd183006b5606df349b4c212acf43e",
+    },
+    // A reset every 7 codes
+    Vector{
+        name: "resets",
+        stream: include_bytes!("vectors/resets.cmp"),
+        output_len: 600,
+        sha256: "02ce94f0e6159ccf92553f10bdb10f43063c671012d081c837121c6fe491807e",
+    },
+    // Codes widened to 12 bits as the dictionary grows
+    Vector{
+        name: "widths",
+        stream: include_bytes!("vectors/widths.cmp"),
+        output_len: 3000,
+        sha256: "dbde82e4cf8354640eda937937e15db3dafd7bb4f28ff492c7f28cd620bafd74",
+    },
+    // Widening before it's needed, and ignored commands
+    Vector{
+        name: "commands",
+        stream: include_bytes!("vectors/commands.cmp"),
+        output_len: 5,
+        sha256: "a52199f0cdcb12b8c733989092f5851f6b9ee667d9042999e6993c52ba53ad07",
+    },
+    // Long chains expanding 100 times over
+    Vector{
+        name: "zeros",
+        stream: include_bytes!("vectors/zeros.cmp"),
+        output_len: 65536,
+        sha256: "de2f256064a0af797747c2b97505dc0b9f3df0de4f489eac731c23ae9ca9cc31",
+    },
+    // From `hpcmp gen-stream --seed 1 --size 4K`
+    Vector{
+        name: "generated",
+        stream: include_bytes!("vectors/generated.cmp"),
+        output_len: 4096,
+        sha256: "e6b4ce10a07a867c4de9bc81f87e5e783d634b72cb5ef80b05b0e6777e664bd4",
+    },
+];
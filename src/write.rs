@@ -0,0 +1,67 @@
+use std::io::{self, IoSlice, Write};
+
+use crate::decoder::Decoder;
+use crate::error::Error;
+use crate::observer::DecodeObserver;
+
+/// Bytes of output gathered before each write.
+const BATCH: usize = 1 << 16;
+
+/// Chains gathered before each write, the most slices one `writev` takes on
+/// Linux and the BSDs.
+const MAX_CHAINS: usize = 1024;
+
+/// Decompresses a complete stream held in memory to `writer` as it is
+/// decoded, returning the decompressed length.
+///
+/// The chain each code expands to is gathered into a list of up to 1024
+/// slices, or 64 KiB, and handed over in one
+/// [`write_vectored`](Write::write_vectored) call, so an unbuffered pipe or
+/// socket sees a single `writev` for each batch where copying from a
+/// [`Decompressor`](crate::Decompressor) writes a fraction of that at a
+/// time. Writers without a vectored write of their own, which take the
+/// first slice alone, are better off wrapped in a `BufWriter`. Decode
+/// errors are turned into `io::Error`s as the decompressor's are.
+pub fn decompress_to_writer(input: &[u8], writer: impl Write) -> io::Result<u64> {
+    decompress_to_writer_with(input, writer, ())
+}
+
+/// Like [`decompress_to_writer`], reporting decode events to `observer`.
+pub fn decompress_to_writer_with(input: &[u8], mut writer: impl Write, mut observer: impl DecodeObserver) -> io::Result<u64> {
+    let mut decoder = Decoder::new();
+    let mut remaining = input;
+    let mut batch = Vec::with_capacity(BATCH);
+    let mut ends = Vec::with_capacity(MAX_CHAINS);
+    while !decoder.is_done() {
+        batch.clear();
+        ends.clear();
+        decoder.decode_chains_with(&mut remaining, &mut batch, &mut ends, BATCH, MAX_CHAINS, &mut observer)?;
+        write_chains(&mut writer, &batch, &ends)?;
+        // Stopping short of both limits means the input ran dry
+        if !decoder.is_done() && batch.len() < BATCH && ends.len() < MAX_CHAINS {
+            return Err(Error::UnexpectedEof.into());
+        }
+    }
+    Ok(decoder.total_out())
+}
+
+// Writes the chains of `batch` ending at each of `ends` as one slice each,
+// going on from wherever a short write left off.
+fn write_chains(writer: &mut impl Write, batch: &[u8], ends: &[usize]) -> io::Result<()> {
+    let mut start = 0;
+    let mut slices: Vec<IoSlice> = ends.iter().map(|&end| {
+        let slice = IoSlice::new(&batch[start..end]);
+        start = end;
+        slice
+    }).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        match writer.write_vectored(slices) {
+            Ok(0)  => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(n)  => IoSlice::advance_slices(&mut slices, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! `--append` and `--at` must put each output where they say in a file
+//! that successive runs build up, leaving the rest of it alone, with every
+//! `--io` backend built in, and an append that fails must leave the file as
+//! it was.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn accumulates() {
+    let dir = TempDir::new("append");
+    let regions: Vec<Vec<u8>> = vec![
+        (0..7000u32).map(|i| (i % 241) as u8).collect(),
+        (0..3000).flat_map(|i| format!("entry {}\n", i % 61).into_bytes()).collect(),
+    ];
+    let inputs: Vec<_> = regions.iter().enumerate().map(|(i, region)| {
+        let path = dir.join(format!("region{}.cmp", i));
+        fs::write(&path, common::compress(region, Some(900))).unwrap();
+        path
+    }).collect();
+    let truncated = dir.join("truncated.cmp");
+    fs::write(&truncated, &fs::read(&inputs[1]).unwrap()[..100]).unwrap();
+    let output = dir.join("flash.bin");
+    let run = |options: &[&str], input: &Path| -> Output {
+        common::run(common::command().arg("-q").args(options).arg(input).arg(&output))
+    };
+
+    let backends: &[&str] = match cfg!(all(target_os = "linux", feature = "io-uring")) {
+        true  => &["std", "uring"],
+        false => &["std"],
+    };
+    let options = backends.iter().flat_map(|&io| [vec!["--io", io], vec!["--io", io, "--chunk-size", "1K"]]);
+    for options in options {
+        let options = &options[..];
+        let _ = fs::remove_file(&output);
+        for input in &inputs {
+            assert_success(&run(&[&["--append"], options].concat(), input));
+        }
+        assert!(fs::read(&output).unwrap() == regions.concat(), "{:?}", options);
+
+        assert!(!run(&[&["--append"], options].concat(), &truncated).status.success());
+        assert!(fs::read(&output).unwrap() == regions.concat(), "{:?}", options);
+
+        // Into the middle, then past the end, where the gap reads as zeros
+        let old = vec![0xee; 20_000];
+        fs::write(&output, &old).unwrap();
+        assert_success(&run(&[&["--at", "0x1388"], options].concat(), &inputs[0]));
+        let mut expected = old.clone();
+        expected[5000..5000 + regions[0].len()].copy_from_slice(&regions[0]);
+        assert!(fs::read(&output).unwrap() == expected, "{:?}", options);
+
+        assert_success(&run(&[&["--at", "30000"], options].concat(), &inputs[1]));
+        expected.resize(30_000, 0);
+        expected.extend_from_slice(&regions[1]);
+        assert!(fs::read(&output).unwrap() == expected, "{:?}", options);
+    }
+}
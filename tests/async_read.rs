@@ -0,0 +1,71 @@
+//! `AsyncDecompressor` must decode a stream however it arrives, here a byte
+//! at a time with the reader not ready between each, and keep what follows
+//! the stream.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hpcmp::{AsyncDecompressor, VECTORS};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+/// Hands out a byte at a time, pending before each.
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    ready: bool,
+}
+
+impl Trickle {
+    fn new(data: &[u8]) -> Trickle {
+        Trickle{ data: data.to_vec(), pos: 0, ready: false }
+    }
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        if let Some(&byte) = self.data.get(self.pos) {
+            buf.put_slice(&[byte]);
+            self.pos += 1;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn byte_at_a_time() {
+    for vector in VECTORS {
+        let mut decompressor = AsyncDecompressor::new(Trickle::new(vector.stream));
+        let mut out = vec![];
+        decompressor.read_to_end(&mut out).await.unwrap();
+        assert_eq!(out, hpcmp::decompress(vector.stream).unwrap(), "{}", vector.name);
+        assert!(decompressor.buffer().is_empty(), "{}", vector.name);
+    }
+}
+
+#[tokio::test]
+async fn truncated() {
+    let stream = VECTORS[0].stream;
+    let mut decompressor = AsyncDecompressor::new(Trickle::new(&stream[..stream.len() - 1]));
+    let e = decompressor.read_to_end(&mut vec![]).await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+/// Read ahead into the decompressor's buffer, rather than left in the
+/// inner reader.
+#[tokio::test]
+async fn trailing_data() {
+    let mut input = VECTORS[0].stream.to_vec();
+    input.extend_from_slice(b"after");
+    let mut decompressor = AsyncDecompressor::new(&input[..]);
+    let mut out = vec![];
+    decompressor.read_to_end(&mut out).await.unwrap();
+    assert_eq!(out, hpcmp::decompress(VECTORS[0].stream).unwrap());
+    assert_eq!(decompressor.buffer(), b"after");
+}
@@ -0,0 +1,53 @@
+//! `--offset` and `--bit-offset` must decode a stream starting partway into
+//! a byte of a larger input.
+
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+
+/// `stream` packed `bits` bits into a byte of `prefix`, least significant
+/// bit first, with the bits around it set.
+fn embed(prefix: &[u8], stream: &[u8], bits: u8) -> Vec<u8> {
+    let mut image = prefix.to_vec();
+    let mut pending = 0xffu16 >> (8 - bits);
+    for &byte in stream {
+        pending |= u16::from(byte) << bits;
+        image.push(pending as u8);
+        pending >>= 8;
+    }
+    image.push((pending | 0xff << bits) as u8);
+    image
+}
+
+#[test]
+fn decodes_at_bits() {
+    let dir = TempDir::new("bit-offset");
+    let data: Vec<u8> = (0..8000u32).map(|i| (i * 5 / 9) as u8).collect();
+    let stream = common::compress(&data, Some(600));
+    let (input, output) = (dir.join("image.bin"), dir.join("out.bin"));
+
+    let run = |options: &[&str]| {
+        let _ = fs::remove_file(&output);
+        common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(&input).arg(&output))
+    };
+    for bits in 0..8 {
+        fs::write(&input, embed(&[0x5a; 0x13], &stream, bits)).unwrap();
+        let bit_offset = bits.to_string();
+        for chunked in [&[][..], &["--chunk-size", "1K"]] {
+            let result = run(&[&["--offset", "0x13", "--bit-offset", &bit_offset][..], chunked].concat());
+            assert!(result.status.success(), "--bit-offset {}: {}", bits, String::from_utf8_lossy(&result.stderr));
+            assert!(fs::read(&output).unwrap() == data, "--bit-offset {} {:?}", bits, chunked);
+        }
+    }
+
+    // Off by a bit
+    let result = run(&["--offset", "0x13", "--bit-offset", "6"]);
+    assert!(!result.status.success());
+    assert!(!output.exists());
+
+    let result = run(&["--offset", "0x100000"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("past the end"), "{}", String::from_utf8_lossy(&result.stderr));
+}
@@ -0,0 +1,80 @@
+//! `hpcmp compress --block-crcs` must record the CRC-32 of each block's
+//! output, and decompressing must name the block a damaged stream goes
+//! wrong in, whether the CRCs come from a container or a file beside a bare
+//! stream.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+/// `stream` with a bit flipped somewhere past its first block that still
+/// decodes, and the block of `blocks` its output first differs in.
+fn damage(stream: &[u8], data: &[u8], blocks: &[hpcmp::BlockReport]) -> (Vec<u8>, usize) {
+    for offset in blocks[1].input_offset as usize + 1..stream.len() - 4 {
+        let mut damaged = stream.to_vec();
+        damaged[offset] ^= 0x10;
+        let output = match hpcmp::decompress(&damaged) {
+            Ok(output) if output != data => output,
+            _                            => continue,
+        };
+        let first = output.iter().zip(data).position(|(a, b)| a != b).unwrap_or(output.len().min(data.len()));
+        let block = blocks.iter().rposition(|block| block.output_offset <= first as u64).unwrap();
+        // Damage to the lengths alone is what the whole output's checks find
+        if block + 1 < blocks.len() {
+            return (damaged, block);
+        }
+    }
+    panic!("no damage that still decodes");
+}
+
+#[test]
+fn names_damaged_blocks() {
+    let dir = TempDir::new("block-crcs");
+    let data: Vec<u8> = (0..5000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    fs::write(dir.join("data.bin"), &data).unwrap();
+
+    assert_success(&hpcmp_in(&dir, ["compress", "--raw", "--block-crcs", "--block-len", "8K", "-o", "data.cmp", "data.bin"]));
+    let stream = fs::read(dir.join("data.cmp")).unwrap();
+    let (_, report) = hpcmp::decompress_with_report(&stream).unwrap();
+    let crcs = fs::read_to_string(dir.join("data.cmp.crcs")).unwrap();
+    let lines: Vec<&str> = crcs.lines().collect();
+    assert_eq!(lines.len(), report.blocks.len() + 2, "{}", crcs);
+    for (line, block) in lines[1..].iter().zip(&report.blocks) {
+        let start = data.len().min(block.output_offset as usize);
+        let crc32 = crc32fast::hash(&data[start..start + block.output_len as usize]);
+        assert_eq!(*line, format!("{} {} {} {:08x}", block.input_offset, block.output_offset, block.output_len, crc32));
+    }
+
+    assert_success(&hpcmp_in(&dir, ["-q", "--block-crcs", "data.cmp.crcs", "data.cmp", "data.out"]));
+    assert!(fs::read(dir.join("data.out")).unwrap() == data);
+
+    let (damaged, block) = damage(&stream, &data, &report.blocks);
+    fs::write(dir.join("bad.cmp"), &damaged).unwrap();
+    for options in [&[][..], &["--chunk-size", "1K"]] {
+        let result = hpcmp_in(&dir, [&["-q", "--errors-json", "--block-crcs", "data.cmp.crcs"], options, &["bad.cmp", "bad.out"]].concat());
+        assert!(!result.status.success());
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("\"code\":\"block_crc_mismatch\""), "{:?}: {}", options, stderr);
+        assert!(stderr.contains(&format!("block {} at stream offset {:#x}", block, report.blocks[block].input_offset)), "{:?}: {}", options, stderr);
+        assert!(!dir.join("bad.out").exists(), "{:?}", options);
+    }
+
+    // In a container, checked without being asked, and by extract
+    assert_success(&hpcmp_in(&dir, ["compress", "--block-crcs", "--block-len", "8K", "-o", "data.hpc", "data.bin"]));
+    let container = fs::read(dir.join("data.hpc")).unwrap();
+    assert!(container.starts_with(b"HPC\x02"));
+    assert_success(&hpcmp_in(&dir, ["-q", "data.hpc", "data.out"]));
+    assert!(fs::read(dir.join("data.out")).unwrap() == data);
+    let start = container.windows(stream.len()).position(|w| w == stream).unwrap();
+    let mut bad = container.clone();
+    bad[start..start + stream.len()].copy_from_slice(&damaged);
+    fs::write(dir.join("bad.hpc"), &bad).unwrap();
+    let result = hpcmp_in(&dir, ["-q", "--errors-json", "bad.hpc", "bad.out"]);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("\"code\":\"block_crc_mismatch\""));
+    fs::create_dir_all(dir.join("out")).unwrap();
+    let result = hpcmp_in(&dir, ["extract", "-d", "out", "bad.hpc"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains(&format!("block {} at stream offset", block)));
+}
@@ -0,0 +1,77 @@
+//! `Decoder::blocks` must split a stream's output where its report does,
+//! with each block's bits running on from the last.
+
+use std::fs;
+use std::path::Path;
+
+use hpcmp::{Block, Decoder, Error};
+
+fn stream(name: &str) -> Vec<u8> {
+    fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("corpus").join(name)).unwrap()
+}
+
+fn concat(blocks: &[Block]) -> Vec<u8> {
+    blocks.iter().flat_map(|block| block.data.iter().copied()).collect()
+}
+
+#[test]
+fn blocks_match_report() {
+    for name in ["text_resets.cmp", "dict_full.cmp", "mix.cmp", "two_bytes.cmp"] {
+        let stream = stream(name);
+        let (data, report) = hpcmp::decompress_with_report(&stream).unwrap();
+        let mut decoder = Decoder::new();
+        let blocks: Vec<_> = decoder.blocks(&stream).collect::<Result<_, _>>().unwrap();
+        assert_eq!(blocks.iter().map(|block| &block.report).collect::<Vec<_>>(), report.blocks.iter().collect::<Vec<_>>(), "{}", name);
+        assert_eq!(concat(&blocks), data, "{}", name);
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[0].bits.end, pair[1].bits.start, "{}", name);
+        }
+        assert_eq!(blocks.last().unwrap().bits.end.div_ceil(8), report.compressed_len, "{}", name);
+        assert!(decoder.is_done());
+    }
+}
+
+#[test]
+fn resumed_blocks() {
+    let stream = stream("text_resets.cmp");
+    let mut decoder = Decoder::new();
+    decoder.record_resets(true);
+    let blocks: Vec<_> = decoder.blocks(&stream).collect::<Result<_, _>>().unwrap();
+    let points = decoder.drain_resets();
+    assert!(points.len() > 2);
+
+    let point = &points[2];
+    let mut resumed = Decoder::resume_at(point);
+    let rest: Vec<_> = resumed.blocks(&stream[point.input_offset as usize..]).collect::<Result<_, _>>().unwrap();
+    assert_eq!(rest.len(), blocks.len() - 2);
+    for (resumed, whole) in rest.iter().zip(&blocks[2..]) {
+        assert_eq!(resumed.data, whole.data);
+        assert_eq!(resumed.report.output_offset, whole.report.output_offset);
+        assert_eq!(resumed.report.dictionary_len, whole.report.dictionary_len);
+    }
+}
+
+#[test]
+fn truncated_blocks() {
+    let stream = stream("text_resets.cmp");
+    let mut decoder = Decoder::new();
+    let results: Vec<_> = decoder.blocks(&stream[..stream.len() / 2]).collect();
+    assert!(results.len() > 1);
+    assert_eq!(results.last(), Some(&Err(Error::UnexpectedEof)));
+    assert!(results[..results.len() - 1].iter().all(Result::is_ok));
+}
+
+#[test]
+fn blocks_resumed_after_widening() {
+    let stream = stream("dict_full.cmp");
+    let (_, report) = hpcmp::decompress_with_report(&stream).unwrap();
+    let mut decoder = Decoder::new();
+    let split = stream.len() / 2;
+    decoder.decode_to_vec(&stream[..split], &mut vec![]).unwrap();
+    let whole = report.blocks.iter()
+        .find(|block| (block.output_offset..block.output_offset + block.output_len).contains(&decoder.total_out()))
+        .unwrap();
+    assert!(whole.max_width > 9);
+    let rest: Vec<_> = decoder.blocks(&stream[split..]).collect::<Result<_, _>>().unwrap();
+    assert_eq!(rest[0].report.max_width, whole.max_width);
+}
@@ -0,0 +1,75 @@
+//! `hpcmp bruteforce` must pick out a flavour's layout from the start of one
+//! of its streams, however unlike the firmware's it is.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+/// How `code` bits `width` wide at bit `pos` read, least significant first.
+fn read_lsb(data: &[u8], pos: usize, width: usize) -> u32 {
+    (0..width).map(|i| u32::from(data[(pos + i) / 8] >> ((pos + i) % 8) & 1) << i).sum()
+}
+
+/// `stream` recoded most significant bit first, with literals from 0 and
+/// the reset, widen and end commands at 0x100, 0x101 and 0x102.
+fn recode(stream: &[u8]) -> Vec<u8> {
+    let mut bits: Vec<bool> = vec![];
+    let align = |pos: usize| pos.div_ceil(8) * 8;
+    let (mut pos, mut width, mut last) = (0, 9, false);
+    loop {
+        let code = read_lsb(stream, pos, width);
+        pos += width;
+        let mapped = match code {
+            1..=3 => 0xff + code,
+            c if c < 0x108 => c - 8,
+            c => c,
+        };
+        bits.extend((0..width).rev().map(|i| mapped >> i & 1 == 1));
+        match code {
+            _ if last => break,
+            1 => width = 9,
+            2 => width += 1,
+            3 => last = true,
+            _ => (),
+        }
+        if code == 1 || code == 3 {
+            pos = align(pos);
+            bits.resize(align(bits.len()), false);
+        }
+    }
+    bits.resize(align(bits.len()), false);
+    bits.chunks(8).map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | u8::from(bit))).collect()
+}
+
+#[test]
+fn finds_layouts() {
+    let dir = TempDir::new("bruteforce");
+    let text: Vec<u8> = (0..2000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    let stream = common::compress(&text, Some(8000));
+    let bruteforce = |stream: &[u8]| {
+        let path = dir.join("stream.bin");
+        fs::write(&path, stream).unwrap();
+        let output = common::run(common::command().args(["bruteforce", "--prefix", "1M"]).arg(&path));
+        assert_success(&output);
+        let spec = String::from_utf8(output.stdout).unwrap();
+        let table: toml::Table = spec.parse().unwrap_or_else(|e| panic!("{}\n{}", e, spec));
+        (spec, table)
+    };
+
+    let (spec, table) = bruteforce(&stream);
+    assert_eq!(table["bit_order"].as_str(), Some("lsb"), "{}", spec);
+    assert_eq!(table["initial_width"].as_integer(), Some(9), "{}", spec);
+    assert_eq!(table["value_bias"].as_integer(), Some(8), "{}", spec);
+    let commands = table["commands"].as_table().unwrap();
+    assert_eq!([&commands["reset"], &commands["widen"], &commands["end"]].map(|c| c.as_integer()), [Some(1), Some(2), Some(3)], "{}", spec);
+
+    let (spec, table) = bruteforce(&recode(&stream));
+    assert_eq!(table["bit_order"].as_str(), Some("msb"), "{}", spec);
+    assert_eq!(table["value_bias"].as_integer(), Some(0), "{}", spec);
+    assert_eq!(table["index_base"].as_integer(), Some(0x108), "{}", spec);
+    let commands = table["commands"].as_table().unwrap();
+    assert_eq!([&commands["reset"], &commands["widen"], &commands["end"]].map(|c| c.as_integer()), [Some(0x100), Some(0x101), Some(0x102)], "{}", spec);
+    assert!(spec.contains(&format!("to {} bytes", text.len())), "{}", spec);
+}
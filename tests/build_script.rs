@@ -0,0 +1,74 @@
+//! The build script helpers must decompress into `OUT_DIR`, rewriting an
+//! output only when it has changed, and tell Cargo to rerun the build
+//! script when an input changes or a directory of them does.
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+use hpcmp::build;
+
+use common::TempDir;
+
+/// Set, to the directory of inputs, for this test run again as a build
+/// script would be.
+const AS_BUILD_SCRIPT: &str = "HPCMP_TEST_AS_BUILD_SCRIPT";
+
+const TEXT: &str = "tests/corpus/text.cmp";
+
+#[test]
+fn build_script() {
+    if let Some(res) = env::var_os(AS_BUILD_SCRIPT) {
+        return run_as_build_script(Path::new(&res));
+    }
+    let dir = TempDir::new("build-script");
+    let (out, res) = (dir.join("out"), dir.join("res"));
+    fs::create_dir(&out).unwrap();
+    fs::create_dir(&res).unwrap();
+    let data: Vec<u8> = (0..3000u32).map(|i| (i * 9 / 7) as u8).collect();
+    fs::write(res.join("a.cmp"), common::compress(&data, None)).unwrap();
+    fs::write(res.join("b.cmp"), common::compress(&data[1000..], Some(100))).unwrap();
+    fs::write(res.join("notes.txt"), b"not a stream").unwrap();
+
+    // Where Cargo would read what it prints
+    let result = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "build_script", "--nocapture", "--test-threads", "1"])
+        .env(AS_BUILD_SCRIPT, &res)
+        .env("OUT_DIR", &out)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(result.stdout).unwrap();
+    assert!(result.status.success(), "{}{}", stdout, String::from_utf8_lossy(&result.stderr));
+    // The first after the harness's `test build_script ... `
+    let reruns: Vec<&str> = stdout.lines().filter_map(|line| line.split_once("cargo:rerun-if-changed=")).map(|(_, path)| path).collect();
+    let (a, b, res) = (res.join("a.cmp"), res.join("b.cmp"), res.display().to_string());
+    assert_eq!(reruns, [TEXT, TEXT, TEXT, &res, &a.to_string_lossy(), &b.to_string_lossy()], "{}", stdout);
+
+    assert!(fs::read(out.join("a")).unwrap() == data);
+    assert!(fs::read(out.join("b")).unwrap() == data[1000..]);
+    assert!(!out.join("notes").exists());
+}
+
+fn run_as_build_script(res: &Path) {
+    let out = Path::new(&env::var_os("OUT_DIR").unwrap()).to_path_buf();
+    let text = hpcmp::decompress(&fs::read(TEXT).unwrap()).unwrap();
+    assert_eq!(build::decompress_file(TEXT).unwrap(), out.join("text"));
+    assert!(fs::read(out.join("text")).unwrap() == text);
+
+    // Left alone when it's the same, and rewritten when it isn't
+    let modified = fs::metadata(out.join("text")).unwrap().modified().unwrap();
+    assert_eq!(build::decompress_file_as(TEXT, "text").unwrap(), out.join("text"));
+    assert_eq!(fs::metadata(out.join("text")).unwrap().modified().unwrap(), modified);
+    fs::write(out.join("text"), b"stale").unwrap();
+    build::decompress_file_as(TEXT, "text").unwrap();
+    assert!(fs::read(out.join("text")).unwrap() == text);
+
+    assert_eq!(build::decompress_dir(res, "cmp").unwrap(), [out.join("a"), out.join("b")]);
+
+    env::remove_var("OUT_DIR");
+    assert_eq!(build::decompress_file(TEXT).unwrap_err().kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,55 @@
+//! What `hpcmp scan` finds in an image must be kept next to it and used
+//! again, by scan and by extract-at, but only while the image is unchanged.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, noise, TempDir};
+
+#[test]
+fn scans_once() {
+    let dir = TempDir::new("cache");
+    let data: Vec<u8> = (0..100_000u64).map(|i| ((i * i) >> 10) as u8).collect();
+    let stream = common::compress(&data, Some(2000));
+    let mut image = noise(0x1000, 1);
+    image.extend_from_slice(&stream);
+    image.extend(noise(0x800, 2));
+    let path = dir.join("fw.bin");
+    let cache = dir.join("fw.bin.hpcmp-index");
+    fs::write(&path, &image).unwrap();
+
+    let scan = |options: &[&str]| {
+        let output = common::run(common::command().arg("scan").args(options).arg(&path));
+        assert_success(&output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let found = format!("0x00001000 {} bytes, {} decompressed, score ", stream.len(), data.len());
+    assert!(scan(&[]).starts_with(&found));
+    let text = fs::read_to_string(&cache).unwrap();
+    assert!(text.starts_with("hpcmp-scan 1\n"), "{}", text);
+    assert!(text.lines().count() > 20, "{}", text);
+
+    // Doctored, to tell it was read rather than scanned again
+    let doctored = text.replacen(&format!(" {} ", data.len()), " 12345 ", 1);
+    fs::write(&cache, &doctored).unwrap();
+    assert!(scan(&[]).starts_with(&format!("0x00001000 {} bytes, 12345 decompressed, score ", stream.len())));
+    assert!(scan(&["--no-cache"]).starts_with(&found));
+    // Other options scan again
+    assert!(scan(&["--min-output", "2K"]).starts_with(&found));
+    fs::write(&cache, &doctored).unwrap();
+    image.push(0);
+    fs::write(&path, &image).unwrap();
+    assert!(scan(&[]).starts_with(&found));
+
+    // The stream's reset points are all extract-at needs
+    let extract_at = || common::run(common::command().args(["extract-at", "--range", "30000..50000", "--offset", "0x1000"]).arg(&path));
+    let output = extract_at();
+    assert_success(&output);
+    assert!(output.stdout == data[30000..50000]);
+    let text = fs::read_to_string(&cache).unwrap();
+    fs::write(&cache, text.replacen(&format!(" {} ", data.len()), " 12345 ", 1)).unwrap();
+    let output = extract_at();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("12345-byte output"), "{}", String::from_utf8_lossy(&output.stderr));
+}
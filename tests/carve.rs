@@ -0,0 +1,75 @@
+//! `hpcmp carve` must write each stream's output once, linking or skipping
+//! any that decode the same as an earlier one, and list them all.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, noise, TempDir};
+
+#[test]
+fn carves_once() {
+    let dir = TempDir::new("carve");
+    let icon: Vec<u8> = (0..9000u32).map(|i| (i % 97) as u8).collect();
+    let font: Vec<u8> = (0..7000u32).map(|i| (i / 13) as u8).collect();
+    let (icon_stream, font_stream) = (common::compress(&icon, None), common::compress(&font, Some(100)));
+    let mut image = vec![];
+    let mut offsets = vec![];
+    for (seed, stream) in [&icon_stream, &font_stream, &icon_stream].iter().enumerate() {
+        image.extend(noise(0x1000, seed as u64 + 1));
+        offsets.push(image.len());
+        image.extend_from_slice(stream);
+    }
+    let image_path = dir.join("fw.bin");
+    fs::write(&image_path, &image).unwrap();
+
+    let carve = |out: &str, duplicates: &str, options: &[&str]| {
+        assert_success(&common::run(common::command().args(["carve", "--duplicates", duplicates]).args(options).arg("-d").arg(dir.join(out)).arg(&image_path)));
+        let manifest = fs::read_to_string(dir.join(out).join("carve.json")).unwrap();
+        serde_json::from_str::<serde_json::Value>(&manifest).unwrap()
+    };
+    let file = |offset: usize| format!("fw_{:#010x}.bin", offset);
+
+    let manifest = carve("linked", "link", &[]);
+    let streams = manifest["streams"].as_array().unwrap();
+    assert_eq!(streams.len(), 3);
+    assert_eq!(streams[0]["offset"], offsets[0]);
+    assert_eq!(streams[0]["duplicate_of"], serde_json::Value::Null);
+    assert_eq!(streams[1]["decompressed_len"], font.len());
+    assert_eq!(streams[2]["duplicate_of"], offsets[0]);
+    assert_eq!(streams[2]["file"], file(offsets[2]));
+    assert_eq!(streams[2]["sha256"], streams[0]["sha256"]);
+    assert_eq!(fs::read(dir.join("linked").join(file(offsets[2]))).unwrap(), icon);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let inode = |offset| fs::metadata(dir.join("linked").join(file(offset))).unwrap().ino();
+        assert_eq!(inode(offsets[0]), inode(offsets[2]));
+        // Running again replaces the links rather than writing through them
+        carve("linked", "keep", &[]);
+        assert_ne!(inode(offsets[0]), inode(offsets[2]));
+    }
+
+    let manifest = carve("skipped", "skip", &["--map", "ghidra", "--map", "ida", "--load-base", "0x20000000"]);
+    assert_eq!(manifest["streams"][2]["file"], serde_json::Value::Null);
+    assert_eq!(manifest["streams"][2]["duplicate_of"], offsets[0]);
+    assert!(!dir.join("skipped").join(file(offsets[2])).exists());
+    assert_eq!(fs::read(dir.join("skipped").join(file(offsets[1]))).unwrap(), font);
+
+    // Each output a page or more after the one before, the skipped one
+    // loaded from the first's
+    let load_addresses = [0x2000_0000u64, 0x2000_3000, 0x2000_5000];
+    for (i, stream) in manifest["streams"].as_array().unwrap().iter().enumerate() {
+        assert_eq!(stream["load_address"], load_addresses[i]);
+    }
+    for script in ["ghidra_import.py", "ida_import.py"] {
+        let text = fs::read_to_string(dir.join("skipped").join(script)).unwrap();
+        let start = text.find("json.loads(r\"\"\"").unwrap() + "json.loads(r\"\"\"".len();
+        let segments: serde_json::Value = serde_json::from_str(&text[start..start + text[start..].find("\"\"\"").unwrap()]).unwrap();
+        assert_eq!(segments.as_array().unwrap().len(), 3);
+        assert_eq!(segments[1]["load_address"], load_addresses[1]);
+        assert_eq!(segments[2]["file"], file(offsets[0]));
+        assert_eq!(segments[2]["length"], icon.len());
+        assert!(segments[2]["comment"].as_str().unwrap().contains(&format!("stream at {:#010x} in ", offsets[2])));
+    }
+}
@@ -0,0 +1,42 @@
+//! `hpcmp cat` must join streams into one that decodes to their outputs in
+//! turn, however wide their codes had grown or full their dictionaries were
+//! by the end.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn joins() {
+    let dir = TempDir::new("cat");
+    let varied: Vec<u8> = (0..60000u32).map(|i| (i * 7919 % 10007) as u8).collect();
+    let text: Vec<u8> = (0..3000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    // Ending in a string too long to add an entry after
+    let run = vec![b'a'; 5000];
+    let parts = [
+        common::compress(&varied, None),
+        common::compress(&text, Some(50)),
+        common::compress(&run, None),
+        common::compress(b"hi", None),
+    ];
+    let mut names = vec![];
+    for (i, stream) in parts.iter().enumerate() {
+        names.push(format!("{}.cmp", i));
+        fs::write(dir.join(&names[i]), stream).unwrap();
+    }
+
+    assert_success(&common::run(common::command().args(["cat", "-o", "joined.cmp"]).args(&names).current_dir(&dir)));
+    let joined = fs::read(dir.join("joined.cmp")).unwrap();
+    let expected: Vec<u8> = [&varied[..], &text, &run, b"hi"].concat();
+    assert_eq!(hpcmp::decompress(&joined).unwrap(), expected);
+    // A literal and a reset more for each join, less the start markers
+    // dropped
+    assert!(joined.len() <= parts.iter().map(Vec::len).sum::<usize>() + 3 * 2, "{} bytes", joined.len());
+
+    fs::write(dir.join("short.cmp"), &parts[1][..parts[1].len() / 2]).unwrap();
+    let output = common::hpcmp_in(&dir, ["cat", "0.cmp", "short.cmp"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("short.cmp"));
+}
@@ -0,0 +1,43 @@
+//! `--chunk-size` must write the same output, and print the same digests,
+//! as decoding it whole, with every `--io` backend built in, and must leave
+//! nothing behind when decoding fails.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn chunked_output() {
+    let data: Vec<u8> = (0..1_000_000u64).map(|i| ((i * i) >> 12) as u8).collect();
+    let dir = TempDir::new("chunked");
+    let stream = common::compress(&data, Some(4000));
+    let (input, truncated, whole, chunked) = (dir.join("in.cmp"), dir.join("bad.cmp"), dir.join("whole"), dir.join("chunked"));
+    fs::write(&input, &stream).unwrap();
+    fs::write(&truncated, &stream[..stream.len() / 2]).unwrap();
+
+    let run = |options: &[&str], input: &std::path::Path, output: &std::path::Path| {
+        common::run(common::command().args(["-q", "--sha256", "--crc32"]).args(options).arg(input).arg(output))
+    };
+    let expected = run(&[], &input, &whole);
+    let backends: &[&str] = match cfg!(all(target_os = "linux", feature = "io-uring")) {
+        true  => &["std", "uring"],
+        false => &["std"],
+    };
+    for io in backends {
+        for size in ["1", "4093", "64K", "16M"] {
+            let output = run(&["--io", io, "--chunk-size", size], &input, &chunked);
+            assert_success(&output);
+            assert!(fs::read(&chunked).unwrap() == data, "--io {} --chunk-size {}", io, size);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout).replace(&*chunked.to_string_lossy(), &whole.to_string_lossy()),
+                String::from_utf8_lossy(&expected.stdout),
+            );
+        }
+
+        let output = run(&["--io", io, "--chunk-size", "4K"], &truncated, &chunked);
+        assert!(!output.status.success());
+        assert!(!chunked.exists(), "a failed output was left behind with --io {}", io);
+    }
+}
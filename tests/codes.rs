@@ -0,0 +1,22 @@
+//! Recording a stream's codes with `hpcmp record` and emitting them again
+//! with `hpcmp emit` must give back the same stream.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{assert_success, hpcmp, TempDir};
+
+#[test]
+fn record_then_emit() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("corpus");
+    let dir = TempDir::new("codes");
+    let (codes, emitted) = (dir.join("codes.hpcodes"), dir.join("emitted.cmp"));
+    for name in ["dict_full", "mix", "random", "text", "text_resets", "two_bytes", "zeros"] {
+        let stream = corpus.join(name).with_extension("cmp");
+        assert_success(&hpcmp([Path::new("record"), &stream, &codes]));
+        assert_success(&hpcmp([Path::new("emit"), &codes, &emitted]));
+        assert!(fs::read(&stream).unwrap() == fs::read(&emitted).unwrap(), "{} emitted differently", name);
+    }
+}
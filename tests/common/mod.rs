@@ -0,0 +1,219 @@
+//! A straightforward encoder following the decoder's rules, for tests to
+//! round-trip data through, and what tests of the command line share.
+
+// Each test uses only some of these
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use std::process::{Command, Output};
+
+use hpcmp::{DICT_LEN, MAX_PREV_LEN, MAX_WIDTH};
+
+/// Packs codes least significant bit first, as the reader expects.
+pub struct Writer {
+    out: Vec<u8>,
+    bits: u64,
+    len: u32,
+    width: u32,
+}
+
+impl Writer {
+    /// A writer of 9-bit codes, as a stream starts with.
+    pub fn new() -> Writer {
+        Writer{ out: vec![], bits: 0, len: 0, width: 9 }
+    }
+
+    /// Bits in a code.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The whole bytes written so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.out
+    }
+
+    /// Writes `code` as it is, in the current width.
+    pub fn put(&mut self, code: u32) {
+        assert!(code < 1 << self.width, "code 0x{:x} too wide for {} bits", code, self.width);
+        self.bits |= u64::from(code) << self.len;
+        self.len += self.width;
+        while self.len >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    /// Widens codes by a bit.
+    pub fn widen(&mut self) {
+        assert!(self.width < u32::from(MAX_WIDTH), "codes are already as wide as they go");
+        self.put(2);
+        self.width += 1;
+    }
+
+    /// Writes `code`, widening codes first if it needs more bits.
+    pub fn code(&mut self, code: u32) {
+        while code >= 1 << self.width {
+            self.widen();
+        }
+        self.put(code);
+    }
+
+    /// Drops to the next byte, as the decoder does after a reset and the
+    /// end-of-file command.
+    pub fn pad(&mut self) {
+        if self.len > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.bits = 0;
+        self.len = 0;
+    }
+
+    pub fn reset(&mut self) {
+        self.put(1);
+        self.pad();
+        self.width = 9;
+    }
+
+    /// The stream written, padded to a byte.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.pad();
+        self.out
+    }
+}
+
+/// Compresses `data`, which must be at least two bytes long, starting a new
+/// block every `reset_every` codes if given.
+pub fn compress(data: &[u8], reset_every: Option<usize>) -> Vec<u8> {
+    assert!(data.len() >= 2, "the format can't hold fewer than two bytes");
+    let mut w = Writer::new();
+    w.reset();
+    let (body, last) = data.split_at(data.len() - 1);
+    let mut i = 0;
+    // Like the decoder's, only reset by an expanded code, not a block's first
+    let mut prev_len = 0;
+    while i < body.len() {
+        if i > 0 {
+            w.reset();
+        }
+        let mut dictionary: HashMap<(u32, u8), u32> = HashMap::new();
+        let mut prev = 8 + u32::from(body[i]);
+        w.code(prev);
+        i += 1;
+        let mut codes = 1;
+        while i < body.len() && reset_every.is_none_or(|every| codes < every) {
+            if prev_len < MAX_PREV_LEN && dictionary.len() < DICT_LEN {
+                let next = 0x108 + dictionary.len() as u32;
+                dictionary.insert((prev, body[i]), next);
+            }
+            let mut code = 8 + u32::from(body[i]);
+            let mut j = i + 1;
+            while let Some(&longer) = body.get(j).and_then(|&byte| dictionary.get(&(code, byte))) {
+                code = longer;
+                j += 1;
+            }
+            w.code(code);
+            prev_len = j - i;
+            i = j;
+            prev = code;
+            codes += 1;
+        }
+    }
+    w.put(3);
+    w.pad();
+    w.put(8 + u32::from(last[0]));
+    w.finish()
+}
+
+/// Bytes that decode to nothing much from anywhere.
+pub fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as u8
+    }).collect()
+}
+
+/// A directory of a test's own, under the system's temporary one, removed
+/// when dropped so that even a test that fails leaves nothing behind.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `hpcmp-<name>-<pid>`, emptied of anything a run before left.
+    pub fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("hpcmp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<OsStr> for TempDir {
+    fn as_ref(&self) -> &OsStr {
+        self.0.as_os_str()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The hpcmp binary, to be given arguments and run with [`run`], without
+/// the config file or environment variables of whoever runs the tests.
+#[cfg(feature = "cli")]
+pub fn command() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hpcmp"));
+    command.env("XDG_CONFIG_HOME", std::env::temp_dir().join("hpcmp-no-config"));
+    for var in ["HPCMP_LOG", "HPCMP_PRESET", "HPCMP_MAX_OUTPUT"] {
+        command.env_remove(var);
+    }
+    command
+}
+
+/// Runs `command` to the end.
+#[cfg(feature = "cli")]
+pub fn run(command: &mut Command) -> Output {
+    command.output().expect("running hpcmp")
+}
+
+/// Runs hpcmp with `args`.
+#[cfg(feature = "cli")]
+pub fn hpcmp<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> Output {
+    run(command().args(args))
+}
+
+/// Runs hpcmp with `args` in `dir`.
+#[cfg(feature = "cli")]
+pub fn hpcmp_in<S: AsRef<OsStr>>(dir: &Path, args: impl IntoIterator<Item = S>) -> Output {
+    run(command().args(args).current_dir(dir))
+}
+
+/// Fails unless hpcmp succeeded, with what it said if not.
+#[cfg(feature = "cli")]
+pub fn assert_success(output: &Output) {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,61 @@
+//! `hpcmp compare-images` must pair up streams however they moved between
+//! images, and say which blocks of them changed.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{noise, TempDir};
+
+/// `streams` one after another, with noise before each.
+fn image(streams: &[&[u8]]) -> Vec<u8> {
+    let mut image = vec![];
+    for (seed, stream) in streams.iter().enumerate() {
+        image.extend(noise(0x1000, seed as u64 + 1));
+        image.extend_from_slice(stream);
+    }
+    image
+}
+
+fn compare(old: &Path, new: &Path) -> (Option<i32>, String) {
+    let output = common::hpcmp([Path::new("compare-images"), old, new]);
+    (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn reports_changes() {
+    let dir = TempDir::new("compare");
+    let table: Vec<u8> = (0..40_000u32).map(|i| (i * 7 % 253) as u8).collect();
+    let text: Vec<u8> = (0..1500).flat_map(|i| format!("message {}\n", i % 90).into_bytes()).collect();
+    let removed: Vec<u8> = (0..5000u32).map(|i| (i / 9) as u8).collect();
+    // A few bytes changed at the end, and more appended, leaves the blocks
+    // before them as they were
+    let mut grown = text.clone();
+    let len = grown.len();
+    grown[len - 3..].copy_from_slice(b"NEW");
+    grown.extend(noise(20_000, 9));
+
+    let (table, text, removed, grown) = (common::compress(&table, None), common::compress(&text, Some(40)),
+                                          common::compress(&removed, None), common::compress(&grown, Some(40)));
+    let old = dir.join("old.bin");
+    let new = dir.join("new.bin");
+    fs::write(&old, image(&[&text, &removed, &table])).unwrap();
+    fs::write(&new, image(&[&table, &grown])).unwrap();
+
+    let (status, out) = compare(&old, &new);
+    assert_eq!(status, Some(1), "{}", out);
+    let lines: Vec<&str> = out.lines().collect();
+    assert!(lines[0].starts_with("0x00001000 -> "), "{}", out);
+    assert!(lines[0].contains(": changed, "), "{}", out);
+    assert!(lines[1].starts_with("  blocks 0-"), "{}", out);
+    assert!(lines[1].contains(": identical, "), "{}", out);
+    assert!(lines.iter().any(|line| line.contains(": changed, ") && line.starts_with("  block ")), "{}", out);
+    assert!(lines.iter().any(|line| line.contains(": only in new, ") && line.starts_with("  block ")), "{}", out);
+    let removed_at = 0x2000 + text.len();
+    assert!(lines.iter().any(|line| line.starts_with(&format!("{:#010x}: only in old, 5000 bytes, crc32 ", removed_at))), "{}", out);
+    let table_at = 0x3000 + text.len() + removed.len();
+    assert!(lines.last().unwrap().starts_with(&format!("{:#010x} -> 0x00001000: identical, 40000 bytes", table_at)), "{}", out);
+
+    assert_eq!(compare(&old, &old).0, Some(0));
+}
@@ -0,0 +1,33 @@
+//! The completion script for every shell must be written whole, covering
+//! the options and subcommands there are, and offering the config file's
+//! presets as the values of `--preset`.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp, TempDir};
+
+fn stdout(args: &[&str]) -> String {
+    let result = hpcmp(args);
+    assert_success(&result);
+    String::from_utf8(result.stdout).unwrap()
+}
+
+#[test]
+fn completions() {
+    let dir = TempDir::new("completions");
+    for (shell, start) in [("bash", "_hpcmp() {"), ("zsh", "#compdef hpcmp"), ("fish", "complete -c hpcmp")] {
+        let script = stdout(&["completions", shell]);
+        assert!(script.starts_with(start), "{}: {}", shell, &script[..100]);
+        assert!(script.contains("out-dir") && script.contains("post-compress"), "{}", shell);
+        assert!(script.contains("extract-at") && script.contains("compare-images"), "{}", shell);
+    }
+
+    // With the presets of the config file as --preset's values
+    fs::create_dir(dir.join("hpcmp")).unwrap();
+    fs::write(dir.join("hpcmp/config.toml"), "[presets.archival]\npost-compress = \"xz\"\n").unwrap();
+    let result = common::run(common::command().env("XDG_CONFIG_HOME", &dir).args(["completions", "bash"]));
+    assert_success(&result);
+    assert!(String::from_utf8(result.stdout).unwrap().contains("archival"));
+}
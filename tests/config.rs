@@ -0,0 +1,104 @@
+//! Defaults from the file given with `--config` must apply as if given on
+//! the command line: strings and numbers as values, booleans turning flags
+//! on or off, arrays giving an option more than once and `verbose` as a
+//! number of `-v`s or `module=level` pairs. A value of another type must be
+//! refused, and the command line must override the file, with
+//! `HPCMP_PRESET`, `HPCMP_MAX_OUTPUT` and `HPCMP_LOG` in between.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::{assert_success, TempDir};
+use sha2::{Digest, Sha256};
+
+fn hpcmp_with(dir: &Path, config: &str, args: &[&str]) -> Output {
+    fs::write(dir.join("config.toml"), config).unwrap();
+    common::run(common::command().arg("--config").arg(dir.join("config.toml")).args(args).current_dir(dir))
+}
+
+#[test]
+fn applies_defaults() {
+    let dir = TempDir::new("config");
+    let data: Vec<u8> = (0..2000).flat_map(|i| format!("value {}\n", i % 19).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(500))).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+
+    let config = "sha256 = true\ncrc32 = false\nskip-existing = true\nverbose = 1\n";
+    let result = hpcmp_with(&dir, config, &["in.cmp", "out.bin"]);
+    assert_success(&result);
+    assert_eq!(String::from_utf8(result.stdout).unwrap(), format!("{}  out.bin\n", sha256));
+    let result = hpcmp_with(&dir, config, &["in.cmp", "out.bin"]);
+    assert_success(&result);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("in.cmp: skipping, output already exists"), "{}", stderr);
+
+    let result = hpcmp_with(&dir, "output = [\"a.bin\", \"b.bin\"]\nverbose = \"reader=trace\"\n", &["in.cmp"]);
+    assert_success(&result);
+    assert!(fs::read(dir.join("a.bin")).unwrap() == data);
+    assert!(fs::read(dir.join("b.bin")).unwrap() == data);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("TRACE [hpcmp::reader]"));
+
+    for config in ["sparse = 1979-05-27T07:32:00Z\n", "output = [\"a.bin\", { name = \"b.bin\" }]\n"] {
+        let result = hpcmp_with(&dir, config, &["in.cmp", "out.bin"]);
+        assert!(!result.status.success(), "{}", config);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let key = config.split(' ').next().unwrap();
+        assert!(stderr.contains(&format!("config.toml: unsupported value for '{}'", key)), "{}", stderr);
+    }
+    let result = common::run(common::command().args(["--config", "missing.toml", "in.cmp", "out.bin"]).current_dir(&dir));
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("missing.toml"));
+
+    // Given on the command line as well, the file's value is left out
+    let result = hpcmp_with(&dir, "max-output = \"100\"\n", &["-q", "in.cmp", "big.bin"]);
+    assert!(!result.status.success());
+    assert_success(&hpcmp_with(&dir, "max-output = \"100\"\n", &["-q", "--max-output", "1M", "in.cmp", "big.bin"]));
+    assert!(fs::read(dir.join("big.bin")).unwrap() == data);
+}
+
+#[test]
+fn layers_environment() {
+    let dir = TempDir::new("config-env");
+    let data: Vec<u8> = (0..2000).flat_map(|i| format!("value {}\n", i % 19).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(500))).unwrap();
+    fs::write(dir.join("config.toml"), "max-output = \"100\"\nverbose = 2\npreset = \"small\"\n\n\
+                                        [presets.small]\nmax-output = \"200\"\n\n\
+                                        [presets.big]\nmax-output = \"1M\"\n").unwrap();
+    let run = |vars: &[(&str, &str)], args: &[&str]| {
+        let mut command = common::command();
+        command.arg("--config").arg(dir.join("config.toml")).args(args).arg("in.cmp").arg("out.bin").current_dir(&dir);
+        for (var, value) in vars {
+            command.env(var, value);
+        }
+        let result = common::run(&mut command);
+        (result.status.success(), String::from_utf8(result.stderr).unwrap())
+    };
+    let too_large = |(success, stderr): &(bool, String), max: &str| {
+        !success && stderr.contains(&format!("more than --max-output {} bytes", max))
+    };
+
+    // The file's preset over its top level, the environment's over that, and
+    // the command line's over everything
+    let result = run(&[], &[]);
+    assert!(too_large(&result, "200") && result.1.contains("DEBUG"), "{}", result.1);
+    assert!(run(&[("HPCMP_PRESET", "big")], &[]).0);
+    assert!(too_large(&run(&[("HPCMP_PRESET", "big")], &["--preset", "small"]), "200"));
+    assert!(run(&[("HPCMP_PRESET", "small")], &["--preset", "big"]).0);
+    assert!(run(&[("HPCMP_PRESET", "none")], &[]).1.contains("no preset 'none'"));
+
+    assert!(run(&[("HPCMP_MAX_OUTPUT", "1M")], &[]).0);
+    assert!(too_large(&run(&[("HPCMP_MAX_OUTPUT", "1M")], &["--max-output", "10"]), "10"));
+
+    let big = ("HPCMP_MAX_OUTPUT", "1M");
+    let (success, stderr) = run(&[big, ("HPCMP_LOG", "trace")], &[]);
+    assert!(success && stderr.contains("TRACE [hpcmp::decoder]"), "{}", stderr);
+    let (success, stderr) = run(&[big, ("HPCMP_LOG", "error")], &[]);
+    assert!(success && !stderr.contains("DEBUG"), "{}", stderr);
+    let (success, stderr) = run(&[big, ("HPCMP_LOG", "reader=trace")], &[]);
+    assert!(success && stderr.contains("TRACE [hpcmp::reader]") && !stderr.contains("TRACE [hpcmp::decoder]"), "{}", stderr);
+    let (success, stderr) = run(&[big, ("HPCMP_LOG", "trace")], &["-v"]);
+    assert!(success && !stderr.contains("DEBUG") && !stderr.contains("TRACE"), "{}", stderr);
+}
@@ -0,0 +1,86 @@
+//! `hpcmp compress` must write a container that decompresses back to each
+//! input under its own name, `list` and `extract` must treat it as an
+//! archive, and decompressing must refuse a member that doesn't match the
+//! size and CRC-32 stored with it.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn round_trips_members() {
+    let dir = TempDir::new("container");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    let icon: Vec<u8> = (0..9000u32).map(|i| (i % 97) as u8).collect();
+    let font: Vec<u8> = (0..7000u32).map(|i| (i / 13) as u8).collect();
+    fs::write(dir.join("icon.bin"), &icon).unwrap();
+    fs::write(dir.join("font.bin"), &font).unwrap();
+
+    assert_success(&hpcmp_in(&dir, ["compress", "-o", "res.hpc", "--block-len", "4K", "--source-offset", "0x1000",
+                                    "--source-offset", "0x4000", "icon.bin", "font.bin"]));
+    assert_success(&hpcmp_in(&dir, ["-q", "-d", "out", "--template", "{stem}_{offset:#x}", "res.hpc"]));
+    assert!(fs::read(dir.join("out/icon_0x1000")).unwrap() == icon);
+    assert!(fs::read(dir.join("out/font_0x4000")).unwrap() == font);
+    // Without --out-dir, there's nowhere to put the second
+    assert!(!hpcmp_in(&dir, ["-q", "res.hpc", "both.bin"]).status.success());
+
+    assert_success(&hpcmp_in(&dir, ["compress", "-o", "icon.hpc", "icon.bin"]));
+    assert_success(&hpcmp_in(&dir, ["compress", "--raw", "-o", "icon.cmp", "icon.bin"]));
+    for input in ["icon.hpc", "icon.cmp"] {
+        assert_success(&hpcmp_in(&dir, ["-q", input, "icon.out"]));
+        assert!(fs::read(dir.join("icon.out")).unwrap() == icon, "{}", input);
+    }
+
+    // The CRC-32 follows the magic number, the count, the name, the source
+    // offset and the size
+    let mut container = fs::read(dir.join("icon.hpc")).unwrap();
+    container[4 + 4 + 2 + "icon.bin".len() + 8 + 8] ^= 1;
+    fs::write(dir.join("bad.hpc"), &container).unwrap();
+    fs::remove_file(dir.join("icon.out")).unwrap();
+    for options in [&[][..], &["--chunk-size", "1K"]] {
+        let result = hpcmp_in(&dir, [&["-q", "--errors-json"], options, &["bad.hpc", "icon.out"]].concat());
+        assert!(!result.status.success());
+        assert!(String::from_utf8_lossy(&result.stderr).contains("\"code\":\"crc32_mismatch\""), "{:?}", options);
+        assert!(!dir.join("icon.out").exists(), "{:?}", options);
+    }
+}
+
+#[test]
+fn lists_and_extracts() {
+    let dir = TempDir::new("container-extract");
+    let files: Vec<(&str, Vec<u8>)> = vec![
+        ("boot.bin", (0..5000u32).map(|i| (i % 61) as u8).collect()),
+        ("logo.bin", (0..3000u32).map(|i| (i / 7) as u8).collect()),
+        ("strings.txt", (0..400).flat_map(|i| format!("message {}\n", i).into_bytes()).collect()),
+    ];
+    for (name, data) in &files {
+        fs::write(dir.join(name), data).unwrap();
+    }
+    assert_success(&hpcmp_in(&dir, ["compress", "-o", "fw.hpc", "--source-offset", "0", "--source-offset", "0x2000",
+                                    "--source-offset", "0x9000", "boot.bin", "logo.bin", "strings.txt"]));
+
+    let listing = hpcmp_in(&dir, ["list", "fw.hpc"]);
+    assert_success(&listing);
+    let listing = String::from_utf8(listing.stdout).unwrap();
+    let lines: Vec<Vec<&str>> = listing.lines().skip(1).map(|line| line.split_whitespace().collect()).collect();
+    assert_eq!(lines.len(), 3, "{}", listing);
+    assert_eq!(lines[1][1..], ["3000", "0x00002000", &format!("{:08x}", crc32fast::hash(&files[1].1)), "logo.bin"], "{}", listing);
+
+    assert_success(&hpcmp_in(&dir, ["extract", "fw.hpc", "--member", "logo.bin", "--member", "*.txt", "-d", "out"]));
+    assert!(!dir.join("out/boot.bin").exists());
+    assert!(fs::read(dir.join("out/logo.bin")).unwrap() == files[1].1);
+    assert!(fs::read(dir.join("out/strings.txt")).unwrap() == files[2].1);
+    assert_eq!(hpcmp_in(&dir, ["extract", "fw.hpc", "--member", "missing.bin", "-d", "out"]).status.code(), Some(1));
+
+    // With the first member's stored CRC-32 wrong, the others still come out
+    let mut container = fs::read(dir.join("fw.hpc")).unwrap();
+    container[4 + 4 + 2 + "boot.bin".len() + 8 + 8] ^= 0x80;
+    fs::write(dir.join("bad.hpc"), &container).unwrap();
+    let result = hpcmp_in(&dir, ["extract", "bad.hpc", "-d", "bad"]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("boot.bin: CRC-32 mismatch"));
+    assert!(!dir.join("bad/boot.bin").exists());
+    assert!(fs::read(dir.join("bad/strings.txt")).unwrap() == files[2].1);
+}
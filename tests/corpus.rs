@@ -0,0 +1,96 @@
+//! Decodes every stream in `tests/corpus` and checks what it decodes to
+//! against `tests/corpus/golden.txt`.
+//!
+//! After adding a stream, or making a change that is meant to alter what
+//! one decodes to, regenerate the expectations with:
+//!
+//!     cargo test --test corpus -- --update-golden
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use hpcmp::{Decoder, Error};
+use sha2::{Digest, Sha256};
+
+fn corpus() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("corpus")
+}
+
+/// The output's length and SHA-256, or the error.
+fn describe(result: &Result<Vec<u8>, Error>) -> String {
+    match result {
+        Ok(data) => format!("{} {:x}", data.len(), Sha256::digest(data)),
+        Err(e)   => format!("error {}", e),
+    }
+}
+
+/// What `stream` decodes to, both in one go and fed a byte at a time, so
+/// that stopping and resuming anywhere in the decode loop is covered too.
+fn outcome(stream: &[u8]) -> String {
+    let whole = describe(&hpcmp::decompress(stream));
+    let mut decoder = Decoder::new();
+    let mut data = vec![];
+    let chunked = stream.chunks(1)
+        .try_for_each(|byte| decoder.decode_to_vec(byte, &mut data).map(drop))
+        .and_then(|()| match decoder.is_done() {
+            true  => Ok(data),
+            false => Err(Error::UnexpectedEof),
+        });
+    let chunked = describe(&chunked);
+    match whole == chunked {
+        true  => whole,
+        false => format!("{} in one go, but {} a byte at a time", whole, chunked),
+    }
+}
+
+fn main() -> ExitCode {
+    let update = std::env::args().any(|arg| arg == "--update-golden");
+    let dir = corpus();
+    let golden = dir.join("golden.txt");
+
+    let mut actual = BTreeMap::new();
+    for entry in fs::read_dir(&dir).expect("reading tests/corpus") {
+        let path = entry.expect("reading tests/corpus").path();
+        if path.extension().is_some_and(|ext| ext == "cmp") {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            actual.insert(name, outcome(&fs::read(&path).expect("reading a stream")));
+        }
+    }
+
+    if update {
+        let lines: String = actual.iter().map(|(name, outcome)| format!("{} {}\n", name, outcome)).collect();
+        fs::write(&golden, lines).expect("writing golden.txt");
+        println!("wrote {} expectations to {}", actual.len(), golden.display());
+        return ExitCode::SUCCESS;
+    }
+
+    let text = fs::read_to_string(&golden).expect("reading golden.txt");
+    let expected: BTreeMap<&str, &str> = text.lines().filter_map(|line| line.split_once(' ')).collect();
+
+    let mut failures = 0;
+    for (name, outcome) in &actual {
+        match expected.get(name.as_str()) {
+            Some(&want) if want == outcome => println!("{} ... ok", name),
+            Some(&want) => {
+                println!("{} ... FAILED\n  expected {}\n  got      {}", name, want, outcome);
+                failures += 1;
+            },
+            None => {
+                println!("{} ... FAILED\n  not in golden.txt; run with --update-golden", name);
+                failures += 1;
+            },
+        }
+    }
+    for name in expected.keys().filter(|name| !actual.contains_key(**name)) {
+        println!("{} ... FAILED\n  in golden.txt but missing from tests/corpus", name);
+        failures += 1;
+    }
+
+    println!("\n{} streams, {} failed", actual.len(), failures);
+    match failures {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
+}
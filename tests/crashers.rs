@@ -0,0 +1,13 @@
+//! Replays the inputs in `tests/crashers`, which once crashed a fuzz
+//! target, through `hpcmp run-corpus`.
+
+mod common;
+
+use std::path::Path;
+
+#[test]
+fn crashers() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("crashers");
+    let output = common::hpcmp([Path::new("run-corpus"), &dir]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+}
@@ -0,0 +1,52 @@
+//! `hpcmp compress --decisions` must account for every byte of the input
+//! with the codes it lists, each one of the matches it says it chose from,
+//! and list each block it ends.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn lists_decisions() {
+    let dir = TempDir::new("decisions");
+    let data: Vec<u8> = (0..6000).flat_map(|i| format!("sample {} of {}\n", i * 7919 % 10007, i % 11).into_bytes()).collect();
+    fs::write(dir.join("data.bin"), &data).unwrap();
+
+    for level in ["greedy", "max"] {
+        assert_success(&hpcmp_in(&dir, ["compress", "--raw", "--block-len", "40K", "--level", level, "--decisions", "decisions.jsonl", "-o", "data.cmp", "data.bin"]));
+        assert_eq!(hpcmp::decompress(&fs::read(dir.join("data.cmp")).unwrap()).unwrap(), data);
+
+        let lines: Vec<serde_json::Value> = fs::read_to_string(dir.join("decisions.jsonl")).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["event"], "input");
+        assert_eq!(lines[0]["path"], "data.bin");
+        let mut offset = 0;
+        let mut whys = vec![];
+        for line in &lines[1..] {
+            assert_eq!(line["offset"], offset, "{}", line);
+            match line["event"].as_str().unwrap() {
+                "code"  => {
+                    let why = line["why"].as_str().unwrap();
+                    if why != "block_start" {
+                        let chosen = |candidate: &serde_json::Value| candidate["code"] == line["code"] && candidate["len"] == line["len"];
+                        assert!(line["candidates"].as_array().unwrap().iter().any(chosen), "{}", line);
+                    }
+                    whys.push(why.to_string());
+                    offset += line["len"].as_u64().unwrap();
+                },
+                "reset" => assert_eq!(line["why"], "block_len", "{}", line),
+                "end"   => assert_eq!(line["last"], u64::from(data[data.len() - 1])),
+                event   => panic!("unknown event {}", event),
+            }
+        }
+        assert!(offset == data.len() as u64 - 1, "{} codes to {} bytes", level, offset);
+        assert_eq!(lines.iter().filter(|line| line["event"] == "reset").count(), (data.len() - 1) / (40 << 10));
+        if level == "max" {
+            assert!(whys.iter().any(|why| why == "fewest_codes"), "{:?}", whys);
+        }
+    }
+}
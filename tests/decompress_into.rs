@@ -0,0 +1,33 @@
+//! `decompress_into` must fill a buffer of exactly the output's size, use
+//! only what it needs of a larger one, and refuse a smaller one.
+
+use hpcmp::{Error, VECTORS};
+
+#[test]
+fn decompress_into() {
+    for vector in VECTORS {
+        let expected = hpcmp::decompress(vector.stream).unwrap();
+
+        let mut exact = vec![0; expected.len()];
+        assert_eq!(hpcmp::decompress_into(vector.stream, &mut exact), Ok(expected.len()), "{}", vector.name);
+        assert_eq!(exact, expected, "{}", vector.name);
+
+        let mut oversize = vec![0xaa; expected.len() + 100];
+        assert_eq!(hpcmp::decompress_into(vector.stream, &mut oversize), Ok(expected.len()), "{}", vector.name);
+        assert_eq!(oversize[..expected.len()], expected[..], "{}", vector.name);
+        assert!(oversize[expected.len()..].iter().all(|&byte| byte == 0xaa), "{}", vector.name);
+
+        for len in [0, 1, expected.len() / 2, expected.len() - 1] {
+            let mut short = vec![0; len];
+            assert_eq!(hpcmp::decompress_into(vector.stream, &mut short), Err(Error::OutputOverflow), "{} into {}", vector.name, len);
+        }
+    }
+}
+
+#[test]
+fn decompress_into_truncated() {
+    let stream = VECTORS[0].stream;
+    let mut out = vec![0; 1 << 16];
+    assert_eq!(hpcmp::decompress_into(&stream[..stream.len() - 1], &mut out), Err(Error::UnexpectedEof));
+    assert_eq!(hpcmp::decompress_into(&[], &mut out), Err(Error::UnexpectedEof));
+}
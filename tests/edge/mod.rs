@@ -0,0 +1,297 @@
+//! Streams built code by code to sit on the format's edges: the longest
+//! chains, references to the entry being added at every code width, blocks
+//! as short as they come and commands wherever they may go. Each comes with
+//! what it should decode to, worked out by following the format's rules
+//! here rather than by the decoder.
+
+use hpcmp::{Error, DICT_LEN, MAX_PREV_LEN, MAX_WIDTH};
+
+use crate::common::Writer;
+
+/// A stream under construction, tracking the dictionary as the decoder
+/// would so that it knows what the stream decodes to.
+pub struct Stream {
+    w: Writer,
+    output: Vec<u8>,
+    dictionary: Vec<Vec<u8>>,
+    /// The previous string, empty at the start of a block.
+    prev: Vec<u8>,
+    /// Like the decoder's, not updated by a block's first literal.
+    prev_scratch_len: usize,
+}
+
+impl Stream {
+    /// A stream opened with the start marker.
+    pub fn new() -> Stream {
+        let mut stream = Stream{
+            w: Writer::new(),
+            output: vec![],
+            dictionary: vec![],
+            prev: vec![],
+            prev_scratch_len: 0,
+        };
+        stream.reset();
+        stream
+    }
+
+    pub fn dictionary_len(&self) -> usize {
+        self.dictionary.len()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.w.width()
+    }
+
+    /// Whether the next string adds a dictionary entry.
+    pub fn inserts(&self) -> bool {
+        !self.prev.is_empty() && self.prev_scratch_len < MAX_PREV_LEN && self.dictionary.len() < DICT_LEN
+    }
+
+    /// The length of entry `index`.
+    pub fn entry_len(&self, index: usize) -> usize {
+        self.dictionary[index].len()
+    }
+
+    pub fn reset(&mut self) -> &mut Stream {
+        self.w.reset();
+        self.dictionary.clear();
+        self.prev.clear();
+        self
+    }
+
+    pub fn widen(&mut self) -> &mut Stream {
+        self.w.widen();
+        self
+    }
+
+    /// One of the commands the decoder ignores.
+    pub fn ignored(&mut self, command: u32) -> &mut Stream {
+        assert!(matches!(command, 0 | 4..=7), "command {} isn't ignored", command);
+        assert!(!self.prev.is_empty(), "a block must open with a literal");
+        self.w.put(command);
+        self
+    }
+
+    pub fn literal(&mut self, byte: u8) -> &mut Stream {
+        self.w.code(8 + u32::from(byte));
+        self.string(vec![byte])
+    }
+
+    /// A reference to entry `index`, or to the one being added if `index`
+    /// is the dictionary's length.
+    pub fn index(&mut self, index: usize) -> &mut Stream {
+        assert!(!self.prev.is_empty(), "a block must open with a literal");
+        let string = match index == self.dictionary.len() {
+            true  => {
+                assert!(self.inserts(), "no entry is being added for index {} to refer to", index);
+                [&self.prev[..], &self.prev[..1]].concat()
+            },
+            false => self.dictionary[index].clone(),
+        };
+        self.w.code(0x108 + index as u32);
+        self.string(string)
+    }
+
+    /// A reference to the entry being added.
+    pub fn kwkwk(&mut self) -> &mut Stream {
+        self.index(self.dictionary.len())
+    }
+
+    /// Writes `code` as it is, without widening first or following what it
+    /// does, for streams that aren't meant to decode.
+    pub fn raw(&mut self, code: u32) -> &mut Stream {
+        self.w.put(code);
+        self
+    }
+
+    /// The end-of-file command, after which the stream's last code starts
+    /// on a byte boundary.
+    pub fn eof(&mut self) -> &mut Stream {
+        self.w.put(3);
+        self.w.pad();
+        self
+    }
+
+    /// Ends the stream with the end-of-file command and `last`, returning
+    /// it and what it decodes to.
+    pub fn finish(&mut self, last: u8) -> (Vec<u8>, Vec<u8>) {
+        self.eof();
+        self.w.put(8 + u32::from(last));
+        self.w.pad();
+        self.output.push(last);
+        (self.w.bytes().to_vec(), self.output.clone())
+    }
+
+    /// The stream written so far, as it is.
+    pub fn end(&mut self) -> Vec<u8> {
+        self.w.pad();
+        self.w.bytes().to_vec()
+    }
+
+    fn string(&mut self, string: Vec<u8>) -> &mut Stream {
+        self.output.extend_from_slice(&string);
+        if self.prev.is_empty() {
+            self.prev = string;
+            return self;
+        }
+        if self.inserts() {
+            self.dictionary.push([&self.prev[..], &string[..1]].concat());
+        }
+        self.prev_scratch_len = string.len();
+        self.prev = string;
+        self
+    }
+}
+
+/// A stream, and what decoding it should give.
+pub struct Case {
+    pub name: &'static str,
+    pub stream: Vec<u8>,
+    pub expected: Result<Vec<u8>, Error>,
+}
+
+fn ok(name: &'static str, (stream, output): (Vec<u8>, Vec<u8>)) -> Case {
+    Case{ name, stream, expected: Ok(output) }
+}
+
+fn err(name: &'static str, stream: Vec<u8>, error: Error) -> Case {
+    Case{ name, stream, expected: Err(error) }
+}
+
+pub fn cases() -> Vec<Case> {
+    vec![
+        ok("two_bytes", Stream::new().literal(0x55).finish(0xaa)),
+        ok("every_literal", {
+            let mut s = Stream::new();
+            (0..=255).for_each(|byte| { s.literal(byte); });
+            s.finish(0)
+        }),
+        // Each reference to the entry being added makes one a byte longer,
+        // until the strings are too long to add any more
+        ok("longest_chain", {
+            let mut s = Stream::new();
+            s.literal(b'a');
+            while s.inserts() {
+                s.kwkwk();
+            }
+            let newest = s.dictionary_len() - 1;
+            assert_eq!(s.entry_len(newest), MAX_PREV_LEN);
+            (0..100).for_each(|_| { s.index(newest); });
+            s.finish(b'a')
+        }),
+        // An entry one short of the longest, referred to over and over,
+        // fills the dictionary with the longest entries there can be
+        ok("full_dictionary_of_longest_chains", {
+            let mut s = Stream::new();
+            s.literal(0);
+            (0..0x7e).for_each(|_| { s.kwkwk(); });
+            let entry = s.dictionary_len() - 1;
+            assert_eq!(s.entry_len(entry), 0x7f);
+            while s.dictionary_len() < DICT_LEN {
+                s.index(entry);
+            }
+            assert_eq!(s.entry_len(DICT_LEN - 1), MAX_PREV_LEN);
+            (0..DICT_LEN).step_by(97).for_each(|entry| { s.index(entry); });
+            s.finish(1)
+        }),
+        // Literals fill the dictionary up to where the entry being added
+        // needs the next width, so the reference to it does
+        ok("kwkwk_at_width_boundaries", {
+            let mut s = Stream::new();
+            s.literal(0);
+            for width in 9..13 {
+                let mut byte = 0u8;
+                while 0x108 + s.dictionary_len() < 1 << width {
+                    byte = byte.wrapping_add(1);
+                    s.literal(byte);
+                }
+                assert_eq!(s.width(), width);
+                s.kwkwk();
+                assert_eq!(s.width(), width + 1);
+            }
+            s.finish(0)
+        }),
+        ok("kwkwk_at_every_width", {
+            let mut s = Stream::new();
+            for width in 9..=u32::from(MAX_WIDTH) {
+                if width > 9 {
+                    s.reset();
+                }
+                s.literal(width as u8);
+                while s.width() < width {
+                    s.widen();
+                }
+                s.kwkwk().kwkwk().index(0);
+            }
+            s.finish(0)
+        }),
+        ok("widest_then_reset", {
+            let mut s = Stream::new();
+            s.literal(1);
+            while s.width() < u32::from(MAX_WIDTH) {
+                s.widen();
+            }
+            s.literal(2).kwkwk().reset().literal(3).kwkwk();
+            s.finish(4)
+        }),
+        ok("eof_after_reset", Stream::new().literal(1).literal(2).reset().literal(3).finish(4)),
+        ok("back_to_back_resets", {
+            let mut s = Stream::new();
+            (0..300).for_each(|i| { s.literal(i as u8).reset(); });
+            s.literal(0);
+            s.finish(0)
+        }),
+        ok("ignored_commands_everywhere", {
+            let mut s = Stream::new();
+            s.literal(0);
+            while s.width() < u32::from(MAX_WIDTH) {
+                for command in [0, 4, 5, 6, 7] {
+                    s.ignored(command);
+                }
+                s.kwkwk().ignored(0).widen();
+            }
+            s.ignored(7);
+            s.finish(0)
+        }),
+        err("start_marker_only", Stream::new().end(), Error::UnexpectedEof),
+        err("eof_straight_after_reset", Stream::new().raw(3).end(), Error::FirstCodeNotValue),
+        err("only_commands", {
+            let mut s = Stream::new();
+            [0, 4, 5, 6, 7, 2, 0].iter().for_each(|&command| { s.raw(command); });
+            s.end()
+        }, Error::FirstCodeNotValue),
+        err("command_after_eof", Stream::new().literal(0).eof().raw(1).end(), Error::FinalCodeNotValue),
+        err("widen_past_the_widest", {
+            let mut s = Stream::new();
+            s.literal(0);
+            while s.width() < u32::from(MAX_WIDTH) {
+                s.widen();
+            }
+            s.raw(2).end()
+        }, Error::WidthOverflow(MAX_WIDTH + 1)),
+        err("index_past_kwkwk", Stream::new().literal(0).raw(0x109).end(), Error::InvalidIndex{ index: 1, dictionary_len: 0 }),
+        // A reference to the entry being added when none is, as after a
+        // string too long to add one for, decodes, but the entry it stands
+        // for is no more there to be referred to by the next
+        err("kwkwk_after_one_not_added", {
+            let mut s = Stream::new();
+            s.literal(b'a');
+            while s.inserts() {
+                s.kwkwk();
+            }
+            let next = s.dictionary_len();
+            s.raw(0x108 + next as u32).raw(0x108 + next as u32).end()
+        }, Error::InvalidIndex{ index: MAX_PREV_LEN - 1, dictionary_len: MAX_PREV_LEN - 1 }),
+        // After a block ending in a string too long to add an entry for, the
+        // next block's entry 0 isn't added when its first reference to it is
+        // read, and would chain to itself if added on the next
+        err("kwkwk_without_insertion", {
+            let mut s = Stream::new();
+            s.literal(0);
+            while s.inserts() {
+                s.kwkwk();
+            }
+            s.reset().literal(1).raw(0x108).raw(8).end()
+        }, Error::InvalidIndex{ index: 0, dictionary_len: 0 }),
+    ]
+}
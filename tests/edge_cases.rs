@@ -0,0 +1,201 @@
+//! Streams on the format's edges, from `tests/edge`, must decode to what
+//! they were built to, in one go and in chunks, and the validator must agree.
+
+mod common;
+mod edge;
+
+use hpcmp::Decoder;
+
+fn chunked(stream: &[u8], size: usize) -> Result<Vec<u8>, hpcmp::Error> {
+    let mut decoder = Decoder::new();
+    let mut data = vec![];
+    for chunk in stream.chunks(size) {
+        decoder.decode_to_vec(chunk, &mut data)?;
+    }
+    match decoder.is_done() {
+        true  => Ok(data),
+        false => Err(hpcmp::Error::UnexpectedEof),
+    }
+}
+
+#[test]
+fn edge_cases() {
+    let mut failures = vec![];
+    for case in edge::cases() {
+        let mut check = |how: &str, result: Result<Vec<u8>, hpcmp::Error>| {
+            if result != case.expected {
+                failures.push(format!(
+                    "{} {}: expected {:?}, got {:?}",
+                    case.name, how, case.expected.as_ref().map(Vec::len), result.map(|data| data.len()),
+                ));
+            }
+        };
+        check("in one go", hpcmp::decompress(&case.stream));
+        for size in [1, 3, 64] {
+            check(&format!("in chunks of {}", size), chunked(&case.stream, size));
+        }
+
+        let report = hpcmp::validate(&case.stream[..]).unwrap();
+        let valid = match (&case.expected, report.violations.first()) {
+            (Ok(data), None)         => report.decompressed_len == data.len() as u64,
+            (Err(e), Some(violation)) => violation.error == *e,
+            _                        => false,
+        };
+        if !valid {
+            failures.push(format!("{}: validated as {:?}", case.name, report));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn index_policy() {
+    use hpcmp::{AnomalyKind, IndexPolicy, ReportBuilder};
+
+    // Entry 5 of a dictionary of 1, where only entry 1 would do
+    let (stream, _) = edge::Stream::new().literal(0).literal(1).raw(0x108 + 5).literal(7).finish(2);
+    assert_eq!(hpcmp::decompress(&stream), Err(hpcmp::Error::InvalidIndex{ index: 5, dictionary_len: 1 }));
+
+    let mut decoder = Decoder::new();
+    decoder.set_index_policy(IndexPolicy::Substitute);
+    let mut builder = ReportBuilder::new();
+    let mut data = vec![];
+    decoder.decode_to_vec_with(&stream, &mut data, &mut builder).unwrap();
+    assert!(decoder.is_done());
+    // Taken as entry 1, the string before it and its own first byte
+    assert_eq!(data, [0, 1, 1, 1, 7, 2]);
+    let report = builder.finish(&decoder);
+    assert_eq!(report.anomalies.len(), 1, "{:?}", report.anomalies);
+    assert_eq!(report.anomalies[0].bit_offset, 16 + 9 * 2);
+    assert_eq!(report.anomalies[0].kind, AnomalyKind::IndexOutOfRange{ index: 5, dictionary_len: 1 });
+}
+
+#[test]
+fn dictionary_cap() {
+    use hpcmp::{AnomalyKind, ReportBuilder};
+
+    let report = |more: usize| {
+        let mut s = edge::Stream::new();
+        // Each literal after the first adds an entry, until there are 0x1000
+        for i in 0..0x1001 + more {
+            s.literal(i as u8);
+        }
+        let (stream, _) = s.finish(0);
+        let mut decoder = Decoder::new();
+        let mut builder = ReportBuilder::new();
+        decoder.decode_to_vec_with(&stream, &mut vec![], &mut builder).unwrap();
+        builder.finish(&decoder)
+    };
+
+    let late = report(0x800);
+    assert_eq!(late.blocks[0].suppressed_full, 0x800);
+    assert_eq!(late.blocks[0].suppressed_long, 0);
+    assert!(late.anomalies.is_empty(), "{:?}", late.anomalies);
+
+    let early = report(0x2000);
+    let block = &early.blocks[0];
+    assert_eq!(block.suppressed_full, 0x2000);
+    assert_eq!(early.anomalies.len(), 1, "{:?}", early.anomalies);
+    assert_eq!(early.anomalies[0].kind, AnomalyKind::EarlySaturation{ codes: 0x1001, block_codes: block.codes });
+
+    // Strings of 2 to 0x80 zeros, after which a literal adds nothing
+    let mut s = edge::Stream::new();
+    s.literal(0);
+    for _ in 0..0x7f {
+        s.kwkwk();
+    }
+    let (stream, _) = s.literal(1).literal(2).finish(3);
+    let mut decoder = Decoder::new();
+    let mut builder = ReportBuilder::new();
+    decoder.decode_to_vec_with(&stream, &mut vec![], &mut builder).unwrap();
+    let report = builder.finish(&decoder);
+    assert_eq!((report.blocks[0].suppressed_full, report.blocks[0].suppressed_long), (0, 1));
+}
+
+#[test]
+fn custom_code_map() {
+    use hpcmp::{AnomalyKind, CodeMap, HpCodeMap, ReportBuilder};
+
+    /// Commands 0-0xf, literals 0x10-0x10f and entries from 0x110.
+    struct Shifted;
+
+    impl CodeMap for Shifted {
+        fn command_limit(&self) -> u32 {
+            0x10
+        }
+
+        fn value_bias(&self) -> u32 {
+            0x10
+        }
+
+        fn index_base(&self) -> u32 {
+            0x110
+        }
+    }
+
+    // "a", "b" adding entry 0 for "ab", which comes next, then an ignored
+    // command of Shifted's own and the end
+    let stream = edge::Stream::new().raw(0x71).raw(0x72).raw(0x110).raw(0xc).eof().raw(0x73).end();
+    let mut decoder = Decoder::<_>::with_code_map(Shifted);
+    let mut builder = ReportBuilder::with_code_map(&Shifted);
+    let mut data = vec![];
+    decoder.decode_to_vec_with(&stream, &mut data, &mut builder).unwrap();
+    assert!(decoder.is_done());
+    assert_eq!(data, b"ababc");
+    let report = builder.finish(&decoder);
+    assert_eq!((report.histogram.values, report.histogram.indices), (3, 1));
+    assert_eq!(report.anomalies.len(), 1, "{:?}", report.anomalies);
+    assert_eq!((report.anomalies[0].bit_offset, &report.anomalies[0].kind), (16 + 9 * 3, &AnomalyKind::UnknownCommand(0xc)));
+
+    // The same codes mean something else to the standard map
+    let mut standard = Decoder::<_>::with_code_map(HpCodeMap);
+    assert_eq!(standard.decode_to_vec(&stream, &mut vec![]), Err(hpcmp::Error::InvalidIndex{ index: 8, dictionary_len: 1 }));
+
+    // And the validator follows whichever map its decoder has
+    assert!(Decoder::<_>::with_code_map(Shifted).validate(&stream[..]).unwrap().is_valid());
+    assert!(!hpcmp::validate(&stream[..]).unwrap().is_valid());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "read only 9 bits wide")]
+fn code_wider_than_read() {
+    use hpcmp::{CodeMap, Code, IndexPolicy};
+
+    /// Takes indices from the raw code without the base, so they encode
+    /// back to more bits than were read.
+    struct Unbased;
+
+    impl CodeMap for Unbased {
+        fn classify(&self, code: u32) -> Code {
+            match code {
+                c if c >= self.index_base() => Code::Index(c as usize),
+                c                           => hpcmp::HpCodeMap.classify(c),
+            }
+        }
+    }
+
+    // "a", then an index far beyond the dictionary, taken as the next entry
+    let stream = edge::Stream::new().raw(0x69).raw(0x1ff).eof().raw(0x69).end();
+    let mut decoder = Decoder::<_>::with_code_map(Unbased);
+    decoder.set_index_policy(IndexPolicy::Substitute);
+    let _ = decoder.decode_to_vec(&stream, &mut vec![]);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "beyond a byte")]
+fn code_map_beyond_a_byte() {
+    use hpcmp::CodeMap;
+
+    /// Literals from 0x8 to 0x208, more than there are bytes.
+    struct Wide;
+
+    impl CodeMap for Wide {
+        fn index_base(&self) -> u32 {
+            0x208
+        }
+    }
+
+    Wide.classify(0x108);
+}
@@ -0,0 +1,36 @@
+//! A batch must exit with 0 when every input decodes, 1 when none does, and
+//! 2 when only some do, which `--strict-exit` must make 1 as well.
+
+mod common;
+
+use std::fs;
+
+use common::{hpcmp_in, TempDir};
+
+#[test]
+fn partial_failure() {
+    let dir = TempDir::new("exit-status");
+    let data: Vec<u8> = (0..4000u32).map(|i| (i * 5 / 11) as u8).collect();
+    let stream = common::compress(&data, Some(300));
+    fs::write(dir.join("a.cmp"), &stream).unwrap();
+    fs::write(dir.join("b.cmp"), &stream).unwrap();
+    fs::write(dir.join("cut.cmp"), &stream[..stream.len() / 2]).unwrap();
+    // As in invalid_index.rs, an index to nothing
+    fs::write(dir.join("bad.cmp"), [0x01, 0x00, 0x08, 0x12, 0x34, 0x1c, 0x00, 0x0a, 0x00]).unwrap();
+    fs::create_dir(dir.join("out")).unwrap();
+
+    for jobs in ["1", "2"] {
+        let status = |options: &[&str], inputs: &[&str]| {
+            hpcmp_in(&dir, [&["-q", "--jobs", jobs, "-d", "out"], options, inputs].concat()).status.code()
+        };
+        assert_eq!(status(&[], &["a.cmp", "b.cmp"]), Some(0), "--jobs {}", jobs);
+        assert_eq!(status(&["--strict-exit"], &["a.cmp", "b.cmp"]), Some(0), "--jobs {}", jobs);
+        assert_eq!(status(&[], &["cut.cmp", "bad.cmp"]), Some(1), "--jobs {}", jobs);
+        assert_eq!(status(&[], &["a.cmp", "cut.cmp", "b.cmp", "bad.cmp"]), Some(2), "--jobs {}", jobs);
+        assert_eq!(status(&["--strict-exit"], &["a.cmp", "cut.cmp", "b.cmp", "bad.cmp"]), Some(1), "--jobs {}", jobs);
+        // The inputs that decoded are still written
+        assert!(fs::read(dir.join("out/b")).unwrap() == data, "--jobs {}", jobs);
+        fs::remove_file(dir.join("out/a")).unwrap();
+        fs::remove_file(dir.join("out/b")).unwrap();
+    }
+}
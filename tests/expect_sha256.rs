@@ -0,0 +1,38 @@
+//! `--expect-sha256` must pass an output with that digest, in either case,
+//! and fail one without it, leaving nothing written, whether the output is
+//! held whole or written with `--chunk-size`; `--sha256` and `--crc32` must
+//! print the output's digests.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+use sha2::{Digest, Sha256};
+
+#[test]
+fn checks_digest() {
+    let dir = TempDir::new("expect-sha256");
+    let data: Vec<u8> = (0..3000).flat_map(|i| format!("reading {} at {}\n", i % 29, i).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(2000))).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+    let crc32 = format!("{:08x}", crc32fast::hash(&data));
+    let wrong = format!("{}0", &sha256[..63]);
+
+    for options in [&[][..], &["--chunk-size", "1K"]] {
+        let _ = fs::remove_file(dir.join("out.bin"));
+        let result = hpcmp_in(&dir, [&["-q", "--sha256", "--crc32", "--expect-sha256", &sha256.to_uppercase()], options, &["in.cmp", "out.bin"]].concat());
+        assert_success(&result);
+        assert_eq!(String::from_utf8(result.stdout).unwrap(), format!("{}  out.bin\n{}  out.bin\n", sha256, crc32), "{:?}", options);
+        assert!(fs::read(dir.join("out.bin")).unwrap() == data, "{:?}", options);
+
+        fs::remove_file(dir.join("out.bin")).unwrap();
+        let result = hpcmp_in(&dir, [&["-q", "--errors-json", "--expect-sha256", &wrong], options, &["in.cmp", "out.bin"]].concat());
+        assert_eq!(result.status.code(), Some(1), "{:?}", options);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+        assert_eq!(error["code"], "sha256_mismatch", "{:?}", options);
+        assert_eq!(error["message"], format!("SHA-256 mismatch: expected {}, got {}", wrong, sha256), "{:?}", options);
+        assert!(!dir.join("out.bin").exists(), "{:?}", options);
+    }
+}
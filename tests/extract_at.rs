@@ -0,0 +1,23 @@
+//! `hpcmp extract-at` must write just the range asked for, whether it finds
+//! the reset points itself or reads them from a saved index.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn extract_at_writes_the_range() {
+    let data: Vec<u8> = (0..300_000u64).map(|i| ((i * i) >> 11) as u8).collect();
+    let dir = TempDir::new("extract-at");
+    let (stream, index, range) = (dir.join("in.cmp"), dir.join("in.idx"), dir.join("range.bin"));
+    fs::write(&stream, common::compress(&data, Some(3000))).unwrap();
+
+    // Building the index, then reading it
+    for _ in 0..2 {
+        assert_success(&common::run(common::command().args(["extract-at", "--range", "0x20000..200000", "--index"]).args([&index, &stream, &range])));
+        assert!(fs::read(&range).unwrap() == data[0x20000..200_000]);
+    }
+    assert!(fs::read_to_string(&index).unwrap().lines().count() > 3);
+}
@@ -0,0 +1,47 @@
+//! `--filter`s must pipe each output through their commands in turn, as in
+//! `cmd1 | cmd2`, letting one that stops reading early through but failing
+//! the output with the exit status of one that fails.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn pipes() {
+    let dir = TempDir::new("filter");
+    let data: Vec<u8> = (0..200_000).flat_map(|i| format!("entry {} of the log\n", i % 97).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(2000))).unwrap();
+    let run = |options: &[&str]| {
+        let _ = fs::remove_file(dir.join("out.txt"));
+        hpcmp_in(&dir, [&["-q", "--errors-json"], options, &["in.cmp", "out.txt"]].concat())
+    };
+
+    let filtered: Vec<u8> = data.to_ascii_uppercase().into_iter().filter(|&b| b != b'E').collect();
+    assert_success(&run(&["--filter", "tr a-z A-Z", "--filter", "tr -d E"]));
+    assert!(fs::read(dir.join("out.txt")).unwrap() == filtered);
+
+    // Far more than fits in a pipe, so the filters before are cut off
+    for filters in [&["--filter", "head -c 10"][..], &["--filter", "cat", "--filter", "head -c 10"]] {
+        let result = run(filters);
+        assert_success(&result);
+        assert_eq!(fs::read(dir.join("out.txt")).unwrap(), &data[..10], "{:?}", filters);
+    }
+
+    for (filters, status) in [
+        (&["--filter", "cat >/dev/null; exit 7"][..], "exit status: 7"),
+        (&["--filter", "cat; exit 5", "--filter", "cat"], "exit status: 5"),
+        (&["--filter", "cat", "--filter", "head -c 10; exit 3"], "exit status: 3"),
+    ] {
+        let result = run(filters);
+        assert!(!result.status.success(), "{:?}", filters);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+        assert_eq!(error["code"], "filter_failed", "{:?}: {}", filters, stderr);
+        assert!(error["message"].as_str().unwrap().ends_with(status), "{:?}: {}", filters, stderr);
+        assert!(!dir.join("out.txt").exists(), "{:?}", filters);
+    }
+}
@@ -0,0 +1,29 @@
+//! `hpcmp gen-data` must write exactly as much as asked, the same for the
+//! same seed every time, of data that compresses about as firmware does.
+
+mod common;
+
+fn gen_data(profile: &str, seed: u64, size: &str) -> Vec<u8> {
+    let output = common::hpcmp(["gen-data", "--profile", profile, "--seed", &seed.to_string(), "--size", size]);
+    common::assert_success(&output);
+    output.stdout
+}
+
+#[test]
+fn generates() {
+    for profile in ["code", "text", "raster"] {
+        let data = gen_data(profile, 7, "200000");
+        assert_eq!(data.len(), 200000, "{}", profile);
+        assert_eq!(gen_data(profile, 7, "200000"), data, "{}", profile);
+        assert_ne!(gen_data(profile, 8, "200000"), data, "{}", profile);
+        // A prefix of more of the same
+        assert_eq!(gen_data(profile, 7, "1000"), data[..1000], "{}", profile);
+        assert!(gen_data(profile, 7, "0").is_empty());
+
+        let compressed = common::compress(&data, Some(2000)).len();
+        assert!(compressed < data.len() * 3 / 4, "{} compressed to {} bytes", profile, compressed);
+        assert!(compressed > data.len() / 50, "{} compressed to {} bytes", profile, compressed);
+    }
+    let text = gen_data("text", 1, "64K");
+    assert!(text.split(|&b| b == 0).filter(|s| s.starts_with(b"usb: ")).count() > 10);
+}
@@ -0,0 +1,31 @@
+//! `hpcmp gen-stream` must write the same stream for the same seed every
+//! time, one that decodes to exactly what it says it should.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn decodes_as_expected() {
+    let dir = TempDir::new("gen-stream");
+    for (seed, size, bytes) in [("0", "64K", 64 << 10), ("1", "1000", 1000), ("2", "300K", 300 << 10)] {
+        let stream = format!("{}.cmp", seed);
+        let expected = format!("{}.expected", seed);
+        assert_success(&hpcmp_in(&dir, ["gen-stream", "--seed", seed, "--size", size, "--expected", &expected, &stream]));
+        let expected = fs::read(dir.join(expected)).unwrap();
+        assert_eq!(expected.len(), bytes, "seed {}", seed);
+
+        // The edge cases are only warned of
+        assert_success(&hpcmp_in(&dir, ["-q", &stream, "out.bin"]));
+        assert!(fs::read(dir.join("out.bin")).unwrap() == expected, "seed {}", seed);
+        assert_success(&hpcmp_in(&dir, ["-q", "--chunk-size", "4K", &stream, "out.bin"]));
+        assert!(fs::read(dir.join("out.bin")).unwrap() == expected, "seed {} with --chunk-size", seed);
+
+        let again = hpcmp_in(&dir, ["gen-stream", "--seed", seed, "--size", size]);
+        assert_success(&again);
+        assert!(again.stdout == fs::read(dir.join(&stream)).unwrap(), "seed {}", seed);
+    }
+    assert_ne!(fs::read(dir.join("0.cmp")).unwrap(), hpcmp_in(&dir, ["gen-stream", "--seed", "3"]).stdout);
+}
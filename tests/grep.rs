@@ -0,0 +1,24 @@
+//! `hpcmp grep` must find what searching the whole output finds.
+
+mod common;
+
+use std::fs;
+
+#[test]
+fn grep_finds_every_occurrence() {
+    // Long enough to span several of the chunks searched at a time
+    let data: Vec<u8> = (0..400_000u64).map(|i| ((i * i) >> 13) as u8).collect();
+    let dir = common::TempDir::new("grep");
+    let stream = dir.join("in.cmp");
+    fs::write(&stream, common::compress(&data, Some(5000))).unwrap();
+    let pattern = &data[70_000..70_004];
+    let expected: String = data.windows(pattern.len()).enumerate()
+        .filter(|(_, window)| window == &pattern)
+        .map(|(i, _)| format!("{:#010x} {}\n", i, pattern.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")))
+        .collect();
+
+    let hex: String = pattern.iter().map(|b| format!("{:02X}", b)).collect();
+    let output = common::run(common::command().args(["grep", "--hex", &hex]).arg(&stream));
+    common::assert_success(&output);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+}
@@ -0,0 +1,68 @@
+//! `build_index` must find every block of a stream, and an index must read
+//! back as it was written. `decompress_range` must give the same bytes as
+//! the same range of the whole output.
+
+mod common;
+
+use std::io::Cursor;
+
+use hpcmp::{Decoder, StreamIndex};
+
+fn data() -> Vec<u8> {
+    (0..30_000u64).map(|i| (((i * 7) >> 4) ^ (i / 300)) as u8).collect()
+}
+
+#[test]
+fn build_index() {
+    let data = data();
+    let stream = common::compress(&data, Some(400));
+    let index = hpcmp::build_index(&stream[..]).unwrap();
+
+    let mut decoder = Decoder::new();
+    decoder.record_resets(true);
+    decoder.decode_to_vec(&stream, &mut vec![]).unwrap();
+    assert!(index.resets.len() > 5);
+    assert_eq!(index.resets, decoder.drain_resets());
+    assert_eq!(index.resets[0].output_offset, 0);
+    assert_eq!(index.compressed_len, stream.len() as u64);
+    assert_eq!(index.decompressed_len, data.len() as u64);
+
+    let mut saved = vec![];
+    index.write_to(&mut saved).unwrap();
+    assert_eq!(StreamIndex::read_from(Cursor::new(saved)).unwrap(), index);
+
+    assert_eq!(hpcmp::build_index(&stream[..stream.len() - 1]).unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn decompress_range() {
+    let data = data();
+    let stream = common::compress(&data, Some(400));
+    let index = hpcmp::build_index(&stream[..]).unwrap();
+    let len = data.len() as u64;
+    let second = index.resets[1].output_offset;
+    let fifth = index.resets[4].output_offset;
+
+    let ranges = [
+        0..len,
+        0..1,
+        // Within a block, up to a reset, from one and across several
+        second + 1..second + 10,
+        second - 10..second,
+        second..second + 1,
+        second - 1..fifth + 1,
+        len - 1..len,
+    ];
+    for range in ranges {
+        let out = hpcmp::decompress_range(Cursor::new(&stream), &index, range.clone()).unwrap();
+        assert!(out == data[range.start as usize..range.end as usize], "{:?}", range);
+    }
+    // Cut short at the end of the output
+    assert_eq!(hpcmp::decompress_range(Cursor::new(&stream), &index, len - 5..len + 100).unwrap(), data[data.len() - 5..]);
+    // Nothing, however it's asked for
+    #[allow(clippy::reversed_empty_ranges)]
+    let empty = [100..100, 200..100, len..len + 10, len + 10..len + 20];
+    for range in empty {
+        assert!(hpcmp::decompress_range(Cursor::new(&stream), &index, range.clone()).unwrap().is_empty(), "{:?}", range);
+    }
+}
@@ -0,0 +1,53 @@
+//! Ctrl-C part way through writing an output must exit with 130, and
+//! remove the output, rename it to `.partial` as `--on-interrupt` says, or
+//! cut an appended-to file back to what it was.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::TempDir;
+
+/// Runs hpcmp with `options` on `input` until some of `output` is written,
+/// or `at_least` bytes of it, then interrupts it. Returns the exit status.
+fn interrupt(options: &[&str], input: &Path, output: &Path, at_least: u64) -> Option<i32> {
+    let mut child = common::command().arg("-q").args(options).arg(input).arg(output).spawn().expect("running hpcmp");
+    let start = Instant::now();
+    while fs::metadata(output).map_or(true, |metadata| metadata.len() <= at_least) {
+        assert!(child.try_wait().unwrap().is_none(), "finished before it could be interrupted");
+        assert!(start.elapsed() < Duration::from_secs(60), "nothing was written");
+        thread::sleep(Duration::from_millis(1));
+    }
+    // SAFETY: only sends a signal to the child, which hasn't been waited on
+    assert_eq!(unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) }, 0);
+    child.wait().unwrap().code()
+}
+
+#[test]
+fn cleans_up() {
+    let dir = TempDir::new("interrupt");
+    // Written a byte at a time, slowly enough to be caught part way
+    let data: Vec<u8> = (0..8_000_000u64).map(|i| ((i * i) >> 15) as u8).collect();
+    let (input, output) = (dir.join("big.cmp"), dir.join("big.bin"));
+    let partial = dir.join("big.bin.partial");
+    fs::write(&input, common::compress(&data, Some(4000))).unwrap();
+
+    assert_eq!(interrupt(&["--chunk-size", "1"], &input, &output, 0), Some(130));
+    assert!(!output.exists());
+    assert!(!partial.exists());
+
+    assert_eq!(interrupt(&["--chunk-size", "1", "--on-interrupt", "partial"], &input, &output, 0), Some(130));
+    assert!(!output.exists());
+    let kept = fs::read(&partial).unwrap();
+    assert!(!kept.is_empty() && kept.len() < data.len(), "{} bytes kept", kept.len());
+    assert!(kept == data[..kept.len()]);
+
+    fs::write(&output, b"head").unwrap();
+    assert_eq!(interrupt(&["--chunk-size", "1", "--append"], &input, &output, 4), Some(130));
+    assert_eq!(fs::read(&output).unwrap(), b"head");
+}
@@ -0,0 +1,36 @@
+//! `--invalid-index substitute` must decode an index that refers to nothing
+//! as the entry about to be added, warning where it was, where by default
+//! it fails.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn substitutes() {
+    let dir = TempDir::new("invalid-index");
+    // The literals 0 and 1, then index 5 with just the one entry, [0, 1],
+    // and the end
+    let stream = [0x01, 0x00, 0x08, 0x12, 0x34, 0x1c, 0x00, 0x0a, 0x00];
+    let (input, output) = (dir.join("damaged.cmp"), dir.join("damaged.bin"));
+    fs::write(&input, stream).unwrap();
+
+    let run = |options: &[&str]| {
+        let _ = fs::remove_file(&output);
+        common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(&input).arg(&output))
+    };
+    let first = |stderr: &[u8]| serde_json::from_str::<serde_json::Value>(String::from_utf8_lossy(stderr).lines().next().unwrap()).unwrap();
+
+    let result = run(&[]);
+    assert!(!result.status.success());
+    assert_eq!(first(&result.stderr)["code"], "invalid_index");
+
+    let result = run(&["--invalid-index", "substitute"]);
+    assert_success(&result);
+    assert_eq!(fs::read(&output).unwrap(), [0, 1, 1, 1, 2]);
+    let warning = first(&result.stderr);
+    assert_eq!(warning["warning"], "index_out_of_range");
+    assert_eq!(warning["bit_offset"], 34);
+}
@@ -0,0 +1,97 @@
+//! With `--jobs`, every log line must say which input it's about, however
+//! the workers' lines are interleaved, and progress bars must be drawn on a
+//! terminal but kept out of stderr anywhere else.
+
+mod common;
+
+use std::fs;
+
+use common::{hpcmp_in, TempDir};
+
+#[test]
+fn prefixes_lines() {
+    let dir = TempDir::new("jobs");
+    let names = ["a.cmp", "b.cmp", "c.cmp", "kept.cmp", "bad.cmp"];
+    for (i, name) in names[..4].iter().enumerate() {
+        let data: Vec<u8> = (0..20_000u32).map(|j| (j * (i as u32 + 2) / 9) as u8).collect();
+        fs::write(dir.join(name), common::compress(&data, Some(300))).unwrap();
+    }
+    // As in invalid_index.rs, an index to nothing, for an error
+    fs::write(dir.join("bad.cmp"), [0x01, 0x00, 0x08, 0x12, 0x34, 0x1c, 0x00, 0x0a, 0x00]).unwrap();
+    fs::create_dir(dir.join("out")).unwrap();
+    fs::write(dir.join("out/kept"), b"already here").unwrap();
+
+    let run = |jobs: &[&str]| {
+        let result = hpcmp_in(&dir, [&["-vv", "--skip-existing", "-d", "out"], jobs, &names].concat());
+        assert_eq!(result.status.code(), Some(2), "{}", String::from_utf8_lossy(&result.stderr));
+        String::from_utf8(result.stderr).unwrap()
+    };
+    let stderr = run(&["--jobs", "2"]);
+    let mut logged = 0;
+    // Not counting the summaries and warnings, which start with their
+    // input, and the context under an error
+    for line in stderr.lines().filter(|line| !line.starts_with(' ') && !line.starts_with("total: ")) {
+        if !names.iter().any(|name| line.starts_with(&format!("{}: ", name))) {
+            assert!(names.iter().any(|name| line.contains(&format!("] [{}] ", name))), "not prefixed: {}", line);
+            logged += 1;
+        }
+    }
+    assert!(stderr.contains("[kept.cmp] kept.cmp: skipping") && stderr.contains("ERROR [hpcmp] [bad.cmp] bad.cmp: "), "{}", stderr);
+    assert!(logged > 100, "{}", stderr);
+
+    // Only one at a time without
+    let stderr = run(&[]);
+    assert!(stderr.contains("ERROR [hpcmp] bad.cmp: "), "{}", stderr);
+    assert!(!names.iter().any(|name| stderr.contains(&format!("[{}]", name))), "{}", stderr);
+}
+
+/// What hpcmp with `args` in `dir` writes to stderr when it's a terminal.
+#[cfg(unix)]
+fn on_terminal(dir: &std::path::Path, args: &[&str]) -> String {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+    use std::process::Stdio;
+    let (mut master, mut slave) = (0, 0);
+    let size = libc::winsize{ ws_row: 24, ws_col: 120, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: only writes the two descriptors
+    assert_eq!(unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) }, 0);
+    // SAFETY: both were just opened, and nothing else owns them
+    let (mut master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    let mut child = common::command().current_dir(dir).args(args).stderr(Stdio::from(slave)).spawn().expect("running hpcmp");
+    let mut stderr = vec![];
+    let mut buf = [0; 4096];
+    // Until the child and with it the last of the terminal are gone
+    while let Ok(n @ 1..) = master.read(&mut buf) {
+        stderr.extend_from_slice(&buf[..n]);
+    }
+    assert!(child.wait().unwrap().success(), "{}", String::from_utf8_lossy(&stderr));
+    String::from_utf8_lossy(&stderr).into_owned()
+}
+
+#[test]
+fn progress_on_terminal() {
+    let dir = TempDir::new("jobs-progress");
+    let names: Vec<String> = (0..8).map(|i| format!("{}.cmp", i)).collect();
+    for (i, name) in names.iter().enumerate() {
+        let data: Vec<u8> = (0..200_000u32).map(|j| (j * (i as u32 + 2) / 9) as u8).collect();
+        fs::write(dir.join(name), common::compress(&data, Some(300))).unwrap();
+    }
+    fs::create_dir(dir.join("out")).unwrap();
+    let args: Vec<&str> = ["--jobs", "2", "-d", "out"].iter().copied().chain(names.iter().map(String::as_str)).collect();
+
+    // Piped, just the summaries
+    let result = hpcmp_in(&dir, &args);
+    assert!(result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), names.len() + 1, "{}", stderr);
+    assert!(!stderr.contains(['\r', '\x1b']) && !stderr.contains(&format!("/{} files", names.len())), "{:?}", stderr);
+
+    #[cfg(unix)]
+    {
+        let stderr = on_terminal(&dir, &args);
+        assert!(stderr.contains(&format!("/{} files", names.len())) && stderr.contains('\x1b'), "{:?}", stderr);
+        let stderr = on_terminal(&dir, &[&["--no-progress"], &args[..]].concat());
+        assert!(!stderr.contains(&format!("/{} files", names.len())) && !stderr.contains('\x1b'), "{:?}", stderr);
+    }
+}
@@ -0,0 +1,97 @@
+//! `hpcmp compress --raw` must stream an input of any size through a fixed
+//! amount of memory, writing the stream as it goes.
+
+mod common;
+
+use std::fs;
+use std::io::{self, Read};
+use std::process::Stdio;
+
+/// `len` bytes of lines that change as they go, between stretches of noise,
+/// the same each time for the same `len`.
+struct Synthetic {
+    left: u64,
+    line: Vec<u8>,
+    n: u64,
+}
+
+impl Synthetic {
+    fn new(len: u64) -> Synthetic {
+        Synthetic{ left: len, line: vec![], n: 0 }
+    }
+}
+
+impl Read for Synthetic {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() && self.left > 0 {
+            if self.line.is_empty() {
+                self.n += 1;
+                self.line = match self.n / 10000 % 4 {
+                    3 => common::noise(64, self.n),
+                    _ => format!("record {} of batch {}: {}\n", self.n % 977, self.n / 5000, self.n % 13).into_bytes(),
+                };
+            }
+            let len = self.line.len().min(buf.len() - filled).min(self.left as usize);
+            buf[filled..filled + len].copy_from_slice(&self.line[..len]);
+            self.line.drain(..len);
+            filled += len;
+            self.left -= len as u64;
+        }
+        Ok(filled)
+    }
+}
+
+/// The most memory the process `pid` has had resident, in bytes, if the
+/// system says.
+fn peak_rss(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    Some(line.split_whitespace().nth(1)?.parse::<u64>().ok()? * 1024)
+}
+
+/// Compresses `len` bytes, checking that the stream decodes back to them
+/// and that the encoder never held more than `max_rss` in memory.
+fn stream(len: u64, max_rss: u64) {
+    let dir = common::TempDir::new(&format!("large-input-{}", len));
+    let path = dir.join("input.bin");
+    io::copy(&mut Synthetic::new(len), &mut fs::File::create(&path).unwrap()).unwrap();
+
+    let mut child = common::command()
+        .args(["compress", "--raw"])
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("running hpcmp");
+    let pid = child.id();
+    let mut decoded = hpcmp::Decompressor::new(child.stdout.take().unwrap());
+    let mut expected = Synthetic::new(len);
+    let (mut got, mut want) = (vec![0; 1 << 16], vec![0; 1 << 16]);
+    let mut offset = 0;
+    let mut peak = 0;
+    loop {
+        let read = decoded.read(&mut got).unwrap();
+        expected.read_exact(&mut want[..read]).unwrap();
+        assert!(got[..read] == want[..read], "differs within {} bytes of {}", read, offset);
+        if read == 0 {
+            break;
+        }
+        offset += read as u64;
+        // The encoder is still running while there's more to decode
+        peak = peak.max(peak_rss(pid).unwrap_or(0));
+    }
+    assert_eq!(offset, len);
+    assert!(child.wait().unwrap().success());
+    assert!(peak <= max_rss, "{} bytes resident compressing {}", peak, len);
+}
+
+#[test]
+fn bounded_memory() {
+    stream(24 << 20, 16 << 20);
+}
+
+#[test]
+#[ignore = "writes and compresses 3 GiB; run with --release -- --ignored"]
+fn multi_gigabyte() {
+    stream(3 << 30, 16 << 20);
+}
@@ -0,0 +1,40 @@
+//! `--length-header` must take the size of the output from the header
+//! before the stream, however the output is written, and fail an input
+//! that comes out another size.
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+#[test]
+fn checks_header() {
+    let dir = common::TempDir::new("length-header");
+    let data: Vec<u8> = (0..40000u32).map(|i| (i % 199) as u8).collect();
+    let stream = common::compress(&data, Some(5000));
+    let (input, output) = (dir.join("fw.cmp"), dir.join("fw.bin"));
+    let run = |header: Vec<u8>, options: &[&str]| -> Output {
+        fs::write(&input, [header, stream.clone()].concat()).unwrap();
+        common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(&input).arg(&output))
+    };
+    let len = data.len() as u32;
+
+    for options in [&[][..], &["--mmap"], &["--chunk-size", "8K"]] {
+        for (format, header) in [("u32le", len.to_le_bytes()), ("u32be", len.to_be_bytes())] {
+            let result = run(header.to_vec(), &[&["--length-header", format], options].concat());
+            assert!(result.status.success(), "{} {:?}: {}", format, options, String::from_utf8_lossy(&result.stderr));
+            assert!(fs::read(&output).unwrap() == data, "{} {:?}", format, options);
+        }
+
+        for wrong in [len - 1, len + 1] {
+            let _ = fs::remove_file(&output);
+            let result = run(wrong.to_le_bytes().to_vec(), &[&["--length-header", "u32le"], options].concat());
+            assert!(!result.status.success());
+            assert!(String::from_utf8_lossy(&result.stderr).contains("\"code\":\"size_mismatch\""), "{} {:?}", wrong, options);
+            assert!(!output.exists(), "{} {:?}", wrong, options);
+        }
+    }
+
+    let result = run(len.to_le_bytes().to_vec(), &["--length-header", "u32le", "--expected-size", "1000"]);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("\"code\":\"bad_length_header\""));
+}
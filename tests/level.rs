@@ -0,0 +1,34 @@
+//! `hpcmp compress --level max` must decode back to its input, and never
+//! give a larger stream than the greedy parse.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, noise, TempDir};
+
+#[test]
+fn max_is_no_larger() {
+    let dir = TempDir::new("level");
+    let text: Vec<u8> = (0..8000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    let mixed: Vec<u8> = noise(60000, 1).into_iter().enumerate().map(|(i, byte)| {
+        // Runs of structure between stretches of noise
+        match (i / 5000) % 2 {
+            0 => (i % 23 + i / 700) as u8,
+            _ => byte % 16,
+        }
+    }).collect();
+
+    for (name, data) in [("text", &text), ("mixed", &mixed)] {
+        fs::write(dir.join(name), data).unwrap();
+        let compress = |level: &str| {
+            let path = dir.join(format!("{}-{}.cmp", name, level));
+            assert_success(&common::run(common::command().args(["compress", "--raw", "--block-len", "32K", "--level", level, "-o"]).arg(&path).arg(dir.join(name))));
+            let stream = fs::read(&path).unwrap();
+            assert!(hpcmp::decompress(&stream).as_ref() == Ok(data), "{} at {} doesn't round-trip", name, level);
+            stream.len()
+        };
+        let (greedy, max) = (compress("greedy"), compress("max"));
+        assert!(max <= greedy, "{}: {} bytes at max, {} greedy", name, max, greedy);
+    }
+}
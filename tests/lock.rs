@@ -0,0 +1,39 @@
+//! `--lock skip` must leave an output another process holds the lock on as
+//! it was, warning under `-v` that it did, however it would have been
+//! written, and `--lock wait` must write it once the lock is let go.
+
+mod common;
+
+use std::fs::{self, File};
+use std::thread;
+use std::time::Duration;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn skips_or_waits() {
+    let dir = TempDir::new("lock");
+    let data: Vec<u8> = (0..50_000u32).map(|i| (i * 5 / 3) as u8).collect();
+    let (input, output) = (dir.join("in.cmp"), dir.join("out.bin"));
+    fs::write(&input, common::compress(&data, Some(3000))).unwrap();
+    fs::write(&output, b"another writer's").unwrap();
+    let held = File::options().write(true).open(&output).unwrap();
+    held.lock().unwrap();
+
+    let size = data.len().to_string();
+    for options in [&[][..], &["--chunk-size", "4K"], &["--mmap", "--expected-size", &size]] {
+        let result = common::run(common::command().args(["-v", "--lock", "skip"]).args(options).arg(&input).arg(&output));
+        assert_success(&result);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("out.bin: skipped, another process is writing it"), "{:?}: {}", options, stderr);
+        assert_eq!(fs::read(&output).unwrap(), b"another writer's", "{:?}", options);
+    }
+
+    let mut child = common::command().args(["-q", "--lock", "wait"]).arg(&input).arg(&output).spawn().expect("running hpcmp");
+    thread::sleep(Duration::from_millis(300));
+    assert!(child.try_wait().unwrap().is_none(), "didn't wait for the lock");
+    assert_eq!(fs::read(&output).unwrap(), b"another writer's");
+    held.unlock().unwrap();
+    assert!(child.wait().unwrap().success());
+    assert!(fs::read(&output).unwrap() == data);
+}
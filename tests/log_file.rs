@@ -0,0 +1,70 @@
+//! `--log-file` with `--log-format json` must append a JSON object per line
+//! to the file, giving the time, level and target of each message, the
+//! input it's about and, for a decode error or a warning about the stream,
+//! the bit offset it was found at.
+
+mod common;
+
+use std::fs;
+
+use serde_json::Value;
+
+use common::TempDir;
+
+#[test]
+fn json_lines() {
+    let dir = TempDir::new("log-file");
+    let data: Vec<u8> = (0..3000u32).map(|i| (i * 7 / 5) as u8).collect();
+    let (good, damaged, log) = (dir.join("good.cmp"), dir.join("damaged.cmp"), dir.join("hpcmp.log"));
+    fs::write(&good, common::compress(&data, Some(400))).unwrap();
+    // As in invalid_index.rs, index 5 with just the one entry, at bit 34
+    fs::write(&damaged, [0x01, 0x00, 0x08, 0x12, 0x34, 0x1c, 0x00, 0x0a, 0x00]).unwrap();
+
+    // Info that the good one's output is left as it is, then the error
+    fs::write(dir.join("good.bin"), &data).unwrap();
+    for input in [&good, &damaged] {
+        let result = common::run(common::command().args(["-v", "--skip-existing", "--log-format", "json", "--log-file"]).arg(&log).arg(input).arg(input.with_extension("bin")));
+        assert_eq!(result.status.success(), input == &good, "{}", String::from_utf8_lossy(&result.stderr));
+    }
+
+    let lines: Vec<Value> = fs::read_to_string(&log).unwrap().lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line)))
+        .collect();
+    for line in &lines {
+        let time = line["time"].as_str().unwrap();
+        assert!(time.len() == "2024-01-01T00:00:00.000Z".len() && time.ends_with('Z'), "{}", line);
+        assert!(["ERROR", "WARN", "INFO"].contains(&line["level"].as_str().unwrap()), "{}", line);
+        assert!(line["target"].as_str().unwrap().starts_with("hpcmp"), "{}", line);
+        assert!(line["message"].is_string(), "{}", line);
+    }
+    let about = |input: &std::path::Path| lines.iter().filter(move |line| line["file"] == *input.to_string_lossy()).collect::<Vec<_>>();
+    let good = about(&good);
+    assert!(good.iter().any(|line| line["message"].as_str().unwrap().ends_with("skipping, output already exists")), "{:?}", lines);
+    assert!(good.iter().all(|line| line["level"] == "INFO" && line["bit_offset"].is_null()), "{:?}", lines);
+    let error = about(&damaged).into_iter().find(|line| line["level"] == "ERROR").unwrap_or_else(|| panic!("{:?}", lines));
+    assert_eq!(error["bit_offset"], 34, "{}", error);
+    assert!(error["message"].as_str().unwrap().contains("damaged.cmp"), "{}", error);
+}
+
+#[test]
+fn warnings() {
+    let dir = TempDir::new("log-file-warnings");
+    let (input, log) = (dir.join("trailing.cmp"), dir.join("hpcmp.log"));
+    let mut stream = common::compress(b"abcabcabc", None);
+    let len = stream.len();
+    stream.extend_from_slice(&[0; 3]);
+    fs::write(&input, &stream).unwrap();
+
+    let result = common::run(common::command().args(["--log-format", "json", "--log-file"]).arg(&log).arg(&input).arg(dir.join("trailing.bin")));
+    assert!(result.status.success(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("3 bytes before the end of the input"));
+    let warning: Value = fs::read_to_string(&log).unwrap().lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|line| line["level"] == "WARN")
+        .unwrap();
+    assert_eq!(warning["bit_offset"], len * 8, "{}", warning);
+
+    // Not with --quiet
+    let result = common::run(common::command().arg("--quiet").arg(&input).arg(dir.join("quiet.bin")));
+    assert!(result.stderr.is_empty(), "{}", String::from_utf8_lossy(&result.stderr));
+}
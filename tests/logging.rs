@@ -0,0 +1,38 @@
+//! `-v` given `module=level` pairs must log those modules at those levels
+//! and everything else at the level the `-v`s alone set.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn levels_by_module() {
+    let dir = TempDir::new("logging");
+    let data: Vec<u8> = (0..2000).flat_map(|i| format!("line {}\n", i % 37).into_bytes()).collect();
+    let (input, output) = (dir.join("in.cmp"), dir.join("out.bin"));
+    fs::write(&input, common::compress(&data, Some(500))).unwrap();
+    let stderr = |args: &[&str]| {
+        let _ = fs::remove_file(&output);
+        let result = common::run(common::command().args(args).arg(&input).arg(&output));
+        assert_success(&result);
+        assert!(fs::read(&output).unwrap() == data, "{:?}", args);
+        String::from_utf8(result.stderr).unwrap()
+    };
+
+    for args in [&["-v", "reader=trace,dict=debug"][..], &["--verbose=reader=trace,dict=debug"], &["-v", "--log-filter", "reader=trace,dict=debug"]] {
+        let log = stderr(args);
+        assert!(log.contains("TRACE [hpcmp::reader] read: "), "{:?}: {}", args, log);
+        assert!(log.contains("DEBUG [hpcmp::dict] dict: reset"), "{:?}: {}", args, log);
+        assert!(!log.contains("[hpcmp::decoder]"), "{:?}: {}", args, log);
+        assert!(!log.contains("TRACE [hpcmp::dict]"), "{:?}: {}", args, log);
+    }
+
+    // What the filter held back, and `-vv` still counting twice
+    assert!(stderr(&["-vvv"]).contains("TRACE [hpcmp::decoder]"));
+    let log = stderr(&["-vv"]);
+    assert!(log.contains("DEBUG [hpcmp::reader]") && !log.contains("TRACE"), "{}", log);
+    let log = stderr(&["-v"]);
+    assert!(!log.contains("DEBUG") && !log.contains("TRACE"), "{}", log);
+}
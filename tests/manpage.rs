@@ -0,0 +1,72 @@
+//! The man page must be written whole, covering the options, subcommands
+//! and environment variables there are, and the usage lines in it and in
+//! `--help` must keep up with the subcommands and the options each takes.
+
+mod common;
+
+use common::{assert_success, hpcmp};
+
+fn stdout(args: &[&str]) -> String {
+    let result = hpcmp(args);
+    assert_success(&result);
+    String::from_utf8(result.stdout).unwrap()
+}
+
+/// The subcommands `--help` lists, but for `help` itself.
+fn subcommands(help: &str) -> Vec<String> {
+    help.lines()
+        .skip_while(|line| *line != "SUBCOMMANDS:")
+        .skip(1)
+        .filter(|line| line.starts_with("    ") && !line.starts_with("     "))
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|name| *name != "help")
+        .map(str::to_string)
+        .collect()
+}
+
+/// The options a usage line names.
+fn options(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| !c.is_ascii_alphanumeric() && c != '-').filter(|word| word.starts_with("--"))
+}
+
+#[test]
+fn usage_covers_subcommands() {
+    let help = stdout(&["--help"]);
+    let usage: Vec<&str> = help.lines()
+        .skip_while(|line| *line != "USAGE:")
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .map(str::trim)
+        .collect();
+    let subcommands = subcommands(&help);
+    assert!(subcommands.len() > 20, "{:?}", subcommands);
+    for name in &subcommands {
+        let lines: Vec<&&str> = usage.iter().filter(|line| line.split_whitespace().nth(1) == Some(name.as_str())).collect();
+        assert_eq!(lines.len(), 1, "{} in the usage: {:?}", name, usage);
+        let own_help = stdout(&[name, "--help"]);
+        for option in options(lines[0]) {
+            assert!(own_help.contains(&format!("{} ", option)), "{} has no {}", name, option);
+        }
+    }
+    for line in usage.iter().filter(|line| line.starts_with("hpcmp [")) {
+        for option in options(line) {
+            assert!(help.contains(&format!("{} ", option)), "no {}", option);
+        }
+    }
+}
+
+#[test]
+fn man_page() {
+    let page = stdout(&["manpage"]);
+    assert!(page.starts_with(".TH HPCMP 1 "), "{}", &page[..100]);
+    for section in ["NAME", "SYNOPSIS", "OPTIONS", "COMMANDS", "ENVIRONMENT", "EXIT STATUS"] {
+        assert!(page.contains(&format!("\n.SH {}\n", section)), "no {}", section);
+    }
+    assert!(page.contains(r"\fB\-d\fR, \fB\-\-out\-dir\fR \fIdir\fR"));
+    assert!(page.contains(r"\fBHPCMP_LOG\fR"));
+    for name in subcommands(&stdout(&["--help"])) {
+        let name = name.replace('-', r"\-");
+        assert!(page.contains(&format!("\n\\fB{}\\fR\n", name)), "no {} under COMMANDS", name);
+        assert!(page.contains(&format!("\nhpcmp {}", name)), "no {} in the synopsis", name);
+    }
+}
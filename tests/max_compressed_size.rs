@@ -0,0 +1,33 @@
+//! `--max-compressed-size` must fail a stream that comes to more, saying by
+//! how much and writing nothing, and leave one that fits as it would be.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn enforces_budget() {
+    let dir = TempDir::new("max-compressed-size");
+    let data: Vec<u8> = (0..4000).flat_map(|i| format!("sample {} of {}\n", i * 7919 % 10007, i % 11).into_bytes()).collect();
+    fs::write(dir.join("data.bin"), &data).unwrap();
+    assert_success(&hpcmp_in(&dir, ["compress", "--raw", "-o", "free.cmp", "data.bin"]));
+    let len = fs::read(dir.join("free.cmp")).unwrap().len();
+
+    let budget = (len - 100).to_string();
+    for args in [&["compress", "--raw", "-o", "over.cmp"][..], &["compress", "-o", "over.cmp"]] {
+        let result = hpcmp_in(&dir, [args, &["--max-compressed-size", &budget, "data.bin"]].concat());
+        assert_eq!(result.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains(&format!("data.bin: the stream is {} bytes, 100 over the --max-compressed-size of {}", len, budget)), "{}", stderr);
+        assert!(!dir.join("over.cmp").exists());
+    }
+    let result = hpcmp_in(&dir, ["recompress", "--max-compressed-size", &budget, "-o", "over.cmp", "free.cmp"]);
+    assert_eq!(result.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("100 over"));
+    assert!(!dir.join("over.cmp").exists());
+
+    assert_success(&hpcmp_in(&dir, ["compress", "--raw", "--max-compressed-size", &len.to_string(), "-o", "fits.cmp", "data.bin"]));
+    assert_eq!(fs::read(dir.join("fits.cmp")).unwrap(), fs::read(dir.join("free.cmp")).unwrap());
+}
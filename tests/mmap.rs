@@ -0,0 +1,42 @@
+//! `--mmap` must write the same output as decoding into memory, from where
+//! `--append` or `--at` say, and must leave nothing behind, or the file as
+//! it was, when the output isn't the size it was said to be.
+
+mod common;
+
+use std::fs;
+
+#[test]
+fn mapped_output() {
+    let data: Vec<u8> = (0..1_000_000u64).map(|i| ((i * i) >> 12) as u8).collect();
+    let dir = common::TempDir::new("mmap");
+    let (input, output) = (dir.join("in.cmp"), dir.join("out"));
+    fs::write(&input, common::compress(&data, Some(4000))).unwrap();
+
+    let run = |options: &[&str], size: usize| {
+        common::run(common::command().args(["-q", "--mmap", "--expected-size", &size.to_string()]).args(options).arg(&input).arg(&output)).status
+    };
+    assert!(run(&[], data.len()).success());
+    assert!(fs::read(&output).unwrap() == data);
+
+    fs::write(&output, b"head").unwrap();
+    assert!(run(&["--append"], data.len()).success());
+    assert!(fs::read(&output).unwrap() == [&b"head"[..], &data].concat());
+
+    // Past the end of the existing file, which is left alone under the page
+    // the mapping starts on
+    fs::write(&output, b"head").unwrap();
+    assert!(run(&["--at", "5000"], data.len()).success());
+    let written = fs::read(&output).unwrap();
+    assert!(written[..4] == *b"head" && written[4..5000].iter().all(|&b| b == 0) && written[5000..] == data);
+
+    for size in [data.len() - 1, data.len() + 1] {
+        fs::remove_file(&output).unwrap();
+        assert!(!run(&[], size).success());
+        assert!(!output.exists(), "a failed output was left behind for --expected-size {}", size);
+
+        fs::write(&output, b"head").unwrap();
+        assert!(!run(&["--append"], size).success());
+        assert_eq!(fs::read(&output).unwrap(), b"head");
+    }
+}
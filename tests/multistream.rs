@@ -0,0 +1,66 @@
+//! `--multistream` must decode streams stored back to back, concatenating
+//! their outputs or writing each to a file of its own, stopping at whatever
+//! follows the last and placing a failure in a later stream within the
+//! whole input.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::{assert_success, TempDir};
+
+fn hpcmp(options: &[&str], input: &Path, output: &Path) -> Output {
+    common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(input).arg(output))
+}
+
+#[test]
+fn decodes_streams_in_turn() {
+    let dir = TempDir::new("multistream");
+    fs::create_dir_all(dir.join("split")).unwrap();
+    let sections: Vec<Vec<u8>> = vec![
+        (0..6000u32).map(|i| (i % 251) as u8).collect(),
+        (0..4000u32).map(|i| (i / 9) as u8).collect(),
+        b"version 1.2.3\0".to_vec(),
+    ];
+    let streams: Vec<Vec<u8>> = sections.iter().map(|section| common::compress(section, Some(800))).collect();
+    let mut image = streams.concat();
+    image.extend([0xff; 37]);
+    let (input, output) = (dir.join("fw.bin"), dir.join("fw.out"));
+    fs::write(&input, &image).unwrap();
+
+    // Without it, only the first is decoded
+    let result = hpcmp(&[], &input, &output);
+    assert!(result.status.success());
+    assert!(fs::read(&output).unwrap() == sections[0]);
+
+    let result = hpcmp(&["--multistream", "concat"], &input, &output);
+    assert_success(&result);
+    assert!(fs::read(&output).unwrap() == sections.concat());
+    let warning: serde_json::Value = serde_json::from_slice(&result.stderr).unwrap();
+    assert_eq!(warning["warning"], "trailing_data");
+    assert_eq!(warning["byte_offset"], image.len() - 37);
+
+    assert_success(&common::run(common::command()
+        .args(["-q", "--multistream", "split", "-d"])
+        .arg(dir.join("split"))
+        .args(["--template", "{stem}_{index}_{offset:#x}"])
+        .arg(&input)));
+    let mut offset = 0;
+    for (index, (section, stream)) in sections.iter().zip(&streams).enumerate() {
+        let path = dir.join("split").join(format!("fw_{}_{:#x}", index, offset));
+        assert!(&fs::read(&path).unwrap() == section, "{}", path.display());
+        offset += stream.len();
+    }
+
+    // Cut short in the middle of the second stream
+    let cut = streams[0].len() + streams[1].len() / 2;
+    fs::write(&input, &image[..cut]).unwrap();
+    let result = hpcmp(&["--multistream", "concat"], &input, &output);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let json: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+    assert_eq!(json["code"], "unexpected_eof");
+    assert!(json["byte_offset"].as_u64().unwrap() > streams[0].len() as u64, "{}", stderr);
+}
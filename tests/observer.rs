@@ -0,0 +1,78 @@
+//! An observer must hear of each code as it is read, then of what it does:
+//! the dictionary entry it adds, the block it ends and the reset opening
+//! the next, in the order the decoder meets them, however the input is fed
+//! to it.
+
+mod common;
+
+use hpcmp::{Code, DecodeObserver, Decoder, ResetPoint, Suppression};
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Code(u64, u8, Code),
+    Insert(usize, u8, Code),
+    Suppressed(Suppression),
+    Reset(ResetPoint),
+    BlockEnd(u64, usize),
+}
+
+#[derive(Default)]
+struct Events(Vec<Event>);
+
+impl DecodeObserver for Events {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        self.0.push(Event::Code(bit_offset, width, code));
+    }
+
+    fn insert(&mut self, index: usize, value: u8, next: Code) {
+        self.0.push(Event::Insert(index, value, next));
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        self.0.push(Event::Suppressed(reason));
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        self.0.push(Event::Reset(*point));
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        self.0.push(Event::BlockEnd(output_offset, dictionary_len));
+    }
+}
+
+#[test]
+fn order() {
+    // Two blocks: "ab", adding the one entry, and "ab" again, ending with
+    // the end marker before the last literal
+    let stream = common::compress(b"abab", Some(2));
+    use Code::*;
+    let expected = [
+        Event::Code(0, 9, Command(1)),
+        Event::Reset(ResetPoint{ bit_offset: 0, input_offset: 2, output_offset: 0, prev_len: 0 }),
+        Event::Code(16, 9, Value(b'a')),
+        Event::Code(25, 9, Value(b'b')),
+        Event::Insert(0, b'b', Value(b'a')),
+        Event::Code(34, 9, Command(1)),
+        Event::BlockEnd(2, 1),
+        Event::Reset(ResetPoint{ bit_offset: 34, input_offset: 6, output_offset: 2, prev_len: 1 }),
+        Event::Code(48, 9, Value(b'a')),
+        Event::Code(57, 9, Command(3)),
+        Event::Code(72, 9, Value(b'b')),
+        Event::BlockEnd(4, 0),
+    ];
+
+    let mut events = Events::default();
+    let mut output = vec![];
+    Decoder::new().decode_to_vec_with(&stream, &mut output, &mut events).unwrap();
+    assert_eq!(output, b"abab");
+    assert_eq!(events.0, expected);
+
+    let mut events = Events::default();
+    let mut decoder = Decoder::new();
+    for byte in stream.chunks(1) {
+        decoder.decode_to_vec_with(byte, &mut vec![], &mut events).unwrap();
+    }
+    assert!(decoder.is_done());
+    assert_eq!(events.0, expected);
+}
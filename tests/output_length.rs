@@ -0,0 +1,41 @@
+//! `--output-length` must stop decoding after exactly that many bytes,
+//! warning unless the stream's end marker comes there, and fail a stream
+//! that ends sooner.
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn stops_at_length() {
+    let dir = TempDir::new("output-length");
+    let data: Vec<u8> = (0..30000u32).map(|i| (i * 3 / 7) as u8).collect();
+    let (input, output) = (dir.join("fw.cmp"), dir.join("fw.bin"));
+    fs::write(&input, common::compress(&data, Some(2500))).unwrap();
+    let run = |len: usize| -> Output {
+        common::run(common::command().args(["--errors-json", "--output-length", &len.to_string()]).arg(&input).arg(&output))
+    };
+
+    let result = run(data.len());
+    assert_success(&result);
+    assert!(result.stderr.is_empty(), "{}", String::from_utf8_lossy(&result.stderr));
+    assert!(fs::read(&output).unwrap() == data);
+
+    // In the middle of a block, and of a string
+    for len in [12345, 2500 * 3 + 1] {
+        let result = run(len);
+        assert_success(&result);
+        let warning: serde_json::Value = serde_json::from_slice(&result.stderr).unwrap();
+        assert_eq!(warning["warning"], "no_end_marker");
+        assert!(fs::read(&output).unwrap() == data[..len], "{}", len);
+    }
+
+    let result = run(data.len() + 1);
+    assert!(!result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+    assert_eq!(error["code"], "size_mismatch");
+}
@@ -0,0 +1,179 @@
+//! `hpcmp patch` must apply each format of patch to the stream it's pointed
+//! at, and only write the image back if the recompressed stream still fits.
+//!
+//! The bsdiff patches in `tests/patch` zero bytes 2000 to 4000 of what
+//! `tests/corpus/mix.cmp` decodes to, seeking back and forth through it on
+//! the way; their sections were compressed with Python's `bz2` module.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use common::{noise, TempDir};
+
+/// `stream` at 0x1000 in an image of noise.
+fn image(stream: &[u8]) -> Vec<u8> {
+    let mut image = noise(0x1000, 1);
+    image.extend_from_slice(stream);
+    image.extend(noise(0x800, 2));
+    image
+}
+
+fn patch(image: &Path, output: &Path, args: &[&str]) -> (Option<i32>, String, String) {
+    let output = common::run(common::command().arg("patch").arg(image).arg(output).args(args));
+    (output.status.code(), String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+/// What the stream at 0x1000 in `image` decodes to, checking it fills its
+/// slot of `slot` bytes with `fill` after its end.
+fn patched(image: &[u8], slot: usize, fill: u8) -> Vec<u8> {
+    let mut decoder = hpcmp::Decoder::new();
+    let mut out = vec![];
+    decoder.decode_to_vec(&image[0x1000..], &mut out).unwrap();
+    assert!(decoder.is_done());
+    let end = 0x1000 + decoder.total_in() as usize;
+    assert!(image[end..0x1000 + slot].iter().all(|&byte| byte == fill));
+    out
+}
+
+fn varint(mut n: u64) -> Vec<u8> {
+    let mut bytes = vec![n as u8 & 0x7f];
+    while n >= 0x80 {
+        n >>= 7;
+        bytes.insert(0, n as u8 | 0x80);
+    }
+    bytes
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+/// A VCDIFF patch of a single window over all of `source`, as xdelta3 would
+/// write one with its checksum and without secondary compression.
+fn vcdiff(source_len: usize, target: &[u8], data: &[u8], insts: &[u8], addresses: &[u8]) -> Vec<u8> {
+    let mut delta = varint(target.len() as u64);
+    delta.push(0);
+    for section in [data, insts, addresses] {
+        delta.extend(varint(section.len() as u64));
+    }
+    delta.extend_from_slice(&adler32(target).to_be_bytes());
+    for section in [data, insts, addresses] {
+        delta.extend_from_slice(section);
+    }
+    let mut patch = vec![0xd6, 0xc3, 0xc4, 0, 0, 0x05];
+    patch.extend(varint(source_len as u64));
+    patch.push(0);
+    patch.extend(varint(delta.len() as u64));
+    patch.extend(delta);
+    patch
+}
+
+#[test]
+fn applies_vcdiff() {
+    let dir = TempDir::new("patch-vcdiff");
+    let mut data: Vec<u8> = (0..2000).flat_map(|i| format!("line {}: calibration table\n", i % 37).into_bytes()).collect();
+    data.splice(5000..5000, noise(1000, 3));
+    let stream = common::compress(&data, Some(500));
+    let image_path = dir.join("image.bin");
+    fs::write(&image_path, image(&stream)).unwrap();
+
+    // Copies from the source, adds, runs, an overlapping copy from the
+    // target, and drops the noise
+    let mut target = data[..100].to_vec();
+    target.extend_from_slice(b"PATCHED BY VCDIFF");
+    target.extend([0xaa; 50]);
+    for i in 0..100 {
+        target.push(target[100 + i]);
+    }
+    target.extend_from_slice(&data[200..5000]);
+    target.extend_from_slice(&data[6000..]);
+    let mut insts = vec![19];
+    insts.extend(varint(100));
+    insts.push(1);
+    insts.extend(varint(17));
+    insts.push(0);
+    insts.extend(varint(50));
+    insts.push(19 + 16);
+    insts.extend(varint(100));
+    insts.push(19);
+    insts.extend(varint(4800));
+    insts.push(19);
+    insts.extend(varint(data.len() as u64 - 6000));
+    let addresses = [varint(0), varint(67), varint(200), varint(6000)].concat();
+    let patch_path = dir.join("changes.xdelta");
+    fs::write(&patch_path, vcdiff(data.len(), &target, b"PATCHED BY VCDIFF\xaa", &insts, &addresses)).unwrap();
+
+    let output = dir.join("patched.bin");
+    let (status, out, err) = patch(&image_path, &output, &["--apply", patch_path.to_str().unwrap()]);
+    assert_eq!(status, Some(0), "{}", err);
+    assert!(out.starts_with(&format!("0x00001000: {} bytes, {} decompressed\n", stream.len(), data.len())), "{}", out);
+    assert!(out.contains("fits with"), "{}", out);
+    let written = fs::read(&output).unwrap();
+    assert_eq!(patched(&written, stream.len(), 0xff), target);
+    assert_eq!(written[..0x1000], noise(0x1000, 1)[..]);
+
+    // Against other data, it fails its checksum
+    let other = common::compress(&vec![b'x'; data.len()], None);
+    fs::write(&image_path, image(&other)).unwrap();
+    let (status, _, err) = patch(&image_path, &output, &["--apply", patch_path.to_str().unwrap(), "--offset", "0x1000"]);
+    assert_eq!(status, Some(1));
+    assert!(err.contains("fails the patch's checksum"), "{}", err);
+}
+
+#[test]
+fn applies_bsdiff() {
+    let dir = TempDir::new("patch-bsdiff");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let stream = fs::read(root.join("corpus").join("mix.cmp")).unwrap();
+    let mut expected = hpcmp::decompress(&stream).unwrap();
+    expected[2000..4000].fill(0);
+    let image_path = dir.join("image.bin");
+    fs::write(&image_path, image(&stream)).unwrap();
+
+    for name in ["zeroed.bsdiff", "zeroed.endsley"] {
+        let output = dir.join(name).with_extension("bin");
+        let patch_path = root.join("patch").join(name);
+        let (status, _, err) = patch(&image_path, &output, &["--apply", patch_path.to_str().unwrap(), "--fill", "0"]);
+        assert_eq!(status, Some(0), "{}: {}", name, err);
+        assert_eq!(patched(&fs::read(&output).unwrap(), stream.len(), 0), expected, "{}", name);
+    }
+}
+
+#[test]
+fn reports_overflow() {
+    let dir = TempDir::new("patch-overflow");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    let stream = common::compress(&data, None);
+    let image_path = dir.join("image.bin");
+    fs::write(&image_path, image(&stream)).unwrap();
+
+    // Noise on the end doesn't compress
+    let added = noise(4000, 4);
+    let mut insts = vec![19];
+    insts.extend(varint(data.len() as u64));
+    insts.push(1);
+    insts.extend(varint(added.len() as u64));
+    let target = [&data[..], &added].concat();
+    let patch_path = dir.join("changes.xdelta");
+    fs::write(&patch_path, vcdiff(data.len(), &target, &added, &insts, &varint(0))).unwrap();
+
+    let output = dir.join("patched.bin");
+    let (status, out, _) = patch(&image_path, &output, &["--apply", patch_path.to_str().unwrap()]);
+    assert_eq!(status, Some(1));
+    assert!(out.contains(&format!("over the {}-byte slot", stream.len())), "{}", out);
+    assert!(!output.exists());
+
+    // With two streams, which one is meant has to be said
+    let mut two = image(&stream);
+    two.extend_from_slice(&stream);
+    fs::write(&image_path, two).unwrap();
+    let (status, _, err) = patch(&image_path, &output, &["--apply", patch_path.to_str().unwrap()]);
+    assert_eq!(status, Some(1));
+    assert!(err.contains("--offset"), "{}", err);
+}
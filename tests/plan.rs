@@ -0,0 +1,41 @@
+//! `hpcmp plan` must try every combination of settings on windows of an
+//! input, recommend the quickest estimated to fit a budget, and exit with 1
+//! when none is.
+
+mod common;
+
+use std::fs;
+use std::process::Output;
+
+fn plan(args: &[&str]) -> Output {
+    common::run(common::command().arg("plan").args(args))
+}
+
+#[test]
+fn plans() {
+    let dir = common::TempDir::new("plan");
+    let path = dir.join("in.bin");
+    let data: Vec<u8> = (0..20000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    fs::write(&path, &data).unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = plan(&["--windows", "4", "--window", "16K", "--max-compressed-size", "200K", "--confirm", path]);
+    common::assert_success(&output);
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.starts_with(&format!("input: {} bytes, 4 windows of 16384", data.len())), "{}", report);
+    // Running blocks on, or 4K at a time, with each policy and level
+    assert_eq!(report.lines().filter(|line| line.contains("--when-full")).count(), 2 * 3 * 2 + 1, "{}", report);
+    assert!(report.contains("--level max --when-full reset --block-len 4K"), "{}", report);
+    let recommended = report.lines().find(|line| line.starts_with("recommended: --level")).expect(&report);
+    let estimate: u64 = recommended.split("about ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+    let confirmed: u64 = report.lines().find(|line| line.starts_with("confirmed: ")).expect(&report)["confirmed: ".len()..]
+        .split(' ').next().unwrap().parse().unwrap();
+    assert!(confirmed <= 200 << 10);
+    assert!(confirmed.abs_diff(estimate) < estimate / 4, "estimated {}, came to {}", estimate, confirmed);
+
+    let output = plan(&["--windows", "2", "--window", "8K", "--max-compressed-size", "100", path]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("no settings fit: the smallest, "), "{}", report);
+    assert!(!report.contains("  fits"), "{}", report);
+}
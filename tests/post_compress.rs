@@ -0,0 +1,46 @@
+//! `--post-compress` must write each output compressed, under its name with
+//! the codec's extension added, and decompressing it again must give back
+//! the output, as it must for what goes to stdout, under no name. Printed
+//! digests are of the output itself.
+
+mod common;
+
+use std::fs;
+use std::io::Read;
+
+use common::{assert_success, hpcmp_in, TempDir};
+use sha2::{Digest, Sha256};
+
+#[test]
+fn round_trips() {
+    let dir = TempDir::new("post-compress");
+    let data: Vec<u8> = (0..4000).flat_map(|i| format!("frame {} of {}\n", i % 43, i % 9).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(700))).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+
+    for (codec, extension) in [("zstd", "zst"), ("xz", "xz")] {
+        let written = format!("out.bin.{}", extension);
+        let result = hpcmp_in(&dir, ["-q", "--sha256", "--post-compress", codec, "in.cmp", "out.bin"]);
+        assert_success(&result);
+        assert_eq!(String::from_utf8(result.stdout).unwrap(), format!("{}  {}\n", sha256, written));
+        assert!(!dir.join("out.bin").exists(), "{}", codec);
+        let compressed = fs::read(dir.join(&written)).unwrap();
+        assert!(compressed.len() < data.len() / 4, "{}: {} bytes", codec, compressed.len());
+        assert!(decompress(codec, &compressed) == data, "{}", codec);
+
+        let result = hpcmp_in(&dir, ["-q", "--post-compress", codec, "in.cmp", "-"]);
+        assert_success(&result);
+        assert!(decompress(codec, &result.stdout) == data, "{}", codec);
+    }
+}
+
+fn decompress(codec: &str, compressed: &[u8]) -> Vec<u8> {
+    match codec {
+        "zstd" => zstd::decode_all(compressed).unwrap(),
+        _      => {
+            let mut decompressed = vec![];
+            xz2::read::XzDecoder::new(compressed).read_to_end(&mut decompressed).unwrap();
+            decompressed
+        },
+    }
+}
@@ -0,0 +1,53 @@
+//! Inputs stored gzip, xz or zstd compressed must be unwrapped before they
+//! are decoded, unless `--pre-decompress off` says not to, and a stream
+//! that is damaged inside its wrapper must fail as it would unwrapped.
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+fn wrap(codec: &str, data: &[u8]) -> Vec<u8> {
+    match codec {
+        "gzip" => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        },
+        "xz" => {
+            let mut encoder = xz2::write::XzEncoder::new(vec![], 6);
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        },
+        _ => zstd::encode_all(data, 0).unwrap(),
+    }
+}
+
+#[test]
+fn unwraps() {
+    let dir = TempDir::new("pre-decompress");
+    let data: Vec<u8> = (0..2500).flat_map(|i| format!("log {} {}\n", i % 31, i % 6).into_bytes()).collect();
+    let stream = common::compress(&data, Some(600));
+
+    for (codec, name) in [("gzip", "Gzip"), ("xz", "Xz"), ("zstd", "Zstd")] {
+        fs::write(dir.join("in.cmp"), wrap(codec, &stream)).unwrap();
+        let result = hpcmp_in(&dir, ["-v", "in.cmp", "out.bin"]);
+        assert_success(&result);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains(&format!("in.cmp: unwrapping {} compression", name)), "{}: {}", codec, stderr);
+        assert!(fs::read(dir.join("out.bin")).unwrap() == data, "{}", codec);
+
+        let result = hpcmp_in(&dir, ["-q", "--errors-json", "--pre-decompress", "off", "in.cmp", "off.bin"]);
+        assert!(!result.status.success(), "{}", codec);
+        assert!(!dir.join("off.bin").exists(), "{}", codec);
+
+        fs::write(dir.join("bad.cmp"), wrap(codec, &stream[..stream.len() / 2])).unwrap();
+        let result = hpcmp_in(&dir, ["-q", "--errors-json", "bad.cmp", "bad.bin"]);
+        assert!(!result.status.success(), "{}", codec);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("\"code\":\"unexpected_eof\""), "{}: {}", codec, stderr);
+        assert!(!dir.join("bad.bin").exists(), "{}", codec);
+    }
+}
@@ -0,0 +1,47 @@
+//! `--preserve` must give each output the modification time and the
+//! permissions of its input, including those of a read-only one.
+
+mod common;
+
+use std::fs::{self, File};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::time::{Duration, SystemTime};
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn copies_times_and_permissions() {
+    let dir = TempDir::new("preserve");
+    let data: Vec<u8> = (0..3000u32).map(|i| (i * 13 / 7) as u8).collect();
+    let input = dir.join("in.cmp");
+    fs::write(&input, common::compress(&data, Some(1000))).unwrap();
+    let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_100_000_000);
+    File::options().write(true).open(&input).unwrap().set_modified(then).unwrap();
+    let mut permissions = fs::metadata(&input).unwrap().permissions();
+    #[cfg(unix)]
+    permissions.set_mode(0o440);
+    #[cfg(not(unix))]
+    permissions.set_readonly(true);
+    fs::set_permissions(&input, permissions).unwrap();
+
+    let cases: [(&[&str], &[&str]); 3] = [
+        (&["in.cmp", "out.bin"], &["out.bin"]),
+        (&["--chunk-size", "1K", "in.cmp", "out.bin"], &["out.bin"]),
+        (&["in.cmp", "--output", "out.bin", "--output", "copy.bin"], &["out.bin", "copy.bin"]),
+    ];
+    for (args, outputs) in cases {
+        for output in ["out.bin", "copy.bin"] {
+            let _ = fs::remove_file(dir.join(output));
+        }
+        assert_success(&hpcmp_in(&dir, [&["-q", "--preserve"], args].concat()));
+        for output in outputs {
+            let metadata = fs::metadata(dir.join(output)).unwrap();
+            assert!(fs::read(dir.join(output)).unwrap() == data, "{:?} {}", args, output);
+            assert_eq!(metadata.modified().unwrap(), then, "{:?} {}", args, output);
+            assert!(metadata.permissions().readonly(), "{:?} {}", args, output);
+            #[cfg(unix)]
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o440, "{:?} {}", args, output);
+        }
+    }
+}
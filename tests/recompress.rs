@@ -0,0 +1,31 @@
+//! `hpcmp recompress` must write a stream that decodes as the original did,
+//! saying how its size compares, and come back with the same stream from
+//! one of its own.
+
+mod common;
+
+use std::fs;
+
+#[test]
+fn recompresses() {
+    let dir = common::TempDir::new("recompress");
+    let data: Vec<u8> = (0..20000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    // Resetting far more often than it needs to
+    fs::write(dir.join("vendor.cmp"), common::compress(&data, Some(50))).unwrap();
+
+    let recompress = |args: &[&str]| {
+        let output = common::run(common::command().arg("recompress").args(args).current_dir(&dir));
+        common::assert_success(&output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let report = recompress(&["--block-len", "1M", "-o", "new.cmp", "vendor.cmp"]);
+    let new = fs::read(dir.join("new.cmp")).unwrap();
+    assert_eq!(hpcmp::decompress(&new).unwrap(), data);
+    let original = fs::metadata(dir.join("vendor.cmp")).unwrap().len();
+    assert!(report.contains(&format!("original: {} bytes, {} decompressed", original, data.len())), "{}", report);
+    assert!(report.contains(&format!("recompressed: {} bytes, {} bytes smaller", new.len(), original - new.len() as u64)), "{}", report);
+
+    let report = recompress(&["new.cmp"]);
+    assert!(report.contains("identical to the original"), "{}", report);
+}
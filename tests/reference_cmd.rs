@@ -0,0 +1,44 @@
+//! `--reference-cmd` must let an output through when the command decodes
+//! the stream the same, fed it on stdin or given `{in}` and `{out}`, and
+//! otherwise fail without writing it, saying where they part.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn agrees_or_not() {
+    let dir = TempDir::new("reference-cmd");
+    let data: Vec<u8> = (0..6000u32).map(|i| (i * 7 / 17) as u8).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(500))).unwrap();
+    fs::write(dir.join("same.bin"), &data).unwrap();
+    let mut other = data.clone();
+    other[4321] ^= 0x20;
+    fs::write(dir.join("other.bin"), &other).unwrap();
+    let itself = format!("'{}' -q {{in}} {{out}}", env!("CARGO_BIN_EXE_hpcmp"));
+
+    for command in ["cat same.bin", "cat >/dev/null; cat same.bin", &itself] {
+        let _ = fs::remove_file(dir.join("out.bin"));
+        assert_success(&hpcmp_in(&dir, ["-q", "--reference-cmd", command, "in.cmp", "out.bin"]));
+        assert!(fs::read(dir.join("out.bin")).unwrap() == data, "{}", command);
+    }
+
+    let _ = fs::remove_file(dir.join("out.bin"));
+    for (command, code, message) in [
+        ("cat other.bin", "reference_mismatch", "output differs from the reference at byte 0x10e1: 0x"),
+        ("head -c 100 same.bin", "reference_mismatch", "output is 6000 bytes and the reference's 100, but the same up to there"),
+        ("exit 3", "reference_failed", "reference command failed: exit status: 3"),
+    ] {
+        let result = hpcmp_in(&dir, ["-q", "--errors-json", "--reference-cmd", command, "in.cmp", "out.bin"]);
+        assert!(!result.status.success(), "{}", command);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+        assert_eq!(error["code"], code, "{}: {}", command, stderr);
+        assert!(error["message"].as_str().unwrap().starts_with(message), "{}: {}", command, stderr);
+        assert!(!dir.join("out.bin").exists(), "{}", command);
+    }
+}
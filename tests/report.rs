@@ -0,0 +1,55 @@
+//! `decompress_with_report` must describe each block of a stream, count its
+//! codes and note anything suspicious, and with the `serde` feature the
+//! report must come back from JSON just as it was.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use hpcmp::{Anomaly, AnomalyKind, BlockReport, CodeHistogram, StreamReport};
+
+/// As in observer.rs, "abab" in two blocks, then a byte after the end.
+fn stream() -> Vec<u8> {
+    let mut stream = common::compress(b"abab", Some(2));
+    stream.push(0xff);
+    stream
+}
+
+fn expected() -> StreamReport {
+    let block = BlockReport{ output_len: 2, codes: 3, max_width: 9, ..BlockReport::default() };
+    StreamReport{
+        compressed_len: 11,
+        decompressed_len: 4,
+        blocks: vec![
+            BlockReport{ bit_offset: 0, input_offset: 2, output_offset: 0, dictionary_len: 1, ..block.clone() },
+            BlockReport{ bit_offset: 34, input_offset: 6, output_offset: 2, dictionary_len: 0, ..block },
+        ],
+        histogram: CodeHistogram{
+            commands: [0, 2, 0, 1, 0, 0, 0, 0],
+            values: 4,
+            indices: 0,
+            widths: BTreeMap::from([(9, 7)]),
+        },
+        anomalies: vec![Anomaly{ bit_offset: 88, kind: AnomalyKind::TrailingData(1) }],
+    }
+}
+
+#[test]
+fn describes_blocks() {
+    let (data, report) = hpcmp::decompress_with_report(&stream()).unwrap();
+    assert_eq!(data, b"abab");
+    assert_eq!(report, expected());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn round_trips_json() {
+    let (_, report) = hpcmp::decompress_with_report(&stream()).unwrap();
+    let json = serde_json::to_string(&report).unwrap();
+    assert_eq!(serde_json::from_str::<StreamReport>(&json).unwrap(), report);
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["blocks"][1]["output_offset"], 2, "{}", json);
+    assert_eq!(value["histogram"]["widths"]["9"], 7, "{}", json);
+    assert_eq!(value["anomalies"][0]["kind"]["TrailingData"], 1, "{}", json);
+}
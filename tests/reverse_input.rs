@@ -0,0 +1,38 @@
+//! `--reverse-input` must decode a stream stored last byte first, however
+//! it is written out.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn decodes_reversed() {
+    let dir = TempDir::new("reverse-input");
+    let data: Vec<u8> = (0..9000u32).map(|i| (i * 3 / 7) as u8).collect();
+    let mut stream = common::compress(&data, Some(700));
+    stream.reverse();
+    let (input, output) = (dir.join("rom.cmp"), dir.join("rom.bin"));
+    fs::write(&input, &stream).unwrap();
+
+    let run = |options: &[&str]| {
+        let _ = fs::remove_file(&output);
+        common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(&input).arg(&output))
+    };
+    let backends: &[&str] = match cfg!(all(target_os = "linux", feature = "io-uring")) {
+        true  => &["std", "uring"],
+        false => &["std"],
+    };
+    for io in backends {
+        for options in [&[][..], &["--chunk-size", "1K"]] {
+            let result = run(&[&["--reverse-input", "--io", io][..], options].concat());
+            assert_success(&result);
+            assert!(fs::read(&output).unwrap() == data, "--io {} {:?}", io, options);
+        }
+    }
+
+    let result = run(&[]);
+    assert!(!result.status.success());
+    assert!(!output.exists());
+}
@@ -0,0 +1,108 @@
+//! Data compressed by the test encoder must decompress back to itself.
+//! proptest shrinks any failure to a minimal input, and keeps it in
+//! `tests/roundtrip.proptest-regressions` to be tried first from then on.
+
+mod common;
+
+use hpcmp::Decoder;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Data of varied sizes and entropy: random bytes, a small alphabet, long
+/// runs and repeated phrases.
+fn data() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        vec(any::<u8>(), 2..4096),
+        vec(0u8..4, 2..20_000),
+        vec((any::<u8>(), 1usize..2000), 1..20)
+            .prop_map(|runs| runs.into_iter().flat_map(|(byte, len)| std::iter::repeat_n(byte, len)).collect()),
+        (vec(any::<u8>(), 1..64), 1usize..500)
+            .prop_map(|(phrase, count)| phrase.repeat(count)),
+    ].prop_filter("the format holds at least two bytes", |data| data.len() >= 2)
+}
+
+proptest! {
+    #[test]
+    fn round_trip(data in data()) {
+        let stream = common::compress(&data, None);
+        prop_assert_eq!(hpcmp::decompress(&stream).unwrap(), data);
+    }
+
+    #[test]
+    fn round_trip_with_resets(data in data(), every in 1usize..200) {
+        let stream = common::compress(&data, Some(every));
+        prop_assert_eq!(hpcmp::decompress(&stream).unwrap(), data);
+    }
+
+    #[test]
+    fn round_trip_in_chunks(data in data(), size in 1usize..64) {
+        let stream = common::compress(&data, None);
+        let mut decoder = Decoder::new();
+        let mut out = vec![];
+        for chunk in stream.chunks(size) {
+            decoder.decode_to_vec(chunk, &mut out).unwrap();
+        }
+        prop_assert!(decoder.is_done());
+        prop_assert_eq!(out, data);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn round_trip_to_writer(data in data(), most in 1usize..5000) {
+        let stream = common::compress(&data, None);
+        let mut writer = Writes { most: usize::MAX, ..Writes::default() };
+        let mut codes = Codes(0);
+        prop_assert_eq!(hpcmp::decompress_to_writer_with(&stream, &mut writer, &mut codes).unwrap(), data.len() as u64);
+        // One call for each batch, ended by 64 KiB or 1024 chains
+        let batches = data.len().div_ceil(1 << 16) + codes.0.div_ceil(1024);
+        prop_assert!(writer.calls <= batches, "{} writes of {} bytes in {} codes", writer.calls, data.len(), codes.0);
+        prop_assert_eq!(writer.data, data.clone());
+
+        // Going on from part way through a slice
+        let mut writer = Writes { most, ..Writes::default() };
+        hpcmp::decompress_to_writer(&stream, &mut writer).unwrap();
+        prop_assert_eq!(writer.data, data);
+    }
+}
+
+/// Keeps what is written, at most `most` bytes a call, counting the calls
+/// it took.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Writes {
+    data: Vec<u8>,
+    calls: usize,
+    most: usize,
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Writes {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_vectored(&[std::io::IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[std::io::IoSlice]) -> std::io::Result<usize> {
+        self.calls += 1;
+        let start = self.data.len();
+        for buf in bufs {
+            let n = buf.len().min(self.most - (self.data.len() - start));
+            self.data.extend_from_slice(&buf[..n]);
+        }
+        Ok(self.data.len() - start)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Counts the codes read.
+#[cfg(feature = "std")]
+struct Codes(usize);
+
+#[cfg(feature = "std")]
+impl hpcmp::DecodeObserver for Codes {
+    fn code(&mut self, _bit_offset: u64, _width: u8, _code: hpcmp::Code) {
+        self.0 += 1;
+    }
+}
@@ -0,0 +1,83 @@
+//! `hpcmp scan` must find streams wherever they are in an image, however
+//! many resets inside them look like starts, and only where asked.
+
+mod common;
+
+use std::fs;
+
+use common::{noise, TempDir};
+
+/// `out` without the score at the end of each line.
+fn unscored(out: &str) -> String {
+    out.lines().map(|line| match line.rfind(", score ") {
+        Some(at) => format!("{}\n", &line[..at]),
+        None     => format!("{}\n", line),
+    }).collect()
+}
+
+#[test]
+fn finds_streams() {
+    let dir = TempDir::new("scan");
+    let data: Vec<u8> = (0..200_000u64).map(|i| ((i * i) >> 12) as u8).collect();
+    // Resets every few codes, some of which land on byte boundaries
+    let stream = common::compress(&data, Some(50));
+    let mut image = noise(0x1000, 1);
+    image.extend_from_slice(&stream);
+    image.extend(noise(0x2000 - image.len() % 0x1000, 2));
+    let second = image.len();
+    image.extend_from_slice(&stream);
+    image.extend(noise(0x1003, 3));
+    let truncated = image.len();
+    image.extend_from_slice(&stream[..stream.len() / 2]);
+    let path = dir.join("image.bin");
+    fs::write(&path, &image).unwrap();
+
+    let scan = |options: &[&str]| {
+        let output = common::run(common::command().arg("scan").args(options).arg(&path));
+        (output.status.code(), unscored(&String::from_utf8(output.stdout).unwrap()))
+    };
+    let whole = |offset: usize| format!("{:#010x} {} bytes, {} decompressed\n", offset, stream.len(), data.len());
+    assert_eq!(scan(&[]), (Some(0), whole(0x1000) + &whole(second)));
+
+    let (status, out) = scan(&["--partial"]);
+    assert_eq!(status, Some(0));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", out);
+    assert!(lines[2].starts_with(&format!("{:#010x} {} bytes", truncated, stream.len() / 2)), "{}", lines[2]);
+    assert!(lines[2].ends_with("before: Unexpected end of input"), "{}", lines[2]);
+
+    // The truncated one doesn't start on a sector boundary
+    assert_eq!(scan(&["--align", "0x1000", "--partial"]), (Some(0), whole(0x1000) + &whole(second)));
+    assert_eq!(scan(&["--min-output", "1M"]), (Some(1), String::new()));
+}
+
+#[test]
+fn scores_out_noise() {
+    let dir = TempDir::new("scan-scores");
+    let text: Vec<u8> = (0..3000).flat_map(|i| format!("line {}\n", i % 70).into_bytes()).collect();
+    // Unlike `noise`, the high bits of each step, which decode to a byte or
+    // two a few times a kilobyte
+    let mut state = 4u64;
+    let mut image: Vec<u8> = (0..0x20000).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 56) as u8
+    }).collect();
+    image.extend_from_slice(&common::compress(&text, Some(500)));
+    let path = dir.join("image.bin");
+    fs::write(&path, &image).unwrap();
+
+    let scan = |min_score: &str| {
+        let output = common::run(common::command().args(["scan", "--no-cache", "--partial", "--min-output", "1", "--min-score", min_score]).arg(&path));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let everything = scan("0");
+    assert!(everything.lines().count() > 10, "{}", everything);
+    let scores: Vec<u32> = everything.lines().map(|line| line.rsplit(' ').next().unwrap().parse().unwrap()).collect();
+    assert!(scores[..scores.len() - 1].iter().all(|&score| score < 40), "{}", everything);
+    assert!(*scores.last().unwrap() >= 60, "{}", everything);
+    let found = scan("40");
+    assert_eq!(found.lines().count(), 1, "{}", found);
+    assert!(found.starts_with("0x00020000 "), "{}", found);
+}
@@ -0,0 +1,82 @@
+//! Seeking a `Decompressor`, forward, back, from the end and past it, must
+//! read what the same offsets of the whole output hold.
+
+mod common;
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use hpcmp::Decompressor;
+
+fn data() -> Vec<u8> {
+    (0..50_000u64).map(|i| (((i * i) >> 9) ^ (i / 700)) as u8).collect()
+}
+
+/// `len` bytes from where `decompressor` is, or as many as there are.
+fn read(decompressor: &mut impl Read, len: usize) -> Vec<u8> {
+    let mut out = vec![];
+    decompressor.take(len as u64).read_to_end(&mut out).unwrap();
+    out
+}
+
+#[test]
+fn seek() {
+    let data = data();
+    let stream = common::compress(&data, Some(500));
+    assert_eq!(hpcmp::decompress(&stream).unwrap(), data);
+    let len = data.len() as u64;
+
+    let mut decompressor = Decompressor::new(Cursor::new(&stream));
+    // Forward past what's been decoded, then forward again among it
+    assert_eq!(decompressor.seek(SeekFrom::Start(20_000)).unwrap(), 20_000);
+    assert_eq!(read(&mut decompressor, 100), data[20_000..20_100]);
+    assert_eq!(decompressor.seek(SeekFrom::Current(1_000)).unwrap(), 21_100);
+    assert_eq!(read(&mut decompressor, 100), data[21_100..21_200]);
+    // Back, to blocks already seen and across several
+    assert_eq!(decompressor.seek(SeekFrom::Start(3)).unwrap(), 3);
+    assert_eq!(read(&mut decompressor, 5_000), data[3..5_003]);
+    assert_eq!(decompressor.seek(SeekFrom::Current(-2_000)).unwrap(), 3_003);
+    assert_eq!(read(&mut decompressor, 10), data[3_003..3_013]);
+    // Relative to the end
+    assert_eq!(decompressor.seek(SeekFrom::End(-100)).unwrap(), len - 100);
+    assert_eq!(read(&mut decompressor, 1_000), data[data.len() - 100..]);
+    assert_eq!(decompressor.seek(SeekFrom::End(-40_000)).unwrap(), len - 40_000);
+    assert_eq!(read(&mut decompressor, 100), data[data.len() - 40_000..data.len() - 39_900]);
+    assert_eq!(decompressor.seek(SeekFrom::End(0)).unwrap(), len);
+    assert!(read(&mut decompressor, 1).is_empty());
+    // Past the end stops there
+    assert_eq!(decompressor.seek(SeekFrom::Start(len + 1_000)).unwrap(), len);
+    assert!(read(&mut decompressor, 1).is_empty());
+    assert_eq!(decompressor.seek(SeekFrom::Start(0)).unwrap(), 0);
+    assert_eq!(read(&mut decompressor, data.len()), data);
+    // Before the start fails, and leaves the position as it was
+    assert!(decompressor.seek(SeekFrom::Current(-(len as i64) - 1)).is_err());
+    assert_eq!(decompressor.stream_position().unwrap(), len);
+}
+
+/// A stream that starts part way into its input, as in an image.
+#[test]
+fn seek_within_image() {
+    let data = data();
+    let mut image = vec![0xff; 777];
+    image.extend(common::compress(&data, Some(500)));
+    let mut input = Cursor::new(&image);
+    input.set_position(777);
+
+    let mut decompressor = Decompressor::new(input);
+    assert_eq!(read(&mut decompressor, 30_000), data[..30_000]);
+    assert_eq!(decompressor.seek(SeekFrom::Start(10_000)).unwrap(), 10_000);
+    assert_eq!(read(&mut decompressor, 100), data[10_000..10_100]);
+}
+
+#[test]
+fn seek_with_index() {
+    let data = data();
+    let stream = common::compress(&data, Some(500));
+    let index = hpcmp::build_index(&stream[..]).unwrap();
+
+    let mut decompressor = Decompressor::with_index(Cursor::new(&stream), &index);
+    assert_eq!(decompressor.seek(SeekFrom::Start(40_000)).unwrap(), 40_000);
+    assert_eq!(read(&mut decompressor, 100), data[40_000..40_100]);
+    assert_eq!(decompressor.seek(SeekFrom::Start(1_234)).unwrap(), 1_234);
+    assert_eq!(read(&mut decompressor, 100), data[1_234..1_334]);
+}
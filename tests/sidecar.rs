@@ -0,0 +1,66 @@
+//! `--sidecar` must write a line to `<output>.trace` for every decode event
+//! and the stream's report to `<output>.stats.json`, and keep the trace of a
+//! stream that fails to decode, without a report.
+
+mod common;
+
+use std::fmt::Write;
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+use hpcmp::{Code, DecodeObserver, ResetPoint, StreamReport, Suppression};
+
+/// The trace the decode of a stream should leave.
+#[derive(Default)]
+struct Expected(String);
+
+impl DecodeObserver for Expected {
+    fn code(&mut self, bit_offset: u64, width: u8, code: Code) {
+        writeln!(self.0, "code {} w{} {:?}", bit_offset, width, code).unwrap();
+    }
+
+    fn insert(&mut self, index: usize, value: u8, next: Code) {
+        writeln!(self.0, "insert {} {:#04x} {:?}", index, value, next).unwrap();
+    }
+
+    fn suppressed(&mut self, reason: Suppression) {
+        writeln!(self.0, "suppressed {:?}", reason).unwrap();
+    }
+
+    fn reset(&mut self, point: &ResetPoint) {
+        writeln!(self.0, "reset {} in={} out={} prev_len={}", point.bit_offset, point.input_offset, point.output_offset, point.prev_len).unwrap();
+    }
+
+    fn block_end(&mut self, output_offset: u64, dictionary_len: usize) {
+        writeln!(self.0, "block_end out={} dict={}", output_offset, dictionary_len).unwrap();
+    }
+}
+
+#[test]
+fn writes_trace_and_stats() {
+    let dir = TempDir::new("sidecar");
+    let data: Vec<u8> = (0..1500).flat_map(|i| format!("cell {} {}\n", i % 23, i % 5).into_bytes()).collect();
+    let stream = common::compress(&data, Some(500));
+    fs::write(dir.join("in.cmp"), &stream).unwrap();
+
+    assert_success(&hpcmp_in(&dir, ["-q", "--sidecar", "in.cmp", "out.bin"]));
+    assert!(fs::read(dir.join("out.bin")).unwrap() == data);
+    let mut expected = Expected::default();
+    let mut decoder = hpcmp::Decoder::new();
+    decoder.decode_to_vec_with(&stream, &mut vec![], &mut expected).unwrap();
+    let trace = fs::read_to_string(dir.join("out.bin.trace")).unwrap();
+    assert!(trace.matches("\nreset ").count() >= 2, "{}", trace);
+    assert!(trace == expected.0);
+
+    let stats: StreamReport = serde_json::from_slice(&fs::read(dir.join("out.bin.stats.json")).unwrap()).unwrap();
+    let (_, report) = hpcmp::decompress_with_report(&stream).unwrap();
+    assert_eq!(stats, report);
+    assert_eq!(stats.decompressed_len, data.len() as u64);
+
+    // Cut short, which still leaves the trace up to there
+    fs::write(dir.join("short.cmp"), &stream[..stream.len() / 2]).unwrap();
+    assert!(!hpcmp_in(&dir, ["-q", "--sidecar", "short.cmp", "short.bin"]).status.success());
+    let trace = fs::read_to_string(dir.join("short.bin.trace")).unwrap();
+    assert!(!trace.is_empty() && expected.0.starts_with(&trace));
+    assert!(!dir.join("short.bin.stats.json").exists());
+}
@@ -0,0 +1,48 @@
+//! `--skip-existing` must leave an output that is already there alone, its
+//! contents and its modification time, and with `=verify`, write it again
+//! unless it matches its digest in the `--manifest`.
+
+mod common;
+
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn leaves_outputs_alone() {
+    let dir = TempDir::new("skip-existing");
+    let data: Vec<u8> = (0..2000).flat_map(|i| format!("row {}\n", i % 47).into_bytes()).collect();
+    fs::write(dir.join("in.cmp"), common::compress(&data, Some(800))).unwrap();
+    let output = dir.join("out.bin");
+    let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let age = |contents: &[u8]| {
+        fs::write(&output, contents).unwrap();
+        File::options().write(true).open(&output).unwrap().set_modified(then).unwrap();
+    };
+
+    assert_success(&hpcmp_in(&dir, ["-q", "--skip-existing", "in.cmp", "out.bin"]));
+    assert!(fs::read(&output).unwrap() == data);
+    for options in [&[][..], &["--chunk-size", "1K"]] {
+        age(b"left from before");
+        let result = hpcmp_in(&dir, [&["-v", "--skip-existing"], options, &["in.cmp", "out.bin"]].concat());
+        assert_success(&result);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("in.cmp: skipping, output already exists"), "{:?}: {}", options, stderr);
+        assert_eq!(fs::read(&output).unwrap(), b"left from before", "{:?}", options);
+        assert_eq!(fs::metadata(&output).unwrap().modified().unwrap(), then, "{:?}", options);
+    }
+
+    // Verified, only what the manifest has a digest for is left alone
+    fs::remove_file(&output).unwrap();
+    assert_success(&hpcmp_in(&dir, ["-q", "--manifest", "SUMS", "in.cmp", "out.bin"]));
+    File::options().write(true).open(&output).unwrap().set_modified(then).unwrap();
+    assert_success(&hpcmp_in(&dir, ["-q", "--skip-existing=verify", "--manifest", "SUMS", "in.cmp", "out.bin"]));
+    assert_eq!(fs::metadata(&output).unwrap().modified().unwrap(), then);
+    age(b"damaged");
+    let result = hpcmp_in(&dir, ["-v", "--skip-existing=verify", "--manifest", "SUMS", "in.cmp", "out.bin"]);
+    assert_success(&result);
+    assert!(String::from_utf8_lossy(&result.stderr).contains("out.bin: doesn't match the manifest, extracting again"));
+    assert!(fs::read(&output).unwrap() == data);
+    assert_ne!(fs::metadata(&output).unwrap().modified().unwrap(), then);
+}
@@ -0,0 +1,39 @@
+//! `--sparse` must write the same output as writing it whole, leaving its
+//! runs of zeros as holes in a new file, and overwriting them in an
+//! existing one.
+
+mod common;
+
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn leaves_holes() {
+    let dir = TempDir::new("sparse");
+    // Ending on zeros, which leave a hole at the end of the file
+    let text: Vec<u8> = (0..4000).flat_map(|i| format!("record {} of {}\n", i % 53, i % 7).into_bytes()).collect();
+    let data = [&text[..], &[0; 1 << 20], &text, &[0; 100_000]].concat();
+    let (input, dense, sparse) = (dir.join("flash.cmp"), dir.join("dense.bin"), dir.join("sparse.bin"));
+    fs::write(&input, common::compress(&data, Some(5000))).unwrap();
+    assert_success(&common::run(common::command().arg("-q").arg(&input).arg(&dense)));
+
+    for options in [&[][..], &["--chunk-size", "5000"]] {
+        let _ = fs::remove_file(&sparse);
+        assert_success(&common::run(common::command().args(["-q", "--sparse"]).args(options).arg(&input).arg(&sparse)));
+        assert!(fs::read(&sparse).unwrap() == data, "{:?}", options);
+        // Where the filesystem makes holes at all
+        #[cfg(unix)]
+        {
+            let (dense, sparse) = (fs::metadata(&dense).unwrap().blocks(), fs::metadata(&sparse).unwrap().blocks());
+            assert!(dense.saturating_sub(sparse) * 512 >= 1 << 20, "{} blocks sparse, {} dense, with {:?}", sparse, dense, options);
+        }
+    }
+
+    // Zeros over what's already in the file are written, not skipped
+    fs::write(&sparse, vec![0xff; text.len() + 10_000]).unwrap();
+    assert_success(&common::run(common::command().args(["-q", "--sparse", "--at", "0"]).arg(&input).arg(&sparse)));
+    assert!(fs::read(&sparse).unwrap() == data);
+}
@@ -0,0 +1,46 @@
+//! Outputs that aren't regular files, like devices and pipes, must be
+//! written straight through, and a reader stopping early must not fail them.
+
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use common::{assert_success, TempDir};
+
+fn stream(name: &str) -> (TempDir, PathBuf, Vec<u8>) {
+    let data: Vec<u8> = (0..2_000_000u64).map(|i| ((i * i) >> 13) as u8).collect();
+    let dir = TempDir::new(&format!("streams-{}", name));
+    let path = dir.join("in.cmp");
+    fs::write(&path, common::compress(&data, Some(5000))).unwrap();
+    (dir, path, data)
+}
+
+#[cfg(unix)]
+#[test]
+fn writes_to_a_device() {
+    let (_dir, input, _) = stream("device");
+    for options in [&[][..], &["--sparse", "--append"][..]] {
+        assert_success(&common::run(common::command().args(options).arg(&input).arg("/dev/null")));
+    }
+}
+
+#[test]
+fn reader_stopping_early() {
+    let (_dir, input, data) = stream("early");
+    let mut child = common::command()
+        .args(["-v", "--sha256"])
+        .arg(&input)
+        .arg("-")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("running hpcmp");
+    let mut start = [0; 100];
+    child.stdout.take().unwrap().read_exact(&mut start).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(start == data[..100]);
+    assert_success(&output);
+}
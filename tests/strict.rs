@@ -0,0 +1,44 @@
+//! `--strict` must fail a stream on the anomalies otherwise only warned of,
+//! saying where, and let a conforming one through.
+
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+
+#[test]
+fn rejects_anomalies() {
+    let dir = TempDir::new("strict");
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 11 / 13) as u8).collect();
+    let good = common::compress(&data, Some(400));
+    let mut trailing = good.clone();
+    trailing.extend_from_slice(&[0xff; 7]);
+    // A block for every literal, each reset closing one of a single literal
+    let redundant = common::compress(&data[..20], Some(1));
+    let (input, output) = (dir.join("in.cmp"), dir.join("out.bin"));
+
+    let run = |stream: &[u8], options: &[&str]| {
+        let _ = fs::remove_file(&output);
+        fs::write(&input, stream).unwrap();
+        common::run(common::command().args(["-q", "--errors-json", "--strict"]).args(options).arg(&input).arg(&output))
+    };
+    for options in [&[][..], &["--chunk-size", "1K"]] {
+        let result = run(&good, options);
+        assert!(result.status.success(), "{:?}: {}", options, String::from_utf8_lossy(&result.stderr));
+        assert!(fs::read(&output).unwrap() == data, "{:?}", options);
+
+        for (stream, code, byte_offset) in [(&trailing, "trailing_data", Some(good.len())), (&redundant, "redundant_reset", None)] {
+            let result = run(stream, options);
+            assert!(!result.status.success(), "{} {:?}", code, options);
+            assert!(!output.exists(), "{} {:?}", code, options);
+            let stderr = String::from_utf8_lossy(&result.stderr);
+            let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+            assert_eq!(error["code"], code, "{}", stderr);
+            assert!(error["bit_offset"].is_u64(), "{}", stderr);
+            if let Some(byte_offset) = byte_offset {
+                assert_eq!(error["byte_offset"], byte_offset, "{}", stderr);
+            }
+        }
+    }
+}
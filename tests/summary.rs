@@ -0,0 +1,42 @@
+//! Each input decoded must get a line of its sizes, ratio, blocks and time,
+//! and a batch of more than one their total, all of which `--quiet` must
+//! leave off.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn sizes_and_total() {
+    let dir = TempDir::new("summary");
+    let mut sizes = vec![];
+    for (i, name) in ["a", "b", "c"].iter().enumerate() {
+        let data: Vec<u8> = (0..1000u32).map(|j| (j * (i as u32 + 3) / 7) as u8).collect();
+        let stream = common::compress(&data, None);
+        sizes.push(stream.len());
+        fs::write(dir.join(format!("{}.cmp", name)), stream).unwrap();
+    }
+    fs::create_dir(dir.join("out")).unwrap();
+
+    let result = hpcmp_in(&dir, ["-d", "out", "a.cmp", "b.cmp", "c.cmp"]);
+    assert_success(&result);
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stderr);
+    for ((line, name), size) in lines.iter().zip(["a", "b", "c"]).zip(&sizes) {
+        let ratio = format!("({:.1}x)", 1000.0 / *size as f64);
+        let expected = format!("{}.cmp: {} B -> 1000 B {}, 1 block, ", name, size, ratio);
+        assert!(line.starts_with(&expected), "not {}: {}", expected, stderr);
+        assert!(line.ends_with(" ms") || line.ends_with(" s"), "{}", line);
+    }
+    let total = format!("total: 3 files, {:.1} KiB -> 2.9 KiB ", sizes.iter().sum::<usize>() as f64 / 1024.0);
+    assert!(lines[3].starts_with(&total) && lines[3].contains("x), 3 blocks, "), "not {}: {}", total, stderr);
+
+    for quiet in ["-q", "--quiet"] {
+        let result = hpcmp_in(&dir, [quiet, "-d", "out", "a.cmp", "b.cmp", "c.cmp"]);
+        assert_success(&result);
+        assert!(result.stderr.is_empty(), "{}: {}", quiet, String::from_utf8_lossy(&result.stderr));
+    }
+}
@@ -0,0 +1,51 @@
+//! `--summary-csv` must write a row per input under a header written only
+//! once, saying whether it was decoded, skipped or failed and why, with its
+//! sizes, blocks, time and, with `--sha256`, its output's digest.
+
+mod common;
+
+use std::fs;
+
+use sha2::{Digest, Sha256};
+
+use common::{hpcmp_in, TempDir};
+
+#[test]
+fn rows() {
+    let dir = TempDir::new("summary-csv");
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 3 / 7) as u8).collect();
+    let stream = common::compress(&data, Some(400));
+    fs::write(dir.join("good.cmp"), &stream).unwrap();
+    fs::write(dir.join("kept.cmp"), &stream).unwrap();
+    // As in invalid_index.rs, an index to nothing; and a name to be quoted
+    fs::write(dir.join("bad,name.cmp"), [0x01, 0x00, 0x08, 0x12, 0x34, 0x1c, 0x00, 0x0a, 0x00]).unwrap();
+    fs::create_dir(dir.join("out")).unwrap();
+    fs::write(dir.join("out/kept"), b"already here").unwrap();
+
+    let args = ["-q", "--sha256", "--skip-existing", "--summary-csv", "summary.csv", "-d", "out", "good.cmp", "bad,name.cmp", "kept.cmp"];
+    for _ in 0..2 {
+        let result = hpcmp_in(&dir, args);
+        assert_eq!(result.status.code(), Some(2), "{}", String::from_utf8_lossy(&result.stderr));
+        fs::remove_file(dir.join("out/good")).unwrap();
+    }
+
+    let csv = fs::read_to_string(dir.join("summary.csv")).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 7, "{}", csv);
+    assert_eq!(lines[0], "path,status,error,input_bytes,output_bytes,ratio,blocks,seconds,sha256");
+    let sha256: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+    for run in lines[1..].chunks(3) {
+        let good: Vec<&str> = run[0].split(',').collect();
+        assert_eq!(good[..5], ["good.cmp", "ok", "", &stream.len().to_string(), "5000"], "{}", csv);
+        assert_eq!(good[5], format!("{:.4}", 5000.0 / stream.len() as f64), "{}", csv);
+        assert!(good[6].parse::<u64>().unwrap() > 1, "{}", csv);
+        assert!(good[7].parse::<f64>().is_ok() && good[7].split_once('.').unwrap().1.len() == 6, "{}", csv);
+        assert_eq!(good[8], sha256, "{}", csv);
+
+        assert!(run[1].starts_with("\"bad,name.cmp\",failed,invalid_index,"), "{}", csv);
+        let kept: Vec<&str> = run[2].split(',').collect();
+        assert_eq!(kept[..7], ["kept.cmp", "skipped", "", &stream.len().to_string(), "0", "0.0000", "0"], "{}", csv);
+        assert_eq!(kept[8], "", "{}", csv);
+    }
+    assert_eq!(fs::read(dir.join("out/kept")).unwrap(), b"already here");
+}
@@ -0,0 +1,32 @@
+//! `--swap` must reverse the bytes of each word of the output, and refuse
+//! an output that doesn't end on a whole word.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn swaps_words() {
+    let dir = TempDir::new("swap");
+    let data: Vec<u8> = (0..6000u32).map(|i| (i * 7 / 5) as u8).collect();
+    let (input, odd, output) = (dir.join("rom.cmp"), dir.join("odd.cmp"), dir.join("rom.bin"));
+    fs::write(&input, common::compress(&data, Some(500))).unwrap();
+    fs::write(&odd, common::compress(&data[..5999], None)).unwrap();
+
+    let run = |bits: &str, input: &std::path::Path| {
+        common::run(common::command().args(["-q", "--errors-json", "--swap", bits]).arg(input).arg(&output))
+    };
+    for (bits, word) in [("16", 2), ("32", 4)] {
+        assert_success(&run(bits, &input));
+        let expected: Vec<u8> = data.chunks(word).flat_map(|word| word.iter().rev()).copied().collect();
+        assert!(fs::read(&output).unwrap() == expected, "--swap {}", bits);
+    }
+
+    let _ = fs::remove_file(&output);
+    let result = run("16", &odd);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("\"code\":\"swap_partial_word\""));
+    assert!(!output.exists());
+}
@@ -0,0 +1,57 @@
+//! A tar archive given with `--out-dir` must have its members decompressed
+//! straight out of it, only those matching `--member`, saying so when none
+//! do, and failing if any member does.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(vec![]);
+    for (name, contents) in members {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, *contents).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+#[test]
+fn extracts_members() {
+    let dir = TempDir::new("tar");
+    let first: Vec<u8> = (0..3000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let second: Vec<u8> = (0..900).flat_map(|i| format!("entry {}\n", i % 17).into_bytes()).collect();
+    let (a, b) = (common::compress(&first, Some(400)), common::compress(&second, None));
+    fs::write(dir.join("fw.tar"), archive(&[("fw/a.cmp", &a), ("fw/b.cmp", &b), ("README", b"not a stream")])).unwrap();
+    for out in ["out", "only", "none", "all"] {
+        fs::create_dir(dir.join(out)).unwrap();
+    }
+
+    assert_success(&hpcmp_in(&dir, ["-q", "--member", "*.cmp", "-d", "out", "fw.tar"]));
+    assert!(fs::read(dir.join("out/a")).unwrap() == first);
+    assert!(fs::read(dir.join("out/b")).unwrap() == second);
+    assert!(!dir.join("out/README").exists());
+
+    assert_success(&hpcmp_in(&dir, ["-q", "--member", "fw/b*", "-d", "only", "fw.tar"]));
+    assert!(fs::read(dir.join("only/b")).unwrap() == second);
+    assert!(!dir.join("only/a").exists());
+
+    let result = hpcmp_in(&dir, ["-v", "--member", "*.bin", "-d", "none", "fw.tar"]);
+    assert_success(&result);
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert!(stderr.contains("fw.tar: no members match *.bin"), "{}", stderr);
+
+    // Every member is tried, and the archive fails for the one that does
+    let result = hpcmp_in(&dir, ["-q", "-d", "all", "fw.tar"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("1 of 3 members failed"));
+    assert!(fs::read(dir.join("all/b")).unwrap() == second);
+
+    let result = hpcmp_in(&dir, ["-q", "fw.tar", "fw.bin"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("tar archives can only be decompressed with --out-dir"));
+}
@@ -0,0 +1,117 @@
+//! With the `tracing` feature, each block must get a `block` span giving
+//! its index and where its output starts, entered while it's decoded and
+//! given its codes and bytes once it ends, and each call into the decoder a
+//! `refill` event of what it took and gave.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use hpcmp::Decoder;
+
+/// Values by field name.
+#[derive(Debug, Default)]
+struct Fields(BTreeMap<String, String>);
+
+#[derive(Debug, Default)]
+struct Log {
+    /// Named, with their fields, by id less one.
+    spans: Vec<(String, Fields)>,
+    /// Times each span was entered.
+    entered: Vec<usize>,
+    /// With the span they were in, if any.
+    events: Vec<(Option<usize>, Fields)>,
+    /// The spans entered, innermost last.
+    stack: Vec<usize>,
+}
+
+struct Recorder(Arc<Mutex<Log>>);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut log = self.0.lock().unwrap();
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        log.spans.push((span.metadata().name().to_string(), fields));
+        log.entered.push(0);
+        Id::from_u64(log.spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record) {
+        let mut log = self.0.lock().unwrap();
+        values.record(&mut log.spans[span.into_u64() as usize - 1].1);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let mut log = self.0.lock().unwrap();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let span = log.stack.last().copied();
+        log.events.push((span, fields));
+    }
+
+    fn enter(&self, span: &Id) {
+        let mut log = self.0.lock().unwrap();
+        let span = span.into_u64() as usize - 1;
+        log.entered[span] += 1;
+        log.stack.push(span);
+    }
+
+    fn exit(&self, _span: &Id) {
+        self.0.lock().unwrap().stack.pop();
+    }
+}
+
+/// What decoding `stream` fed `chunk` bytes at a time logs.
+fn log(stream: &[u8], chunk: usize) -> Log {
+    let log = Arc::new(Mutex::new(Log::default()));
+    tracing::subscriber::with_default(Recorder(log.clone()), || {
+        let mut decoder = Decoder::new();
+        let mut output = vec![];
+        for input in stream.chunks(chunk) {
+            decoder.decode_to_vec(input, &mut output).unwrap();
+        }
+        assert!(decoder.is_done());
+    });
+    Arc::try_unwrap(log).unwrap().into_inner().unwrap()
+}
+
+#[test]
+fn spans_and_events() {
+    // As in observer.rs, "abab" in two blocks
+    let stream = common::compress(b"abab", Some(2));
+    for chunk in [stream.len(), 1] {
+        let log = log(&stream, chunk);
+        let fields = |fields: &Fields| fields.0.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join(" ");
+        let spans: Vec<_> = log.spans.iter().map(|(name, span)| format!("{} {}", name, fields(span))).collect();
+        // Counting the reset opening each block, and the end marker
+        assert_eq!(spans, ["block bytes=2 codes=3 index=0 offset=0", "block bytes=2 codes=4 index=1 offset=2"], "chunk {}", chunk);
+        assert!(log.entered.iter().all(|&entered| entered > 0), "chunk {}: {:?}", chunk, log);
+        assert!(log.stack.is_empty(), "chunk {}: {:?}", chunk, log);
+
+        let resets: Vec<_> = log.events.iter().filter(|(_, event)| event.0["message"] == "dictionary reset").map(|&(span, _)| span).collect();
+        assert_eq!(resets, [Some(0), Some(1)], "chunk {}", chunk);
+        let refills: Vec<_> = log.events.iter().map(|(_, event)| event).filter(|event| event.0["message"] == "refill").collect();
+        assert_eq!(refills.len(), stream.len() / chunk, "chunk {}", chunk);
+        let total = |field: &str| refills.iter().map(|event| event.0[field].parse::<usize>().unwrap()).sum::<usize>();
+        assert_eq!((total("consumed"), total("produced")), (stream.len(), 4), "chunk {}", chunk);
+    }
+}
@@ -0,0 +1,39 @@
+//! `--time` must give each input's duration and throughput, and their total
+//! after a batch of more than one, even with `--quiet`.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, hpcmp_in, TempDir};
+
+#[test]
+fn durations() {
+    let dir = TempDir::new("time");
+    for (i, name) in ["a", "b", "c"].iter().enumerate() {
+        let data: Vec<u8> = (0..1000u32).map(|j| (j * (i as u32 + 3) / 7) as u8).collect();
+        fs::write(dir.join(format!("{}.cmp", name)), common::compress(&data, None)).unwrap();
+    }
+    fs::create_dir(dir.join("out")).unwrap();
+
+    let result = hpcmp_in(&dir, ["-q", "--time", "-d", "out", "a.cmp", "b.cmp", "c.cmp"]);
+    assert_success(&result);
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stderr);
+    for (line, name) in lines.iter().zip(["a.cmp: ", "b.cmp: ", "c.cmp: ", "total: 3 files, "]) {
+        let timing = line.strip_prefix(name).unwrap_or_else(|| panic!("not {}: {}", name, stderr));
+        let (seconds, rates) = timing.split_once(" s, in ").unwrap_or_else(|| panic!("{}", line));
+        assert!(seconds.parse::<f64>().is_ok() && seconds.split_once('.').unwrap().1.len() == 3, "{}", line);
+        assert!(rates.ends_with(" MB/s") && rates.contains(" MB/s, out "), "{}", line);
+    }
+
+    // Just the one input, with no total, and the summary from without -q
+    let result = hpcmp_in(&dir, ["--time", "-d", "out", "a.cmp"]);
+    assert_success(&result);
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", stderr);
+    assert!(lines[0].starts_with("a.cmp: ") && lines[0].contains(" -> "), "{}", stderr);
+    assert!(lines[1].starts_with("a.cmp: ") && lines[1].contains(" s, in "), "{}", stderr);
+}
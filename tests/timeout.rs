@@ -0,0 +1,38 @@
+//! `--timeout` must give up on an input that takes too long, saying how far
+//! it got and leaving no output behind, and let one that doesn't through.
+
+mod common;
+
+use std::fs;
+
+use common::TempDir;
+
+#[test]
+fn gives_up() {
+    let dir = TempDir::new("timeout");
+    let data: Vec<u8> = (0..1u32 << 22).map(|i| (i % 251 / 50) as u8).collect();
+    let (input, output) = (dir.join("big.cmp"), dir.join("big.bin"));
+    fs::write(&input, common::compress(&data, None)).unwrap();
+
+    let run = |options: &[&str]| {
+        let _ = fs::remove_file(&output);
+        common::run(common::command().args(["-q", "--errors-json"]).args(options).arg(&input).arg(&output))
+    };
+    for options in [&[][..], &["--chunk-size", "64K"], &["--mmap", "--expected-size", "4M"]] {
+        let result = run(&[&["--timeout", "1ms"][..], options].concat());
+        assert!(!result.status.success(), "{:?}", options);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        let error: serde_json::Value = serde_json::from_str(stderr.lines().next().unwrap()).unwrap();
+        assert_eq!(error["code"], "timeout", "{:?}: {}", options, stderr);
+        assert!(error["message"].as_str().unwrap().contains("bytes decoded"), "{}", stderr);
+        assert!(!output.exists(), "{:?}", options);
+
+        let result = run(&[&["--timeout", "10m"][..], options].concat());
+        assert!(result.status.success(), "{:?}: {}", options, String::from_utf8_lossy(&result.stderr));
+        assert!(fs::read(&output).unwrap() == data, "{:?}", options);
+    }
+
+    let result = run(&["--timeout", "30 s"]);
+    assert!(!result.status.success());
+    assert!(String::from_utf8_lossy(&result.stderr).contains("--timeout"));
+}
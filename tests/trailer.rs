@@ -0,0 +1,40 @@
+//! Bytes after the end of a stream must be reported with where the stream
+//! ended, and kept with `--save-trailer` however the output is written.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn saves_trailer() {
+    let dir = TempDir::new("trailer");
+    let data: Vec<u8> = (0..20000u32).map(|i| (i % 113) as u8).collect();
+    let stream = common::compress(&data, Some(3000));
+    let trailer = b"\x7fELF next structure".to_vec();
+    let (input, plain, output) = (dir.join("fw.bin"), dir.join("plain.cmp"), dir.join("fw.out"));
+    fs::write(&input, [&stream[..], &trailer].concat()).unwrap();
+    fs::write(&plain, &stream).unwrap();
+
+    let saved = dir.join("fw.out.trailer");
+    for options in [&[][..], &["--chunk-size", "4K"]] {
+        let _ = fs::remove_file(&saved);
+        let result = common::run(common::command().arg("--save-trailer").args(options).arg(&input).arg(&output));
+        assert_success(&result);
+        let warning = format!("the stream ends at byte 0x{:x}, {} bytes before the end of the input", stream.len(), trailer.len());
+        assert!(String::from_utf8_lossy(&result.stderr).contains(&warning), "{:?}", options);
+        assert!(fs::read(&output).unwrap() == data);
+        assert_eq!(fs::read(&saved).unwrap(), trailer, "{:?}", options);
+    }
+
+    // Next to the input when the output goes to stdout
+    let result = common::run(common::command().args(["-q", "--save-trailer"]).arg(&input).arg("-"));
+    assert_success(&result);
+    assert!(result.stdout == data);
+    assert_eq!(fs::read(dir.join("fw.bin.trailer")).unwrap(), trailer);
+
+    // And not at all when nothing follows the stream
+    assert_success(&common::run(common::command().args(["-q", "--save-trailer"]).arg(&plain).arg(dir.join("plain.out"))));
+    assert!(!dir.join("plain.out.trailer").exists());
+}
@@ -0,0 +1,36 @@
+//! `hpcmp compress --when-full` must keep, drop or weigh up a full
+//! dictionary as asked, every way decoding back to the input.
+
+mod common;
+
+use std::fs;
+
+use common::{assert_success, TempDir};
+
+#[test]
+fn handles_full_dictionaries() {
+    let dir = TempDir::new("when-full");
+    // Text the dictionary fills on, then bytes none of its entries suit
+    let mut data: Vec<u8> = (0..20000).flat_map(|i| format!("sample {} of {}\n", i % 37, i % 11).into_bytes()).collect();
+    data.extend((0..200000u32).map(|i| (i * 7 % 251) as u8));
+    fs::write(dir.join("data.bin"), &data).unwrap();
+
+    let compress = |how: &str| {
+        let path = dir.join(format!("{}.cmp", how));
+        assert_success(&common::run(common::command().args(["compress", "--raw", "--when-full", how, "-o"]).arg(&path).arg(dir.join("data.bin"))));
+        let stream = fs::read(&path).unwrap();
+        let (decoded, report) = hpcmp::decompress_with_report(&stream).unwrap();
+        assert!(decoded == data, "{} decoded to {} bytes", how, decoded.len());
+        (stream.len(), report.blocks)
+    };
+
+    let (frozen, blocks) = compress("freeze");
+    assert_eq!(blocks.len(), 1);
+    let (reset, blocks) = compress("reset");
+    assert!(blocks[..blocks.len() - 1].iter().all(|block| block.dictionary_len == 0x1000));
+    assert!(reset < frozen, "{} bytes reset, {} frozen", reset, frozen);
+    // Starting over only once the text gives way
+    let (adaptive, blocks) = compress("adaptive");
+    assert!(blocks.len() > 1 && blocks[0].output_len > 0x1000 * 4, "{:?}", blocks);
+    assert!(adaptive < frozen, "{} bytes adaptive, {} frozen", adaptive, frozen);
+}
@@ -0,0 +1,125 @@
+//! JavaScript bindings to the hpcmp decoder, built with wasm-bindgen.
+
+use wasm_bindgen::prelude::*;
+
+use hpcmp::Decoder;
+
+fn js_error(e: hpcmp::Error) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Decompresses a complete stream held in a `Uint8Array`.
+#[wasm_bindgen]
+pub fn decompress(input: &[u8]) -> Result<Vec<u8>, JsError> {
+    hpcmp::decompress(input).map_err(js_error)
+}
+
+/// Incremental decoder for input that arrives in chunks.
+///
+/// Pass each chunk to `push`, which returns whatever output it completes,
+/// then call `finish` once the input is exhausted to check the stream ended
+/// properly.
+#[wasm_bindgen]
+pub struct StreamDecoder {
+    decoder: Decoder,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> StreamDecoder {
+        StreamDecoder::new()
+    }
+}
+
+// What `push` and `finish` do, with errors not yet made into `JsError`s,
+// which can only be built on wasm32
+impl StreamDecoder {
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<u8>, hpcmp::Error> {
+        let mut out = vec![];
+        self.decoder.decode_to_vec(chunk, &mut out)?;
+        Ok(out)
+    }
+
+    fn check_done(&self) -> Result<(), hpcmp::Error> {
+        if self.decoder.is_done() {
+            Ok(())
+        } else {
+            Err(hpcmp::Error::UnexpectedEof)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl StreamDecoder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> StreamDecoder {
+        StreamDecoder{ decoder: Decoder::new() }
+    }
+
+    /// Decodes `chunk`, returning the output it completes. Input after the
+    /// end of the stream is ignored.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.decode(chunk).map_err(js_error)
+    }
+
+    /// Fails if the stream has not reached its end-of-file marker.
+    pub fn finish(&self) -> Result<(), JsError> {
+        self.check_done().map_err(js_error)
+    }
+
+    /// True once the end-of-file marker has been decoded.
+    #[wasm_bindgen(getter)]
+    pub fn done(&self) -> bool {
+        self.decoder.is_done()
+    }
+
+    /// Number of decompressed bytes produced so far.
+    #[wasm_bindgen(getter, js_name = totalOut)]
+    pub fn total_out(&self) -> f64 {
+        self.decoder.total_out() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hpcmp::VECTORS;
+
+    #[test]
+    fn decompress_whole() {
+        for vector in VECTORS {
+            let output = decompress(vector.stream).ok().unwrap();
+            assert_eq!(output, hpcmp::decompress(vector.stream).unwrap(), "{}", vector.name);
+            assert_eq!(output.len(), vector.output_len, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn stream_in_chunks() {
+        for vector in VECTORS {
+            let expected = hpcmp::decompress(vector.stream).unwrap();
+            for chunk_len in [1, 7, vector.stream.len()] {
+                let mut decoder = StreamDecoder::new();
+                let mut out = vec![];
+                for chunk in vector.stream.chunks(chunk_len) {
+                    assert!(!decoder.done(), "{} done early", vector.name);
+                    out.extend(decoder.push(chunk).ok().unwrap());
+                    assert_eq!(decoder.total_out(), out.len() as f64);
+                }
+                assert!(decoder.done());
+                assert!(decoder.finish().is_ok());
+                assert!(out == expected, "{} in chunks of {}", vector.name, chunk_len);
+            }
+        }
+    }
+
+    // `push` and `finish` throw what these return, as `JsError`s
+    #[test]
+    fn stream_errors() {
+        let stream = VECTORS[0].stream;
+        let mut decoder = StreamDecoder::new();
+        decoder.decode(&stream[..stream.len() - 1]).unwrap();
+        assert_eq!(decoder.check_done(), Err(hpcmp::Error::UnexpectedEof));
+        assert_eq!(StreamDecoder::new().decode(&[0xff; 4]), Err(hpcmp::Error::MissingStartMarker));
+    }
+}